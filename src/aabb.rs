@@ -0,0 +1,211 @@
+use glam::Vec3A;
+
+use crate::{interval::Interval, ray::Ray};
+
+/// The smallest thickness of a bounding box along any axis,
+/// so that flat objects don't produce degenerate boxes
+const MINIMUM_THICKNESS: f32 = 0.0001;
+
+/// Axis-aligned bounding box, represented by an interval on each axis
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    x: Interval,
+    y: Interval,
+    z: Interval,
+}
+
+impl Aabb {
+    /// Creates a new bounding box
+    ///
+    /// Axes thinner than the minimum thickness are padded.
+    ///
+    /// ## Parameters
+    /// * `x` - extent of the box along the X axis
+    /// * `y` - extent of the box along the Y axis
+    /// * `z` - extent of the box along the Z axis
+    pub fn new(x: Interval, y: Interval, z: Interval) -> Self {
+        let pad = |interval: Interval| {
+            if interval.size() < MINIMUM_THICKNESS {
+                interval.expand(MINIMUM_THICKNESS)
+            } else {
+                interval
+            }
+        };
+
+        Self {
+            x: pad(x),
+            y: pad(y),
+            z: pad(z),
+        }
+    }
+
+    /// Creates the smallest bounding box that contains all points
+    ///
+    /// ## Parameters
+    /// * `points` - points to enclose (should not be empty)
+    pub fn from_points(points: &[Vec3A]) -> Self {
+        let min = points
+            .iter()
+            .fold(Vec3A::splat(f32::INFINITY), |min, point| min.min(*point));
+        let max = points
+            .iter()
+            .fold(Vec3A::splat(-f32::INFINITY), |max, point| max.max(*point));
+
+        Self::new(
+            Interval::new(min.x, max.x),
+            Interval::new(min.y, max.y),
+            Interval::new(min.z, max.z),
+        )
+    }
+
+    /// Creates the smallest bounding box that contains both boxes
+    ///
+    /// ## Parameters
+    /// * `a` - the first box
+    /// * `b` - the second box
+    pub fn surrounding(a: Aabb, b: Aabb) -> Self {
+        Self {
+            x: Interval::surrounding(a.x, b.x),
+            y: Interval::surrounding(a.y, b.y),
+            z: Interval::surrounding(a.z, b.z),
+        }
+    }
+
+    /// Grows the box, so that it also contains the other box
+    ///
+    /// ## Parameters
+    /// * `other` - the box to include
+    pub fn merge(&mut self, other: &Aabb) {
+        *self = Self::surrounding(*self, *other);
+    }
+
+    /// Returns the index of the axis along which the box is the longest (0 = X, 1 = Y, 2 = Z)
+    ///
+    /// Ties go to the earlier axis.
+    pub fn longest_axis(&self) -> usize {
+        let size = self.max() - self.min();
+        if size.x >= size.y && size.x >= size.z {
+            0
+        } else if size.y >= size.z {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// Returns the center point of the box
+    pub fn centroid(&self) -> Vec3A {
+        (self.min() + self.max()) / 2.0
+    }
+
+    /// Returns the area of the box surface
+    pub fn surface_area(&self) -> f32 {
+        let size = self.max() - self.min();
+        2.0 * (size.x * size.y + size.y * size.z + size.z * size.x)
+    }
+
+    /// Whether the box is finite along all axes
+    pub fn is_finite(&self) -> bool {
+        self.min().is_finite() && self.max().is_finite()
+    }
+
+    /// Returns the extent of the box along the axis
+    ///
+    /// ## Parameters
+    /// * `axis` - index of the axis (0 = X, 1 = Y, 2 = Z)
+    pub fn axis_interval(&self, axis: usize) -> Interval {
+        match axis {
+            0 => self.x,
+            1 => self.y,
+            _ => self.z,
+        }
+    }
+
+    /// Checks whether the ray passes through the box inside the interval
+    ///
+    /// ## Parameters
+    /// * `ray` - the ray to check
+    /// * `ray_interval` - the allowed range of the ray parameter
+    pub fn hit(&self, ray: &Ray, ray_interval: Interval) -> bool {
+        self.clip(ray, ray_interval).is_some()
+    }
+
+    /// Returns the part of the interval in which the ray is inside the box,
+    /// or `None` if the ray misses it
+    ///
+    /// Uses the slab method: the interval is narrowed to the part
+    /// where the ray is between both planes of each axis.
+    ///
+    /// ## Parameters
+    /// * `ray` - the ray to check
+    /// * `ray_interval` - the allowed range of the ray parameter
+    pub fn clip(&self, ray: &Ray, ray_interval: Interval) -> Option<Interval> {
+        let origin = ray.origin();
+        let direction = ray.direction();
+
+        let mut t_min = ray_interval.min();
+        let mut t_max = ray_interval.max();
+        for axis in 0..3 {
+            let interval = self.axis_interval(axis);
+            let inverse_direction = 1.0 / direction[axis];
+
+            let t0 = (interval.min() - origin[axis]) * inverse_direction;
+            let t1 = (interval.max() - origin[axis]) * inverse_direction;
+            let (t_near, t_far) = if t0 < t1 { (t0, t1) } else { (t1, t0) };
+
+            // Comparisons with NaN (ray parallel to and on the slab plane) keep the interval
+            if t_near > t_min {
+                t_min = t_near;
+            }
+            if t_far < t_max {
+                t_max = t_far;
+            }
+
+            if t_max < t_min {
+                return None;
+            }
+        }
+        Some(Interval::new(t_min, t_max))
+    }
+
+    /// Returns the corner with the lowest coordinates
+    pub fn min(&self) -> Vec3A {
+        Vec3A::new(self.x.min(), self.y.min(), self.z.min())
+    }
+
+    /// Returns the corner with the highest coordinates
+    pub fn max(&self) -> Vec3A {
+        Vec3A::new(self.x.max(), self.y.max(), self.z.max())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn surrounding_box_contains_both_boxes() {
+        let a = Aabb::from_points(&[Vec3A::new(-2.0, 0.0, 0.0), Vec3A::new(-1.0, 1.0, 1.0)]);
+        let b = Aabb::from_points(&[Vec3A::new(3.0, -1.0, 2.0), Vec3A::new(4.0, 0.5, 3.0)]);
+        let surrounding = Aabb::surrounding(a, b);
+        assert_eq!(surrounding.min(), Vec3A::new(-2.0, -1.0, 0.0));
+        assert_eq!(surrounding.max(), Vec3A::new(4.0, 1.0, 3.0));
+        assert_eq!(surrounding.centroid(), Vec3A::new(1.0, 0.0, 1.5));
+
+        let mut merged = b;
+        merged.merge(&a);
+        assert_eq!(merged.min(), surrounding.min());
+        assert_eq!(merged.max(), surrounding.max());
+    }
+
+    #[test]
+    fn longest_axis_is_found() {
+        let size_box =
+            |x: f32, y: f32, z: f32| Aabb::from_points(&[Vec3A::ZERO, Vec3A::new(x, y, z)]);
+        assert_eq!(size_box(3.0, 1.0, 2.0).longest_axis(), 0);
+        assert_eq!(size_box(1.0, 3.0, 2.0).longest_axis(), 1);
+        assert_eq!(size_box(1.0, 2.0, 3.0).longest_axis(), 2);
+        // Ties go to the earlier axis
+        assert_eq!(size_box(1.0, 2.0, 2.0).longest_axis(), 1);
+    }
+}
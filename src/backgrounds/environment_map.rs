@@ -0,0 +1,271 @@
+use std::{f32::consts::PI, path::Path};
+
+use glam::Vec3A;
+use rand::Rng;
+use rand_xoshiro::Xoshiro256Plus;
+
+use crate::{
+    color::RGBColor,
+    input_formats::ImportError,
+    math::normalize_vec3,
+    ray::Ray,
+    textures::{image::ImageTexture, Texture},
+};
+
+use super::Background;
+
+/// Background that surrounds the scene with an image
+///
+/// The image is expected to be in the equirectangular projection
+/// (longitude on the horizontal axis, latitude on the vertical axis).
+///
+/// Directions towards the environment can be importance sampled,
+/// so that bright regions (like the sun) are found more often.
+pub struct EnvironmentMap {
+    texture: ImageTexture,
+    texel_probabilities: Vec<f32>, // Probability of sampling each texel
+    row_cdf: Vec<f32>,             // Cumulative probability of the rows
+    column_cdfs: Vec<f32>,         // Cumulative probability of texels inside each row
+}
+
+impl EnvironmentMap {
+    /// Creates a new environment map
+    ///
+    /// ## Parameters
+    /// * `texture` - equirectangular image of the environment
+    pub fn new(texture: ImageTexture) -> Self {
+        let width = texture.width();
+        let height = texture.height();
+
+        // Rows near the poles cover a smaller solid angle, so their weight is reduced
+        let mut weights: Vec<f32> = (0..height)
+            .flat_map(|j| {
+                let cos_latitude = Self::row_latitude(j, height).cos();
+                let texture = &texture;
+                (0..width).map(move |i| texture.pixel(i, j).luminance().max(0.0) * cos_latitude)
+            })
+            .collect();
+        let mut total: f32 = weights.iter().sum();
+
+        // A completely black image is sampled uniformly over the sphere
+        if total <= 0.0 || !total.is_finite() {
+            for (index, weight) in weights.iter_mut().enumerate() {
+                *weight = Self::row_latitude(index / width.max(1), height).cos();
+            }
+            total = weights.iter().sum();
+        }
+
+        let texel_probabilities: Vec<f32> = weights.iter().map(|weight| weight / total).collect();
+
+        let mut row_cdf = Vec::with_capacity(height);
+        let mut column_cdfs = Vec::with_capacity(width * height);
+        let mut cumulative_row = 0.0;
+        for row in texel_probabilities.chunks(width.max(1)) {
+            let row_probability: f32 = row.iter().sum();
+            cumulative_row += row_probability;
+            row_cdf.push(cumulative_row);
+
+            let mut cumulative_column = 0.0;
+            for probability in row {
+                cumulative_column += probability;
+                column_cdfs.push(if row_probability > 0.0 {
+                    cumulative_column / row_probability
+                } else {
+                    0.0
+                });
+            }
+        }
+
+        Self {
+            texture,
+            texel_probabilities,
+            row_cdf,
+            column_cdfs,
+        }
+    }
+
+    /// Loads an environment map from file
+    ///
+    /// ## Parameters
+    /// * `path` - path to the image file (e.g. Radiance `.hdr`)
+    pub fn load(path: &Path) -> Result<Self, ImportError> {
+        Ok(Self::new(ImageTexture::load(path)?))
+    }
+
+    /// Calculates the color of the environment in the direction of the ray
+    pub fn color(&self, ray: &Ray) -> RGBColor {
+        let direction = normalize_vec3(ray.direction());
+        let (u, v) = Self::direction_to_uv(direction);
+        self.texture.value(u, v, direction)
+    }
+
+    /// Generates a random direction towards the environment,
+    /// where brighter directions are more likely
+    ///
+    /// Returns the direction and its probability density (over solid angle).
+    ///
+    /// ## Parameters
+    /// * `rng` - random number generator instance
+    pub fn sample_direction(&self, rng: &mut Xoshiro256Plus) -> (Vec3A, f32) {
+        let width = self.texture.width();
+        let height = self.texture.height();
+        if width == 0 || height == 0 {
+            return (Vec3A::Y, 0.0);
+        }
+
+        let j = Self::sample_cdf(&self.row_cdf, rng.gen());
+        let row = &self.column_cdfs[j * width..(j + 1) * width];
+        let i = Self::sample_cdf(row, rng.gen());
+
+        // We pick a uniformly random point inside the chosen texel
+        let u = (i as f32 + rng.gen::<f32>()) / width as f32;
+        let v = 1.0 - (j as f32 + rng.gen::<f32>()) / height as f32;
+        let direction = Self::uv_to_direction(u, v);
+
+        (direction, self.pdf(direction))
+    }
+
+    /// Calculates the probability density (over solid angle)
+    /// of `sample_direction` generating the direction
+    ///
+    /// ## Parameters
+    /// * `direction` - direction towards the environment
+    pub fn pdf(&self, direction: Vec3A) -> f32 {
+        let width = self.texture.width();
+        let height = self.texture.height();
+        if width == 0 || height == 0 {
+            return 0.0;
+        }
+
+        let direction = normalize_vec3(direction);
+        let (u, v) = Self::direction_to_uv(direction);
+        let cos_latitude = (1.0 - direction.y * direction.y).max(0.0).sqrt();
+        if cos_latitude <= 0.0 {
+            return 0.0;
+        }
+
+        let i = ((u * width as f32) as usize).min(width - 1);
+        let j = (((1.0 - v) * height as f32) as usize).min(height - 1);
+
+        // Density over the texture is converted to density over the sphere,
+        // where the texture covers 2π x π radians
+        let uv_pdf = self.texel_probabilities[j * width + i] * (width * height) as f32;
+        uv_pdf / (2.0 * PI * PI * cos_latitude)
+    }
+
+    /// Finds the first index where the cumulative probability exceeds `x`
+    fn sample_cdf(cdf: &[f32], x: f32) -> usize {
+        let total = cdf.last().copied().unwrap_or(0.0);
+        cdf.partition_point(|cumulative| *cumulative <= x * total)
+            .min(cdf.len() - 1)
+    }
+
+    /// Returns the latitude (angle from the horizon) of the center of the row
+    fn row_latitude(j: usize, height: usize) -> f32 {
+        (0.5 - (j as f32 + 0.5) / height as f32) * PI
+    }
+
+    /// Converts direction to texture coordinates
+    ///
+    /// Center of the image is in the direction of -Z, top of the image is +Y.
+    fn direction_to_uv(direction: Vec3A) -> (f32, f32) {
+        let u = 0.5 + direction.x.atan2(-direction.z) / (2.0 * PI);
+        let v = 0.5 + direction.y.clamp(-1.0, 1.0).asin() / PI;
+        (u, v)
+    }
+
+    /// Converts texture coordinates to direction (inverse of `direction_to_uv`)
+    fn uv_to_direction(u: f32, v: f32) -> Vec3A {
+        let longitude = (u - 0.5) * 2.0 * PI;
+        let latitude = (v - 0.5) * PI;
+        Vec3A::new(
+            latitude.cos() * longitude.sin(),
+            latitude.sin(),
+            -latitude.cos() * longitude.cos(),
+        )
+    }
+}
+
+impl Background for EnvironmentMap {
+    fn radiance(&self, ray: &Ray) -> RGBColor {
+        self.color(ray)
+    }
+
+    fn is_importance_sampled(&self) -> bool {
+        true
+    }
+
+    fn sample(&self, rng: &mut Xoshiro256Plus) -> Option<(Vec3A, f32)> {
+        Some(self.sample_direction(rng))
+    }
+
+    fn pdf(&self, direction: Vec3A) -> f32 {
+        EnvironmentMap::pdf(self, direction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use crate::input_formats::ImportedImage;
+
+    use super::*;
+
+    const WIDTH: usize = 16;
+    const HEIGHT: usize = 8;
+    const BRIGHT_TEXEL: (usize, usize) = (12, 2);
+
+    /// Dim environment with a single very bright texel (a sun)
+    fn sun_environment() -> EnvironmentMap {
+        let mut image_data = vec![RGBColor::new(0.1, 0.1, 0.1); WIDTH * HEIGHT];
+        image_data[BRIGHT_TEXEL.1 * WIDTH + BRIGHT_TEXEL.0] = RGBColor::new(1000.0, 900.0, 800.0);
+        EnvironmentMap::new(ImageTexture::new(ImportedImage {
+            width: WIDTH,
+            height: HEIGHT,
+            image_data,
+        }))
+    }
+
+    #[test]
+    fn samples_cluster_towards_the_brightest_texel() {
+        let environment = sun_environment();
+        let (i, j) = BRIGHT_TEXEL;
+        let sun_direction = EnvironmentMap::uv_to_direction(
+            (i as f32 + 0.5) / WIDTH as f32,
+            1.0 - (j as f32 + 0.5) / HEIGHT as f32,
+        );
+
+        let mut rng = Xoshiro256Plus::seed_from_u64(3);
+        let sample_count = 10000;
+        let mut towards_sun = 0;
+        for _ in 0..sample_count {
+            let (direction, pdf) = environment.sample_direction(&mut rng);
+            assert!((direction.length() - 1.0).abs() < 1e-5);
+            assert!((pdf - environment.pdf(direction)).abs() <= 1e-3 * pdf);
+            if direction.dot(sun_direction) > 0.4f32.cos() {
+                towards_sun += 1;
+            }
+        }
+        assert!(towards_sun * 10 > sample_count * 9, "{}", towards_sun);
+    }
+
+    #[test]
+    fn pdf_integrates_to_one() {
+        let environment = sun_environment();
+        // Several points in every texel, each covering an equal part of the (u, v) square
+        let (columns, rows) = (WIDTH * 5, HEIGHT * 5);
+        let mut integral = 0.0;
+        for row in 0..rows {
+            for column in 0..columns {
+                let u = (column as f32 + 0.5) / columns as f32;
+                let v = (row as f32 + 0.5) / rows as f32;
+                let direction = EnvironmentMap::uv_to_direction(u, v);
+                let cos_latitude = ((v - 0.5) * PI).cos();
+                let solid_angle = cos_latitude * (2.0 * PI / columns as f32) * (PI / rows as f32);
+                integral += environment.pdf(direction) * solid_angle;
+            }
+        }
+        assert!((integral - 1.0).abs() < 1e-2, "{}", integral);
+    }
+}
@@ -0,0 +1,106 @@
+use glam::Vec3A;
+
+use crate::{color::RGBColor, math::normalize_vec3, ray::Ray};
+
+use super::Background;
+
+/// Sky with a gradient between two colors
+///
+/// The colors are interpolated by `0.5 * (cos + 1)`, where `cos` is the cosine
+/// between the ray and the zenith direction. So the zenith color is reached looking
+/// along the zenith direction, the horizon color looking the opposite way,
+/// and rays parallel to the horizon get the average of both.
+pub struct GradientSky {
+    zenith_color: RGBColor,
+    horizon_color: RGBColor,
+    zenith_direction: Vec3A,
+}
+
+impl Default for GradientSky {
+    /// White to blue gradient with the zenith straight up
+    fn default() -> Self {
+        Self::new(
+            RGBColor::new(0.5, 0.7, 1.0),
+            RGBColor::new(1.0, 1.0, 1.0),
+            Vec3A::Y,
+        )
+    }
+}
+
+impl GradientSky {
+    /// Creates a new gradient sky
+    ///
+    /// ## Parameters
+    /// * `zenith_color` - color in the zenith direction
+    /// * `horizon_color` - color opposite of the zenith direction
+    /// * `zenith_direction` - direction of the zenith (does not need to be normalized)
+    pub fn new(zenith_color: RGBColor, horizon_color: RGBColor, zenith_direction: Vec3A) -> Self {
+        Self {
+            zenith_color,
+            horizon_color,
+            zenith_direction: normalize_vec3(zenith_direction),
+        }
+    }
+
+    /// Calculates the color of the sky in the direction of the ray
+    pub fn color(&self, ray: &Ray) -> RGBColor {
+        let unit_direction = normalize_vec3(ray.direction());
+        let parameter = 0.5 * (unit_direction.dot(self.zenith_direction) + 1.0);
+        RGBColor::lerp(self.horizon_color, self.zenith_color, parameter)
+    }
+}
+
+impl Background for GradientSky {
+    fn radiance(&self, ray: &Ray) -> RGBColor {
+        self.color(ray)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backgrounds::BackgroundKind;
+
+    use super::*;
+
+    fn assert_color_near(actual: RGBColor, expected: RGBColor) {
+        let difference = actual - expected;
+        assert!(
+            [difference.r(), difference.g(), difference.b()]
+                .iter()
+                .all(|channel| channel.abs() < 1e-6),
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn tilted_gradient_follows_the_zenith_direction() {
+        let zenith_color = RGBColor::new(1.0, 0.5, 0.25);
+        let horizon_color = RGBColor::new(0.0, 0.25, 0.5);
+        let zenith_direction = Vec3A::new(1.0, 1.0, 0.0);
+        let sky = GradientSky::new(zenith_color, horizon_color, zenith_direction);
+        let color_towards = |direction: Vec3A| sky.color(&Ray::new(Vec3A::ZERO, direction));
+
+        assert_color_near(color_towards(zenith_direction * 3.0), zenith_color);
+        assert_color_near(color_towards(-zenith_direction), horizon_color);
+        // Every direction perpendicular to the zenith is halfway between the two colors
+        let average = RGBColor::lerp(horizon_color, zenith_color, 0.5);
+        assert_color_near(color_towards(Vec3A::new(1.0, -1.0, 0.0)), average);
+        assert_color_near(color_towards(Vec3A::Z), average);
+    }
+
+    #[test]
+    fn gradient_is_parsed_from_the_background_argument() {
+        let kind: BackgroundKind = "gradient:1,0.5,0,0,0,1,0,0,-2".parse().unwrap();
+        assert_eq!(
+            kind,
+            BackgroundKind::Gradient {
+                zenith_color: RGBColor::new(1.0, 0.5, 0.0),
+                horizon_color: RGBColor::new(0.0, 0.0, 1.0),
+                zenith_direction: Some(Vec3A::new(0.0, 0.0, -2.0)),
+            }
+        );
+        assert_eq!(kind.to_string().parse(), Ok(kind));
+    }
+}
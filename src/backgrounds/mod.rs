@@ -0,0 +1,176 @@
+use std::{fmt::Display, str::FromStr};
+
+use glam::Vec3A;
+use rand_xoshiro::Xoshiro256Plus;
+
+use crate::{color::RGBColor, ray::Ray};
+
+pub mod environment_map;
+pub mod gradient_sky;
+pub mod sky;
+pub mod solid_color;
+pub mod sun_sky;
+
+/// Anything that surrounds the scene and lights the rays that miss all objects
+pub trait Background {
+    /// Returns the light arriving from the background along the ray
+    fn radiance(&self, ray: &Ray) -> RGBColor;
+
+    /// Whether the background can be sampled directly (like a light)
+    ///
+    /// Only then are `sample` and `pdf` meaningful.
+    fn is_importance_sampled(&self) -> bool {
+        false
+    }
+
+    /// Generates a random direction towards the background
+    ///
+    /// Returns the direction and its probability density (over solid angle),
+    /// or `None` if the background cannot be sampled.
+    ///
+    /// ## Parameters
+    /// * `rng` - random number generator instance
+    fn sample(&self, _rng: &mut Xoshiro256Plus) -> Option<(Vec3A, f32)> {
+        None
+    }
+
+    /// Calculates the probability density (over solid angle)
+    /// of `sample` generating the direction
+    ///
+    /// ## Parameters
+    /// * `direction` - direction towards the background
+    fn pdf(&self, _direction: Vec3A) -> f32 {
+        0.0
+    }
+}
+
+impl<F> Background for F
+where
+    F: Fn(&Ray) -> RGBColor,
+{
+    fn radiance(&self, ray: &Ray) -> RGBColor {
+        self(ray)
+    }
+}
+
+/// Background selected from the command line
+///
+/// ## Formats
+/// * `sky` - white to blue gradient
+/// * `color:r,g,b` - single color in every direction
+/// * `sun-sky[:x,y,z[,size[,intensity]]]` - gradient with a sun in the given direction
+/// * `gradient:r,g,b,r,g,b[,x,y,z]` - gradient from the zenith to the horizon color,
+///   with the zenith in the given direction (straight up by default)
+#[derive(Clone, Debug, PartialEq)]
+pub enum BackgroundKind {
+    Sky,
+    SolidColor(RGBColor),
+    Gradient {
+        zenith_color: RGBColor,
+        horizon_color: RGBColor,
+        /// `None` places the zenith straight up
+        zenith_direction: Option<Vec3A>,
+    },
+    SunSky {
+        /// `None` places the sun at the default position above the horizon
+        sun_direction: Option<Vec3A>,
+        angular_size: f32,
+        intensity: f32,
+    },
+}
+
+impl FromStr for BackgroundKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, parameters) = s.split_once(':').unwrap_or((s, ""));
+        let values = parameters
+            .split(',')
+            .filter(|value| !value.is_empty())
+            .map(|value| {
+                value
+                    .trim()
+                    .parse::<f32>()
+                    .map_err(|_| format!("Invalid number '{}' in background '{}'", value, s))
+            })
+            .collect::<Result<Vec<f32>, String>>()?;
+
+        match name {
+            "sky" => Ok(Self::Sky),
+            "color" => match values[..] {
+                [r, g, b] => Ok(Self::SolidColor(RGBColor::new(r, g, b))),
+                _ => Err(format!("Background color needs 3 components in '{}'", s)),
+            },
+            "gradient" => {
+                let zenith_direction = match values[..] {
+                    [_, _, _, _, _, _] => None,
+                    [_, _, _, _, _, _, x, y, z] => Some(Vec3A::new(x, y, z)),
+                    _ => {
+                        return Err(format!(
+                            "Gradient needs 6 color components and an optional direction in '{}'",
+                            s
+                        ))
+                    }
+                };
+                Ok(Self::Gradient {
+                    zenith_color: RGBColor::new(values[0], values[1], values[2]),
+                    horizon_color: RGBColor::new(values[3], values[4], values[5]),
+                    zenith_direction,
+                })
+            }
+            "sun-sky" => {
+                let sun_direction = match values[..] {
+                    [] => None,
+                    [x, y, z, ..] => Some(Vec3A::new(x, y, z)),
+                    _ => return Err(format!("Sun direction needs 3 components in '{}'", s)),
+                };
+                Ok(Self::SunSky {
+                    sun_direction,
+                    angular_size: values.get(3).copied().unwrap_or(0.53),
+                    intensity: values.get(4).copied().unwrap_or(50.0),
+                })
+            }
+            _ => Err(format!("Unknown background '{}'", name)),
+        }
+    }
+}
+
+impl Display for BackgroundKind {
+    /// Writes the background in the same format as it is parsed
+    ///
+    /// The size and intensity of the sun are only written with its direction
+    /// (the format does not allow them alone).
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let rgb = |color: &RGBColor| format!("{},{},{}", color.r(), color.g(), color.b());
+        let xyz = |vector: &Vec3A| format!("{},{},{}", vector.x, vector.y, vector.z);
+        match self {
+            BackgroundKind::Sky => write!(f, "sky"),
+            BackgroundKind::SolidColor(color) => write!(f, "color:{}", rgb(color)),
+            BackgroundKind::Gradient {
+                zenith_color,
+                horizon_color,
+                zenith_direction,
+            } => {
+                write!(f, "gradient:{},{}", rgb(zenith_color), rgb(horizon_color))?;
+                match zenith_direction {
+                    Some(direction) => write!(f, ",{}", xyz(direction)),
+                    None => Ok(()),
+                }
+            }
+            BackgroundKind::SunSky {
+                sun_direction,
+                angular_size,
+                intensity,
+            } => match sun_direction {
+                Some(direction) => write!(
+                    f,
+                    "sun-sky:{},{},{}",
+                    xyz(direction),
+                    angular_size,
+                    intensity
+                ),
+                None => write!(f, "sun-sky"),
+            },
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use crate::{color::RGBColor, ray::Ray};
+
+use super::{gradient_sky::GradientSky, Background};
+
+/// Simple sky with a vertical gradient from white to blue
+pub struct Sky;
+
+impl Background for Sky {
+    fn radiance(&self, ray: &Ray) -> RGBColor {
+        sky_background(ray)
+    }
+}
+
+/// Calculates sky background color
+pub fn sky_background(ray: &Ray) -> RGBColor {
+    // The default gradient goes from white to blue based on vertical direction of the ray
+    GradientSky::default().color(ray)
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3A;
+    use rand::SeedableRng;
+    use rand_xoshiro::Xoshiro256Plus;
+
+    use crate::math::normalize_vec3;
+
+    use super::*;
+
+    /// The sky gradient as it was computed before backgrounds were objects
+    fn original_sky_background(ray: &Ray) -> RGBColor {
+        let unit_direction = normalize_vec3(ray.direction());
+        let a = 0.5 * (unit_direction.y + 1.0);
+        RGBColor::lerp(RGBColor::white(), RGBColor::new(0.5, 0.7, 1.0), a)
+    }
+
+    #[test]
+    fn sky_radiance_matches_the_sky_function() {
+        let closure_sky = |ray: &Ray| original_sky_background(ray);
+        for direction in [
+            Vec3A::Y,
+            -Vec3A::Y,
+            Vec3A::new(1.0, 0.2, -3.0),
+            Vec3A::new(-0.5, -0.7, 0.1),
+        ] {
+            let ray = Ray::new(Vec3A::ZERO, direction);
+            let expected = original_sky_background(&ray);
+            let difference = Sky.radiance(&ray) - expected;
+            assert!(
+                difference.r().abs() < 1e-6
+                    && difference.g().abs() < 1e-6
+                    && difference.b().abs() < 1e-6,
+                "{}: {:?} != {:?}",
+                direction,
+                Sky.radiance(&ray),
+                expected
+            );
+            // Closures are still backgrounds
+            assert!(closure_sky.radiance(&ray) == expected);
+        }
+        assert!(!Sky.is_importance_sampled());
+        let mut rng = Xoshiro256Plus::seed_from_u64(0);
+        assert!(Sky.sample(&mut rng).is_none());
+    }
+}
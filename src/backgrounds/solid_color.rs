@@ -0,0 +1,24 @@
+use crate::{color::RGBColor, ray::Ray};
+
+use super::Background;
+
+/// Background with the same color in every direction
+pub struct SolidColor {
+    color: RGBColor,
+}
+
+impl SolidColor {
+    /// Creates a new solid color background
+    ///
+    /// ## Parameters
+    /// * `color` - color (radiance) of the background
+    pub fn new(color: RGBColor) -> Self {
+        Self { color }
+    }
+}
+
+impl Background for SolidColor {
+    fn radiance(&self, _ray: &Ray) -> RGBColor {
+        self.color
+    }
+}
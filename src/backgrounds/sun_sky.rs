@@ -0,0 +1,73 @@
+use glam::Vec3A;
+
+use crate::{color::RGBColor, math::normalize_vec3, ray::Ray};
+
+use super::{sky::sky_background, Background};
+
+/// Sky background with a bright sun disk
+///
+/// Rays pointing towards the sun (within its angular radius)
+/// receive the light of the sun on top of the sky gradient.
+pub struct SunSky {
+    sun_direction: Vec3A,
+    cos_sun_radius: f32,
+    sun_color: RGBColor,
+}
+
+impl SunSky {
+    /// Creates a new sky with the sun
+    ///
+    /// ## Parameters
+    /// * `sun_direction` - direction from the scene towards the sun
+    /// * `angular_size` - apparent diameter of the sun in degrees (the real one is about 0.53)
+    /// * `intensity` - brightness of the sun relative to white
+    pub fn new(sun_direction: Vec3A, angular_size: f32, intensity: f32) -> Self {
+        let sun_radius = (angular_size / 2.0).to_radians();
+        Self {
+            sun_direction: normalize_vec3(sun_direction),
+            cos_sun_radius: sun_radius.cos(),
+            sun_color: RGBColor::white() * intensity,
+        }
+    }
+
+    /// Calculates the color of the sky in the direction of the ray
+    pub fn color(&self, ray: &Ray) -> RGBColor {
+        let sky = sky_background(ray);
+        let unit_direction = normalize_vec3(ray.direction());
+        if unit_direction.dot(self.sun_direction) >= self.cos_sun_radius {
+            sky + self.sun_color
+        } else {
+            sky
+        }
+    }
+}
+
+impl Background for SunSky {
+    fn radiance(&self, ray: &Ray) -> RGBColor {
+        self.color(ray)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sun_is_much_brighter_than_the_sky() {
+        let sun_direction = Vec3A::new(1.0, 1.0, -1.0);
+        let sun_sky = SunSky::new(sun_direction, 0.53, 1000.0);
+        let towards_sun = Ray::new(Vec3A::ZERO, sun_direction * 3.0);
+        let away_from_sun = Ray::new(Vec3A::ZERO, Vec3A::new(-1.0, 1.0, 1.0));
+
+        let sun = sun_sky.radiance(&towards_sun).luminance();
+        let sky = sun_sky.radiance(&away_from_sun).luminance();
+        assert!(sun > 100.0 * sky, "{} {}", sun, sky);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "Normalization of [NaN, 1, 0] is invalid")]
+    fn invalid_sun_direction_panics_in_debug_builds() {
+        SunSky::new(Vec3A::new(f32::NAN, 1.0, 0.0), 0.53, 1000.0);
+    }
+}
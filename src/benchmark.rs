@@ -0,0 +1,121 @@
+use std::{
+    fmt::Display,
+    time::{Duration, Instant},
+};
+
+use argh::FromArgs;
+
+use crate::{error::RaybowError, preparation, rendering, Arguments};
+
+/// Settings of the benchmark render, the same on every run
+const BENCHMARK_ARGUMENTS: [&str; 10] = [
+    "--output-width",
+    "512",
+    "--output-height",
+    "512",
+    "--samples-per-pixel",
+    "16",
+    "--steps",
+    "10",
+    "--seed",
+    "0",
+];
+
+/// Result of the benchmark render
+pub struct BenchmarkResult {
+    pub width: usize,
+    pub height: usize,
+    pub samples_per_pixel: usize,
+    /// Duration of the render pass alone
+    pub render_time: Duration,
+    /// Rays tested against the scene during the render
+    pub rays: u64,
+}
+
+impl BenchmarkResult {
+    /// Returns the amount of rays tested against the scene per second
+    pub fn rays_per_second(&self) -> f64 {
+        self.rays as f64 / self.render_time.as_secs_f64()
+    }
+
+    /// Returns the amount of finished image pixels per second (in millions)
+    pub fn megapixels_per_second(&self) -> f64 {
+        (self.width * self.height) as f64 / self.render_time.as_secs_f64() / 1e6
+    }
+}
+
+impl Display for BenchmarkResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Benchmark: {}x{}, {} samples per pixel",
+            self.width, self.height, self.samples_per_pixel
+        )?;
+        writeln!(f, "Render time: {:.2?}", self.render_time)?;
+        writeln!(
+            f,
+            "Rays: {} ({:.2} Mrays/s)",
+            self.rays,
+            self.rays_per_second() / 1e6
+        )?;
+        write!(f, "Megapixels: {:.4} Mpx/s", self.megapixels_per_second())
+    }
+}
+
+/// Renders the built-in scene with fixed settings and measures the speed
+///
+/// Only the render pass is timed. Nothing is written to files,
+/// and other options given to the program are ignored,
+/// so that results of different builds can be compared.
+pub fn run_benchmark() -> Result<BenchmarkResult, RaybowError> {
+    benchmark_with(&BENCHMARK_ARGUMENTS)
+}
+
+/// Renders the built-in scene with the settings and measures the speed (see `run_benchmark`)
+///
+/// ## Parameters
+/// * `options` - command line options with the settings of the render
+fn benchmark_with(options: &[&str]) -> Result<BenchmarkResult, RaybowError> {
+    let arguments =
+        Arguments::from_args(&["raybow-2"], options).expect("Benchmark arguments should be valid");
+
+    let mut scene_data = preparation::prepare_render_data(&arguments)?;
+    scene_data
+        .renderables
+        .build_accelerator(arguments.accelerator);
+
+    let render_start = Instant::now();
+    let render_result = rendering::render::render(&arguments, &scene_data, 0);
+    let render_time = render_start.elapsed();
+
+    Ok(BenchmarkResult {
+        width: render_result.width,
+        height: render_result.height,
+        samples_per_pixel: render_result.sample_count,
+        render_time,
+        rays: render_result.stats.rays,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn benchmark_reports_positive_speed() {
+        // The same scene as the benchmark, small enough for a debug build
+        let mut options = BENCHMARK_ARGUMENTS;
+        options[1] = "16";
+        options[3] = "12";
+        options[5] = "2";
+        let result = benchmark_with(&options).unwrap();
+
+        assert_eq!((result.width, result.height), (16, 12));
+        assert_eq!(result.samples_per_pixel, 2);
+        // Every sample casts at least its camera ray
+        assert!(result.rays >= 16 * 12 * 2, "{}", result.rays);
+        assert!(result.rays_per_second() > 0.0);
+        assert!(result.megapixels_per_second() > 0.0);
+        assert!(result.to_string().contains("Mrays/s"));
+    }
+}
@@ -1,9 +1,99 @@
+use std::str::FromStr;
+
 use glam::{Mat4, Vec3A, Vec4, Vec4Swizzles};
 use rand::Rng;
 use rand_xoshiro::Xoshiro256Plus;
 
-use crate::{math::random_vec3_on_unit_disk, ray::Ray};
+use crate::{
+    math::{
+        build_onb, is_vec3_near_zero, normalize_vec3, random_vec3_in_regular_polygon,
+        random_vec3_on_unit_disk, spline::catmull_rom,
+    },
+    parse_vec3,
+    ray::Ray,
+};
+
+/// The smallest supported vertical field of view (in degrees)
+const MIN_VERTICAL_FOV: f32 = 0.1;
+/// The largest supported vertical field of view (in degrees)
+const MAX_VERTICAL_FOV: f32 = 179.0;
+
+/// Clamps the vertical field of view to the supported range
+///
+/// At 0 degrees the viewport collapses into a point and
+/// at 180 degrees it becomes infinite, so both would render garbage.
+fn clamp_vertical_fov(fov: f32) -> f32 {
+    let clamped_fov = if fov.is_nan() {
+        MIN_VERTICAL_FOV
+    } else {
+        fov.clamp(MIN_VERTICAL_FOV, MAX_VERTICAL_FOV)
+    };
+    if clamped_fov != fov {
+        log::warn!(
+            "Vertical FOV {} is out of range ({}, {}), clamping it to {}",
+            fov,
+            MIN_VERTICAL_FOV,
+            MAX_VERTICAL_FOV,
+            clamped_fov
+        );
+    }
+    clamped_fov
+}
+
+/// The smallest supported depth of field (focus) distance
+const MIN_DOF_DISTANCE: f32 = 0.001;
+/// The largest supported depth of field cone angle (in degrees), exclusive
+const MAX_DOF_ANGLE: f32 = 180.0;
+
+/// Clamps the depth of field (focus) distance to positive values
+///
+/// The viewport is placed at this distance, so at 0 it collapses into a point,
+/// and negative distances would put it behind the camera (inverting the image and the blur).
+fn clamp_dof_distance(distance: f32) -> f32 {
+    let clamped_distance = if distance.is_nan() {
+        MIN_DOF_DISTANCE
+    } else {
+        distance.max(MIN_DOF_DISTANCE)
+    };
+    if clamped_distance != distance {
+        log::warn!(
+            "Depth of field distance {} is not positive, clamping it to {}",
+            distance,
+            clamped_distance
+        );
+    }
+    clamped_distance
+}
 
+/// Clamps the depth of field cone angle to the `[0, 180)` degrees range
+///
+/// Negative angles would flip the aperture and at 180 degrees the aperture becomes infinite.
+fn clamp_dof_angle(angle: f32) -> f32 {
+    // The largest value below the maximum, since the range excludes it
+    let max_angle = MAX_DOF_ANGLE - MAX_DOF_ANGLE * f32::EPSILON;
+    let clamped_angle = if angle.is_nan() {
+        0.0
+    } else {
+        angle.clamp(0.0, max_angle)
+    };
+    if clamped_angle != angle {
+        log::warn!(
+            "Depth of field angle {} is out of range [0, {}), clamping it to {}",
+            angle,
+            MAX_DOF_ANGLE,
+            clamped_angle
+        );
+    }
+    clamped_angle
+}
+
+/// Pinhole or thin lens camera
+///
+/// The camera works in right-handed coordinates with Y up (other conventions
+/// are converted before they reach it, see `UpAxis`). Looking along the view
+/// direction, the world up vector points to the top of the image and
+/// `up × backward` to its right. Pixel `(0, 0)` is the upper left corner
+/// and row indices grow downwards, matching the order of `image_data`.
 pub struct Camera {
     origin: Vec3A,
     look_at: Vec3A,
@@ -11,6 +101,7 @@ pub struct Camera {
     upper_left: Vec3A,
     horizontal_shift: Vec3A,
     vertical_shift: Vec3A,
+    forward: Vec3A,
 
     width: f32,
     height: f32,
@@ -20,6 +111,8 @@ pub struct Camera {
     dof_distance: f32,
     dof_disk_horizontal: Vec3A,
     dof_disk_vertical: Vec3A,
+    aperture_blades: usize,
+    chromatic_aberration: f32,
 }
 
 impl Default for Camera {
@@ -45,6 +138,7 @@ impl Default for Camera {
             upper_left,
             horizontal_shift: pixel_horizontal_shift,
             vertical_shift: pixel_vertical_shift,
+            forward: look_at - origin,
             width,
             height,
             vertical_fov,
@@ -52,6 +146,8 @@ impl Default for Camera {
             dof_distance,
             dof_disk_horizontal,
             dof_disk_vertical,
+            aperture_blades: 0,
+            chromatic_aberration: 0.0,
         };
         camera.update_transforms();
         camera
@@ -67,6 +163,7 @@ impl Camera {
     /// * `vertical_fov` - vertical field of view (zoom)
     /// * `dof_distance` - distance of depth of field
     /// * `dof_cone_angle` - size of the depth of field
+    #[allow(dead_code)] // Part of the camera API, not needed by the built-in scenes
     pub fn new(
         width: usize,
         height: usize,
@@ -99,13 +196,16 @@ impl Camera {
             upper_left,
             horizontal_shift,
             vertical_shift,
-            vertical_fov,
+            forward: look_at - origin,
+            vertical_fov: clamp_vertical_fov(vertical_fov),
             width,
             height,
-            dof_angle: dof_cone_angle,
-            dof_distance,
+            dof_angle: clamp_dof_angle(dof_cone_angle),
+            dof_distance: clamp_dof_distance(dof_distance),
             dof_disk_horizontal,
             dof_disk_vertical,
+            aperture_blades: 0,
+            chromatic_aberration: 0.0,
         };
         camera.update_transforms();
         camera
@@ -114,6 +214,7 @@ impl Camera {
     /// Sets the up vector of the camera
     ///
     /// This decides how the in-camera view is rotated
+    #[allow(dead_code)] // Part of the camera API, not needed by the built-in scenes
     pub fn set_up_direction(&mut self, up: Vec3A) {
         self.up = up;
         self.update_transforms();
@@ -132,8 +233,10 @@ impl Camera {
     }
 
     /// Sets the vertical field of view
+    ///
+    /// Values outside of the (0.1, 179.0) degrees range are clamped.
     pub fn set_vertical_fov(&mut self, fov: f32) {
-        self.vertical_fov = fov;
+        self.vertical_fov = clamp_vertical_fov(fov);
         self.update_transforms();
     }
 
@@ -143,19 +246,130 @@ impl Camera {
         self.update_transforms();
     }
 
+    /// Sets the depth of field
+    ///
+    /// The distance is clamped to positive values and the angle to the `[0, 180)` degrees range.
+    /// An angle of 0 disables the effect (pinhole camera).
+    ///
+    /// ## Parameters
+    /// * `dof_distance` - distance of the plane in focus
+    /// * `dof_cone_angle` - size of the depth of field (in degrees)
     pub fn set_defocus(&mut self, dof_distance: f32, dof_cone_angle: f32) {
-        self.dof_distance = dof_distance;
-        self.dof_angle = dof_cone_angle;
+        self.dof_distance = clamp_dof_distance(dof_distance);
+        self.dof_angle = clamp_dof_angle(dof_cone_angle);
         self.update_transforms();
     }
 
+    /// Sets the shape of the aperture, which decides the shape of out-of-focus highlights (bokeh)
+    ///
+    /// ## Parameters
+    /// * `blades` - the number of aperture blades (polygon sides), or 0 for a round aperture
+    pub fn set_aperture_blades(&mut self, blades: usize) {
+        if blades != 0 && blades < 3 {
+            panic!("Aperture needs at least 3 blades (or 0 for a round aperture)!");
+        }
+        self.aperture_blades = blades;
+    }
+
+    /// Returns a random point on the aperture (inside the unit circle)
+    ///
+    /// ## Parameters
+    /// * `rng` - instance of a random value generator
+    fn sample_aperture(&self, rng: &mut Xoshiro256Plus) -> Vec3A {
+        if self.aperture_blades == 0 {
+            random_vec3_on_unit_disk(rng)
+        } else {
+            random_vec3_in_regular_polygon(self.aperture_blades, rng)
+        }
+    }
+
+    /// Sets the strength of lateral chromatic aberration
+    ///
+    /// Cheap lenses magnify each wavelength slightly differently, so colored fringes
+    /// appear along edges, growing towards the image borders.
+    /// The red channel is magnified by `1 + amount` and the blue one by `1 - amount`
+    /// around the image center, while the green channel stays in place.
+    ///
+    /// ## Parameters
+    /// * `amount` - relative magnification difference, 0.0 disables the effect
+    pub fn set_chromatic_aberration(&mut self, amount: f32) {
+        self.chromatic_aberration = amount;
+    }
+
+    /// Whether the color channels need to be traced separately
+    pub fn has_chromatic_aberration(&self) -> bool {
+        self.chromatic_aberration != 0.0
+    }
+
+    /// Returns the (unit) direction in which the camera looks
+    pub fn forward(&self) -> Vec3A {
+        self.forward
+    }
+
+    /// Whether rays start on a lens instead of a single point
+    pub fn has_depth_of_field(&self) -> bool {
+        self.dof_angle > 0.0
+    }
+
+    /// Whether all camera rays start in a single point
+    ///
+    /// This is not the case with depth of field (rays start anywhere on the lens)
+    /// or chromatic aberration (each channel sees a slightly different image).
+    pub fn is_pinhole(&self) -> bool {
+        !self.has_depth_of_field() && !self.has_chromatic_aberration()
+    }
+
+    /// Projects the point in the scene onto the image
+    ///
+    /// Returns the (fractional) horizontal and vertical pixel coordinates,
+    /// where pixel centers lie on whole numbers, or `None` if the point
+    /// is not in front of the camera.
+    ///
+    /// ## Parameters
+    /// * `point` - in-scene location to project
+    pub fn project_to_image(&self, point: Vec3A) -> Option<(f32, f32)> {
+        let offset = point - self.origin;
+        let depth = offset.dot(self.forward);
+        if depth <= f32::EPSILON {
+            return None;
+        }
+        let on_viewport = self.origin + offset * (self.dof_distance / depth);
+        let relative = on_viewport - self.upper_left;
+        Some((
+            relative.dot(self.horizontal_shift) / self.horizontal_shift.length_squared(),
+            relative.dot(self.vertical_shift) / self.vertical_shift.length_squared(),
+        ))
+    }
+
+    /// Moves the location on the viewport towards or away from the image center
+    /// by the magnification of each color channel
+    ///
+    /// Returns the locations for the red, green and blue channel.
+    ///
+    /// ## Parameters
+    /// * `location` - in-scene location on the viewport
+    fn channel_locations(&self, location: Vec3A) -> [Vec3A; 3] {
+        let image_center = self.origin + self.forward * self.dof_distance;
+        let offset = location - image_center;
+        [1.0, 0.0, -1.0].map(|channel_factor| {
+            image_center + offset * (1.0 + channel_factor * self.chromatic_aberration)
+        })
+    }
+
     /// Sets the point at which the camera looks
-    pub fn look_at(&mut self, look_at: Vec3A) {
-        self.look_at = look_at.normalize();
+    ///
+    /// The target is a point in the scene, not a direction,
+    /// so the camera stays aimed at it when its position changes.
+    ///
+    /// ## Parameters
+    /// * `target` - the point in the center of the image
+    pub fn look_at(&mut self, target: Vec3A) {
+        self.look_at = target;
         self.update_transforms();
     }
 
     /// Transforms camera with the given transform matrix
+    #[allow(dead_code)] // Part of the camera API, not needed by the built-in scenes
     pub fn transform(&mut self, matrix: Mat4) {
         let origin: Vec4 = self.origin.extend(1.0);
         let look_at: Vec4 = self.look_at.extend(1.0);
@@ -179,20 +393,34 @@ impl Camera {
         let viewport_height = 2.0 * h * self.dof_distance;
         let viewport_width = viewport_height * aspect_ratio;
 
-        let look_difference = (self.origin - self.look_at).normalize();
-        let side_direction = self.up.cross(look_difference).normalize();
+        // A camera at its target looks along -Z, and a camera looking along
+        // the up direction turns to any side, instead of producing NaNs
+        let look_difference = self.origin - self.look_at;
+        let look_difference = if is_vec3_near_zero(look_difference.abs()) {
+            Vec3A::Z
+        } else {
+            normalize_vec3(look_difference)
+        };
+        let side_direction = self.up.cross(look_difference);
+        let side_direction = if is_vec3_near_zero(side_direction.abs()) {
+            build_onb(look_difference).0
+        } else {
+            normalize_vec3(side_direction)
+        };
         let up_direction = look_difference.cross(side_direction);
 
+        // Image rows are stored from the top down, so the viewport is walked
+        // to the right (`side_direction`) and down (against `up_direction`)
         let viewport_side = viewport_width * side_direction;
-        let viewport_up = viewport_height * (-up_direction);
+        let viewport_down = viewport_height * (-up_direction);
 
         let pixel_horizontal_shift = viewport_side / self.width;
-        let pixel_vertical_shift = viewport_up / self.height;
+        let pixel_vertical_shift = viewport_down / self.height;
 
         let upper_left = self.origin
             - (look_difference * self.dof_distance)
             - viewport_side / 2.0
-            - viewport_up / 2.0;
+            - viewport_down / 2.0;
         let upper_left = upper_left + 0.5 * (pixel_horizontal_shift + pixel_vertical_shift);
 
         // Depth of field
@@ -201,6 +429,7 @@ impl Camera {
         let defocus_disk_vertical = up_direction * depth_of_field_radius;
 
         self.upper_left = upper_left;
+        self.forward = -look_difference;
         self.horizontal_shift = pixel_horizontal_shift;
         self.vertical_shift = pixel_vertical_shift;
         self.dof_disk_horizontal = defocus_disk_horizontal;
@@ -242,6 +471,19 @@ impl Camera {
         px * self.horizontal_shift + py * self.vertical_shift
     }
 
+    /// Returns the direction from `origin` towards the `target`
+    ///
+    /// If both points are (almost) the same, the direction would be zero
+    /// and produce NaNs later, so the viewing direction of the camera is used instead.
+    fn direction_towards(&self, origin: Vec3A, target: Vec3A) -> Vec3A {
+        let direction = target - origin;
+        if direction.length_squared() > f32::EPSILON * f32::EPSILON {
+            direction
+        } else {
+            self.forward
+        }
+    }
+
     /// Generates a ray through the center of the pixel
     ///
     /// ## Parameters
@@ -249,10 +491,62 @@ impl Camera {
     /// * `j` - vertical image location of the pixel
     pub fn get_ray_through_pixel_center(&self, i: usize, j: usize) -> Ray {
         let origin = self.origin;
-        let direction = self.get_pixel_center(i, j) - self.origin;
+        let direction = self.direction_towards(origin, self.get_pixel_center(i, j));
         Ray::new(origin, direction)
     }
 
+    /// Generates the ray through the center of the pixel together with
+    /// the rays through the centers of its right (+x) and lower (+y) neighbours
+    ///
+    /// The differences between the hits of these rays estimate how large
+    /// the pixel is on the hit surface (e.g. to filter textures).
+    /// The neighbours may lie outside of the image.
+    ///
+    /// ## Parameters
+    /// * `i` - horizontal image location of the pixel
+    /// * `j` - vertical image location of the pixel
+    #[cfg(test)] // For texture filtering, which no texture does yet
+    pub fn ray_differentials(&self, i: usize, j: usize) -> (Ray, Ray, Ray) {
+        let origin = self.origin;
+        let center = self.get_pixel_center(i, j);
+        let ray_towards = |target: Vec3A| Ray::new(origin, self.direction_towards(origin, target));
+        (
+            ray_towards(center),
+            ray_towards(center + self.horizontal_shift),
+            ray_towards(center + self.vertical_shift),
+        )
+    }
+
+    /// Generates a ray through the center of the pixel for each color channel
+    /// (see `set_chromatic_aberration`)
+    ///
+    /// ## Parameters
+    /// * `i` - horizontal image location of the pixel
+    /// * `j` - vertical image location of the pixel
+    pub fn get_channel_rays_through_pixel_center(&self, i: usize, j: usize) -> [Ray; 3] {
+        let origin = self.origin;
+        self.channel_locations(self.get_pixel_center(i, j))
+            .map(|location| Ray::new(origin, self.direction_towards(origin, location)))
+    }
+
+    /// Returns a random point on the camera lens, from which rays start
+    ///
+    /// ## Parameters
+    /// * `rng` - an instance of random value generator
+    fn sample_ray_origin(&self, rng: &mut Xoshiro256Plus) -> Vec3A {
+        if !self.has_depth_of_field() {
+            self.origin
+        } else {
+            // This creates depth of field.
+            // We set ray origin as a random point on the aperture in the camera origin.
+            // Since the projection plane is the same as the DOF plane,
+            // the rays hit "correctly" only in that region, making everything
+            // else blurry.
+            let p = self.sample_aperture(rng);
+            self.origin + (p.x * self.dof_disk_horizontal) + (p.y * self.dof_disk_vertical)
+        }
+    }
+
     /// Generates a ray throught a random point on the pixel
     ///
     /// This is useful for multisampling.
@@ -267,18 +561,262 @@ impl Camera {
         j: usize,
         rng: &mut Xoshiro256Plus,
     ) -> Ray {
-        let origin = if self.dof_angle <= 0.0 {
-            self.origin
-        } else {
-            // This creates depth of field.
-            // We set ray origin as a random point on a disk in the camera origin.
-            // Since the projection plane is the same as the DOF plane,
-            // the rays hit "correctly" only in that region, making everything
-            // else blurry.
-            let p = random_vec3_on_unit_disk(rng);
-            self.origin + (p.x * self.dof_disk_horizontal) + (p.y * self.dof_disk_vertical)
+        let origin = self.sample_ray_origin(rng);
+        let direction =
+            self.direction_towards(origin, self.get_random_location_on_pixel(i, j, rng));
+        // Every sample is taken at a random moment while the shutter is open
+        Ray::new_at_time(origin, direction, rng.gen())
+    }
+
+    /// Generates a ray throught a random point on the pixel for each color channel
+    /// (see `set_chromatic_aberration`)
+    ///
+    /// All rays share the same point on the lens, point on the pixel and time,
+    /// so the channels only differ by the aberration.
+    ///
+    /// ## Parameters
+    /// * `i` - horizontal image location of the pixel
+    /// * `j` - vertical image location of the pixel
+    /// * `rng` - an instance of random value generator
+    pub fn get_random_channel_rays_through_pixel(
+        &self,
+        i: usize,
+        j: usize,
+        rng: &mut Xoshiro256Plus,
+    ) -> [Ray; 3] {
+        let origin = self.sample_ray_origin(rng);
+        let location = self.get_random_location_on_pixel(i, j, rng);
+        let time = rng.gen();
+        self.channel_locations(location).map(|channel_location| {
+            Ray::new_at_time(
+                origin,
+                self.direction_towards(origin, channel_location),
+                time,
+            )
+        })
+    }
+}
+
+/// Position of the camera and the point it looks at in a keyframe of an animation,
+/// given as `x,y,z:x,y,z`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CameraKeyframe {
+    pub position: Vec3A,
+    pub look_at: Vec3A,
+}
+
+impl FromStr for CameraKeyframe {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((position, look_at)) = s.split_once(':') else {
+            return Err(format!(
+                "Camera keyframe '{}' needs the form `<position>:<look-at>`",
+                s
+            ));
         };
-        let direction = self.get_random_location_on_pixel(i, j, rng) - origin;
-        Ray::new(origin, direction)
+        Ok(Self {
+            position: parse_vec3(position)?,
+            look_at: parse_vec3(look_at)?,
+        })
+    }
+}
+
+/// Smooth path of the camera through keyframes
+///
+/// Positions and look-at points are interpolated separately with Catmull-Rom splines,
+/// and the camera is aimed at the interpolated look-at point in every frame.
+/// Keyframes are spread evenly over the path.
+pub struct CameraPath {
+    positions: Vec<Vec3A>,
+    look_ats: Vec<Vec3A>,
+}
+
+impl CameraPath {
+    /// Creates a new camera path
+    ///
+    /// Panics if there are no keyframes or the amounts of positions and look-at points differ.
+    ///
+    /// ## Parameters
+    /// * `positions` - positions of the camera in every keyframe
+    /// * `look_ats` - points at which the camera looks in every keyframe
+    pub fn new(positions: Vec<Vec3A>, look_ats: Vec<Vec3A>) -> Self {
+        if positions.is_empty() {
+            panic!("Camera path needs at least one keyframe!");
+        }
+        if positions.len() != look_ats.len() {
+            panic!(
+                "Camera path has {} positions, but {} look-at points!",
+                positions.len(),
+                look_ats.len()
+            );
+        }
+        Self {
+            positions,
+            look_ats,
+        }
+    }
+
+    /// Moves the camera to its place on the path
+    ///
+    /// ## Parameters
+    /// * `camera` - the camera to move
+    /// * `t` - position along the path, from 0.0 (first keyframe) to 1.0 (last keyframe)
+    pub fn apply(&self, camera: &mut Camera, t: f32) {
+        let spline_t = t * (self.positions.len() - 1) as f32;
+        camera.look_at(catmull_rom(&self.look_ats, spline_t));
+        camera.set_position(catmull_rom(&self.positions, spline_t));
+    }
+
+    /// Returns the position along the path of a frame of the animation
+    ///
+    /// The first frame is at the first keyframe and the last frame at the last keyframe.
+    ///
+    /// ## Parameters
+    /// * `frame` - index of the frame
+    /// * `frame_count` - amount of frames in the animation
+    pub fn frame_parameter(frame: usize, frame_count: usize) -> f32 {
+        if frame_count <= 1 {
+            0.0
+        } else {
+            frame as f32 / (frame_count - 1) as f32
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+
+    fn assert_near(actual: Vec3A, expected: Vec3A) {
+        assert!(
+            actual.abs_diff_eq(expected, 1e-5),
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn ray_differentials_target_neighbouring_pixel_centers() {
+        let mut camera = Camera::default();
+        camera.set_width(64);
+        camera.set_height(48);
+        camera.set_position(Vec3A::new(1.0, 2.0, 3.0));
+        camera.look_at(Vec3A::new(-1.0, 0.0, -2.0));
+
+        let (center, right, down) = camera.ray_differentials(10, 20);
+        assert_eq!(right.origin(), center.origin());
+        assert_eq!(down.origin(), center.origin());
+        assert_near(
+            right.direction() - center.direction(),
+            camera.horizontal_shift,
+        );
+        assert_near(down.direction() - center.direction(), camera.vertical_shift);
+        assert_near(
+            right.direction(),
+            camera.get_ray_through_pixel_center(11, 20).direction(),
+        );
+        assert_near(
+            down.direction(),
+            camera.get_ray_through_pixel_center(10, 21).direction(),
+        );
+    }
+
+    #[test]
+    fn pixel_center_on_the_origin_gives_a_valid_ray() {
+        let mut camera = Camera::default();
+        camera.set_position(Vec3A::new(1.0, 2.0, 3.0));
+        camera.look_at(Vec3A::new(1.0, 2.0, -3.0));
+        // Degenerate setup: the first pixel center coincides with the camera
+        camera.upper_left = camera.origin;
+
+        let ray = camera.get_ray_through_pixel_center(0, 0);
+        assert!(!ray.is_degenerate());
+        assert!(ray.direction().normalize().is_finite());
+        assert_near(ray.direction().normalize(), -Vec3A::Z);
+    }
+
+    #[test]
+    fn invalid_fov_is_clamped_to_a_finite_viewport() {
+        for (fov, expected_fov) in [
+            (0.0, MIN_VERTICAL_FOV),
+            (180.0, MAX_VERTICAL_FOV),
+            (-30.0, MIN_VERTICAL_FOV),
+            (f32::NAN, MIN_VERTICAL_FOV),
+        ] {
+            let mut camera = Camera::default();
+            camera.set_vertical_fov(fov);
+            assert_eq!(camera.vertical_fov, expected_fov);
+            for shift in [camera.horizontal_shift, camera.vertical_shift] {
+                assert!(
+                    shift.is_finite() && shift.length() > 0.0,
+                    "{}: {}",
+                    fov,
+                    shift
+                );
+            }
+            assert_eq!(Camera::new(4, 4, fov, 1.0, 0.0).vertical_fov, expected_fov);
+        }
+
+        let mut camera = Camera::default();
+        camera.set_vertical_fov(90.0);
+        assert_eq!(camera.vertical_fov, 90.0);
+    }
+
+    #[test]
+    fn moved_camera_keeps_looking_at_the_target() {
+        let target = Vec3A::new(1.0, -2.0, -5.0);
+        let mut camera = Camera::default();
+        camera.set_width(64);
+        camera.set_height(48);
+        camera.look_at(target);
+
+        for position in [Vec3A::new(3.0, 4.0, 2.0), Vec3A::new(-6.0, 0.5, -1.0)] {
+            camera.set_position(position);
+            assert_near(camera.forward(), (target - position).normalize());
+            // The target lands between the four middle pixels
+            let (x, y) = camera.project_to_image(target).unwrap();
+            assert!(
+                (x - 31.5).abs() < 1e-3 && (y - 23.5).abs() < 1e-3,
+                "{} {}",
+                x,
+                y
+            );
+        }
+    }
+
+    #[test]
+    fn negative_dof_distance_is_clamped() {
+        let mut camera = Camera::default();
+        camera.set_defocus(-3.0, 5.0);
+        assert_eq!(camera.dof_distance, MIN_DOF_DISTANCE);
+        // The viewport stays in front of the camera
+        assert!(camera.forward().dot(camera.upper_left - camera.origin) > 0.0);
+        assert_eq!(
+            Camera::new(4, 4, 60.0, -1.0, 5.0).dof_distance,
+            MIN_DOF_DISTANCE
+        );
+
+        camera.set_defocus(2.0, 270.0);
+        assert!(camera.dof_angle < MAX_DOF_ANGLE);
+        camera.set_defocus(2.0, -10.0);
+        assert_eq!(camera.dof_angle, 0.0);
+    }
+
+    #[test]
+    fn zero_dof_angle_gives_a_pinhole_camera() {
+        let mut camera = Camera::default();
+        camera.set_position(Vec3A::new(1.0, 2.0, 3.0));
+        camera.set_defocus(4.0, 0.0);
+        assert!(camera.is_pinhole());
+
+        let mut rng = Xoshiro256Plus::seed_from_u64(8);
+        for _ in 0..100 {
+            let ray = camera.get_random_ray_through_pixel(3, 5, &mut rng);
+            assert_eq!(ray.origin(), camera.origin);
+        }
     }
 }
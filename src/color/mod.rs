@@ -0,0 +1,391 @@
+use std::{
+    fmt::Debug,
+    ops::{Add, Div, Mul, Sub},
+};
+
+use serde::{Deserialize, Serialize};
+
+pub mod spectrum;
+
+/// RGB color structure. Handles operations with colors.
+///
+/// Components should be on the interval `[0.0, 1.0]`,
+/// but this is not enforced and larger/smaller values can be expected.
+/// To handle these cases, use the `clamp` method.
+///
+/// Colors are serialized as `[r, g, b]` arrays of linear components,
+/// and can also be read from hex codes (see `from_hex`).
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "SerializedColor", into = "[f32; 3]")]
+pub struct RGBColor {
+    r: f32,
+    g: f32,
+    b: f32,
+}
+
+impl RGBColor {
+    /// Creates new color from RED, GREEN and BLUE components
+    pub const fn new(r: f32, g: f32, b: f32) -> Self {
+        Self { r, g, b }
+    }
+
+    /// Returns the representation of color black (0.0, 0.0, 0.0)
+    pub fn black() -> Self {
+        Self {
+            r: 0.0,
+            g: 0.0,
+            b: 0.0,
+        }
+    }
+
+    /// Returns the representation of color white (1.0, 1.0, 1.0)
+    pub fn white() -> Self {
+        Self {
+            r: 1.0,
+            g: 1.0,
+            b: 1.0,
+        }
+    }
+
+    /// Creates a color from a hex code (`#rrggbb` or `rrggbb`)
+    ///
+    /// Hex codes are given in sRGB (as in image editors), so they are converted to linear space.
+    ///
+    /// ## Parameters
+    /// * `code` - the hex code of the color
+    pub fn from_hex(code: &str) -> Result<Self, String> {
+        let digits = code.strip_prefix('#').unwrap_or(code);
+        if digits.len() != 6 || !digits.is_ascii() {
+            return Err(format!("Invalid hex color '{}', expected #rrggbb", code));
+        }
+        let component = |index: usize| {
+            u8::from_str_radix(&digits[index..index + 2], 16)
+                .map(|value| srgb_to_linear(value as f32 / 255.0))
+                .map_err(|_| format!("Invalid hex color '{}', expected #rrggbb", code))
+        };
+        Ok(Self::new(component(0)?, component(2)?, component(4)?))
+    }
+
+    /// Returns the color of a black body at the given temperature
+    ///
+    /// Uses the piecewise approximation of the Planckian locus by Tanner Helland,
+    /// which is valid between 1000 K and 40000 K (temperatures are clamped to this range).
+    /// The result is converted to linear space and normalized,
+    /// so that the largest component is `1.0`.
+    ///
+    /// ## Some temperatures:
+    /// * candle flame = 1900 K
+    /// * tungsten bulb = 3200 K
+    /// * noon daylight = 5500 K
+    /// * overcast daylight = 6500 K
+    ///
+    /// ## Parameters
+    /// * `temperature` - temperature in Kelvins
+    pub fn from_kelvin(temperature: f32) -> Self {
+        let temperature = temperature.clamp(1000.0, 40000.0) / 100.0;
+
+        let r = if temperature <= 66.0 {
+            255.0
+        } else {
+            329.69873 * (temperature - 60.0).powf(-0.13320476)
+        };
+        let g = if temperature <= 66.0 {
+            99.4708 * temperature.ln() - 161.11957
+        } else {
+            288.12216 * (temperature - 60.0).powf(-0.075514846)
+        };
+        let b = if temperature >= 66.0 {
+            255.0
+        } else if temperature <= 19.0 {
+            0.0
+        } else {
+            138.51773 * (temperature - 10.0).ln() - 305.0448
+        };
+
+        // The approximation returns display (sRGB) values
+        let r = srgb_to_linear(r.clamp(0.0, 255.0) / 255.0);
+        let g = srgb_to_linear(g.clamp(0.0, 255.0) / 255.0);
+        let b = srgb_to_linear(b.clamp(0.0, 255.0) / 255.0);
+
+        let max = r.max(g).max(b);
+        Self::new(r / max, g / max, b / max)
+    }
+
+    /// Creates a color from hue, saturation and value (HSV)
+    ///
+    /// ## Parameters
+    /// * `hue` - angle on the color wheel in degrees (0 is red, 120 green, 240 blue)
+    /// * `saturation` - 0.0 is gray, 1.0 is the pure hue
+    /// * `value` - the largest component of the color (can be larger than `1.0`)
+    pub fn from_hsv(hue: f32, saturation: f32, value: f32) -> Self {
+        let hue = hue.rem_euclid(360.0) / 60.0;
+        let chroma = value * saturation;
+        let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+        let (r, g, b) = match hue as u32 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        let m = value - chroma;
+        Self::new(r + m, g + m, b + m)
+    }
+
+    /// Returns the hue (in degrees), saturation and value of the color (see `from_hsv`)
+    ///
+    /// Grays have no hue, so it is 0.
+    pub fn to_hsv(self) -> (f32, f32, f32) {
+        let value = self.r.max(self.g).max(self.b);
+        let chroma = value - self.r.min(self.g).min(self.b);
+        if chroma <= 0.0 {
+            return (0.0, 0.0, value);
+        }
+
+        let hue = if value == self.r {
+            ((self.g - self.b) / chroma).rem_euclid(6.0)
+        } else if value == self.g {
+            (self.b - self.r) / chroma + 2.0
+        } else {
+            (self.r - self.g) / chroma + 4.0
+        };
+        (hue * 60.0, chroma / value, value)
+    }
+
+    /// Clamps values of components to the interval [0.0, 1.0]
+    pub fn clamp(&mut self) {
+        self.r = self.r.clamp(0.0, 1.0);
+        self.g = self.g.clamp(0.0, 1.0);
+        self.b = self.b.clamp(0.0, 1.0);
+    }
+
+    /// Does a simple gamma 2 transformation (square root) on itself
+    pub fn linear_to_gamma(&mut self) {
+        self.r = self.r.sqrt();
+        self.g = self.g.sqrt();
+        self.b = self.b.sqrt();
+    }
+
+    /// Encodes itself with the sRGB transfer function
+    ///
+    /// Unlike a pure power curve, the function is linear close to black,
+    /// which keeps dark tones accurate.
+    pub fn linear_to_srgb(&mut self) {
+        self.r = linear_to_srgb(self.r);
+        self.g = linear_to_srgb(self.g);
+        self.b = linear_to_srgb(self.b);
+    }
+
+    /// Returns the color as 8-bit components (clamped to the interval [0.0, 1.0] first)
+    pub fn to_rgb8(mut self) -> [u8; 3] {
+        self.clamp();
+        let color = self * 255.0;
+        [color.r() as u8, color.g() as u8, color.b() as u8]
+    }
+
+    /// Returns the RED component
+    pub fn r(&self) -> f32 {
+        self.r
+    }
+
+    /// Returns the GREEN component
+    pub fn g(&self) -> f32 {
+        self.g
+    }
+
+    /// Returns the BLUE component
+    pub fn b(&self) -> f32 {
+        self.b
+    }
+
+    /// Returns the relative luminance of the (linear) color,
+    /// using the Rec. 709 coefficients
+    pub fn luminance(&self) -> f32 {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+    }
+
+    /// Returns a linear interpolation between two colors
+    ///
+    /// ## Parameters
+    /// * `start_color` - the left end of interpolation range
+    /// * `end_color` - the right end of interpolation range
+    /// * `a` - the factor of interpolation
+    pub fn lerp(start_color: Self, end_color: Self, a: f32) -> Self {
+        (1.0 - a) * start_color + a * end_color
+    }
+}
+
+/// Encodes a single linear component with the sRGB transfer function
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Converts a single sRGB encoded component to linear space
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Forms in which a color can be deserialized
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SerializedColor {
+    /// Linear components `[r, g, b]`
+    Components([f32; 3]),
+    /// sRGB hex code `#rrggbb`
+    Hex(String),
+}
+
+impl TryFrom<SerializedColor> for RGBColor {
+    type Error = String;
+
+    fn try_from(value: SerializedColor) -> Result<Self, Self::Error> {
+        match value {
+            SerializedColor::Components([r, g, b]) => Ok(Self::new(r, g, b)),
+            SerializedColor::Hex(code) => Self::from_hex(&code),
+        }
+    }
+}
+
+impl From<RGBColor> for [f32; 3] {
+    fn from(value: RGBColor) -> Self {
+        [value.r, value.g, value.b]
+    }
+}
+
+impl Debug for RGBColor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "RGB[{},{},{}]", self.r, self.g, self.b)
+    }
+}
+
+impl Add for RGBColor {
+    type Output = RGBColor;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self::Output {
+            r: self.r + rhs.r,
+            g: self.g + rhs.g,
+            b: self.b + rhs.b,
+        }
+    }
+}
+
+impl Sub for RGBColor {
+    type Output = RGBColor;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self::Output {
+            r: self.r - rhs.r,
+            g: self.g - rhs.g,
+            b: self.b - rhs.b,
+        }
+    }
+}
+
+impl Mul<RGBColor> for RGBColor {
+    type Output = RGBColor;
+
+    fn mul(self, rhs: RGBColor) -> Self::Output {
+        Self::Output {
+            r: self.r * rhs.r,
+            g: self.g * rhs.g,
+            b: self.b * rhs.b,
+        }
+    }
+}
+
+impl Mul<f32> for RGBColor {
+    type Output = RGBColor;
+
+    fn mul(self, rhs: f32) -> Self::Output {
+        Self::Output {
+            r: self.r * rhs,
+            g: self.g * rhs,
+            b: self.b * rhs,
+        }
+    }
+}
+
+impl Mul<RGBColor> for f32 {
+    type Output = RGBColor;
+
+    fn mul(self, rhs: RGBColor) -> Self::Output {
+        Self::Output {
+            r: self * rhs.r,
+            g: self * rhs.g,
+            b: self * rhs.b,
+        }
+    }
+}
+
+impl Div<f32> for RGBColor {
+    type Output = RGBColor;
+
+    fn div(self, rhs: f32) -> Self::Output {
+        Self::Output {
+            r: self.r / rhs,
+            g: self.g / rhs,
+            b: self.b / rhs,
+        }
+    }
+}
+
+impl Div<RGBColor> for f32 {
+    type Output = RGBColor;
+
+    fn div(self, rhs: RGBColor) -> Self::Output {
+        Self::Output {
+            r: self / rhs.r,
+            g: self / rhs.g,
+            b: self / rhs.b,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3A;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Placed {
+        color: RGBColor,
+        position: Vec3A,
+    }
+
+    #[test]
+    fn colors_and_positions_survive_a_round_trip() {
+        let placed = Placed {
+            color: RGBColor::new(0.25, 0.5, 1.0),
+            position: Vec3A::new(1.0, -2.0, 3.5),
+        };
+        let serialized = toml::to_string(&placed).unwrap();
+        assert_eq!(toml::from_str::<Placed>(&serialized), Ok(placed));
+
+        // Colors may also be given as sRGB hex codes
+        let hex: Placed =
+            toml::from_str("color = \"#ffffff\"\nposition = [0.0, 0.0, 0.0]").unwrap();
+        assert_eq!(hex.color, RGBColor::white());
+    }
+
+    #[test]
+    fn kelvin_colors_follow_the_planckian_locus() {
+        let daylight = RGBColor::from_kelvin(6500.0);
+        assert!(daylight.r() > 0.9 && daylight.g() > 0.85 && daylight.b() > 0.85);
+
+        let candle = RGBColor::from_kelvin(2000.0);
+        assert_eq!(candle.r(), 1.0);
+        assert!(candle.g() < 0.3, "{:?}", candle);
+        assert!(candle.b() < 0.05, "{:?}", candle);
+    }
+}
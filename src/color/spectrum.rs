@@ -0,0 +1,187 @@
+use std::sync::OnceLock;
+
+use glam::{Mat3A, Vec3A};
+use rand::Rng;
+use rand_xoshiro::Xoshiro256Plus;
+
+use super::RGBColor;
+
+/// The shortest wavelength of visible light that is sampled (in nanometers)
+pub const MIN_WAVELENGTH: f32 = 380.0;
+
+/// The longest wavelength of visible light that is sampled (in nanometers)
+pub const MAX_WAVELENGTH: f32 = 720.0;
+
+/// Centers of the smooth spectra that RGB colors are built from (red, green, blue)
+///
+/// Chosen so that pure RGB primaries keep their color after a round trip
+/// through the spectrum (within a few percent).
+const BASIS_CENTERS: [f32; 3] = [640.0, 535.0, 440.0];
+
+/// Width of the smooth spectra that RGB colors are built from
+const BASIS_WIDTH: f32 = 30.0;
+
+/// Conversion from CIE XYZ to linear sRGB (with the D65 white point)
+const XYZ_TO_RGB: Mat3A = Mat3A::from_cols_array(&[
+    3.2404542, -0.969266, 0.0556434, // First column
+    -1.5371385, 1.8760108, -0.2040259, // Second column
+    -0.4985314, 0.041556, 1.0572252, // Third column
+]);
+
+/// A single wavelength of light, carried by a path in spectral rendering
+///
+/// Colors (of materials, lights and backgrounds) are given in RGB,
+/// so they are turned into smooth spectra and evaluated at the wavelength.
+/// The light found by the path is turned back into RGB
+/// through the CIE 1931 color matching functions.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Wavelength(f32);
+
+impl Wavelength {
+    /// Creates a new wavelength
+    ///
+    /// ## Parameters
+    /// * `nanometers` - the wavelength in nanometers (visible light is between 380 and 720)
+    pub fn new(nanometers: f32) -> Self {
+        Self(nanometers)
+    }
+
+    /// Samples a wavelength of visible light uniformly
+    ///
+    /// ## Parameters
+    /// * `rng` - random number generator instance
+    pub fn sample(rng: &mut Xoshiro256Plus) -> Self {
+        Self(rng.gen_range(MIN_WAVELENGTH..MAX_WAVELENGTH))
+    }
+
+    /// Returns the wavelength in nanometers
+    pub fn nanometers(self) -> f32 {
+        self.0
+    }
+
+    /// Returns the wavelength in micrometers
+    pub fn micrometers(self) -> f32 {
+        self.0 / 1000.0
+    }
+
+    /// Returns the CIE 1931 color matching functions at the wavelength (as XYZ)
+    ///
+    /// Uses the multi-lobe Gaussian fit by Wyman, Sloan and Shirley.
+    pub fn xyz(self) -> Vec3A {
+        let lobe = |mean: f32, width_below: f32, width_above: f32| {
+            let width = if self.0 < mean {
+                width_below
+            } else {
+                width_above
+            };
+            let x = (self.0 - mean) / width;
+            (-0.5 * x * x).exp()
+        };
+
+        Vec3A::new(
+            1.056 * lobe(599.8, 37.9, 31.0) + 0.362 * lobe(442.0, 16.0, 26.7)
+                - 0.065 * lobe(501.1, 20.4, 26.2),
+            0.821 * lobe(568.8, 46.9, 40.5) + 0.286 * lobe(530.9, 16.3, 31.1),
+            1.217 * lobe(437.0, 11.8, 36.0) + 0.681 * lobe(459.0, 26.0, 13.8),
+        )
+    }
+
+    /// Returns the value of the color's spectrum at the wavelength
+    ///
+    /// Every color channel is a smooth spectrum, and the spectra of all channels
+    /// add up to one at every wavelength, so white is a flat spectrum of ones.
+    ///
+    /// ## Parameters
+    /// * `color` - linear RGB color (reflectance or radiance)
+    pub fn value_of(self, color: RGBColor) -> f32 {
+        let weights = BASIS_CENTERS.map(|center| {
+            let x = (self.0 - center) / BASIS_WIDTH;
+            (-0.5 * x * x).exp()
+        });
+        let total: f32 = weights.iter().sum();
+        (color.r() * weights[0] + color.g() * weights[1] + color.b() * weights[2]) / total
+    }
+
+    /// Converts the radiance found at this (uniformly sampled) wavelength
+    /// to an RGB estimate of the whole spectrum
+    ///
+    /// The estimate is balanced, so that a flat spectrum of ones averages to white.
+    /// Single estimates can have negative channels, since pure spectral colors
+    /// lie outside of the RGB gamut.
+    ///
+    /// ## Parameters
+    /// * `radiance` - the radiance carried by the path at this wavelength
+    pub fn to_rgb(self, radiance: f32) -> RGBColor {
+        let rgb = XYZ_TO_RGB * self.xyz() * (MAX_WAVELENGTH - MIN_WAVELENGTH) / white_balance();
+        RGBColor::new(rgb.x, rgb.y, rgb.z) * radiance
+    }
+}
+
+/// Returns the RGB color of a flat spectrum of ones, integrated over visible light
+fn white_balance() -> Vec3A {
+    static WHITE_BALANCE: OnceLock<Vec3A> = OnceLock::new();
+    *WHITE_BALANCE.get_or_init(|| {
+        // Integrated in steps of 0.1 nm
+        let steps = ((MAX_WAVELENGTH - MIN_WAVELENGTH) * 10.0) as usize;
+        let step_size = (MAX_WAVELENGTH - MIN_WAVELENGTH) / steps as f32;
+        (0..steps)
+            .map(|step| {
+                let wavelength = Wavelength::new(MIN_WAVELENGTH + (step as f32 + 0.5) * step_size);
+                XYZ_TO_RGB * wavelength.xyz() * step_size
+            })
+            .sum()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+
+    /// Averages the RGB estimates of the color's spectrum over many random wavelengths
+    fn spectral_round_trip(color: RGBColor) -> RGBColor {
+        let mut rng = Xoshiro256Plus::seed_from_u64(12);
+        let sample_count = 200000;
+        let sum = (0..sample_count)
+            .map(|_| {
+                let wavelength = Wavelength::sample(&mut rng);
+                wavelength.to_rgb(wavelength.value_of(color))
+            })
+            .fold(RGBColor::black(), |sum, estimate| sum + estimate);
+        sum / sample_count as f32
+    }
+
+    fn assert_color_near(actual: RGBColor, expected: RGBColor, tolerance: f32) {
+        let difference = actual - expected;
+        assert!(
+            [difference.r(), difference.g(), difference.b()]
+                .iter()
+                .all(|channel| channel.abs() < tolerance),
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn flat_white_spectrum_integrates_to_neutral_rgb() {
+        let white = RGBColor::white();
+        let mut rng = Xoshiro256Plus::seed_from_u64(1);
+        for _ in 0..100 {
+            assert!((Wavelength::sample(&mut rng).value_of(white) - 1.0).abs() < 1e-5);
+        }
+        assert_color_near(spectral_round_trip(white), white, 0.03);
+    }
+
+    #[test]
+    fn primaries_keep_their_color() {
+        for primary in [
+            RGBColor::new(1.0, 0.0, 0.0),
+            RGBColor::new(0.0, 1.0, 0.0),
+            RGBColor::new(0.0, 0.0, 1.0),
+        ] {
+            assert_color_near(spectral_round_trip(primary), primary, 0.1);
+        }
+    }
+}
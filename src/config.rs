@@ -0,0 +1,341 @@
+use std::{error::Error, fmt::Display, fs, path::Path, str::FromStr};
+
+use glam::Vec3A;
+use serde::Deserialize;
+
+use crate::Arguments;
+
+/// Render settings loaded from a TOML file
+///
+/// Keys have the same names as the command line options (with underscores),
+/// e.g. `output_width = 512` or `camera_position = [0.0, 1.0, 2.0]`.
+/// Every key is optional, missing ones keep their command line values.
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    output_path: Option<String>,
+    format: Option<String>,
+    output_width: Option<usize>,
+    output_height: Option<usize>,
+    fov: Option<f32>,
+    dof_distance: Option<f32>,
+    dof_size: Option<f32>,
+    aperture_blades: Option<usize>,
+    chromatic_aberration: Option<f32>,
+    scene: Option<String>,
+    dump_scene: Option<String>,
+    camera_position: Option<[f32; 3]>,
+    look_at: Option<[f32; 3]>,
+    animate: Option<usize>,
+    camera_keyframe: Option<Vec<String>>,
+    up_axis: Option<String>,
+    near: Option<f32>,
+    far: Option<f32>,
+    samples_per_pixel: Option<usize>,
+    time_budget: Option<f32>,
+    steps: Option<usize>,
+    diffuse_depth: Option<usize>,
+    specular_depth: Option<usize>,
+    shadow_samples: Option<usize>,
+    caustics: Option<bool>,
+    accurate_fresnel: Option<bool>,
+    spectral: Option<bool>,
+    firefly_reject: Option<bool>,
+    edge_anti_aliasing: Option<bool>,
+    render_order: Option<String>,
+    preview_every: Option<f32>,
+    accelerator: Option<String>,
+    debug_pixel: Option<[usize; 2]>,
+    seed: Option<u64>,
+    accumulate: Option<bool>,
+    background: Option<String>,
+    background_only: Option<bool>,
+    preset: Option<String>,
+    isolate: Option<u32>,
+    visibility: Option<Vec<String>>,
+    environment_map: Option<String>,
+    denoiser_buffers: Option<bool>,
+    alpha: Option<bool>,
+    object_ids: Option<bool>,
+    term_preview: Option<bool>,
+    skip_empty_tiles: Option<bool>,
+    show_bvh: Option<bool>,
+    exposure: Option<f32>,
+    auto_exposure: Option<bool>,
+    saturation: Option<f32>,
+    hue_shift: Option<f32>,
+    contrast: Option<f32>,
+    gamma_correction: Option<bool>,
+    gamma_curve: Option<String>,
+    output_scale: Option<f32>,
+    composite_over: Option<String>,
+    benchmark: Option<bool>,
+    verbose: Option<bool>,
+}
+
+/// Errors in reading the configuration file
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The file could not be read
+    Io(std::io::Error),
+    /// The file is not valid TOML or contains unknown keys
+    Parse(toml::de::Error),
+    /// A value could not be converted to the option type
+    InvalidValue(String),
+}
+
+/// Sets the argument field to the value from the config (if any),
+/// unless the option was given on the command line
+macro_rules! apply_value {
+    ($arguments:ident . $field:ident, $value:expr, $is_given:expr) => {
+        if let Some(value) = $value {
+            if !$is_given(stringify!($field)) {
+                $arguments.$field = value;
+            }
+        }
+    };
+}
+
+impl Config {
+    /// Reads the config from a TOML file
+    ///
+    /// ## Parameters
+    /// * `path` - path to the config file
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let content = fs::read_to_string(path)?;
+        Self::parse(&content)
+    }
+
+    /// Parses the config from TOML text
+    ///
+    /// ## Parameters
+    /// * `content` - the TOML document
+    pub fn parse(content: &str) -> Result<Self, ConfigError> {
+        toml::from_str(content).map_err(ConfigError::Parse)
+    }
+
+    /// Overrides the arguments with values from the config
+    ///
+    /// Options that were explicitly given on the command line take precedence.
+    ///
+    /// ## Parameters
+    /// * `arguments` - arguments parsed from the command line
+    /// * `command_line` - raw command line arguments (to check which options were given)
+    pub fn apply(
+        self,
+        arguments: &mut Arguments,
+        command_line: &[String],
+    ) -> Result<(), ConfigError> {
+        let is_given = |field: &str| {
+            let flag = format!("--{}", field.replace('_', "-"));
+            // Short versions of the options, as declared in `Arguments`
+            let short_flag = match field {
+                "output_path" => Some("-o"),
+                "verbose" => Some("-v"),
+                _ => None,
+            };
+            command_line
+                .iter()
+                .any(|argument| *argument == flag || Some(argument.as_str()) == short_flag)
+        };
+
+        // Values that need parsing are checked before anything is changed
+        let render_order = self.render_order.as_deref().map(parse_value).transpose()?;
+        let accelerator = self.accelerator.as_deref().map(parse_value).transpose()?;
+        let background = self.background.as_deref().map(parse_value).transpose()?;
+        let preset = self.preset.as_deref().map(parse_value).transpose()?;
+        let up_axis = self.up_axis.as_deref().map(parse_value).transpose()?;
+        let gamma_curve = self.gamma_curve.as_deref().map(parse_value).transpose()?;
+        let format = self.format.as_deref().map(parse_value).transpose()?;
+        let camera_keyframe = self
+            .camera_keyframe
+            .map(|values| values.iter().map(|value| parse_value(value)).collect())
+            .transpose()?;
+        let visibility = self
+            .visibility
+            .map(|values| values.iter().map(|value| parse_value(value)).collect())
+            .transpose()?;
+
+        apply_value!(arguments.output_path, self.output_path, is_given);
+        apply_value!(arguments.format, format, is_given);
+        apply_value!(arguments.output_width, self.output_width, is_given);
+        apply_value!(arguments.output_height, self.output_height, is_given);
+        apply_value!(arguments.fov, self.fov, is_given);
+        apply_value!(arguments.dof_distance, self.dof_distance, is_given);
+        apply_value!(arguments.dof_size, self.dof_size, is_given);
+        apply_value!(arguments.aperture_blades, self.aperture_blades, is_given);
+        apply_value!(
+            arguments.chromatic_aberration,
+            self.chromatic_aberration,
+            is_given
+        );
+        apply_value!(arguments.scene, self.scene.map(Some), is_given);
+        apply_value!(arguments.dump_scene, self.dump_scene.map(Some), is_given);
+        apply_value!(
+            arguments.camera_position,
+            self.camera_position.map(Vec3A::from_array),
+            is_given
+        );
+        apply_value!(
+            arguments.look_at,
+            self.look_at.map(Vec3A::from_array),
+            is_given
+        );
+        apply_value!(arguments.animate, self.animate.map(Some), is_given);
+        apply_value!(arguments.camera_keyframe, camera_keyframe, is_given);
+        apply_value!(arguments.up_axis, up_axis, is_given);
+        apply_value!(arguments.near, self.near.map(Some), is_given);
+        apply_value!(arguments.far, self.far.map(Some), is_given);
+        apply_value!(
+            arguments.samples_per_pixel,
+            self.samples_per_pixel,
+            is_given
+        );
+        apply_value!(arguments.time_budget, self.time_budget.map(Some), is_given);
+        apply_value!(arguments.steps, self.steps, is_given);
+        apply_value!(
+            arguments.diffuse_depth,
+            self.diffuse_depth.map(Some),
+            is_given
+        );
+        apply_value!(
+            arguments.specular_depth,
+            self.specular_depth.map(Some),
+            is_given
+        );
+        apply_value!(arguments.shadow_samples, self.shadow_samples, is_given);
+        apply_value!(arguments.caustics, self.caustics, is_given);
+        apply_value!(arguments.accurate_fresnel, self.accurate_fresnel, is_given);
+        apply_value!(arguments.spectral, self.spectral, is_given);
+        apply_value!(arguments.firefly_reject, self.firefly_reject, is_given);
+        apply_value!(
+            arguments.edge_anti_aliasing,
+            self.edge_anti_aliasing,
+            is_given
+        );
+        apply_value!(arguments.render_order, render_order, is_given);
+        apply_value!(arguments.accelerator, accelerator, is_given);
+        apply_value!(
+            arguments.preview_every,
+            self.preview_every.map(Some),
+            is_given
+        );
+        apply_value!(
+            arguments.debug_pixel,
+            self.debug_pixel.map(|[x, y]| Some((x, y))),
+            is_given
+        );
+        apply_value!(arguments.seed, self.seed.map(Some), is_given);
+        apply_value!(arguments.accumulate, self.accumulate, is_given);
+        apply_value!(arguments.background, background, is_given);
+        apply_value!(arguments.background_only, self.background_only, is_given);
+        apply_value!(arguments.preset, preset.map(Some), is_given);
+        apply_value!(arguments.isolate, self.isolate.map(Some), is_given);
+        apply_value!(arguments.visibility, visibility, is_given);
+        apply_value!(
+            arguments.environment_map,
+            self.environment_map.map(Some),
+            is_given
+        );
+        apply_value!(arguments.denoiser_buffers, self.denoiser_buffers, is_given);
+        apply_value!(arguments.alpha, self.alpha, is_given);
+        apply_value!(arguments.object_ids, self.object_ids, is_given);
+        apply_value!(arguments.term_preview, self.term_preview, is_given);
+        apply_value!(arguments.skip_empty_tiles, self.skip_empty_tiles, is_given);
+        apply_value!(arguments.show_bvh, self.show_bvh, is_given);
+        apply_value!(arguments.exposure, self.exposure, is_given);
+        apply_value!(arguments.auto_exposure, self.auto_exposure, is_given);
+        apply_value!(arguments.saturation, self.saturation, is_given);
+        apply_value!(arguments.hue_shift, self.hue_shift, is_given);
+        apply_value!(arguments.contrast, self.contrast, is_given);
+        apply_value!(arguments.gamma_correction, self.gamma_correction, is_given);
+        apply_value!(arguments.gamma_curve, gamma_curve, is_given);
+        apply_value!(arguments.output_scale, self.output_scale, is_given);
+        apply_value!(
+            arguments.composite_over,
+            self.composite_over.map(Some),
+            is_given
+        );
+        apply_value!(arguments.benchmark, self.benchmark, is_given);
+        apply_value!(arguments.verbose, self.verbose, is_given);
+
+        Ok(())
+    }
+}
+
+/// Parses a config value the same way as the command line option
+fn parse_value<T>(value: &str) -> Result<T, ConfigError>
+where
+    T: FromStr<Err = String>,
+{
+    T::from_str(value).map_err(ConfigError::InvalidValue)
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            ConfigError::Io(err) => err.to_string(),
+            ConfigError::Parse(err) => err.to_string(),
+            ConfigError::InvalidValue(reason) => reason.clone(),
+        };
+        write!(f, "ConfigError: {}", message)
+    }
+}
+
+impl Error for ConfigError {}
+
+impl From<std::io::Error> for ConfigError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses the command line and applies the config over it
+    fn configured_arguments(config: &str, options: &[&str]) -> Arguments {
+        let mut arguments = Arguments::from_options(options);
+        let command_line: Vec<String> = options.iter().map(|option| option.to_string()).collect();
+        Config::parse(config)
+            .unwrap()
+            .apply(&mut arguments, &command_line)
+            .unwrap();
+        arguments
+    }
+
+    #[test]
+    fn command_line_overrides_config() {
+        let config = r#"
+            output_width = 320
+            output_height = 240
+            output_path = "from_config"
+            verbose = true
+        "#;
+
+        let arguments = configured_arguments(config, &[]);
+        assert_eq!(arguments.output_width, 320);
+        assert_eq!(arguments.output_height, 240);
+        assert_eq!(arguments.output_path, "from_config");
+
+        let arguments = configured_arguments(
+            config,
+            &["--output-width", "100", "-o", "from_command_line"],
+        );
+        assert_eq!(arguments.output_width, 100);
+        assert_eq!(arguments.output_height, 240);
+        assert_eq!(arguments.output_path, "from_command_line");
+    }
+
+    #[test]
+    fn empty_argument_is_not_an_option() {
+        let mut arguments = Arguments::from_options(&[]);
+        let command_line = vec![String::new()];
+        Config::parse("steps = 3")
+            .unwrap()
+            .apply(&mut arguments, &command_line)
+            .unwrap();
+        assert_eq!(arguments.steps, 3);
+    }
+}
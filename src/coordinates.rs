@@ -0,0 +1,62 @@
+use std::str::FromStr;
+
+use glam::Vec3A;
+
+/// Coordinate convention of the positions and directions given to the renderer
+///
+/// Internally, the renderer uses a right-handed coordinate system with Y pointing up
+/// (the camera looks along -Z by default, with +X to its right).
+/// Positions and directions given in a different convention are converted to it
+/// before the scene is constructed.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum UpAxis {
+    /// Right-handed, Y up (the internal convention, no conversion)
+    #[default]
+    Y,
+    /// Right-handed, Z up (e.g. Blender)
+    ///
+    /// Converted by rotating around the X axis, so that `(x, y, z)` becomes `(x, z, -y)`.
+    Z,
+}
+
+impl FromStr for UpAxis {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "y" => Ok(Self::Y),
+            "z" => Ok(Self::Z),
+            _ => Err(format!("Unknown up axis '{}'", s)),
+        }
+    }
+}
+
+impl UpAxis {
+    /// Converts a position or direction from this convention to the internal (Y up) one
+    ///
+    /// The conversion is a rotation, so it applies to both points and normals.
+    /// Components are only swapped and negated, so no precision is lost.
+    ///
+    /// ## Parameters
+    /// * `vector` - position or direction in this convention
+    pub fn to_y_up(self, vector: Vec3A) -> Vec3A {
+        match self {
+            UpAxis::Y => vector,
+            UpAxis::Z => Vec3A::new(vector.x, vector.z, -vector.y),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn z_up_position_maps_to_y_up() {
+        // A sphere 3 units above the ground and 2 units in front (+Y) in Blender
+        let position = Vec3A::new(1.0, 2.0, 3.0);
+        assert_eq!(UpAxis::Z.to_y_up(position), Vec3A::new(1.0, 3.0, -2.0));
+        assert_eq!(UpAxis::Z.to_y_up(Vec3A::Z), Vec3A::Y);
+        assert_eq!(UpAxis::Y.to_y_up(position), position);
+    }
+}
@@ -0,0 +1,83 @@
+use std::{error::Error, fmt::Display};
+
+use crate::{config::ConfigError, input_formats::ImportError, output_formats::ExportError};
+
+/// Errors that stop the program
+#[derive(Debug)]
+pub enum RaybowError {
+    /// A file could not be read or written
+    Io(std::io::Error),
+    /// The image could not be encoded
+    Export(ExportError),
+    /// Scene data (e.g. an image) could not be loaded
+    Import(ImportError),
+    /// The scene file does not describe a valid scene
+    SceneParse(String),
+    /// The configuration file could not be used
+    Config(ConfigError),
+    /// The arguments do not describe a valid render
+    InvalidArguments(String),
+}
+
+impl RaybowError {
+    /// Returns the exit code of the program for the error
+    ///
+    /// Invalid input given by the user exits with 2, other failures with 1.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            RaybowError::Config(_)
+            | RaybowError::SceneParse(_)
+            | RaybowError::InvalidArguments(_) => 2,
+            _ => 1,
+        }
+    }
+}
+
+impl Display for RaybowError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RaybowError::Io(err) => write!(f, "IOError: {}", err),
+            RaybowError::Export(err) => err.fmt(f),
+            RaybowError::Import(err) => err.fmt(f),
+            RaybowError::Config(err) => err.fmt(f),
+            RaybowError::SceneParse(reason) => write!(f, "Invalid scene file: {}", reason),
+            RaybowError::InvalidArguments(reason) => write!(f, "Invalid arguments: {}", reason),
+        }
+    }
+}
+
+impl Error for RaybowError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RaybowError::Io(err) => Some(err),
+            RaybowError::Export(err) => Some(err),
+            RaybowError::Import(err) => Some(err),
+            RaybowError::Config(err) => Some(err),
+            RaybowError::SceneParse(_) | RaybowError::InvalidArguments(_) => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for RaybowError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<ExportError> for RaybowError {
+    fn from(value: ExportError) -> Self {
+        Self::Export(value)
+    }
+}
+
+impl From<ImportError> for RaybowError {
+    fn from(value: ImportError) -> Self {
+        Self::Import(value)
+    }
+}
+
+impl From<ConfigError> for RaybowError {
+    fn from(value: ConfigError) -> Self {
+        Self::Config(value)
+    }
+}
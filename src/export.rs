@@ -1,23 +1,214 @@
-use std::{error::Error, fs};
+use std::{
+    fs,
+    io::{self, Write},
+};
 
-use crate::{output_formats::ppm::rgb_to_binary_ppm, postprocessing::PostProcessResult, Arguments};
+use crate::{
+    color::RGBColor, error::RaybowError, output_formats::ansi::rgb_to_ansi,
+    postprocessing::PostProcessResult, Arguments,
+};
+
+/// Output path that makes the image go to the standard output
+const STDOUT_PATH: &str = "-";
+
+/// Terminal size used when the environment does not tell it (in characters)
+const DEFAULT_TERMINAL_SIZE: (usize, usize) = (80, 24);
 
 /// Writes image data to file
 ///
+/// If the output path is `-`, the image is written to the standard output instead,
+/// so it can be piped into other programs.
+///
 /// ## Parameters
 /// * `parameters` - global application parameters
 /// * `postprocessing_result` - the result from postprocessing stage
 pub fn export_to_file(
     arguments: &Arguments,
     postprocessing_result: &PostProcessResult,
-) -> Result<(), Box<dyn Error>> {
-    let ppm_data = rgb_to_binary_ppm(
+) -> Result<(), RaybowError> {
+    let format = arguments.format;
+    if arguments.output_path == STDOUT_PATH {
+        write_image(arguments, postprocessing_result, &mut io::stdout().lock())?;
+        if postprocessing_result.albedo_data.is_some()
+            || postprocessing_result.variance_data.is_some()
+            || postprocessing_result.alpha_data.is_some()
+            || postprocessing_result.object_id_data.is_some()
+        {
+            log::warn!(
+                "Additional buffers are not written when the image goes to the standard output"
+            );
+        }
+        return Ok(());
+    }
+
+    let output = format!("{}.{}", arguments.output_path, format.extension());
+    write_image(
+        arguments,
+        postprocessing_result,
+        &mut fs::File::create(output)?,
+    )?;
+
+    // Additional buffers are written next to the image, alpha as grayscale
+    // and object IDs as colors
+    let alpha_data = postprocessing_result.alpha_data.as_ref().map(|alpha_data| {
+        alpha_data
+            .iter()
+            .map(|&alpha| RGBColor::new(alpha, alpha, alpha))
+            .collect()
+    });
+    let object_id_data = postprocessing_result
+        .object_id_data
+        .as_ref()
+        .map(|object_id_data| object_id_data.iter().map(|&id| id_color(id)).collect());
+    let buffers = [
+        ("albedo", &postprocessing_result.albedo_data),
+        ("variance", &postprocessing_result.variance_data),
+        ("alpha", &alpha_data),
+        ("object_id", &object_id_data),
+    ];
+    for (name, buffer) in buffers {
+        if let Some(buffer_data) = buffer {
+            let buffer_data = format.encode(
+                buffer_data,
+                postprocessing_result.width,
+                postprocessing_result.height,
+            )?;
+            let output = format!("{}_{}.{}", arguments.output_path, name, format.extension());
+            fs::write(output, buffer_data)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Encodes the image in the output format and writes it into the sink
+///
+/// ## Parameters
+/// * `arguments` - global application parameters
+/// * `postprocessing_result` - the result from postprocessing stage
+/// * `sink` - where the encoded image is written to
+pub fn write_image<W>(
+    arguments: &Arguments,
+    postprocessing_result: &PostProcessResult,
+    sink: &mut W,
+) -> Result<(), RaybowError>
+where
+    W: Write,
+{
+    let image_data = arguments.format.encode(
         &postprocessing_result.image_data,
         postprocessing_result.width,
         postprocessing_result.height,
     )?;
-    let output = format!("{}.ppm", arguments.output_path);
-    fs::write(output, ppm_data)?;
+    write_to(sink, &image_data)?;
+    Ok(())
+}
+
+/// Prints a preview of the image to the terminal
+///
+/// The terminal size is read from the `COLUMNS` and `LINES` environmental variables.
+/// If the image itself goes to the standard output, the preview is printed
+/// to the standard error instead, so it does not corrupt the image.
+///
+/// ## Parameters
+/// * `arguments` - global application parameters
+/// * `postprocessing_result` - the result from postprocessing stage
+pub fn print_terminal_preview(
+    arguments: &Arguments,
+    postprocessing_result: &PostProcessResult,
+) -> Result<(), RaybowError> {
+    let size_from_env = |name: &str, default: usize| {
+        std::env::var(name)
+            .ok()
+            .and_then(|value| value.trim().parse::<usize>().ok())
+            .filter(|&value| value > 0)
+            .unwrap_or(default)
+    };
+    let columns = size_from_env("COLUMNS", DEFAULT_TERMINAL_SIZE.0);
+    // One line is left for the prompt
+    let rows = size_from_env("LINES", DEFAULT_TERMINAL_SIZE.1)
+        .saturating_sub(1)
+        .max(1);
 
+    let preview = rgb_to_ansi(
+        &postprocessing_result.image_data,
+        postprocessing_result.width,
+        postprocessing_result.height,
+        columns,
+        rows,
+    )?;
+    if arguments.output_path == STDOUT_PATH {
+        write_to(&mut io::stderr().lock(), preview.as_bytes())?;
+    } else {
+        write_to(&mut io::stdout().lock(), preview.as_bytes())?;
+    }
     Ok(())
 }
+
+/// Returns the color that shows the object ID
+///
+/// The ID is hashed, so that neighbouring IDs get clearly different colors.
+/// The background (ID 0) is black.
+///
+/// ## Parameters
+/// * `id` - the object ID
+fn id_color(id: u32) -> RGBColor {
+    if id == 0 {
+        return RGBColor::black();
+    }
+    let hash = id
+        .wrapping_mul(0x9e37_79b9)
+        .rotate_left(13)
+        .wrapping_mul(0x85eb_ca6b);
+    let channel = |shift: u32| 0.2 + 0.8 * ((hash >> shift) & 0xff) as f32 / 255.0;
+    RGBColor::new(channel(0), channel(8), channel(16))
+}
+
+/// Writes encoded image data into the sink
+///
+/// ## Parameters
+/// * `sink` - where the data is written to
+/// * `data` - the encoded image
+pub fn write_to<W>(sink: &mut W, data: &[u8]) -> io::Result<()>
+where
+    W: Write,
+{
+    sink.write_all(data)?;
+    sink.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sink_gets_the_same_bytes_as_the_file() {
+        let output_path =
+            std::env::temp_dir().join(format!("raybow-export-{}", std::process::id()));
+        let output_path = output_path.to_str().unwrap();
+        let result = PostProcessResult {
+            width: 3,
+            height: 2,
+            image_data: (0..6)
+                .map(|i| RGBColor::new(i as f32 / 5.0, 0.5, 1.0 - i as f32 / 5.0))
+                .collect(),
+            albedo_data: None,
+            variance_data: None,
+            alpha_data: None,
+            object_id_data: None,
+        };
+
+        for format in ["ppm", "pfm"] {
+            let arguments = Arguments::from_options(&["-o", output_path, "--format", format]);
+            export_to_file(&arguments, &result).unwrap();
+            let file_path = format!("{}.{}", output_path, arguments.format.extension());
+            let file_bytes = fs::read(&file_path).unwrap();
+            fs::remove_file(&file_path).unwrap();
+
+            let mut sink_bytes = Vec::new();
+            write_image(&arguments, &result, &mut sink_bytes).unwrap();
+            assert!(!sink_bytes.is_empty());
+            assert_eq!(sink_bytes, file_bytes, "{}", format);
+        }
+    }
+}
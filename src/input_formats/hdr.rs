@@ -0,0 +1,243 @@
+use crate::color::RGBColor;
+
+use super::{ImportError, ImportedImage};
+
+/// Decodes Radiance HDR (RGBE) image data into linear colors
+///
+/// Values are not clamped, so colors brighter than `1.0` are preserved.
+/// Both flat and run-length encoded scanlines are supported,
+/// but only in the standard orientation (`-Y height +X width`).
+///
+/// ## Arguments
+/// * `data` - the content of the `.hdr` file
+pub fn hdr_to_rgb(data: &[u8]) -> Result<ImportedImage, ImportError> {
+    let mut position = 0;
+
+    // Header is a list of text lines, terminated by an empty line
+    let magic = read_line(data, &mut position)?;
+    if !magic.starts_with("#?") {
+        return Err(ImportError::InvalidData(
+            "Missing Radiance header".to_string(),
+        ));
+    }
+    loop {
+        let line = read_line(data, &mut position)?;
+        if line.is_empty() {
+            break;
+        }
+        if let Some(format) = line.strip_prefix("FORMAT=") {
+            if format != "32-bit_rle_rgbe" {
+                return Err(ImportError::InvalidData(format!(
+                    "Unsupported pixel format '{}'",
+                    format
+                )));
+            }
+        }
+    }
+
+    let resolution = read_line(data, &mut position)?;
+    let (width, height) = match resolution.split_whitespace().collect::<Vec<_>>()[..] {
+        ["-Y", height, "+X", width] => (parse_dimension(width)?, parse_dimension(height)?),
+        _ => {
+            return Err(ImportError::InvalidData(format!(
+                "Unsupported resolution line '{}'",
+                resolution
+            )))
+        }
+    };
+
+    // The dimensions are checked before allocating, since a malformed header can claim
+    // any size. A run-length encoded component stores up to 127 pixels in 2 bytes,
+    // so every remaining byte holds at most 16 pixels.
+    let max_pixel_count = data.len().saturating_sub(position).saturating_mul(16);
+    let pixel_count = width
+        .checked_mul(height)
+        .filter(|count| (*count).max(width) <= max_pixel_count)
+        .ok_or_else(|| {
+            ImportError::InvalidData(format!(
+                "Image size {}x{} does not fit the pixel data",
+                width, height
+            ))
+        })?;
+
+    let mut image_data = Vec::with_capacity(pixel_count);
+    let mut scanline = vec![[0u8; 4]; width];
+    for _ in 0..height {
+        read_scanline(data, &mut position, &mut scanline)?;
+        image_data.extend(scanline.iter().map(|rgbe| rgbe_to_color(*rgbe)));
+    }
+    if image_data.len() != pixel_count {
+        return Err(ImportError::InvalidData(format!(
+            "Decoded {} pixels instead of {}",
+            image_data.len(),
+            pixel_count
+        )));
+    }
+
+    Ok(ImportedImage {
+        width,
+        height,
+        image_data,
+    })
+}
+
+/// Converts a shared-exponent pixel into a linear color
+fn rgbe_to_color(rgbe: [u8; 4]) -> RGBColor {
+    if rgbe[3] == 0 {
+        return RGBColor::black();
+    }
+    // Mantissas are stored as 8-bit fractions, hence the additional 8
+    let factor = 2.0f32.powi(rgbe[3] as i32 - (128 + 8));
+    RGBColor::new(
+        rgbe[0] as f32 * factor,
+        rgbe[1] as f32 * factor,
+        rgbe[2] as f32 * factor,
+    )
+}
+
+/// Reads a single scanline into the provided buffer
+fn read_scanline(
+    data: &[u8],
+    position: &mut usize,
+    scanline: &mut [[u8; 4]],
+) -> Result<(), ImportError> {
+    let width = scanline.len();
+    let header = read_bytes(data, position, 4)?;
+
+    // The run-length encoding is only used for reasonably wide images
+    let is_rle = (8..=0x7fff).contains(&width) && header[0] == 2 && header[1] == 2;
+    if !is_rle {
+        scanline[0].copy_from_slice(header);
+        for pixel in scanline.iter_mut().skip(1) {
+            pixel.copy_from_slice(read_bytes(data, position, 4)?);
+        }
+        return Ok(());
+    }
+
+    let encoded_width = ((header[2] as usize) << 8) | header[3] as usize;
+    if encoded_width != width {
+        return Err(ImportError::InvalidData(
+            "Scanline width does not match the image width".to_string(),
+        ));
+    }
+
+    // Each of the four components is encoded separately
+    for component in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let count = read_bytes(data, position, 1)?[0] as usize;
+            if count > 128 {
+                let count = count - 128;
+                if x + count > width {
+                    return Err(ImportError::InvalidData("Run exceeds scanline".to_string()));
+                }
+                let value = read_bytes(data, position, 1)?[0];
+                for pixel in scanline[x..x + count].iter_mut() {
+                    pixel[component] = value;
+                }
+                x += count;
+            } else {
+                if count == 0 || x + count > width {
+                    return Err(ImportError::InvalidData(
+                        "Invalid literal run in scanline".to_string(),
+                    ));
+                }
+                let values = read_bytes(data, position, count)?;
+                for (pixel, value) in scanline[x..x + count].iter_mut().zip(values) {
+                    pixel[component] = *value;
+                }
+                x += count;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads a line of text, without the trailing newline
+pub fn read_line<'a>(data: &'a [u8], position: &mut usize) -> Result<&'a str, ImportError> {
+    let remaining = &data[*position..];
+    let length = remaining
+        .iter()
+        .position(|byte| *byte == b'\n')
+        .ok_or_else(|| ImportError::InvalidData("Unexpected end of header".to_string()))?;
+    *position += length + 1;
+    std::str::from_utf8(&remaining[..length])
+        .map(|line| line.trim_end_matches('\r'))
+        .map_err(|_| ImportError::InvalidData("Header is not valid text".to_string()))
+}
+
+/// Reads the given amount of bytes
+fn read_bytes<'a>(
+    data: &'a [u8],
+    position: &mut usize,
+    count: usize,
+) -> Result<&'a [u8], ImportError> {
+    let bytes = data
+        .get(*position..*position + count)
+        .ok_or_else(|| ImportError::InvalidData("Unexpected end of pixel data".to_string()))?;
+    *position += count;
+    Ok(bytes)
+}
+
+/// Parses the width or height of the image
+pub fn parse_dimension(value: &str) -> Result<usize, ImportError> {
+    value
+        .parse()
+        .map_err(|_| ImportError::InvalidData(format!("Invalid image dimension '{}'", value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &[u8] = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n";
+
+    #[test]
+    fn flat_pixels_keep_values_above_one() {
+        let mut data = HEADER.to_vec();
+        data.extend_from_slice(b"-Y 1 +X 2\n");
+        data.extend_from_slice(&[128, 64, 32, 130, 128, 128, 128, 129]);
+
+        let image = hdr_to_rgb(&data).unwrap();
+        assert_eq!((image.width, image.height), (2, 1));
+        let bright = image.image_data[0];
+        assert_eq!((bright.r(), bright.g(), bright.b()), (2.0, 1.0, 0.5));
+        assert!(image.image_data[1] == RGBColor::white());
+    }
+
+    #[test]
+    fn run_length_encoded_scanline_is_decoded() {
+        let mut data = HEADER.to_vec();
+        data.extend_from_slice(b"-Y 1 +X 8\n");
+        data.extend_from_slice(&[2, 2, 0, 8]);
+        // A run of 8 equal values for each component
+        for value in [128, 64, 32, 130] {
+            data.extend_from_slice(&[128 + 8, value]);
+        }
+
+        let image = hdr_to_rgb(&data).unwrap();
+        assert_eq!(image.image_data.len(), 8);
+        for color in image.image_data {
+            assert_eq!((color.r(), color.g(), color.b()), (2.0, 1.0, 0.5));
+        }
+    }
+
+    #[test]
+    fn oversized_dimensions_are_rejected() {
+        for resolution in [
+            format!("-Y {} +X 2\n", usize::MAX),
+            "-Y 100000 +X 100000\n".to_string(),
+            format!("-Y 0 +X {}\n", usize::MAX),
+        ] {
+            let mut data = HEADER.to_vec();
+            data.extend_from_slice(resolution.as_bytes());
+            data.extend_from_slice(&[128, 64, 32, 130, 128, 128, 128, 129]);
+            assert!(
+                matches!(hdr_to_rgb(&data), Err(ImportError::InvalidData(_))),
+                "{}",
+                resolution
+            );
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use std::{error::Error, fmt::Display, fs, path::Path};
+
+use crate::color::RGBColor;
+
+use self::{hdr::hdr_to_rgb, pfm::pfm_to_rgb};
+
+pub mod hdr;
+pub mod pfm;
+
+/// Decoded image with linear color data
+pub struct ImportedImage {
+    pub width: usize,
+    pub height: usize,
+    pub image_data: Vec<RGBColor>,
+}
+
+/// Errors in reading image data
+#[derive(Debug)]
+pub enum ImportError {
+    /// The file could not be read
+    Io(std::io::Error),
+    /// The file extension is not recognized
+    UnsupportedFormat(String),
+    /// The data does not follow the format specification
+    InvalidData(String),
+}
+
+/// Reads an image file, detecting its format from the file extension
+///
+/// ## Parameters
+/// * `path` - path to the image file
+pub fn read_image_file(path: &Path) -> Result<ImportedImage, ImportError> {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+
+    match extension.as_str() {
+        "hdr" => hdr_to_rgb(&fs::read(path)?),
+        "pfm" => pfm_to_rgb(&fs::read(path)?),
+        _ => Err(ImportError::UnsupportedFormat(extension)),
+    }
+}
+
+impl Display for ImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            ImportError::Io(err) => err.to_string(),
+            ImportError::UnsupportedFormat(extension) => {
+                format!("Unsupported image format: '{}'", extension)
+            }
+            ImportError::InvalidData(reason) => format!("Invalid image data: {}", reason),
+        };
+        write!(f, "ImportError: {}", message)
+    }
+}
+
+impl Error for ImportError {}
+
+impl From<std::io::Error> for ImportError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
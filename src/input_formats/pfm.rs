@@ -0,0 +1,103 @@
+use crate::color::RGBColor;
+
+use super::{
+    hdr::{parse_dimension, read_line},
+    ImportError, ImportedImage,
+};
+
+/// Decodes Portable Float Map (PFM) image data into linear colors
+///
+/// Both color (`PF`) and grayscale (`Pf`) images are supported.
+/// The sign of the scale tells the byte order of the values
+/// (negative is little-endian), its magnitude is ignored.
+/// Scanlines are stored from the bottom to the top of the image.
+///
+/// ## Arguments
+/// * `data` - the content of the `.pfm` file
+pub fn pfm_to_rgb(data: &[u8]) -> Result<ImportedImage, ImportError> {
+    let mut position = 0;
+
+    let channels = match read_line(data, &mut position)? {
+        "PF" => 3,
+        "Pf" => 1,
+        _ => return Err(ImportError::InvalidData("Missing PFM header".to_string())),
+    };
+
+    let resolution = read_line(data, &mut position)?;
+    let (width, height) = match resolution.split_whitespace().collect::<Vec<_>>()[..] {
+        [width, height] => (parse_dimension(width)?, parse_dimension(height)?),
+        _ => {
+            return Err(ImportError::InvalidData(format!(
+                "Invalid resolution line '{}'",
+                resolution
+            )))
+        }
+    };
+
+    let scale = read_line(data, &mut position)?;
+    let is_little_endian = scale
+        .trim()
+        .parse::<f32>()
+        .map_err(|_| ImportError::InvalidData(format!("Invalid scale '{}'", scale)))?
+        < 0.0;
+
+    let pixel_data = &data[position..];
+    if pixel_data.len() < width * height * channels * 4 {
+        return Err(ImportError::InvalidData(
+            "Unexpected end of pixel data".to_string(),
+        ));
+    }
+    let values: Vec<f32> = pixel_data
+        .chunks_exact(4)
+        .take(width * height * channels)
+        .map(|bytes| {
+            let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+            if is_little_endian {
+                f32::from_le_bytes(bytes)
+            } else {
+                f32::from_be_bytes(bytes)
+            }
+        })
+        .collect();
+
+    let mut image_data = Vec::with_capacity(width * height);
+    if width > 0 {
+        for scanline in values.chunks_exact(width * channels).rev() {
+            image_data.extend(scanline.chunks_exact(channels).map(|pixel| match pixel {
+                [r, g, b] => RGBColor::new(*r, *g, *b),
+                _ => RGBColor::new(pixel[0], pixel[0], pixel[0]),
+            }));
+        }
+    }
+
+    Ok(ImportedImage {
+        width,
+        height,
+        image_data,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::output_formats::pfm::rgb_to_pfm;
+
+    use super::*;
+
+    #[test]
+    fn written_image_is_read_back_exactly() {
+        let image_data = vec![
+            RGBColor::new(0.1, 0.2, 0.3),
+            RGBColor::new(12.5, 0.0, -1.0),
+            RGBColor::new(1e-7, 1e7, 0.5),
+            RGBColor::new(1.0 / 3.0, 2.0 / 3.0, 1.0),
+            RGBColor::black(),
+            RGBColor::white(),
+        ];
+        let encoded = rgb_to_pfm(&image_data, 3, 2).unwrap();
+        assert!(encoded.starts_with(b"PF\n3 2\n-1.0\n"));
+
+        let image = pfm_to_rgb(&encoded).unwrap();
+        assert_eq!((image.width, image.height), (3, 2));
+        assert!(image.image_data == image_data, "{:?}", image.image_data);
+    }
+}
@@ -1,4 +1,5 @@
 /// Handles the calculations regarding intervals of real numbers
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Interval {
     min: f32,
     max: f32,
@@ -24,6 +25,11 @@ impl Interval {
         Self { min, max }
     }
 
+    /// Creates an interval that contains no values
+    pub fn empty() -> Self {
+        Self::new(f32::INFINITY, -f32::INFINITY)
+    }
+
     /// Returns true if `x` is on the interval, including both bounds
     ///
     /// ## Parameters
@@ -40,6 +46,29 @@ impl Interval {
         self.min < x && x < self.max
     }
 
+    /// Returns the length of the interval
+    pub fn size(&self) -> f32 {
+        self.max - self.min
+    }
+
+    /// Returns a new interval, padded by `delta` (half on each side)
+    ///
+    /// ## Parameters
+    /// * `delta` - how much larger the new interval is
+    pub fn expand(&self, delta: f32) -> Self {
+        let padding = delta / 2.0;
+        Self::new(self.min - padding, self.max + padding)
+    }
+
+    /// Returns the smallest interval that contains both intervals
+    ///
+    /// ## Parameters
+    /// * `a` - the first interval
+    /// * `b` - the second interval
+    pub fn surrounding(a: Interval, b: Interval) -> Self {
+        Self::new(a.min.min(b.min), a.max.max(b.max))
+    }
+
     /// Returns the upper bound of the interval
     pub fn max(&self) -> f32 {
         self.max
@@ -1,10 +1,33 @@
-use std::time::Instant;
+use std::{path::Path, process::ExitCode, time::Instant};
 
 use argh::FromArgs;
+use backgrounds::BackgroundKind;
+use camera::{CameraKeyframe, CameraPath};
+use config::Config;
+use coordinates::UpAxis;
+use error::RaybowError;
+use glam::Vec3A;
+use output_formats::OutputFormat;
+use postprocessing::GammaCurve;
+use preparation::SceneData;
+use presets::ScenePreset;
+use rendering::{
+    history::RenderHistory, order::RenderOrder, renderables::AccelerationStructure,
+    visibility::ObjectVisibility,
+};
+use scene_file::SceneFile;
+use timings::StageTimings;
 
+mod aabb;
+mod backgrounds;
+mod benchmark;
 mod camera;
 mod color;
+mod config;
+mod coordinates;
+mod error;
 mod export;
+mod input_formats;
 mod interval;
 mod materials;
 mod math;
@@ -12,17 +35,27 @@ mod objects;
 mod output_formats;
 mod postprocessing;
 mod preparation;
+mod presets;
 mod progress;
 mod ray;
-
 mod rendering;
+mod scene_file;
+mod textures;
+mod timings;
+
 #[derive(FromArgs)]
 /// # Raybow 2
 /// A little raytracer
 pub struct Arguments {
-    /// output path without final extension [String]
+    /// path to a TOML file with render settings (options given on the command line take precedence)
+    #[argh(option)]
+    config: Option<String>,
+    /// output path without final extension, or `-` for the standard output [String]
     #[argh(option, default = "String::from(\"untitled\")", short = 'o')]
     output_path: String,
+    /// format of the written images: `ppm` (8 bits per channel) or `pfm` (floats, keeps values brighter than white) [String]
+    #[argh(option, default = "OutputFormat::Ppm")]
+    format: OutputFormat,
     /// output image width [u32]
     #[argh(option, default = "256")]
     output_width: usize,
@@ -38,25 +71,331 @@ pub struct Arguments {
     /// blurriness of the depth-of-field effect [f32]
     #[argh(option, default = "0.0")]
     dof_size: f32,
-    /// amount of rays to send from each pixel [u32] (more means better quality and anti-aliasing, but is slower)
+    /// number of aperture blades [u32] (decides the shape of out-of-focus highlights; 0 means round, otherwise at least 3)
+    #[argh(option, default = "0")]
+    aperture_blades: usize,
+    /// strength of lateral chromatic aberration [f32] (colored fringes towards image borders; 0 disables it)
+    #[argh(option, default = "0.0")]
+    chromatic_aberration: f32,
+    /// path to a TOML scene file with the camera, objects and background (replaces the built-in scene, `--camera-position`, `--look-at`, `--fov` and `--background`; see `--dump-scene`) [String]
+    #[argh(option)]
+    scene: Option<String>,
+    /// write the scene (the built-in one with the camera and background from the arguments, or the one from `--scene`) to a TOML scene file and exit, to start a scene of your own [String]
+    #[argh(option)]
+    dump_scene: Option<String>,
+    /// position of the camera as `x,y,z` [f32, f32, f32]
+    #[argh(
+        option,
+        default = "Vec3A::new(-3.0, 3.0, 1.0)",
+        from_str_fn(parse_vec3)
+    )]
+    camera_position: Vec3A,
+    /// point at which the camera looks as `x,y,z` [f32, f32, f32]
+    #[argh(
+        option,
+        default = "Vec3A::new(0.0, 0.0, -1.0)",
+        from_str_fn(parse_vec3)
+    )]
+    look_at: Vec3A,
+    /// render an animation with the given amount of frames [u32] (the camera moves smoothly through the `--camera-keyframe`s; frames are written as `<output>_0000`, `<output>_0001`, ...)
+    #[argh(option)]
+    animate: Option<usize>,
+    /// keyframe of the camera path as `x,y,z:x,y,z` (position and point at which the camera looks) [String] (can be repeated; keyframes are spread evenly over `--animate` frames)
+    #[argh(option)]
+    camera_keyframe: Vec<CameraKeyframe>,
+    /// up axis of the given coordinates: `y` or `z` [String] (both right-handed; `z` matches e.g. Blender)
+    #[argh(option, default = "UpAxis::Y")]
+    up_axis: UpAxis,
+    /// distance of the near clipping plane from the camera [f32] (closer objects are cut away, revealing what is inside or behind them)
+    #[argh(option)]
+    near: Option<f32>,
+    /// distance of the far clipping plane from the camera [f32] (farther objects are cut away, showing the background)
+    #[argh(option)]
+    far: Option<f32>,
+    /// amount of rays to send from each pixel [u32] (at least 1; more means better quality and anti-aliasing, but is slower)
     #[argh(option, default = "1")]
     samples_per_pixel: usize,
-    /// amount of bounces each ray makes [u32] (more means more realism and better quality, but is slower)
+    /// render for the given amount of seconds instead of a fixed amount of samples per pixel [f32] (whole passes over the image are rendered until the time runs out)
+    #[argh(option)]
+    time_budget: Option<f32>,
+    /// amount of bounces each ray makes [u32] (at least 1; more means more realism and better quality, but is slower)
     #[argh(option, default = "10")]
     steps: usize,
+    /// amount of bounces off diffuse surfaces each ray makes [u32] (at least 1; fewer than `--steps` speeds up mostly diffuse scenes)
+    #[argh(option)]
+    diffuse_depth: Option<usize>,
+    /// amount of bounces off specular surfaces (mirrors and glass) each ray makes [u32] (at least 1)
+    #[argh(option)]
+    specular_depth: Option<usize>,
+    /// amount of shadow rays sent towards each light from every hit point [u32] (more means smoother shadows, but is slower)
+    #[argh(option, default = "1")]
+    shadow_samples: usize,
+    /// let shadow rays refract through glass, so that light focused by it (caustics) is found more easily (adds some bias)
+    #[argh(switch)]
+    caustics: bool,
+    /// calculate reflections on glass with the full Fresnel equations instead of Schlick's approximation (slightly more accurate, but slower)
+    #[argh(switch)]
+    accurate_fresnel: bool,
+    /// trace a single random wavelength of light along each path, so that glass with dispersion splits light into colors (needs more samples per pixel)
+    #[argh(switch)]
+    spectral: bool,
+    /// drop outlier samples (fireflies) before averaging each pixel (slightly biased, but less noisy)
+    #[argh(switch)]
+    firefly_reject: bool,
+    /// first render one sample per pixel, then use all samples per pixel only on edges of objects (faster for previews, but flat regions stay noisy)
+    #[argh(switch)]
+    edge_anti_aliasing: bool,
+    /// order in which pixels are rendered: `scanline`, `spiral` or `morton` [String] (does not change the result)
+    #[argh(option, default = "RenderOrder::Scanline")]
+    render_order: RenderOrder,
+    /// write the partially rendered image every given amount of seconds [f32] (as `<output>_preview`, to peek at long renders)
+    #[argh(option)]
+    preview_every: Option<f32>,
+    /// structure that speeds up finding hit objects: `bvh` or `grid` [String] (does not change the result; `grid` suits many small, evenly spread objects)
+    #[argh(option, default = "AccelerationStructure::Bvh")]
+    accelerator: AccelerationStructure,
+    /// fill parts of the image that only show the background without tracing rays through the scene (does not change the result; needs a camera without depth of field and chromatic aberration)
+    #[argh(switch)]
+    skip_empty_tiles: bool,
+    /// show the edges of all boxes in the bounding volume hierarchy (glowing orange), to see how the scene is split (needs `--accelerator bvh`)
+    #[argh(switch)]
+    show_bvh: bool,
+    /// trace a single ray through the pixel at `x,y`, print every bounce of its path and exit, without rendering [usize, usize] (the path is also written as an OBJ polyline to `<output>_path.obj`)
+    #[argh(option, from_str_fn(parse_pixel))]
+    debug_pixel: Option<(usize, usize)>,
+    /// seed of the random number generator [u64] (random if not given; the same seed produces the same image)
+    #[argh(option)]
+    seed: Option<u64>,
+    /// merge the samples with those of earlier runs with the same output path (kept in `<output>.samples`)
+    #[argh(switch)]
+    accumulate: bool,
+    /// background of the scene: `sky`, `color:r,g,b`, `gradient:r,g,b,r,g,b[,x,y,z]` or `sun-sky[:x,y,z[,size[,intensity]]]` [String]
+    #[argh(option, default = "BackgroundKind::Sky")]
+    background: BackgroundKind,
+    /// render only the background, as if the scene had no objects (e.g. as a plate for compositing)
+    #[argh(switch)]
+    background_only: bool,
+    /// ready-made lighting of the scene: `clay` (gray objects under a soft sky), `studio` (three area lights) or `outdoor` (sun and sky) [String] (replaces `--background`)
+    #[argh(option)]
+    preset: Option<ScenePreset>,
+    /// render only the object with the given ID [u32] (other objects and lights are removed; see `--object-ids`)
+    #[argh(option)]
+    isolate: Option<u32>,
+    /// kinds of rays that see the object with the given ID as `<id>:<rays>`, where rays are a comma separated list of `camera`, `shadow` and `reflection`, or `none` [String] (can be repeated; e.g. `3:shadow,reflection` hides object 3 from the camera, but keeps its shadow)
+    #[argh(option)]
+    visibility: Vec<ObjectVisibility>,
+    /// path to an equirectangular environment map used as background (e.g. Radiance `.hdr`)
+    #[argh(option)]
+    environment_map: Option<String>,
+    /// also write albedo and sample variance of every pixel (as `<output>_albedo` and `<output>_variance`), to guide external denoisers
+    #[argh(switch)]
+    denoiser_buffers: bool,
+    /// also write the opacity of every pixel (as `<output>_alpha`), including shadows on shadow catchers
+    #[argh(switch)]
+    alpha: bool,
+    /// also write the ID of the object seen in every pixel (as `<output>_object_id`, each ID with its own color; the background is black)
+    #[argh(switch)]
+    object_ids: bool,
+    /// exposure adjustment in stops [f32] (+1 doubles the brightness, -1 halves it)
+    #[argh(option, default = "0.0")]
+    exposure: f32,
+    /// choose the exposure automatically, so that the brightest 5 % of the image (highlights and fireflies) are white (`--exposure` adjusts it further)
+    #[argh(switch)]
+    auto_exposure: bool,
+    /// saturation of the colors [f32] (0 gives a grayscale image, more than 1 makes colors more vivid)
+    #[argh(option, default = "1.0")]
+    saturation: f32,
+    /// rotation of the hue of all colors in degrees [f32] (e.g. 120 turns red into green)
+    #[argh(option, default = "0.0")]
+    hue_shift: f32,
+    /// contrast around middle gray [f32] (more than 1 darkens shadows and brightens highlights, less than 1 flattens the image)
+    #[argh(option, default = "1.0")]
+    contrast: f32,
     /// whether to apply gamma correction to the final image
     #[argh(switch)]
     gamma_correction: bool,
+    /// curve of the gamma correction: `power` (square root) or `srgb` (exact sRGB, more accurate in dark tones) [String]
+    #[argh(option, default = "GammaCurve::Power")]
+    gamma_curve: GammaCurve,
+    /// factor by which the final image is resized [f32] (colors are interpolated bilinearly; e.g. 2 doubles the width and height)
+    #[argh(option, default = "1.0")]
+    output_scale: f32,
+    /// path to a background image (e.g. Radiance `.hdr`) to place the final image over, using the opacity of every pixel [String] (needs the size of the final image; also writes `<output>_alpha`)
+    #[argh(option)]
+    composite_over: Option<String>,
+    /// also print a small preview of the image to the terminal (with 24-bit colors; the size is taken from `COLUMNS` and `LINES`)
+    #[argh(switch)]
+    term_preview: bool,
+    /// render a built-in scene with fixed settings and print its speed, without writing files (other options are ignored)
+    #[argh(switch)]
+    benchmark: bool,
     /// show verbose messages about program execution
     #[argh(switch, short = 'v')]
     verbose: bool,
 }
 
+/// Parses a vector from comma separated components (`x,y,z`)
+fn parse_vec3(value: &str) -> Result<Vec3A, String> {
+    let components = value
+        .split(',')
+        .map(|component| {
+            component
+                .trim()
+                .parse::<f32>()
+                .map_err(|_| format!("Invalid number '{}' in vector '{}'", component, value))
+        })
+        .collect::<Result<Vec<f32>, String>>()?;
+
+    match components[..] {
+        [x, y, z] => Ok(Vec3A::new(x, y, z)),
+        _ => Err(format!("Vector '{}' needs 3 components", value)),
+    }
+}
+
+/// Parses pixel coordinates (`x,y`)
+fn parse_pixel(value: &str) -> Result<(usize, usize), String> {
+    let coordinates = value
+        .split(',')
+        .map(|coordinate| {
+            coordinate
+                .trim()
+                .parse::<usize>()
+                .map_err(|_| format!("Invalid coordinate '{}' in pixel '{}'", coordinate, value))
+        })
+        .collect::<Result<Vec<usize>, String>>()?;
+
+    match coordinates[..] {
+        [x, y] => Ok((x, y)),
+        _ => Err(format!("Pixel '{}' needs 2 coordinates", value)),
+    }
+}
+
+/// Checks that the arguments can produce a valid image
+///
+/// Invalid values that have an obvious replacement are fixed with a warning,
+/// others are rejected with an error.
+fn validate_arguments(arguments: &mut Arguments) -> Result<(), RaybowError> {
+    if arguments.samples_per_pixel == 0 {
+        return Err(RaybowError::InvalidArguments(String::from(
+            "Samples per pixel must be at least 1 (there would be no samples to average)",
+        )));
+    }
+    if !(arguments.output_scale > 0.0 && arguments.output_scale.is_finite()) {
+        return Err(RaybowError::InvalidArguments(format!(
+            "Output scale must be a positive number, not {}",
+            arguments.output_scale
+        )));
+    }
+    if !(arguments.saturation >= 0.0 && arguments.saturation.is_finite()) {
+        return Err(RaybowError::InvalidArguments(format!(
+            "Saturation must not be negative, not {}",
+            arguments.saturation
+        )));
+    }
+    if !(arguments.contrast > 0.0 && arguments.contrast.is_finite()) {
+        return Err(RaybowError::InvalidArguments(format!(
+            "Contrast must be a positive number, not {}",
+            arguments.contrast
+        )));
+    }
+    if !arguments.hue_shift.is_finite() {
+        return Err(RaybowError::InvalidArguments(format!(
+            "Hue shift must be a number of degrees, not {}",
+            arguments.hue_shift
+        )));
+    }
+    if arguments.steps == 0 {
+        log::warn!("With 0 steps the image would be black, using 1 step instead");
+        arguments.steps = 1;
+    }
+    if arguments.diffuse_depth == Some(0) {
+        log::warn!("With a diffuse depth of 0 diffuse surfaces would be black, using 1 instead");
+        arguments.diffuse_depth = Some(1);
+    }
+    if arguments.specular_depth == Some(0) {
+        log::warn!("With a specular depth of 0 mirrors and glass would be black, using 1 instead");
+        arguments.specular_depth = Some(1);
+    }
+    if arguments.accumulate && arguments.output_path == "-" {
+        log::warn!(
+            "Samples cannot be accumulated when writing to the standard output, ignoring it"
+        );
+        arguments.accumulate = false;
+    }
+    if let (Some(near), Some(far)) = (arguments.near, arguments.far) {
+        if near >= far {
+            return Err(RaybowError::InvalidArguments(format!(
+                "Near clipping plane ({}) must be closer than the far clipping plane ({})",
+                near, far
+            )));
+        }
+    }
+    if let Some(frame_count) = arguments.animate {
+        if frame_count == 0 {
+            return Err(RaybowError::InvalidArguments(String::from(
+                "An animation needs at least 1 frame",
+            )));
+        }
+        if arguments.camera_keyframe.is_empty() {
+            return Err(RaybowError::InvalidArguments(String::from(
+                "An animation needs at least one `--camera-keyframe`",
+            )));
+        }
+        if arguments.output_path == "-" {
+            return Err(RaybowError::InvalidArguments(String::from(
+                "Animation frames cannot be written to the standard output",
+            )));
+        }
+        if arguments.accumulate {
+            log::warn!("Samples of animation frames cannot be accumulated, ignoring it");
+            arguments.accumulate = false;
+        }
+    } else if !arguments.camera_keyframe.is_empty() {
+        log::warn!("Camera keyframes are only used with `--animate`, ignoring them");
+    }
+    if let Some((x, y)) = arguments.debug_pixel {
+        if x >= arguments.output_width || y >= arguments.output_height {
+            return Err(RaybowError::InvalidArguments(format!(
+                "Pixel {},{} to debug is outside of the {}x{} image",
+                x, y, arguments.output_width, arguments.output_height
+            )));
+        }
+    }
+    if arguments.edge_anti_aliasing && arguments.accumulate {
+        log::warn!("Anti-aliasing only on edges cannot be accumulated, ignoring it");
+        arguments.edge_anti_aliasing = false;
+    }
+    if arguments.composite_over.is_some() && !arguments.alpha {
+        log::info!("Compositing needs the opacity of every pixel, so it is written as well");
+        arguments.alpha = true;
+    }
+    if arguments.preview_every.is_some() && arguments.output_path == "-" {
+        log::warn!("Previews cannot be written when writing to the standard output, ignoring it");
+        arguments.preview_every = None;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+impl Arguments {
+    /// Parses the options as if they were given on the command line (for tests)
+    ///
+    /// ## Parameters
+    /// * `options` - the options, without the program name
+    pub fn from_options(options: &[&str]) -> Self {
+        let mut arguments = Self::from_args(&["raybow-2"], options).expect("Invalid test options");
+        validate_arguments(&mut arguments).expect("Invalid test options");
+        arguments
+    }
+}
+
 /// Initializes logging (filtered by environmental variable `LOG_LEVEL`)
 fn init_logger(is_verbose: bool) {
     //let environment = env_logger::Env::default().filter("LOG_LEVEL");
     //env_logger::Builder::from_env(environment).init();
+    // Logs go to stderr, so they don't mix with an image written to stdout
     let mut builder = env_logger::Builder::new();
+    builder.target(env_logger::Target::Stderr);
     if is_verbose {
         builder.filter_level(log::LevelFilter::Debug);
     } else {
@@ -65,35 +404,254 @@ fn init_logger(is_verbose: bool) {
     builder.init();
 }
 
-fn main() -> Result<(), String> {
-    // Initialize and configure all basic stuff
+fn main() -> ExitCode {
     let arguments: Arguments = argh::from_env();
+    match run(arguments) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            ExitCode::from(err.exit_code())
+        }
+    }
+}
+
+/// Runs all stages of the program
+///
+/// ## Parameters
+/// * `arguments` - arguments parsed from the command line
+fn run(mut arguments: Arguments) -> Result<(), RaybowError> {
+    // Initialize and configure all basic stuff
+    if let Some(config_path) = arguments.config.clone() {
+        let command_line: Vec<String> = std::env::args().collect();
+        Config::load(Path::new(&config_path))
+            .and_then(|config| config.apply(&mut arguments, &command_line))?;
+    }
     init_logger(arguments.verbose);
+    if arguments.benchmark {
+        let benchmark_result = benchmark::run_benchmark()?;
+        println!("{}", benchmark_result);
+        return Ok(());
+    }
+    validate_arguments(&mut arguments)?;
+    if let Some(path) = &arguments.dump_scene {
+        let scene = match &arguments.scene {
+            Some(scene_path) => SceneFile::load(Path::new(scene_path))?,
+            None => preparation::default_scene(&arguments),
+        };
+        scene.save(Path::new(path))?;
+        log::info!("Scene written to {}", path);
+        return Ok(());
+    }
 
     let execution_time = Instant::now();
+    let mut timings = StageTimings::default();
 
     log::info!("Starting...");
 
     // ------ PREPARATION PASS ------ //
     log::info!("Preparing scene data...");
-    let scene_data = preparation::prepare_render_data(&arguments);
+    let stage_time = Instant::now();
+    let mut scene_data = preparation::prepare_render_data(&arguments)?;
+    timings.preparation = stage_time.elapsed();
+
+    // --- ACCELERATOR BUILD PASS --- //
+    log::info!("Building acceleration structure...");
+    let stage_time = Instant::now();
+    scene_data
+        .renderables
+        .build_accelerator(arguments.accelerator);
+    if arguments.show_bvh && !scene_data.renderables.show_bvh() {
+        log::warn!("There is no bounding volume hierarchy to show, ignoring `--show-bvh`");
+    }
+    timings.accelerator_build = stage_time.elapsed();
+
+    if let Some((x, y)) = arguments.debug_pixel {
+        let path = rendering::render::trace_pixel_path(x, y, &arguments, &scene_data);
+        println!("{}", path);
+        if arguments.output_path != "-" {
+            let obj_path = format!("{}_path.obj", arguments.output_path);
+            std::fs::write(&obj_path, path.to_obj())?;
+            log::info!("Path written to {}", obj_path);
+        }
+        return Ok(());
+    }
 
+    // -------- RENDER PASS -------- //
+    match arguments.animate {
+        Some(frame_count) => {
+            let up_axis = arguments.up_axis;
+            let keyframes = &arguments.camera_keyframe;
+            let path = CameraPath::new(
+                keyframes
+                    .iter()
+                    .map(|keyframe| up_axis.to_y_up(keyframe.position))
+                    .collect(),
+                keyframes
+                    .iter()
+                    .map(|keyframe| up_axis.to_y_up(keyframe.look_at))
+                    .collect(),
+            );
+            let base_path = arguments.output_path.clone();
+            for frame in 0..frame_count {
+                log::info!("Frame {} of {}", frame + 1, frame_count);
+                let t = CameraPath::frame_parameter(frame, frame_count);
+                path.apply(&mut scene_data.camera, t);
+                arguments.output_path = format!("{}_{:04}", base_path, frame);
+                render_frame(&arguments, &scene_data, &mut timings)?;
+            }
+        }
+        None => render_frame(&arguments, &scene_data, &mut timings)?,
+    }
+
+    // Finalize and close everything
+    let execution_duration = execution_time.elapsed();
+    log::debug!("Stage timings: {}", timings);
+    log::debug!("Done in {:.2?}", execution_duration);
+
+    log::info!("Exit");
+    Ok(())
+}
+
+/// Renders the image, postprocesses it and writes it to files
+///
+/// ## Parameters
+/// * `arguments` - arguments parsed from the command line
+/// * `scene_data` - the prepared scene, with its acceleration structure
+/// * `timings` - receives the durations of the stages (added to the earlier ones)
+fn render_frame(
+    arguments: &Arguments,
+    scene_data: &SceneData,
+    timings: &mut StageTimings,
+) -> Result<(), RaybowError> {
     // -------- RENDER PASS -------- //
     log::info!("Rendering...");
-    let render_result = rendering::render::render(&arguments, scene_data);
+    let stage_time = Instant::now();
+    let history_path = format!("{}.samples", arguments.output_path);
+    let history = if arguments.accumulate {
+        RenderHistory::load(Path::new(&history_path))?
+    } else {
+        None
+    };
+    // The render continues after the earlier samples, so that they are not repeated
+    let first_sample = history.as_ref().map_or(0, |history| history.sample_count());
+    if history.is_some() {
+        log::info!("Accumulating into {} earlier samples", first_sample);
+    }
+
+    let mut render_result = rendering::render::render(arguments, scene_data, first_sample);
+    if let Some(history) = &history {
+        history.merge_into(&mut render_result)?;
+    }
+    if arguments.accumulate {
+        RenderHistory::from_result(&render_result).save(Path::new(&history_path))?;
+    }
+    timings.rendering += stage_time.elapsed();
+    log::debug!("{:?}", render_result);
 
     // ------ POSTPROCESSING ------- //
     log::info!("Postprocessing...");
-    let postprocessing_result = postprocessing::postprocess(&arguments, &render_result);
+    let stage_time = Instant::now();
+    let mut postprocessing_result = postprocessing::postprocess(arguments, &render_result);
+    if let Some(path) = &arguments.composite_over {
+        postprocessing::composite_over_file(
+            arguments,
+            &mut postprocessing_result,
+            Path::new(path),
+        )?;
+    }
+    timings.postprocessing += stage_time.elapsed();
 
     // -------- EXPORT PASS -------- //
     log::info!("Writing to files...");
-    export::export_to_file(&arguments, &postprocessing_result).map_err(|err| err.to_string())?;
-
-    // Finalize and close everything
-    let execution_duration = execution_time.elapsed();
-    log::debug!("Done in {:.2?}", execution_duration);
+    let stage_time = Instant::now();
+    export::export_to_file(arguments, &postprocessing_result)?;
+    if arguments.term_preview {
+        export::print_terminal_preview(arguments, &postprocessing_result)?;
+    }
+    timings.export += stage_time.elapsed();
 
-    log::info!("Exit");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses the options and validates them, returning the validation error
+    fn validated(options: &[&str]) -> Result<Arguments, RaybowError> {
+        let mut arguments =
+            Arguments::from_args(&["raybow-2"], options).expect("Options should parse");
+        validate_arguments(&mut arguments)?;
+        Ok(arguments)
+    }
+
+    #[test]
+    fn zero_samples_are_rejected() {
+        let result = validated(&["--samples-per-pixel", "0"]);
+        assert!(matches!(result, Err(RaybowError::InvalidArguments(_))));
+    }
+
+    #[test]
+    fn zero_steps_render_a_valid_image() {
+        let arguments = validated(&[
+            "--steps",
+            "0",
+            "--output-width",
+            "4",
+            "--output-height",
+            "3",
+            "--seed",
+            "1",
+        ])
+        .unwrap();
+        assert_eq!(arguments.steps, 1);
+
+        let mut scene_data = preparation::prepare_render_data(&arguments).unwrap();
+        scene_data
+            .renderables
+            .build_accelerator(arguments.accelerator);
+        let render_result = rendering::render::render(&arguments, &scene_data, 0);
+        assert!(render_result.image_data.iter().all(|color| {
+            color.r().is_finite() && color.g().is_finite() && color.b().is_finite()
+        }));
+    }
+
+    #[test]
+    fn missing_scene_file_is_an_io_error() {
+        let arguments = validated(&["--scene", "missing-directory/missing-scene.toml"]).unwrap();
+        let result = preparation::prepare_render_data(&arguments);
+        let err = result.err().expect("Missing scene should not load");
+        assert!(
+            matches!(
+                err,
+                RaybowError::Io(ref io_error) if io_error.kind() == std::io::ErrorKind::NotFound
+            ),
+            "{}",
+            err
+        );
+        assert_eq!(err.exit_code(), 1);
+    }
+
+    #[test]
+    fn malformed_scene_file_is_a_scene_parse_error() {
+        let path =
+            std::env::temp_dir().join(format!("raybow-malformed-{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            "background = \"sky\"\n[camera]\nposition = [0.0, 0.0]\n",
+        )
+        .unwrap();
+        let arguments = validated(&["--scene", path.to_str().unwrap()]).unwrap();
+        let result = preparation::prepare_render_data(&arguments);
+        std::fs::remove_file(&path).unwrap();
+
+        let err = result.err().expect("Malformed scene should not load");
+        assert!(matches!(err, RaybowError::SceneParse(_)), "{}", err);
+        assert!(
+            err.to_string().starts_with("Invalid scene file: "),
+            "{}",
+            err
+        );
+        assert_eq!(err.exit_code(), 2);
+    }
+}
@@ -0,0 +1,79 @@
+use rand_xoshiro::Xoshiro256Plus;
+
+use crate::{color::RGBColor, objects::HitRecord, ray::Ray};
+
+use super::{Material, MaterialScatterOutput};
+
+/// Emissive material whose light changes while the shutter is open
+///
+/// The emitted color is interpolated between the start and the end color
+/// based on the time of the incoming ray.
+pub struct AnimatedEmissive {
+    start_emit: RGBColor,
+    end_emit: RGBColor,
+}
+
+impl AnimatedEmissive {
+    /// Creates a new animated emissive material
+    ///
+    /// ## Parameters
+    /// * `start_emit` - emitted light at time `0.0`
+    /// * `end_emit` - emitted light at time `1.0`
+    pub fn new(start_emit: RGBColor, end_emit: RGBColor) -> Self {
+        Self {
+            start_emit,
+            end_emit,
+        }
+    }
+}
+
+impl Material for AnimatedEmissive {
+    fn scatter(
+        &self,
+        _incoming_ray: &Ray,
+        _hit_record: &HitRecord,
+        _rng: &mut Xoshiro256Plus,
+    ) -> Option<MaterialScatterOutput> {
+        None
+    }
+
+    fn emitted(&self, incoming_ray: &Ray, _hit_record: &HitRecord) -> RGBColor {
+        let time = incoming_ray.time().clamp(0.0, 1.0);
+        RGBColor::lerp(self.start_emit, self.end_emit, time)
+    }
+
+    fn is_emissive(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3A;
+
+    use crate::materials::lambertarian::LambertarianDiffuse;
+
+    use super::*;
+
+    #[test]
+    fn emission_follows_the_ray_time() {
+        let start = RGBColor::new(1.0, 0.0, 0.5);
+        let end = RGBColor::new(3.0, 2.0, 0.5);
+        let material = AnimatedEmissive::new(start, end);
+        let hit_record = HitRecord::new(
+            Vec3A::ZERO,
+            Vec3A::Y,
+            1.0,
+            true,
+            LambertarianDiffuse::new_counter(RGBColor::white()),
+        );
+        let emitted_at = |time: f32| {
+            let ray = Ray::new_at_time(Vec3A::Y, -Vec3A::Y, time);
+            material.emitted(&ray, &hit_record)
+        };
+
+        assert!(emitted_at(0.0) == start);
+        assert!(emitted_at(1.0) == end);
+        assert!(emitted_at(0.5) == RGBColor::new(2.0, 1.0, 0.5));
+    }
+}
@@ -1,35 +1,182 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
+use glam::Vec3A;
+use rand::Rng;
 use rand_xoshiro::Xoshiro256Plus;
 
 use crate::{
     color::RGBColor,
-    math::{reflect_vec3, refract_vec3},
+    math::{normalize_vec3, random_unit_vec3, reflect_vec3, refract_vec3},
     objects::HitRecord,
     ray::Ray,
 };
 
-use super::Material;
+use super::{
+    stats::{record_scatter, ScatterEvent, ScatteringMaterialKind},
+    AnyMaterial, Material,
+};
 
 /// Dielectric material where rays bounce off the surface
 /// or enter the objects refracted
 ///
 /// Used for water, glass, ...
+///
+/// Light travelling through the material can be absorbed (Beer-Lambert law),
+/// which gives colored glass its tint. Thicker parts of the object absorb more light.
+///
+/// In spectral rendering, the index of refraction can depend on the wavelength (dispersion),
+/// which splits white light into a rainbow.
+///
+/// A rough surface (frosted glass) scatters reflected and refracted rays around
+/// their ideal directions, which blurs everything seen through the material.
 pub struct Dielectric {
     index_of_refraction: f32,
+    absorption: RGBColor,
+    dispersion: f32,        // Cauchy's B coefficient (in square micrometers)
+    roughness: f32,         // How much scattered rays are displaced
+    accurate_fresnel: bool, // Whether to use the full Fresnel equations instead of Schlick's approximation
 }
 
 impl Dielectric {
     pub fn new(index_of_refraction: f32) -> Self {
         Self {
             index_of_refraction,
+            absorption: RGBColor::black(),
+            dispersion: 0.0,
+            roughness: 0.0,
+            accurate_fresnel: false,
         }
     }
 
-    pub fn new_counter(index_of_refraction: f32) -> Rc<Box<dyn Material>> {
-        let dielectric = Self::new(index_of_refraction);
-        let dielectric_box: Box<dyn Material> = Box::new(dielectric);
-        Rc::new(dielectric_box)
+    /// Sets whether the reflectance is calculated with the full Fresnel equations
+    ///
+    /// Schlick's approximation (the default) is faster, but slightly off
+    /// at large differences between the indices of refraction.
+    ///
+    /// ## Parameters
+    /// * `accurate_fresnel` - true for the full Fresnel equations, false for Schlick's approximation
+    pub fn set_accurate_fresnel(&mut self, accurate_fresnel: bool) {
+        self.accurate_fresnel = accurate_fresnel;
+    }
+
+    /// Calculates the reflectance with the formula selected for the material
+    ///
+    /// ## Parameters
+    /// * `cosine` - cosine of the angle at which the ray hits the surface
+    /// * `k` - ratio of refraction indices
+    fn surface_reflectance(&self, cosine: f32, k: f32) -> f32 {
+        if self.accurate_fresnel {
+            Dielectric::fresnel_reflectance(cosine, k)
+        } else {
+            Dielectric::reflectance(cosine, k)
+        }
+    }
+
+    /// Sets the roughness of the surface (frosted glass)
+    ///
+    /// Like with `Metal`, the reflected and refracted directions are shifted
+    /// by a random unit vector scaled by the roughness.
+    ///
+    /// ## Parameters
+    /// * `roughness` - 0.0 means clear glass, 1.0 means very rough glass
+    pub fn set_roughness(&mut self, roughness: f32) {
+        self.roughness = roughness.max(0.0);
+    }
+
+    /// Shifts the scattered direction randomly by the roughness
+    ///
+    /// If the shift would move the direction to the other side of the surface
+    /// (turning a reflection into a refraction or the other way around),
+    /// the ideal direction is kept.
+    ///
+    /// ## Parameters
+    /// * `direction` - the ideal (unit) direction of the scattered ray
+    /// * `normal` - normal of the surface
+    /// * `rng` - random number generator
+    fn roughen(&self, direction: Vec3A, normal: Vec3A, rng: &mut Xoshiro256Plus) -> Vec3A {
+        if self.roughness <= 0.0 {
+            return direction;
+        }
+        let shifted = direction + self.roughness * random_unit_vec3(rng);
+        let same_side = shifted.dot(normal) * direction.dot(normal) > 0.0;
+        if same_side {
+            normalize_vec3(shifted)
+        } else {
+            direction
+        }
+    }
+
+    /// Sets how much the index of refraction changes with the wavelength (Cauchy's equation)
+    ///
+    /// The index of refraction of the material is kept at the sodium D line (589.3 nm),
+    /// shorter wavelengths are refracted more. Only has an effect in spectral rendering.
+    /// Common glass has a coefficient around `0.004`, dense flint glass around `0.01`.
+    ///
+    /// ## Parameters
+    /// * `dispersion` - Cauchy's B coefficient in square micrometers (0 means no dispersion)
+    pub fn set_dispersion(&mut self, dispersion: f32) {
+        self.dispersion = dispersion;
+    }
+
+    /// Returns the index of refraction for the wavelength carried by the ray
+    ///
+    /// ## Parameters
+    /// * `incoming_ray` - the ray that hits the surface
+    fn index_of_refraction_for(&self, incoming_ray: &Ray) -> f32 {
+        match incoming_ray.wavelength() {
+            Some(wavelength) if self.dispersion != 0.0 => {
+                const SODIUM_D_LINE: f32 = 0.5893;
+                let inverse_square = |micrometers: f32| 1.0 / (micrometers * micrometers);
+                self.index_of_refraction
+                    + self.dispersion
+                        * (inverse_square(wavelength.micrometers()) - inverse_square(SODIUM_D_LINE))
+            }
+            _ => self.index_of_refraction,
+        }
+    }
+
+    /// Sets how much light of each color is absorbed per unit of distance inside the material
+    ///
+    /// Black (the default) means the material is perfectly clear.
+    /// For green bottle glass, red and blue light should be absorbed more than green.
+    ///
+    /// ## Parameters
+    /// * `absorption` - absorption coefficient of each color channel
+    pub fn set_absorption(&mut self, absorption: RGBColor) {
+        self.absorption = absorption;
+    }
+
+    /// Calculates the fraction of light that is not absorbed
+    /// on the way to the hit point
+    ///
+    /// Only rays leaving the object (hitting the back face) travelled through the material.
+    ///
+    /// ## Parameters
+    /// * `incoming_ray` - the ray that hits the surface
+    /// * `hit_record` - the record of the current hit
+    fn transmittance(&self, incoming_ray: &Ray, hit_record: &HitRecord) -> RGBColor {
+        if hit_record.front_face() || self.absorption == RGBColor::black() {
+            return RGBColor::white();
+        }
+
+        let distance = hit_record.t() * incoming_ray.direction().length();
+        RGBColor::new(
+            (-self.absorption.r() * distance).exp(),
+            (-self.absorption.g() * distance).exp(),
+            (-self.absorption.b() * distance).exp(),
+        )
+    }
+
+    /// Creates a new Dielectric material
+    /// and returns reference counter of this material.
+    /// The instance is generalized to all Materials.
+    ///
+    /// This is a helper function in creation of the Material.
+    ///
+    /// ## Parameters
+    /// * `index_of_refraction` - index of refraction of the material (1.5 for glass)
+    pub fn new_counter(index_of_refraction: f32) -> Arc<AnyMaterial> {
+        Arc::new(Self::new(index_of_refraction).into())
     }
 
     /// Calculates the reflectance at the angle at which the ray hits the surface
@@ -43,23 +190,49 @@ impl Dielectric {
         let r0_2 = r0 * r0;
         r0_2 + (1.0 - r0_2) * (1.0 - cosine).powi(5)
     }
+
+    /// Calculates the reflectance with the full Fresnel equations
+    ///
+    /// Unpolarized light is assumed, so the reflectances of s- and p-polarized
+    /// light are averaged. Returns 1.0 if the light is totally reflected.
+    ///
+    /// ## Parameters
+    /// * `cosine` - cosine of the angle at which the ray hits the surface
+    /// * `k` - ratio of refraction indices
+    pub fn fresnel_reflectance(cosine: f32, k: f32) -> f32 {
+        let sin_transmitted_2 = k * k * (1.0 - cosine * cosine);
+        if sin_transmitted_2 >= 1.0 {
+            return 1.0;
+        }
+        let cos_transmitted = (1.0 - sin_transmitted_2).sqrt();
+        let s_polarized = (k * cosine - cos_transmitted) / (k * cosine + cos_transmitted);
+        let p_polarized = (cosine - k * cos_transmitted) / (cosine + k * cos_transmitted);
+        (s_polarized * s_polarized + p_polarized * p_polarized) / 2.0
+    }
 }
 
 impl Material for Dielectric {
+    fn index_of_refraction(&self, incoming_ray: &Ray) -> Option<f32> {
+        Some(self.index_of_refraction_for(incoming_ray))
+    }
+
     fn scatter(
         &self,
         incoming_ray: &Ray,
         hit_record: &HitRecord,
-        _rng: &mut Xoshiro256Plus,
+        rng: &mut Xoshiro256Plus,
     ) -> Option<super::MaterialScatterOutput> {
-        let attenuation = RGBColor::new(1.0, 1.0, 1.0);
+        let attenuation = self.transmittance(incoming_ray, hit_record);
+        let index_of_refraction = self.index_of_refraction_for(incoming_ray);
+        // The medium around the object may also refract the light (e.g. water around glass)
+        let outside_index_of_refraction = incoming_ray.outside_index_of_refraction();
         let refraction_ratio = if hit_record.front_face() {
-            1.0 / self.index_of_refraction
+            outside_index_of_refraction / index_of_refraction
         } else {
-            self.index_of_refraction
+            index_of_refraction / outside_index_of_refraction
         };
 
-        let unit_direction = incoming_ray.direction().normalize();
+        let unit_direction = normalize_vec3(incoming_ray.direction());
 
         let cos_theta = -unit_direction.dot(hit_record.normal()).min(1.0);
         let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
@@ -69,19 +242,248 @@ impl Material for Dielectric {
         // them instead.
         // Theta is the angle between incoming direction and normal.
         let cannot_refract = refraction_ratio * sin_theta > 1.0;
-        let randomly_reflects =
-            Dielectric::reflectance(cos_theta, refraction_ratio) > rand::random();
+        let randomly_reflects = self.surface_reflectance(cos_theta, refraction_ratio) > rng.gen();
 
         let direction = if cannot_refract || randomly_reflects {
+            record_scatter(ScatteringMaterialKind::Dielectric, ScatterEvent::Reflected);
             reflect_vec3(unit_direction, hit_record.normal())
         } else {
+            record_scatter(ScatteringMaterialKind::Dielectric, ScatterEvent::Refracted);
             refract_vec3(unit_direction, hit_record.normal(), refraction_ratio)
         };
+        let direction = self.roughen(direction, hit_record.normal(), rng);
 
-        let scattered_ray = Ray::new(hit_record.point(), direction);
+        let scattered_ray = Ray::new_at_time(hit_record.point(), direction, incoming_ray.time());
         Some(super::MaterialScatterOutput {
             scattered_ray,
             attenuation,
         })
     }
+
+    fn transmit(
+        &self,
+        incoming_ray: &Ray,
+        hit_record: &HitRecord,
+    ) -> Option<super::MaterialScatterOutput> {
+        let index_of_refraction = self.index_of_refraction_for(incoming_ray);
+        // The medium around the object may also refract the light (e.g. water around glass)
+        let outside_index_of_refraction = incoming_ray.outside_index_of_refraction();
+        let refraction_ratio = if hit_record.front_face() {
+            outside_index_of_refraction / index_of_refraction
+        } else {
+            index_of_refraction / outside_index_of_refraction
+        };
+
+        let unit_direction = normalize_vec3(incoming_ray.direction());
+        let cos_theta = -unit_direction.dot(hit_record.normal()).min(1.0);
+        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+
+        // Totally reflected light never passes through
+        if refraction_ratio * sin_theta > 1.0 {
+            return None;
+        }
+
+        // Only the light that is not reflected passes through
+        let transmitted_fraction = 1.0 - self.surface_reflectance(cos_theta, refraction_ratio);
+        let direction = refract_vec3(unit_direction, hit_record.normal(), refraction_ratio);
+        let scattered_ray = Ray::new_at_time(hit_record.point(), direction, incoming_ray.time());
+        Some(super::MaterialScatterOutput {
+            scattered_ray,
+            attenuation: self.transmittance(incoming_ray, hit_record) * transmitted_fraction,
+        })
+    }
+
+    fn is_specular(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn scattered_rays_follow_reflection_and_snells_law() {
+        let glass = Dielectric::new(1.5);
+        let hit_record = HitRecord::new(
+            Vec3A::ZERO,
+            Vec3A::Y,
+            1.0,
+            true,
+            Dielectric::new_counter(1.5),
+        );
+        let mut rng = Xoshiro256Plus::seed_from_u64(9);
+        let incident_directions = [
+            Vec3A::new(0.0, -1.0, 0.0),
+            Vec3A::new(1.0, -1.0, 0.0),
+            Vec3A::new(0.3, -2.0, 0.7),
+            Vec3A::new(4.0, -1.0, -1.0),
+        ];
+
+        for direction in incident_directions {
+            let unit_direction = direction.normalize();
+            let ray = Ray::new(Vec3A::new(0.0, 1.0, 0.0), direction);
+            let reflected = reflect_vec3(unit_direction, Vec3A::Y);
+            let refracted = refract_vec3(unit_direction, Vec3A::Y, 1.0 / 1.5);
+
+            // Both directions are in the plane of incidence, mirrored or bent by Snell's law
+            let sin_incident = unit_direction.cross(Vec3A::Y).length();
+            assert!((reflected.y + unit_direction.y).abs() < 1e-6);
+            assert!((refracted.cross(Vec3A::Y).length() - sin_incident / 1.5).abs() < 1e-5);
+
+            for _ in 0..20 {
+                let output = glass.scatter(&ray, &hit_record, &mut rng).unwrap();
+                let scattered = output.scattered_ray.direction();
+                assert!(
+                    scattered.abs_diff_eq(reflected, 1e-6)
+                        || scattered.abs_diff_eq(refracted, 1e-6),
+                    "{} scattered to {}",
+                    direction,
+                    scattered
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn same_seed_gives_same_scattering() {
+        let mut frosted_glass = Dielectric::new(1.5);
+        frosted_glass.set_roughness(0.2);
+        let hit_record = HitRecord::new(
+            Vec3A::ZERO,
+            Vec3A::Y,
+            1.0,
+            true,
+            Dielectric::new_counter(1.5),
+        );
+        // At a grazing angle, a good part of the light is reflected
+        let ray = Ray::new(Vec3A::new(-5.0, 1.0, 0.0), Vec3A::new(5.0, -1.0, 0.0));
+        let scattered_directions = |seed: u64| -> Vec<Vec3A> {
+            let mut rng = Xoshiro256Plus::seed_from_u64(seed);
+            (0..200)
+                .map(|_| {
+                    let output = frosted_glass.scatter(&ray, &hit_record, &mut rng).unwrap();
+                    output.scattered_ray.direction()
+                })
+                .collect()
+        };
+
+        let directions = scattered_directions(4);
+        assert_eq!(directions, scattered_directions(4));
+        assert_ne!(directions, scattered_directions(5));
+        let reflected = directions
+            .iter()
+            .filter(|direction| direction.y > 0.0)
+            .count();
+        assert!(
+            reflected > 0 && reflected < directions.len(),
+            "{}",
+            reflected
+        );
+    }
+
+    #[test]
+    fn longer_paths_inside_glass_absorb_more() {
+        let mut green_glass = Dielectric::new(1.5);
+        green_glass.set_absorption(RGBColor::new(2.0, 0.2, 2.0));
+        let ray = Ray::new(Vec3A::ZERO, Vec3A::new(0.0, 0.0, -2.0));
+        // The ray leaves the glass, so it travelled through it up to the hit
+        let exit = |t: f32| {
+            let mut hit_record =
+                HitRecord::new(ray.at(t), Vec3A::Z, t, false, Dielectric::new_counter(1.5));
+            hit_record.set_face_normal(&ray, -Vec3A::Z);
+            green_glass.transmittance(&ray, &hit_record)
+        };
+
+        let short = exit(0.1);
+        let long = exit(1.0);
+        assert!(long.r() < short.r() && long.g() < short.g() && long.b() < short.b());
+        assert!(long.g() / long.r() > short.g() / short.r());
+        // exp(-absorption * distance), the distance is 0.2
+        assert!((short.r() - (-0.4f32).exp()).abs() < 1e-6);
+
+        // Entering rays travelled outside of the glass
+        let mut entry = HitRecord::new(
+            Vec3A::ZERO,
+            Vec3A::Z,
+            1.0,
+            true,
+            Dielectric::new_counter(1.5),
+        );
+        entry.set_face_normal(&ray, Vec3A::Z);
+        assert!(green_glass.transmittance(&ray, &entry) == RGBColor::white());
+    }
+
+    #[test]
+    fn roughness_spreads_the_scattered_rays() {
+        let hit_record = HitRecord::new(
+            Vec3A::ZERO,
+            Vec3A::Y,
+            1.0,
+            true,
+            Dielectric::new_counter(1.5),
+        );
+        let ray = Ray::new(Vec3A::new(-1.0, 2.0, 0.0), Vec3A::new(1.0, -2.0, 0.0));
+        let scattered_directions = |glass: &Dielectric| -> Vec<Vec3A> {
+            let mut rng = Xoshiro256Plus::seed_from_u64(8);
+            (0..200)
+                .map(|_| {
+                    let output = glass.scatter(&ray, &hit_record, &mut rng).unwrap();
+                    output.scattered_ray.direction().normalize()
+                })
+                .collect()
+        };
+
+        let clear_glass = Dielectric::new(1.5);
+        let mut smooth_glass = Dielectric::new(1.5);
+        smooth_glass.set_roughness(0.0);
+        let clear = scattered_directions(&clear_glass);
+        assert_eq!(scattered_directions(&smooth_glass), clear);
+
+        let mut frosted_glass = Dielectric::new(1.5);
+        frosted_glass.set_roughness(0.3);
+        let refracted = refract_vec3(ray.direction().normalize(), Vec3A::Y, 1.0 / 1.5);
+        let spread: Vec<f32> = scattered_directions(&frosted_glass)
+            .iter()
+            .filter(|direction| direction.y < 0.0)
+            .map(|direction| direction.dot(refracted))
+            .collect();
+        assert!(spread.len() > 100);
+        assert!(spread.iter().all(|cosine| *cosine > 0.8));
+        let blurred = spread.iter().filter(|cosine| **cosine < 0.999).count();
+        assert!(blurred * 2 > spread.len(), "{}", blurred);
+    }
+
+    #[test]
+    fn schlick_and_fresnel_agree_at_normal_incidence() {
+        for k in [1.0 / 1.5, 1.5, 1.0 / 2.4] {
+            let schlick = Dielectric::reflectance(1.0, k);
+            let fresnel = Dielectric::fresnel_reflectance(1.0, k);
+            assert!(
+                (schlick - fresnel).abs() < 1e-6,
+                "{} != {}",
+                schlick,
+                fresnel
+            );
+        }
+        // About 4% of light is reflected by glass
+        assert!((Dielectric::fresnel_reflectance(1.0, 1.0 / 1.5) - 0.04).abs() < 1e-6);
+    }
+
+    #[test]
+    fn fresnel_reflects_everything_at_grazing_incidence() {
+        let k = 1.0 / 1.5;
+        let fresnel = Dielectric::fresnel_reflectance(1e-4, k);
+        let schlick = Dielectric::reflectance(1e-4, k);
+        assert!(fresnel > 0.999, "{}", fresnel);
+        assert!(
+            (schlick - fresnel).abs() < 1e-3,
+            "{} != {}",
+            schlick,
+            fresnel
+        );
+        assert_eq!(Dielectric::fresnel_reflectance(0.0, k), 1.0);
+    }
 }
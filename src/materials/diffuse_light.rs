@@ -0,0 +1,207 @@
+use std::{f32::consts::PI, sync::Arc};
+
+use rand_xoshiro::Xoshiro256Plus;
+
+use crate::{
+    color::RGBColor,
+    objects::HitRecord,
+    ray::Ray,
+    textures::{AnyTexture, Texture},
+};
+
+use super::{Material, MaterialScatterOutput};
+
+/// Emissive material that acts as a light source
+///
+/// It does not scatter incoming rays. The emitted light is the same everywhere,
+/// unless an emission texture is set (e.g. for a TV screen).
+/// By default the light is emitted from both sides of the surface.
+pub struct DiffuseLight {
+    emit: RGBColor,
+    emit_texture: Option<Arc<AnyTexture>>, // Color of the emitted light at each point of the surface
+    two_sided: bool,
+}
+
+impl DiffuseLight {
+    /// Creates a new diffuse light
+    ///
+    /// ## Parameters
+    /// * `emit` - color (and intensity) of the emitted light, can be larger than `1.0`
+    pub fn new(emit: RGBColor) -> Self {
+        Self {
+            emit,
+            emit_texture: None,
+            two_sided: true,
+        }
+    }
+
+    /// Creates a new diffuse light with the total emitted power instead of the radiance
+    ///
+    /// Emitted radiance is given per unit of area, so a larger light with the same material
+    /// is brighter overall. Here the power is divided by the area of the surface,
+    /// so resizing the light (and updating the area) keeps its total output the same.
+    /// A two-sided light splits the power between both sides of the surface.
+    ///
+    /// ## Parameters
+    /// * `power` - total emitted power of each color channel
+    /// * `area` - area of the surface the material is used on
+    ///   (see `Parallelogram::area_of` and `Sphere::area_of`)
+    /// * `two_sided` - whether the light is emitted from both sides (see `set_two_sided`)
+    pub fn from_power(power: RGBColor, area: f32, two_sided: bool) -> Self {
+        // A diffuse surface with radiance L emits L * area * PI from each side
+        let sides = if two_sided { 2.0 } else { 1.0 };
+        let mut light = Self::new(power / (PI * area * sides));
+        light.set_two_sided(two_sided);
+        light
+    }
+
+    /// Creates a new diffuse light that emits the colors of the texture
+    ///
+    /// ## Parameters
+    /// * `texture` - color of the emitted light at each point of the surface
+    /// * `intensity` - multiplier of the emitted light
+    pub fn from_texture<T>(texture: T, intensity: f32) -> Self
+    where
+        T: Into<Arc<AnyTexture>>,
+    {
+        let mut light = Self::new(RGBColor::white() * intensity);
+        light.set_emit_texture(texture);
+        light
+    }
+
+    /// Creates a new diffuse light with the color of a black body
+    ///
+    /// ## Parameters
+    /// * `temperature` - color temperature in Kelvins (e.g. 3200 for tungsten, 6500 for daylight)
+    /// * `intensity` - multiplier of the emitted light
+    pub fn from_kelvin(temperature: f32, intensity: f32) -> Self {
+        Self::new(RGBColor::from_kelvin(temperature) * intensity)
+    }
+
+    /// Sets the texture that determines the color of the emitted light at each point of the surface
+    ///
+    /// The texture color is multiplied by the emitted color of the light,
+    /// which then acts as its intensity.
+    ///
+    /// ## Parameters
+    /// * `texture` - color of the emitted light
+    pub fn set_emit_texture<T>(&mut self, texture: T)
+    where
+        T: Into<Arc<AnyTexture>>,
+    {
+        self.emit_texture = Some(texture.into());
+    }
+
+    /// Sets whether the light is emitted from both sides of the surface
+    ///
+    /// One-sided lights only emit in the direction of the outward normal
+    /// (e.g. a ceiling panel that only lights the room below).
+    pub fn set_two_sided(&mut self, two_sided: bool) {
+        self.two_sided = two_sided;
+    }
+}
+
+impl Material for DiffuseLight {
+    fn scatter(
+        &self,
+        _incoming_ray: &Ray,
+        _hit_record: &HitRecord,
+        _rng: &mut Xoshiro256Plus,
+    ) -> Option<MaterialScatterOutput> {
+        None
+    }
+
+    fn emitted(&self, _incoming_ray: &Ray, hit_record: &HitRecord) -> RGBColor {
+        if !self.two_sided && !hit_record.front_face() {
+            return RGBColor::black();
+        }
+
+        match &self.emit_texture {
+            Some(texture) => {
+                self.emit * texture.value(hit_record.u(), hit_record.v(), hit_record.point())
+            }
+            None => self.emit,
+        }
+    }
+
+    fn is_emissive(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3A;
+
+    use crate::{input_formats::ImportedImage, textures::image::ImageTexture};
+
+    use super::*;
+
+    fn emitted_at_uv(light: &DiffuseLight, u: f32, v: f32) -> RGBColor {
+        let ray = Ray::new(Vec3A::ZERO, Vec3A::NEG_Z);
+        let mut hit_record = HitRecord::new(
+            Vec3A::NEG_Z,
+            Vec3A::Z,
+            1.0,
+            true,
+            DiffuseLight::new(RGBColor::black()).into(),
+        );
+        hit_record.set_uv(u, v);
+        light.emitted(&ray, &hit_record)
+    }
+
+    fn emitted_from_side(light: &DiffuseLight, front_face: bool) -> RGBColor {
+        let ray = Ray::new(Vec3A::ZERO, Vec3A::NEG_Z);
+        let hit_record = HitRecord::new(
+            Vec3A::NEG_Z,
+            Vec3A::Z,
+            1.0,
+            front_face,
+            DiffuseLight::new(RGBColor::black()).into(),
+        );
+        light.emitted(&ray, &hit_record)
+    }
+
+    #[test]
+    fn one_sided_light_is_dark_from_behind() {
+        let color = RGBColor::new(4.0, 3.0, 2.0);
+        let mut one_sided = DiffuseLight::new(color);
+        one_sided.set_two_sided(false);
+        assert!(emitted_from_side(&one_sided, true) == color);
+        assert!(emitted_from_side(&one_sided, false) == RGBColor::black());
+
+        let two_sided = DiffuseLight::new(color);
+        assert!(emitted_from_side(&two_sided, false) == color);
+    }
+
+    #[test]
+    fn checker_texture_emits_both_colors() {
+        let (red, blue) = (RGBColor::new(1.0, 0.0, 0.0), RGBColor::new(0.0, 0.0, 1.0));
+        let checker = ImageTexture::new(ImportedImage {
+            width: 2,
+            height: 2,
+            image_data: vec![red, blue, blue, red],
+        });
+        let light = DiffuseLight::from_texture(checker, 3.0);
+
+        // The top row of the image is at v = 1
+        assert!(emitted_at_uv(&light, 0.25, 0.75) == red * 3.0);
+        assert!(emitted_at_uv(&light, 0.75, 0.75) == blue * 3.0);
+        assert!(emitted_at_uv(&light, 0.25, 0.25) == blue * 3.0);
+        assert!(emitted_at_uv(&light, 0.75, 0.25) == red * 3.0);
+    }
+
+    #[test]
+    fn larger_light_with_the_same_power_is_dimmer() {
+        let power = RGBColor::new(8.0, 4.0, 2.0);
+        let small = emitted_from_side(&DiffuseLight::from_power(power, 1.0, true), true);
+        let large = emitted_from_side(&DiffuseLight::from_power(power, 2.0, true), true);
+        assert!(large == small / 2.0, "{:?} != {:?}", large, small / 2.0);
+
+        // A one-sided light puts all of its power into the front side
+        let one_sided = DiffuseLight::from_power(power, 1.0, false);
+        assert!(emitted_from_side(&one_sided, true) == small * 2.0);
+        assert!(emitted_from_side(&one_sided, false) == RGBColor::black());
+        assert!(small == power / PI / 2.0, "{:?}", small);
+    }
+}
@@ -1,21 +1,29 @@
-use std::sync::Arc;
+use std::{f32::consts::PI, sync::Arc};
 
 use rand_xoshiro::Xoshiro256Plus;
 
 use crate::{
     color::RGBColor,
-    math::{is_invalid_vec3, random_vec3_on_unit_sphere},
+    math::{is_invalid_vec3, normalize_vec3, random_unit_vec3},
     objects::HitRecord,
     ray::Ray,
+    textures::{AnyTexture, Texture},
 };
 
-use super::{Material, MaterialScatterOutput};
+use super::{AnyMaterial, Material, MaterialScatterOutput};
+
+/// Opacity below which a surface with an alpha texture is cut out
+const ALPHA_CUTOUT_THRESHOLD: f32 = 0.5;
 
 /// Lambertarian diffuse material
 ///
 /// Works by sending rays in random directions from point of contact.
+///
+/// An alpha texture cuts holes into the surface, so that e.g. foliage or fences
+/// can be made from textured parallelograms.
 pub struct LambertarianDiffuse {
     albedo: RGBColor,
+    alpha_texture: Option<Arc<AnyTexture>>, // Opacity at each point of the surface (its luminance)
 }
 
 impl LambertarianDiffuse {
@@ -24,30 +32,49 @@ impl LambertarianDiffuse {
     /// ## Parameters
     /// * `albedo` - albedo color of the material
     pub fn new(albedo: RGBColor) -> Self {
-        Self { albedo }
+        Self {
+            albedo,
+            alpha_texture: None,
+        }
+    }
+
+    /// Sets the texture that determines the opacity at each point of the surface
+    ///
+    /// The luminance of the texture is the opacity. Where it is below 0.5,
+    /// the surface is cut out and rays pass through it (there is no partial transparency).
+    ///
+    /// ## Parameters
+    /// * `alpha_texture` - opacity of the surface (black is a hole, white is solid)
+    pub fn set_alpha_texture<T>(&mut self, alpha_texture: T)
+    where
+        T: Into<Arc<AnyTexture>>,
+    {
+        self.alpha_texture = Some(alpha_texture.into());
     }
 
     /// Creates a new Lambertarian diffuse material
-    /// and returns reference counter of the box with this
-    /// material in it. The instance is generalized to all Materials.
+    /// and returns reference counter of this material.
+    /// The instance is generalized to all Materials.
     ///
     /// This is a helper function in creation of the Material.
     ///
     /// ## Parameters
     /// * `albedo` - albedo color of the material
-    pub fn new_counter(albedo: RGBColor) -> Arc<Self> {
-        Arc::new(Self::new(albedo))
+    pub fn new_counter(albedo: RGBColor) -> Arc<AnyMaterial> {
+        Arc::new(Self::new(albedo).into())
     }
 }
 
 impl Material for LambertarianDiffuse {
     fn scatter(
         &self,
-        _incoming_ray: &Ray,
+        incoming_ray: &Ray,
         hit_record: &HitRecord,
         rng: &mut Xoshiro256Plus,
     ) -> Option<MaterialScatterOutput> {
-        let random_unit_vector = random_vec3_on_unit_sphere(rng);
+        // Only points on the surface of the unit sphere give scattered directions
+        // distributed by the cosine, as `scattering_pdf` (and the MIS weights) assume
+        let random_unit_vector = random_unit_vec3(rng).normalize_or_zero();
         let scatter_direction = hit_record.normal() + random_unit_vector;
 
         // Handles the nasty instance where direction of the new vector
@@ -62,7 +89,8 @@ impl Material for LambertarianDiffuse {
             log::debug!("{}, {}", hit_record.normal(), random_unit_vector);
         }
 
-        let scattered_ray = Ray::new(hit_record.point(), scatter_direction);
+        let scattered_ray =
+            Ray::new_at_time(hit_record.point(), scatter_direction, incoming_ray.time());
         let attenuation = self.albedo;
 
         Some(super::MaterialScatterOutput {
@@ -70,4 +98,69 @@ impl Material for LambertarianDiffuse {
             attenuation,
         })
     }
+
+    fn scattering_pdf(
+        &self,
+        _incoming_ray: &Ray,
+        hit_record: &HitRecord,
+        scattered_ray: &Ray,
+    ) -> Option<f32> {
+        // Scattered directions are distributed by the cosine of the angle with the normal
+        let cosine = hit_record
+            .normal()
+            .dot(normalize_vec3(scattered_ray.direction()));
+        Some(cosine.max(0.0) / PI)
+    }
+
+    fn albedo(&self, _hit_record: &HitRecord) -> RGBColor {
+        self.albedo
+    }
+
+    fn is_cut_out(&self, hit_record: &HitRecord) -> bool {
+        self.alpha_texture.as_ref().is_some_and(|alpha_texture| {
+            let alpha = alpha_texture.value(hit_record.u(), hit_record.v(), hit_record.point());
+            alpha.luminance() < ALPHA_CUTOUT_THRESHOLD
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3A;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn scattered_directions_follow_the_cosine_distribution() {
+        let lambertian = LambertarianDiffuse::new(RGBColor::white());
+        let hit_record = HitRecord::new(
+            Vec3A::ZERO,
+            Vec3A::Y,
+            1.0,
+            true,
+            LambertarianDiffuse::new_counter(RGBColor::white()),
+        );
+        let incoming_ray = Ray::new(Vec3A::new(0.0, 1.0, 1.0), Vec3A::new(0.0, -1.0, -1.0));
+        let mut rng = Xoshiro256Plus::seed_from_u64(17);
+        let sample_count = 100000;
+        let mut cosine_sum = 0.0;
+        let mut steep_count = 0;
+        for _ in 0..sample_count {
+            let output = lambertian
+                .scatter(&incoming_ray, &hit_record, &mut rng)
+                .unwrap();
+            let cosine = normalize_vec3(output.scattered_ray.direction()).y;
+            assert!(cosine >= 0.0, "{}", cosine);
+            cosine_sum += cosine;
+            steep_count += (cosine > 0.5f32.sqrt()) as usize;
+        }
+
+        // With the pdf cos / PI, the cosine averages to 2/3,
+        // and half of the directions are within 45 degrees of the normal
+        let mean_cosine = cosine_sum / sample_count as f32;
+        assert!((mean_cosine - 2.0 / 3.0).abs() < 0.005, "{}", mean_cosine);
+        let steep_fraction = steep_count as f32 / sample_count as f32;
+        assert!((steep_fraction - 0.5).abs() < 0.01, "{}", steep_fraction);
+    }
 }
@@ -1,24 +1,30 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
 use rand_xoshiro::Xoshiro256Plus;
 
 use crate::{
     color::RGBColor,
-    math::{random_vec3_on_unit_sphere, reflect_vec3},
+    math::{normalize_vec3, random_unit_vec3, reflect_vec3},
     objects::HitRecord,
     ray::Ray,
+    textures::{AnyTexture, Texture},
 };
 
-use super::{Material, MaterialScatterOutput};
+use super::{
+    stats::{record_scatter, ScatterEvent, ScatteringMaterialKind},
+    AnyMaterial, Material, MaterialScatterOutput,
+};
 
 /// Metallic material
 ///
 /// Works by reflecting incoming rays over the normal in the contact point of the surface.
 /// To control roughness (clearness) of the material, a roughness parameter displaces
 /// reflected rays to create a hazy reflections.
+/// Roughness can also vary over the surface by using a roughness texture.
 pub struct Metal {
-    albedo: RGBColor, // Color of the surface
-    roughness: f32,   // How rough (unclear) is the surface
+    albedo: RGBColor,                           // Color of the surface
+    roughness: f32,                             // How rough (unclear) is the surface
+    roughness_texture: Option<Arc<AnyTexture>>, // Roughness at each point of the surface
 }
 
 impl Metal {
@@ -31,22 +37,46 @@ impl Metal {
         Self {
             albedo: color,
             roughness,
+            roughness_texture: None,
+        }
+    }
+
+    /// Sets the texture that determines roughness at each point of the surface
+    ///
+    /// The luminance of the texture is used as roughness
+    /// (black means completely clear, white means rough),
+    /// replacing the uniform roughness of the material.
+    ///
+    /// ## Parameters
+    /// * `texture` - grayscale roughness map
+    pub fn set_roughness_texture<T>(&mut self, texture: T)
+    where
+        T: Into<Arc<AnyTexture>>,
+    {
+        self.roughness_texture = Some(texture.into());
+    }
+
+    /// Returns the roughness of the surface at the hit point
+    fn roughness_at(&self, hit_record: &HitRecord) -> f32 {
+        match &self.roughness_texture {
+            Some(texture) => texture
+                .value(hit_record.u(), hit_record.v(), hit_record.point())
+                .luminance(),
+            None => self.roughness,
         }
     }
 
     /// Creates a new Metal material
-    /// and returns reference counter of the box with this
-    /// material in it. The instance is generalized to all Materials.
+    /// and returns reference counter of this material.
+    /// The instance is generalized to all Materials.
     ///
     /// This is a helper function in creation of the Material.
     ///
     /// ## Parameters
-    /// * `albedo` - albedo color of the material
+    /// * `color` - albedo color of the material
     /// * `roughness` - 0.0 means completely clear material, 1.0 means rough material
-    pub fn new_counter(color: RGBColor, roughness: f32) -> Rc<Box<dyn Material>> {
-        let metal = Metal::new(color, roughness);
-        let metal_box: Box<dyn Material> = Box::new(metal);
-        Rc::new(metal_box)
+    pub fn new_counter(color: RGBColor, roughness: f32) -> Arc<AnyMaterial> {
+        Arc::new(Metal::new(color, roughness).into())
     }
 }
 
@@ -59,17 +89,79 @@ impl Material for Metal {
     ) -> Option<MaterialScatterOutput> {
         // We reflect the ray over the normal so the bounce is clean.
         // We achieve roughness by shifting scatter direction by a random unit vector, scaled by roughness parameter
-        let reflected = reflect_vec3(incoming_ray.direction().normalize(), hit_record.normal())
-            + self.roughness * random_vec3_on_unit_sphere(rng);
-        let scattered_ray = Ray::new(hit_record.point(), reflected);
+        let reflected = reflect_vec3(
+            normalize_vec3(incoming_ray.direction()),
+            hit_record.normal(),
+        ) + self.roughness_at(hit_record) * random_unit_vec3(rng);
+        let scattered_ray = Ray::new_at_time(hit_record.point(), reflected, incoming_ray.time());
         let attenuation = self.albedo;
         if scattered_ray.direction().dot(hit_record.normal()) > 0.0 {
+            record_scatter(ScatteringMaterialKind::Metal, ScatterEvent::Reflected);
             Some(super::MaterialScatterOutput {
                 scattered_ray,
                 attenuation,
             })
         } else {
+            // Rays shifted below the surface are absorbed
+            record_scatter(ScatteringMaterialKind::Metal, ScatterEvent::Absorbed);
             None
         }
     }
+
+    fn albedo(&self, _hit_record: &HitRecord) -> RGBColor {
+        self.albedo
+    }
+
+    fn is_specular(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3A;
+    use rand::SeedableRng;
+
+    use crate::textures::solid_color::SolidColor;
+
+    use super::*;
+
+    /// Scatters a ray hitting a floor at 45 degrees, once for each seed
+    fn scattered_directions(roughness: RGBColor) -> Vec<Vec3A> {
+        // The uniform roughness would be fully rough, the texture replaces it
+        let mut metal = Metal::new(RGBColor::white(), 1.0);
+        metal.set_roughness_texture(SolidColor::new(roughness));
+        let incoming_ray = Ray::new(Vec3A::new(-1.0, 1.0, 0.0), Vec3A::new(1.0, -1.0, 0.0));
+        let hit_record = HitRecord::new(
+            Vec3A::ZERO,
+            Vec3A::Y,
+            1.0,
+            true,
+            Metal::new_counter(RGBColor::white(), 0.0),
+        );
+        (0..100)
+            .filter_map(|seed| {
+                let mut rng = Xoshiro256Plus::seed_from_u64(seed);
+                metal.scatter(&incoming_ray, &hit_record, &mut rng)
+            })
+            .map(|output| output.scattered_ray.direction().normalize())
+            .collect()
+    }
+
+    #[test]
+    fn roughness_texture_controls_the_reflection() {
+        let mirror = Vec3A::new(1.0, 1.0, 0.0).normalize();
+        let smooth = scattered_directions(RGBColor::black());
+        assert_eq!(smooth.len(), 100);
+        for direction in smooth {
+            assert!(direction.abs_diff_eq(mirror, 1e-6), "{}", direction);
+        }
+
+        let rough = scattered_directions(RGBColor::white());
+        let spread = rough
+            .iter()
+            .filter(|direction| direction.dot(mirror) < 0.9)
+            .count();
+        assert!(spread > rough.len() / 3, "{} of {}", spread, rough.len());
+    }
 }
@@ -0,0 +1,177 @@
+use std::f32::consts::PI;
+
+use glam::Vec3A;
+use rand::Rng;
+use rand_xoshiro::Xoshiro256Plus;
+
+use crate::{
+    color::RGBColor,
+    math::{build_onb, normalize_vec3, reflect_vec3},
+    objects::HitRecord,
+    ray::Ray,
+};
+
+use super::{Material, MaterialScatterOutput};
+
+/// The smallest GGX alpha, used for perfectly smooth metal
+const MIN_ALPHA: f32 = 0.0001;
+
+/// Physically based metallic material
+///
+/// The surface is modelled as many tiny mirrors (microfacets)
+/// with normals distributed by the GGX (Trowbridge-Reitz) distribution.
+/// Each bounce reflects the ray over a randomly chosen microfacet normal,
+/// and light blocked by neighbouring microfacets (masking and shadowing)
+/// is removed from the attenuation.
+pub struct MicrofacetMetal {
+    albedo: RGBColor,
+    alpha: f32,
+}
+
+impl MicrofacetMetal {
+    /// Creates a new microfacet metal material
+    ///
+    /// ## Parameters
+    /// * `albedo` - color of the metal at normal incidence
+    /// * `roughness` - 0.0 means a perfect mirror, 1.0 means a very rough material
+    pub fn new(albedo: RGBColor, roughness: f32) -> Self {
+        let roughness = roughness.clamp(0.0, 1.0);
+        // Squaring roughness makes the perceived roughness change more linearly.
+        // Very small values are kept above zero to avoid divisions by zero.
+        let alpha = (roughness * roughness).max(MIN_ALPHA);
+        Self { albedo, alpha }
+    }
+
+    /// Returns a random microfacet normal from the GGX distribution,
+    /// in the local space where the surface normal is +Z
+    ///
+    /// ## Parameters
+    /// * `rng` - random number generator instance
+    fn sample_microfacet_normal(&self, rng: &mut Xoshiro256Plus) -> Vec3A {
+        // The distribution has long tails, so even the smallest alpha
+        // would occasionally tilt the normal far away from a mirror reflection
+        if self.alpha <= MIN_ALPHA {
+            return Vec3A::Z;
+        }
+
+        let u1 = rng.gen::<f32>();
+        let u2 = rng.gen::<f32>();
+
+        let tan_theta_squared = self.alpha * self.alpha * u1 / (1.0 - u1).max(f32::EPSILON);
+        let cos_theta = 1.0 / (1.0 + tan_theta_squared).sqrt();
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * u2;
+
+        Vec3A::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta)
+    }
+
+    /// Smith masking function for a single direction
+    ///
+    /// ## Parameters
+    /// * `cos_theta` - cosine between the direction and the surface normal
+    fn smith_g1(&self, cos_theta: f32) -> f32 {
+        let cos_theta_squared = cos_theta * cos_theta;
+        let tan_theta_squared = (1.0 - cos_theta_squared) / cos_theta_squared;
+        2.0 / (1.0 + (1.0 + self.alpha * self.alpha * tan_theta_squared).sqrt())
+    }
+}
+
+impl Material for MicrofacetMetal {
+    fn scatter(
+        &self,
+        incoming_ray: &Ray,
+        hit_record: &HitRecord,
+        rng: &mut Xoshiro256Plus,
+    ) -> Option<MaterialScatterOutput> {
+        let normal = hit_record.normal();
+        let unit_direction = normalize_vec3(incoming_ray.direction());
+        let view = -unit_direction;
+
+        let cos_view = view.dot(normal);
+        if cos_view <= 0.0 {
+            return None;
+        }
+
+        let (tangent, bitangent, normal) = build_onb(normal);
+        let local_microfacet_normal = self.sample_microfacet_normal(rng);
+        let microfacet_normal = local_microfacet_normal.x * tangent
+            + local_microfacet_normal.y * bitangent
+            + local_microfacet_normal.z * normal;
+
+        let reflected = reflect_vec3(unit_direction, microfacet_normal);
+        let cos_light = reflected.dot(normal);
+        if cos_light <= 0.0 {
+            // The ray was reflected into the surface
+            return None;
+        }
+
+        // With microfacet normals sampled by D(h) * cos(h), the BRDF times cosine
+        // divided by the sampling density reduces to F * G * (v.h) / ((n.v) * (n.h))
+        let cos_view_microfacet = view.dot(microfacet_normal).max(0.0);
+        let cos_microfacet = local_microfacet_normal.z;
+        let fresnel =
+            self.albedo + (RGBColor::white() - self.albedo) * (1.0 - cos_view_microfacet).powi(5);
+        let masking_shadowing = self.smith_g1(cos_view) * self.smith_g1(cos_light);
+        let weight = masking_shadowing * cos_view_microfacet / (cos_view * cos_microfacet);
+
+        let scattered_ray = Ray::new_at_time(hit_record.point(), reflected, incoming_ray.time());
+        Some(MaterialScatterOutput {
+            scattered_ray,
+            attenuation: fresnel * weight,
+        })
+    }
+
+    fn albedo(&self, _hit_record: &HitRecord) -> RGBColor {
+        self.albedo
+    }
+
+    fn is_specular(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use crate::materials::lambertarian::LambertarianDiffuse;
+
+    use super::*;
+
+    /// Directions of rays reflected from a floor hit at 45 degrees
+    fn reflected_directions(roughness: f32) -> Vec<Vec3A> {
+        let metal = MicrofacetMetal::new(RGBColor::white(), roughness);
+        let incoming_ray = Ray::new(Vec3A::new(-1.0, 1.0, 0.0), Vec3A::new(1.0, -1.0, 0.0));
+        let hit_record = HitRecord::new(
+            Vec3A::ZERO,
+            Vec3A::Y,
+            1.0,
+            true,
+            LambertarianDiffuse::new_counter(RGBColor::white()),
+        );
+        let mut rng = Xoshiro256Plus::seed_from_u64(6);
+        (0..1000)
+            .filter_map(|_| metal.scatter(&incoming_ray, &hit_record, &mut rng))
+            .map(|output| output.scattered_ray.direction().normalize())
+            .collect()
+    }
+
+    #[test]
+    fn roughness_spreads_the_reflection() {
+        let mirror = Vec3A::new(1.0, 1.0, 0.0).normalize();
+        let smooth = reflected_directions(0.0);
+        assert_eq!(smooth.len(), 1000);
+        assert!(smooth
+            .iter()
+            .all(|direction| direction.abs_diff_eq(mirror, 1e-6)));
+
+        let rough = reflected_directions(1.0);
+        let spread = rough
+            .iter()
+            .filter(|direction| direction.dot(mirror) < 0.8)
+            .count();
+        assert!(spread * 4 > rough.len(), "{} of {}", spread, rough.len());
+        // Reflections never go into the surface
+        assert!(rough.iter().all(|direction| direction.y > 0.0));
+    }
+}
@@ -0,0 +1,119 @@
+use std::sync::Arc;
+
+use rand::Rng;
+use rand_xoshiro::Xoshiro256Plus;
+
+use crate::{color::RGBColor, objects::HitRecord, ray::Ray};
+
+use super::{AnyMaterial, Material, MaterialScatterOutput};
+
+/// Weighted combination of two materials
+///
+/// Every time light hits the surface, one of the materials is randomly chosen
+/// to scatter it, so that e.g. a factor of `0.3` makes 30 % of the light behave
+/// like the second material. Emitted light and albedo are blended by the factor.
+///
+/// Lights are not sampled directly from the surface, since the chosen material
+/// is not known when they are, so lighting converges slower than on the materials alone.
+pub struct MixMaterial {
+    a: Arc<AnyMaterial>,
+    b: Arc<AnyMaterial>,
+    factor: f32,
+}
+
+impl MixMaterial {
+    /// Creates a new mix of two materials
+    ///
+    /// ## Parameters
+    /// * `a` - the first material (used alone at factor `0.0`)
+    /// * `b` - the second material (used alone at factor `1.0`)
+    /// * `factor` - share of the second material, between `0.0` and `1.0`
+    pub fn new<A, B>(a: A, b: B, factor: f32) -> Self
+    where
+        A: Into<Arc<AnyMaterial>>,
+        B: Into<Arc<AnyMaterial>>,
+    {
+        Self {
+            a: a.into(),
+            b: b.into(),
+            factor: factor.clamp(0.0, 1.0),
+        }
+    }
+}
+
+impl Material for MixMaterial {
+    fn scatter(
+        &self,
+        incoming_ray: &Ray,
+        hit_record: &HitRecord,
+        rng: &mut Xoshiro256Plus,
+    ) -> Option<MaterialScatterOutput> {
+        if rng.gen::<f32>() < self.factor {
+            self.b.scatter(incoming_ray, hit_record, rng)
+        } else {
+            self.a.scatter(incoming_ray, hit_record, rng)
+        }
+    }
+
+    fn emitted(&self, incoming_ray: &Ray, hit_record: &HitRecord) -> RGBColor {
+        RGBColor::lerp(
+            self.a.emitted(incoming_ray, hit_record),
+            self.b.emitted(incoming_ray, hit_record),
+            self.factor,
+        )
+    }
+
+    fn albedo(&self, hit_record: &HitRecord) -> RGBColor {
+        RGBColor::lerp(
+            self.a.albedo(hit_record),
+            self.b.albedo(hit_record),
+            self.factor,
+        )
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.a.is_emissive() || self.b.is_emissive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3A;
+    use rand::SeedableRng;
+
+    use crate::materials::metal::Metal;
+
+    use super::*;
+
+    /// Counts how many of the scattered rays were scattered by the first material
+    fn first_material_share(factor: f32) -> usize {
+        let (red, green) = (RGBColor::new(1.0, 0.0, 0.0), RGBColor::new(0.0, 1.0, 0.0));
+        let mix = MixMaterial::new(Metal::new(red, 0.0), Metal::new(green, 0.0), factor);
+        let incoming_ray = Ray::new(Vec3A::new(-1.0, 1.0, 0.0), Vec3A::new(1.0, -1.0, 0.0));
+        let hit_record = HitRecord::new(Vec3A::ZERO, Vec3A::Y, 1.0, true, mix.a.clone());
+        let mut rng = Xoshiro256Plus::seed_from_u64(10);
+
+        let attenuations: Vec<RGBColor> = (0..1000)
+            .map(|_| {
+                mix.scatter(&incoming_ray, &hit_record, &mut rng)
+                    .unwrap()
+                    .attenuation
+            })
+            .collect();
+        assert!(attenuations
+            .iter()
+            .all(|attenuation| *attenuation == red || *attenuation == green));
+        attenuations
+            .iter()
+            .filter(|attenuation| **attenuation == red)
+            .count()
+    }
+
+    #[test]
+    fn factor_chooses_between_the_materials() {
+        assert_eq!(first_material_share(0.0), 1000);
+        assert_eq!(first_material_share(1.0), 0);
+        let half = first_material_share(0.5);
+        assert!((450..550).contains(&half), "{}", half);
+    }
+}
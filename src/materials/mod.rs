@@ -4,16 +4,33 @@ use rand_xoshiro::Xoshiro256Plus;
 
 use crate::{color::RGBColor, objects::HitRecord, ray::Ray};
 
-use self::{dielectric::Dielectric, lambertarian::LambertarianDiffuse, metal::Metal};
+use self::{
+    animated_emissive::AnimatedEmissive, dielectric::Dielectric, diffuse_light::DiffuseLight,
+    lambertarian::LambertarianDiffuse, metal::Metal, microfacet_metal::MicrofacetMetal,
+    mix::MixMaterial, shadow_catcher::ShadowCatcher, thin_glass::ThinGlass,
+};
 
+pub mod animated_emissive;
 pub mod dielectric;
+pub mod diffuse_light;
 pub mod lambertarian;
 pub mod metal;
+pub mod microfacet_metal;
+pub mod mix;
+pub mod shadow_catcher;
+pub mod stats;
+pub mod thin_glass;
 
 pub enum AnyMaterial {
     Metal(Metal),
     Lambertarian(LambertarianDiffuse),
     Dielectric(Dielectric),
+    DiffuseLight(DiffuseLight),
+    AnimatedEmissive(AnimatedEmissive),
+    MicrofacetMetal(MicrofacetMetal),
+    ShadowCatcher(ShadowCatcher),
+    ThinGlass(ThinGlass),
+    Mix(MixMaterial),
 }
 
 impl From<Metal> for AnyMaterial {
@@ -52,6 +69,95 @@ impl From<Dielectric> for Arc<AnyMaterial> {
     }
 }
 
+impl From<DiffuseLight> for AnyMaterial {
+    fn from(value: DiffuseLight) -> Self {
+        Self::DiffuseLight(value)
+    }
+}
+
+impl From<DiffuseLight> for Arc<AnyMaterial> {
+    fn from(value: DiffuseLight) -> Self {
+        Arc::new(AnyMaterial::DiffuseLight(value))
+    }
+}
+
+impl From<AnimatedEmissive> for AnyMaterial {
+    fn from(value: AnimatedEmissive) -> Self {
+        Self::AnimatedEmissive(value)
+    }
+}
+
+impl From<AnimatedEmissive> for Arc<AnyMaterial> {
+    fn from(value: AnimatedEmissive) -> Self {
+        Arc::new(AnyMaterial::AnimatedEmissive(value))
+    }
+}
+
+impl From<MicrofacetMetal> for AnyMaterial {
+    fn from(value: MicrofacetMetal) -> Self {
+        Self::MicrofacetMetal(value)
+    }
+}
+
+impl From<MicrofacetMetal> for Arc<AnyMaterial> {
+    fn from(value: MicrofacetMetal) -> Self {
+        Arc::new(AnyMaterial::MicrofacetMetal(value))
+    }
+}
+
+impl From<ShadowCatcher> for AnyMaterial {
+    fn from(value: ShadowCatcher) -> Self {
+        Self::ShadowCatcher(value)
+    }
+}
+
+impl From<ShadowCatcher> for Arc<AnyMaterial> {
+    fn from(value: ShadowCatcher) -> Self {
+        Arc::new(AnyMaterial::ShadowCatcher(value))
+    }
+}
+
+impl From<ThinGlass> for AnyMaterial {
+    fn from(value: ThinGlass) -> Self {
+        Self::ThinGlass(value)
+    }
+}
+
+impl From<ThinGlass> for Arc<AnyMaterial> {
+    fn from(value: ThinGlass) -> Self {
+        Arc::new(AnyMaterial::ThinGlass(value))
+    }
+}
+
+impl From<MixMaterial> for AnyMaterial {
+    fn from(value: MixMaterial) -> Self {
+        Self::Mix(value)
+    }
+}
+
+impl From<MixMaterial> for Arc<AnyMaterial> {
+    fn from(value: MixMaterial) -> Self {
+        Arc::new(AnyMaterial::Mix(value))
+    }
+}
+
+impl AnyMaterial {
+    /// Returns the name of the material type (e.g. for debug output)
+    pub fn name(&self) -> &'static str {
+        match self {
+            AnyMaterial::Metal(_) => "metal",
+            AnyMaterial::Lambertarian(_) => "lambertarian diffuse",
+            AnyMaterial::Dielectric(_) => "dielectric",
+            AnyMaterial::DiffuseLight(_) => "diffuse light",
+            AnyMaterial::AnimatedEmissive(_) => "animated emissive",
+            AnyMaterial::MicrofacetMetal(_) => "microfacet metal",
+            AnyMaterial::ShadowCatcher(_) => "shadow catcher",
+            AnyMaterial::ThinGlass(_) => "thin glass",
+            AnyMaterial::Mix(_) => "mix",
+        }
+    }
+}
+
 impl Material for AnyMaterial {
     fn scatter(
         &self,
@@ -63,6 +169,188 @@ impl Material for AnyMaterial {
             AnyMaterial::Metal(inner) => inner.scatter(incoming_ray, hit_record, rng),
             AnyMaterial::Lambertarian(inner) => inner.scatter(incoming_ray, hit_record, rng),
             AnyMaterial::Dielectric(inner) => inner.scatter(incoming_ray, hit_record, rng),
+            AnyMaterial::DiffuseLight(inner) => inner.scatter(incoming_ray, hit_record, rng),
+            AnyMaterial::AnimatedEmissive(inner) => inner.scatter(incoming_ray, hit_record, rng),
+            AnyMaterial::MicrofacetMetal(inner) => inner.scatter(incoming_ray, hit_record, rng),
+            AnyMaterial::ShadowCatcher(inner) => inner.scatter(incoming_ray, hit_record, rng),
+            AnyMaterial::ThinGlass(inner) => inner.scatter(incoming_ray, hit_record, rng),
+            AnyMaterial::Mix(inner) => inner.scatter(incoming_ray, hit_record, rng),
+        }
+    }
+
+    fn scatter_multi(
+        &self,
+        incoming_ray: &Ray,
+        hit_record: &HitRecord,
+        rng: &mut Xoshiro256Plus,
+    ) -> Vec<(Ray, RGBColor, f32)> {
+        match self {
+            AnyMaterial::Metal(inner) => inner.scatter_multi(incoming_ray, hit_record, rng),
+            AnyMaterial::Lambertarian(inner) => inner.scatter_multi(incoming_ray, hit_record, rng),
+            AnyMaterial::Dielectric(inner) => inner.scatter_multi(incoming_ray, hit_record, rng),
+            AnyMaterial::DiffuseLight(inner) => inner.scatter_multi(incoming_ray, hit_record, rng),
+            AnyMaterial::AnimatedEmissive(inner) => {
+                inner.scatter_multi(incoming_ray, hit_record, rng)
+            }
+            AnyMaterial::MicrofacetMetal(inner) => {
+                inner.scatter_multi(incoming_ray, hit_record, rng)
+            }
+            AnyMaterial::ShadowCatcher(inner) => inner.scatter_multi(incoming_ray, hit_record, rng),
+            AnyMaterial::ThinGlass(inner) => inner.scatter_multi(incoming_ray, hit_record, rng),
+            AnyMaterial::Mix(inner) => inner.scatter_multi(incoming_ray, hit_record, rng),
+        }
+    }
+
+    fn scattering_pdf(
+        &self,
+        incoming_ray: &Ray,
+        hit_record: &HitRecord,
+        scattered_ray: &Ray,
+    ) -> Option<f32> {
+        match self {
+            AnyMaterial::Metal(inner) => {
+                inner.scattering_pdf(incoming_ray, hit_record, scattered_ray)
+            }
+            AnyMaterial::Lambertarian(inner) => {
+                inner.scattering_pdf(incoming_ray, hit_record, scattered_ray)
+            }
+            AnyMaterial::Dielectric(inner) => {
+                inner.scattering_pdf(incoming_ray, hit_record, scattered_ray)
+            }
+            AnyMaterial::DiffuseLight(inner) => {
+                inner.scattering_pdf(incoming_ray, hit_record, scattered_ray)
+            }
+            AnyMaterial::AnimatedEmissive(inner) => {
+                inner.scattering_pdf(incoming_ray, hit_record, scattered_ray)
+            }
+            AnyMaterial::MicrofacetMetal(inner) => {
+                inner.scattering_pdf(incoming_ray, hit_record, scattered_ray)
+            }
+            AnyMaterial::ShadowCatcher(inner) => {
+                inner.scattering_pdf(incoming_ray, hit_record, scattered_ray)
+            }
+            AnyMaterial::ThinGlass(inner) => {
+                inner.scattering_pdf(incoming_ray, hit_record, scattered_ray)
+            }
+            AnyMaterial::Mix(inner) => {
+                inner.scattering_pdf(incoming_ray, hit_record, scattered_ray)
+            }
+        }
+    }
+
+    fn transmit(
+        &self,
+        incoming_ray: &Ray,
+        hit_record: &HitRecord,
+    ) -> Option<MaterialScatterOutput> {
+        match self {
+            AnyMaterial::Metal(inner) => inner.transmit(incoming_ray, hit_record),
+            AnyMaterial::Lambertarian(inner) => inner.transmit(incoming_ray, hit_record),
+            AnyMaterial::Dielectric(inner) => inner.transmit(incoming_ray, hit_record),
+            AnyMaterial::DiffuseLight(inner) => inner.transmit(incoming_ray, hit_record),
+            AnyMaterial::AnimatedEmissive(inner) => inner.transmit(incoming_ray, hit_record),
+            AnyMaterial::MicrofacetMetal(inner) => inner.transmit(incoming_ray, hit_record),
+            AnyMaterial::ShadowCatcher(inner) => inner.transmit(incoming_ray, hit_record),
+            AnyMaterial::ThinGlass(inner) => inner.transmit(incoming_ray, hit_record),
+            AnyMaterial::Mix(inner) => inner.transmit(incoming_ray, hit_record),
+        }
+    }
+
+    fn emitted(&self, incoming_ray: &Ray, hit_record: &HitRecord) -> RGBColor {
+        match self {
+            AnyMaterial::Metal(inner) => inner.emitted(incoming_ray, hit_record),
+            AnyMaterial::Lambertarian(inner) => inner.emitted(incoming_ray, hit_record),
+            AnyMaterial::Dielectric(inner) => inner.emitted(incoming_ray, hit_record),
+            AnyMaterial::DiffuseLight(inner) => inner.emitted(incoming_ray, hit_record),
+            AnyMaterial::AnimatedEmissive(inner) => inner.emitted(incoming_ray, hit_record),
+            AnyMaterial::MicrofacetMetal(inner) => inner.emitted(incoming_ray, hit_record),
+            AnyMaterial::ShadowCatcher(inner) => inner.emitted(incoming_ray, hit_record),
+            AnyMaterial::ThinGlass(inner) => inner.emitted(incoming_ray, hit_record),
+            AnyMaterial::Mix(inner) => inner.emitted(incoming_ray, hit_record),
+        }
+    }
+
+    fn index_of_refraction(&self, incoming_ray: &Ray) -> Option<f32> {
+        match self {
+            AnyMaterial::Metal(inner) => inner.index_of_refraction(incoming_ray),
+            AnyMaterial::Lambertarian(inner) => inner.index_of_refraction(incoming_ray),
+            AnyMaterial::Dielectric(inner) => inner.index_of_refraction(incoming_ray),
+            AnyMaterial::DiffuseLight(inner) => inner.index_of_refraction(incoming_ray),
+            AnyMaterial::AnimatedEmissive(inner) => inner.index_of_refraction(incoming_ray),
+            AnyMaterial::MicrofacetMetal(inner) => inner.index_of_refraction(incoming_ray),
+            AnyMaterial::ShadowCatcher(inner) => inner.index_of_refraction(incoming_ray),
+            AnyMaterial::ThinGlass(inner) => inner.index_of_refraction(incoming_ray),
+            AnyMaterial::Mix(inner) => inner.index_of_refraction(incoming_ray),
+        }
+    }
+
+    fn albedo(&self, hit_record: &HitRecord) -> RGBColor {
+        match self {
+            AnyMaterial::Metal(inner) => inner.albedo(hit_record),
+            AnyMaterial::Lambertarian(inner) => inner.albedo(hit_record),
+            AnyMaterial::Dielectric(inner) => inner.albedo(hit_record),
+            AnyMaterial::DiffuseLight(inner) => inner.albedo(hit_record),
+            AnyMaterial::AnimatedEmissive(inner) => inner.albedo(hit_record),
+            AnyMaterial::MicrofacetMetal(inner) => inner.albedo(hit_record),
+            AnyMaterial::ShadowCatcher(inner) => inner.albedo(hit_record),
+            AnyMaterial::ThinGlass(inner) => inner.albedo(hit_record),
+            AnyMaterial::Mix(inner) => inner.albedo(hit_record),
+        }
+    }
+
+    fn is_shadow_catcher(&self) -> bool {
+        match self {
+            AnyMaterial::Metal(inner) => inner.is_shadow_catcher(),
+            AnyMaterial::Lambertarian(inner) => inner.is_shadow_catcher(),
+            AnyMaterial::Dielectric(inner) => inner.is_shadow_catcher(),
+            AnyMaterial::DiffuseLight(inner) => inner.is_shadow_catcher(),
+            AnyMaterial::AnimatedEmissive(inner) => inner.is_shadow_catcher(),
+            AnyMaterial::MicrofacetMetal(inner) => inner.is_shadow_catcher(),
+            AnyMaterial::ShadowCatcher(inner) => inner.is_shadow_catcher(),
+            AnyMaterial::ThinGlass(inner) => inner.is_shadow_catcher(),
+            AnyMaterial::Mix(inner) => inner.is_shadow_catcher(),
+        }
+    }
+
+    fn is_emissive(&self) -> bool {
+        match self {
+            AnyMaterial::Metal(inner) => inner.is_emissive(),
+            AnyMaterial::Lambertarian(inner) => inner.is_emissive(),
+            AnyMaterial::Dielectric(inner) => inner.is_emissive(),
+            AnyMaterial::DiffuseLight(inner) => inner.is_emissive(),
+            AnyMaterial::AnimatedEmissive(inner) => inner.is_emissive(),
+            AnyMaterial::MicrofacetMetal(inner) => inner.is_emissive(),
+            AnyMaterial::ShadowCatcher(inner) => inner.is_emissive(),
+            AnyMaterial::ThinGlass(inner) => inner.is_emissive(),
+            AnyMaterial::Mix(inner) => inner.is_emissive(),
+        }
+    }
+
+    fn is_specular(&self) -> bool {
+        match self {
+            AnyMaterial::Metal(inner) => inner.is_specular(),
+            AnyMaterial::Lambertarian(inner) => inner.is_specular(),
+            AnyMaterial::Dielectric(inner) => inner.is_specular(),
+            AnyMaterial::DiffuseLight(inner) => inner.is_specular(),
+            AnyMaterial::AnimatedEmissive(inner) => inner.is_specular(),
+            AnyMaterial::MicrofacetMetal(inner) => inner.is_specular(),
+            AnyMaterial::ShadowCatcher(inner) => inner.is_specular(),
+            AnyMaterial::ThinGlass(inner) => inner.is_specular(),
+            AnyMaterial::Mix(inner) => inner.is_specular(),
+        }
+    }
+
+    fn is_cut_out(&self, hit_record: &HitRecord) -> bool {
+        match self {
+            AnyMaterial::Metal(inner) => inner.is_cut_out(hit_record),
+            AnyMaterial::Lambertarian(inner) => inner.is_cut_out(hit_record),
+            AnyMaterial::Dielectric(inner) => inner.is_cut_out(hit_record),
+            AnyMaterial::DiffuseLight(inner) => inner.is_cut_out(hit_record),
+            AnyMaterial::AnimatedEmissive(inner) => inner.is_cut_out(hit_record),
+            AnyMaterial::MicrofacetMetal(inner) => inner.is_cut_out(hit_record),
+            AnyMaterial::ShadowCatcher(inner) => inner.is_cut_out(hit_record),
+            AnyMaterial::ThinGlass(inner) => inner.is_cut_out(hit_record),
+            AnyMaterial::Mix(inner) => inner.is_cut_out(hit_record),
         }
     }
 }
@@ -88,4 +376,159 @@ pub trait Material {
         hit_record: &HitRecord,
         rng: &mut Xoshiro256Plus,
     ) -> Option<MaterialScatterOutput>;
+
+    /// Calculates all the ways the light can bounce off the surface at once
+    ///
+    /// Returns the scattered rays with their attenuation and the probability
+    /// of following them (the probabilities sum to `1.0`).
+    /// Materials that split the light (e.g. into a reflected and a transmitted part)
+    /// return several rays, others return the single ray of `scatter`.
+    ///
+    /// ## Parameters
+    /// * `incoming_ray` - the ray that hits the surface
+    /// * `hit_record` - the record of the current hit
+    /// * `rng` - random number generator instance (thread local)
+    fn scatter_multi(
+        &self,
+        incoming_ray: &Ray,
+        hit_record: &HitRecord,
+        rng: &mut Xoshiro256Plus,
+    ) -> Vec<(Ray, RGBColor, f32)> {
+        self.scatter(incoming_ray, hit_record, rng)
+            .map(|output| vec![(output.scattered_ray, output.attenuation, 1.0)])
+            .unwrap_or_default()
+    }
+
+    /// Calculates the probability density of the material scattering
+    /// the incoming ray in the direction of the `scattered_ray`.
+    ///
+    /// Materials that scatter in a single (specular) direction return `None`,
+    /// since they cannot be lit by sampling the lights directly.
+    ///
+    /// ## Parameters
+    /// * `incoming_ray` - the ray that hits the surface
+    /// * `hit_record` - the record of the current hit
+    /// * `scattered_ray` - the ray leaving the surface
+    fn scattering_pdf(
+        &self,
+        _incoming_ray: &Ray,
+        _hit_record: &HitRecord,
+        _scattered_ray: &Ray,
+    ) -> Option<f32> {
+        None
+    }
+
+    /// Calculates how the ray passes through a transparent surface, without any randomness
+    ///
+    /// Used by shadow rays, which can only follow a single path towards the light.
+    /// Returns the refracted ray and the fraction of light that is transmitted,
+    /// or `None` if the surface is opaque.
+    ///
+    /// ## Parameters
+    /// * `incoming_ray` - the ray that hits the surface
+    /// * `hit_record` - the record of the current hit
+    fn transmit(
+        &self,
+        _incoming_ray: &Ray,
+        _hit_record: &HitRecord,
+    ) -> Option<MaterialScatterOutput> {
+        None
+    }
+
+    /// Returns the color of the light the surface emits towards the incoming ray
+    ///
+    /// ## Parameters
+    /// * `incoming_ray` - the ray that hits the surface
+    /// * `hit_record` - the record of the current hit
+    fn emitted(&self, _incoming_ray: &Ray, _hit_record: &HitRecord) -> RGBColor {
+        RGBColor::black()
+    }
+
+    /// Returns the index of refraction of the material, if it refracts light
+    ///
+    /// Refracting materials fill the inside of objects as a medium, which also bends
+    /// the light at the surfaces of other objects inside of them (see `MediumStack`).
+    ///
+    /// ## Parameters
+    /// * `incoming_ray` - the ray that hits the surface (its wavelength may matter)
+    fn index_of_refraction(&self, _incoming_ray: &Ray) -> Option<f32> {
+        None
+    }
+
+    /// Returns the base color of the surface, independent of the lighting
+    ///
+    /// Used for the albedo buffer of denoisers.
+    /// Surfaces without a color of their own (e.g. glass) are white.
+    ///
+    /// ## Parameters
+    /// * `hit_record` - the record of the current hit
+    fn albedo(&self, _hit_record: &HitRecord) -> RGBColor {
+        RGBColor::white()
+    }
+
+    /// Whether the surface is a shadow catcher (see `ShadowCatcher`)
+    ///
+    /// Shadow catchers are invisible to all rays,
+    /// only the camera sees the shadows cast onto them.
+    fn is_shadow_catcher(&self) -> bool {
+        false
+    }
+
+    /// Whether the surface emits light (see `emitted`)
+    ///
+    /// Emissive materials are kept when all other materials are overridden,
+    /// so that lights still light the scene.
+    fn is_emissive(&self) -> bool {
+        false
+    }
+
+    /// Whether the material scatters light in (nearly) a single direction,
+    /// like mirrors and glass, instead of spreading it around like diffuse surfaces
+    ///
+    /// Used to limit the diffuse and specular bounces of a path separately.
+    fn is_specular(&self) -> bool {
+        false
+    }
+
+    /// Whether the surface is cut away at the hit point (e.g. between the leaves of foliage)
+    ///
+    /// Rays pass through cut out parts as if the surface was not there.
+    ///
+    /// ## Parameters
+    /// * `hit_record` - the record of the current hit
+    fn is_cut_out(&self, _hit_record: &HitRecord) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3A;
+
+    use crate::{
+        interval::Interval,
+        objects::{sphere::Sphere, Hittable},
+    };
+
+    use super::*;
+
+    #[test]
+    fn counter_constructors_build_spheres() {
+        let materials = [
+            (Metal::new_counter(RGBColor::white(), 0.1), "metal"),
+            (
+                LambertarianDiffuse::new_counter(RGBColor::white()),
+                "lambertarian diffuse",
+            ),
+            (Dielectric::new_counter(1.5), "dielectric"),
+        ];
+        for (material, name) in materials {
+            let sphere = Sphere::new(Vec3A::ZERO, 1.0, material);
+            let ray = Ray::new(Vec3A::new(0.0, 0.0, 5.0), -Vec3A::Z);
+            let hit_record = sphere
+                .hit(&ray, Interval::new(0.001, f32::INFINITY))
+                .unwrap();
+            assert_eq!(hit_record.material().name(), name);
+        }
+    }
 }
@@ -0,0 +1,40 @@
+use rand_xoshiro::Xoshiro256Plus;
+
+use crate::{objects::HitRecord, ray::Ray};
+
+use super::{Material, MaterialScatterOutput};
+
+/// Invisible surface that only shows the shadows cast onto it
+///
+/// Used for compositing rendered objects onto photos: the surface stands in
+/// for the real ground, so that the objects cast shadows onto the photo.
+/// Rays pass through it as if it was not there, except for camera rays,
+/// which are darkened by the fraction of direct light that is blocked
+/// by other objects. The alpha buffer stores the darkness of the shadow.
+///
+/// Shadows are only cast from the lights and importance sampled backgrounds
+/// (environment maps).
+#[derive(Default)]
+pub struct ShadowCatcher;
+
+impl ShadowCatcher {
+    /// Creates a new shadow catcher material
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Material for ShadowCatcher {
+    fn scatter(
+        &self,
+        _incoming_ray: &Ray,
+        _hit_record: &HitRecord,
+        _rng: &mut Xoshiro256Plus,
+    ) -> Option<MaterialScatterOutput> {
+        None
+    }
+
+    fn is_shadow_catcher(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,123 @@
+use std::cell::Cell;
+
+/// Types of materials whose scattering is counted
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScatteringMaterialKind {
+    Dielectric,
+    Metal,
+}
+
+impl ScatteringMaterialKind {
+    /// All counted material types, in the order of their counters
+    pub const ALL: [ScatteringMaterialKind; 2] = [
+        ScatteringMaterialKind::Dielectric,
+        ScatteringMaterialKind::Metal,
+    ];
+
+    /// Returns the name of the type
+    pub fn name(self) -> &'static str {
+        match self {
+            ScatteringMaterialKind::Dielectric => "dielectric",
+            ScatteringMaterialKind::Metal => "metal",
+        }
+    }
+}
+
+/// What happened to a ray hitting the material
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScatterEvent {
+    /// The ray bounced off the surface
+    Reflected,
+    /// The ray passed into or out of the object
+    Refracted,
+    /// The ray was not scattered
+    Absorbed,
+}
+
+/// Counts of scatter events of a single material type
+#[derive(Clone, Copy, Debug)]
+pub struct ScatterStats {
+    pub kind: ScatteringMaterialKind,
+    pub reflected: u64,
+    pub refracted: u64,
+    pub absorbed: u64,
+}
+
+impl ScatterStats {
+    /// Returns the amount of all scatter events
+    pub fn total(&self) -> u64 {
+        self.reflected + self.refracted + self.absorbed
+    }
+
+    /// Returns the counts added since the earlier counts (of the same type)
+    pub fn since(&self, earlier: &ScatterStats) -> Self {
+        Self {
+            kind: self.kind,
+            reflected: self.reflected - earlier.reflected,
+            refracted: self.refracted - earlier.refracted,
+            absorbed: self.absorbed - earlier.absorbed,
+        }
+    }
+}
+
+/// Counters of a single material type
+struct ScatterCounter {
+    reflected: Cell<u64>,
+    refracted: Cell<u64>,
+    absorbed: Cell<u64>,
+}
+
+impl ScatterCounter {
+    const fn new() -> Self {
+        Self {
+            reflected: Cell::new(0),
+            refracted: Cell::new(0),
+            absorbed: Cell::new(0),
+        }
+    }
+}
+
+thread_local! {
+    /// Counters of all material types, indexed the same as `ScatteringMaterialKind::ALL`
+    ///
+    /// Kept per thread for the same reason as the intersection counters,
+    /// so that renders running at the same time do not mix their counts.
+    static COUNTERS: [ScatterCounter; ScatteringMaterialKind::ALL.len()] =
+        const { [const { ScatterCounter::new() }; ScatteringMaterialKind::ALL.len()] };
+}
+
+/// Counts a scatter event of the material type
+///
+/// ## Parameters
+/// * `kind` - type of the material
+/// * `event` - what happened to the ray
+pub fn record_scatter(kind: ScatteringMaterialKind, event: ScatterEvent) {
+    COUNTERS.with(|counters| {
+        let counter = &counters[kind as usize];
+        let count = match event {
+            ScatterEvent::Reflected => &counter.reflected,
+            ScatterEvent::Refracted => &counter.refracted,
+            ScatterEvent::Absorbed => &counter.absorbed,
+        };
+        count.set(count.get() + 1);
+    });
+}
+
+/// Returns the counts of scatter events on this thread for every material type
+///
+/// The counters are never reset, so the scattering of a single render
+/// is the difference between the counts after and before it.
+pub fn scatter_stats() -> Vec<ScatterStats> {
+    COUNTERS.with(|counters| {
+        ScatteringMaterialKind::ALL
+            .iter()
+            .zip(counters.iter())
+            .map(|(&kind, counter)| ScatterStats {
+                kind,
+                reflected: counter.reflected.get(),
+                refracted: counter.refracted.get(),
+                absorbed: counter.absorbed.get(),
+            })
+            .collect()
+    })
+}
@@ -0,0 +1,169 @@
+use rand::Rng;
+use rand_xoshiro::Xoshiro256Plus;
+
+use crate::{
+    color::RGBColor,
+    math::{normalize_vec3, reflect_vec3},
+    objects::HitRecord,
+    ray::Ray,
+};
+
+use super::{dielectric::Dielectric, Material, MaterialScatterOutput};
+
+/// Infinitely thin sheet of glass, such as a window pane
+///
+/// Light is partly reflected and partly transmitted. Both surfaces of the sheet
+/// are parallel, so the transmitted light leaves it in the same direction it came from
+/// (there is no refraction). Unlike `Dielectric`, it can be used on single surfaces
+/// (e.g. parallelograms), since the ray never stays inside of the object.
+pub struct ThinGlass {
+    index_of_refraction: f32,
+    tint: RGBColor,
+}
+
+impl ThinGlass {
+    /// Creates a new thin glass material
+    ///
+    /// ## Parameters
+    /// * `index_of_refraction` - index of refraction of the material (1.5 for glass)
+    pub fn new(index_of_refraction: f32) -> Self {
+        Self {
+            index_of_refraction,
+            tint: RGBColor::white(),
+        }
+    }
+
+    /// Sets the color of the light that passes through the sheet
+    ///
+    /// ## Parameters
+    /// * `tint` - fraction of each color channel that is transmitted (white is clear glass)
+    pub fn set_tint(&mut self, tint: RGBColor) {
+        self.tint = tint;
+    }
+
+    /// Calculates the fraction of light that is reflected by the sheet
+    ///
+    /// Includes the light reflected back and forth between both surfaces.
+    ///
+    /// ## Parameters
+    /// * `incoming_ray` - the ray that hits the surface
+    /// * `hit_record` - the record of the current hit
+    fn reflectance(&self, incoming_ray: &Ray, hit_record: &HitRecord) -> f32 {
+        let unit_direction = normalize_vec3(incoming_ray.direction());
+        let cos_theta = -unit_direction.dot(hit_record.normal()).min(1.0);
+        let surface_reflectance =
+            Dielectric::reflectance(cos_theta, 1.0 / self.index_of_refraction);
+        2.0 * surface_reflectance / (1.0 + surface_reflectance)
+    }
+
+    /// Returns the reflected and the transmitted ray
+    ///
+    /// ## Parameters
+    /// * `incoming_ray` - the ray that hits the surface
+    /// * `hit_record` - the record of the current hit
+    fn split_rays(&self, incoming_ray: &Ray, hit_record: &HitRecord) -> (Ray, Ray) {
+        let direction = normalize_vec3(incoming_ray.direction());
+        let reflected = Ray::new_at_time(
+            hit_record.point(),
+            reflect_vec3(direction, hit_record.normal()),
+            incoming_ray.time(),
+        );
+        let transmitted = Ray::new_at_time(hit_record.point(), direction, incoming_ray.time());
+        (reflected, transmitted)
+    }
+}
+
+impl Material for ThinGlass {
+    fn scatter(
+        &self,
+        incoming_ray: &Ray,
+        hit_record: &HitRecord,
+        rng: &mut Xoshiro256Plus,
+    ) -> Option<MaterialScatterOutput> {
+        let (reflected, transmitted) = self.split_rays(incoming_ray, hit_record);
+        let output = if self.reflectance(incoming_ray, hit_record) > rng.gen() {
+            MaterialScatterOutput {
+                scattered_ray: reflected,
+                attenuation: RGBColor::white(),
+            }
+        } else {
+            MaterialScatterOutput {
+                scattered_ray: transmitted,
+                attenuation: self.tint,
+            }
+        };
+        Some(output)
+    }
+
+    fn scatter_multi(
+        &self,
+        incoming_ray: &Ray,
+        hit_record: &HitRecord,
+        _rng: &mut Xoshiro256Plus,
+    ) -> Vec<(Ray, RGBColor, f32)> {
+        let reflectance = self.reflectance(incoming_ray, hit_record);
+        let (reflected, transmitted) = self.split_rays(incoming_ray, hit_record);
+        vec![
+            (reflected, RGBColor::white(), reflectance),
+            (transmitted, self.tint, 1.0 - reflectance),
+        ]
+    }
+
+    fn transmit(
+        &self,
+        incoming_ray: &Ray,
+        hit_record: &HitRecord,
+    ) -> Option<MaterialScatterOutput> {
+        // Only the light that is not reflected passes through
+        let transmitted_fraction = 1.0 - self.reflectance(incoming_ray, hit_record);
+        let (_, transmitted) = self.split_rays(incoming_ray, hit_record);
+        Some(MaterialScatterOutput {
+            scattered_ray: transmitted,
+            attenuation: self.tint * transmitted_fraction,
+        })
+    }
+
+    fn is_specular(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3A;
+    use rand::SeedableRng;
+
+    use crate::materials::lambertarian::LambertarianDiffuse;
+
+    use super::*;
+
+    #[test]
+    fn sheet_reflects_and_transmits() {
+        let glass = ThinGlass::new(1.5);
+        let incoming_direction = Vec3A::new(1.0, -1.0, 0.0).normalize();
+        let incoming_ray = Ray::new(-incoming_direction, incoming_direction);
+        let hit_record = HitRecord::new(
+            Vec3A::ZERO,
+            Vec3A::Y,
+            1.0,
+            true,
+            LambertarianDiffuse::new_counter(RGBColor::white()),
+        );
+        let mut rng = Xoshiro256Plus::seed_from_u64(2);
+
+        let scatters = glass.scatter_multi(&incoming_ray, &hit_record, &mut rng);
+        assert_eq!(scatters.len(), 2);
+        let (reflected, _, reflected_weight) = &scatters[0];
+        let (transmitted, _, transmitted_weight) = &scatters[1];
+        assert!(reflected
+            .direction()
+            .normalize()
+            .abs_diff_eq(Vec3A::new(1.0, 1.0, 0.0).normalize(), 1e-5));
+        assert!(transmitted
+            .direction()
+            .normalize()
+            .abs_diff_eq(incoming_direction, 1e-5));
+        assert!(*reflected_weight > 0.0 && *transmitted_weight > *reflected_weight);
+        assert!((reflected_weight + transmitted_weight - 1.0).abs() < 1e-5);
+    }
+}
@@ -1,9 +1,11 @@
 use std::f32::consts::PI;
 
-use glam::Vec3A;
-use rand::{rngs::ThreadRng, Rng};
+use glam::{Mat3A, Mat4, Vec3A};
+use rand::Rng;
 use rand_xoshiro::Xoshiro256Plus;
 
+pub mod spline;
+
 /// Generate random normal variable with Box-Muller Transform
 ///
 /// Warning: This can return INF!!!
@@ -28,6 +30,38 @@ pub fn random_vec3_on_unit_disk(rng: &mut Xoshiro256Plus) -> Vec3A {
     Vec3A::new(x, y, 0.0)
 }
 
+/// Calculates a random point inside a regular polygon on the XY plane
+///
+/// The polygon is inscribed in the unit circle, with one vertex pointing up (+Y).
+/// The points are distributed uniformly over its area.
+///
+/// ## Parameters
+/// * `sides` - the number of polygon sides (at least 3)
+/// * `rng` - random number generator
+pub fn random_vec3_in_regular_polygon(sides: usize, rng: &mut Xoshiro256Plus) -> Vec3A {
+    if sides < 3 {
+        panic!("Polygon needs at least 3 sides!");
+    }
+
+    // The polygon is split into equal triangles between the center and each side,
+    // so we first pick a triangle and then a uniform point inside it
+    let segment_angle = 2.0 * PI / sides as f32;
+    let segment = rng.gen_range(0..sides) as f32;
+    let start_angle = PI / 2.0 + segment * segment_angle;
+    let end_angle = start_angle + segment_angle;
+    let start = Vec3A::new(start_angle.cos(), start_angle.sin(), 0.0);
+    let end = Vec3A::new(end_angle.cos(), end_angle.sin(), 0.0);
+
+    let mut a = rng.gen::<f32>();
+    let mut b = rng.gen::<f32>();
+    // Points outside the triangle are mirrored back into it
+    if a + b > 1.0 {
+        a = 1.0 - a;
+        b = 1.0 - b;
+    }
+    a * start + b * end
+}
+
 /// Calculates a random vector on unit sphere
 ///
 /// ## Parameters
@@ -53,19 +87,34 @@ pub fn random_vec3_on_unit_sphere(rng: &mut Xoshiro256Plus) -> Vec3A {
         norm_z = 0.0;
     }
 
-    Vec3A::new(norm_x, norm_y, norm_z)
+    let result = Vec3A::new(norm_x, norm_y, norm_z);
+    debug_assert!(
+        !is_invalid_vec3(result),
+        "Random vector on unit sphere is invalid: {}",
+        result
+    );
+    result
 }
 
-/// Creates a random vector with components in provided range
+/// Calculates a random unit vector (uniformly distributed direction)
+///
+/// Same as `random_vec3_on_unit_sphere`, so the length can be below 1
+/// (normalize it where only points on the sphere surface will do).
 ///
 /// ## Parameters
-/// `min` - lower bound of the range
-/// `max` - upper bound of the range
-pub fn random_vec3_in_range(min: f32, max: f32, rng: &mut ThreadRng) -> Vec3A {
-    // This is a fast (but not precise) RNG implementation
-    //let mut rng = Xoshiro256Plus::from_rng(thread_rng()).expect("Could not retrieve RNG");
-    //let mut rng = thread_rng();
+/// * `rng` - random number generator
+pub fn random_unit_vec3(rng: &mut Xoshiro256Plus) -> Vec3A {
+    random_vec3_on_unit_sphere(rng)
+}
 
+/// Creates a random vector with components in provided range
+///
+/// ## Parameters
+/// * `min` - lower bound of the range
+/// * `max` - upper bound of the range
+/// * `rng` - random number generator
+#[cfg(test)]
+pub fn random_vec3_in_range(min: f32, max: f32, rng: &mut Xoshiro256Plus) -> Vec3A {
     let diff = max - min;
 
     Vec3A::new(
@@ -100,7 +149,16 @@ pub fn refract_vec3(vector: Vec3A, normal: Vec3A, k: f32) -> Vec3A {
     let refracted_perpendicular = k * (vector + cos_theta * normal);
     let refracted_parallel =
         -((1.0 - refracted_perpendicular.dot(refracted_perpendicular)).abs()).sqrt() * normal;
-    refracted_perpendicular + refracted_parallel
+    let result = refracted_perpendicular + refracted_parallel;
+    debug_assert!(
+        !is_invalid_vec3(result),
+        "Refraction of {} over {} (k = {}) is invalid: {}",
+        vector,
+        normal,
+        k,
+        result
+    );
+    result
 }
 
 /// Returns a new vector that is a reflection of the `vector` over the `normal`
@@ -109,10 +167,89 @@ pub fn refract_vec3(vector: Vec3A, normal: Vec3A, k: f32) -> Vec3A {
 /// * `vector` - vector to reflect
 /// * `normal` - vector to reflect over
 pub fn reflect_vec3(vector: Vec3A, normal: Vec3A) -> Vec3A {
-    vector - 2.0 * vector.dot(normal) * normal
+    let result = vector - 2.0 * vector.dot(normal) * normal;
+    debug_assert!(
+        !is_invalid_vec3(result),
+        "Reflection of {} over {} is invalid: {}",
+        vector,
+        normal,
+        result
+    );
+    result
+}
+
+/// Builds an orthonormal basis around the provided vector
+///
+/// Returns the tangent, the bitangent and the normal (in that order).
+/// Uses the branchless method by Duff et al. (2017), which stays
+/// numerically stable even when the normal points towards the poles.
+///
+/// ## Parameters
+/// * `normal` - the vector to build the basis around (should be normalized!)
+pub fn build_onb(normal: Vec3A) -> (Vec3A, Vec3A, Vec3A) {
+    let sign = 1.0f32.copysign(normal.z);
+    let a = -1.0 / (sign + normal.z);
+    let b = normal.x * normal.y * a;
+    let tangent = Vec3A::new(
+        1.0 + sign * normal.x * normal.x * a,
+        sign * b,
+        -sign * normal.x,
+    );
+    let bitangent = Vec3A::new(b, sign + normal.y * normal.y * a, -normal.y);
+    (tangent, bitangent, normal)
+}
+
+/// Calculates the weight of a sample with the power heuristic (exponent 2)
+/// for multiple importance sampling of two strategies
+///
+/// The probability densities should already be multiplied by the amount of samples
+/// taken with each strategy.
+///
+/// ## Parameters
+/// * `pdf` - probability density of the strategy that produced the sample
+/// * `other_pdf` - probability density of the other strategy for the same sample
+pub fn power_heuristic(pdf: f32, other_pdf: f32) -> f32 {
+    let pdf_squared = pdf * pdf;
+    let other_pdf_squared = other_pdf * other_pdf;
+    if pdf_squared + other_pdf_squared <= 0.0 {
+        return 0.0;
+    }
+    pdf_squared / (pdf_squared + other_pdf_squared)
+}
+
+/// Calculates the inverse of the matrix
+///
+/// Returns `None` if the matrix is singular (cannot be inverted).
+///
+/// ## Parameters
+/// * `matrix` - the matrix to invert
+pub fn inverse_mat4(matrix: Mat4) -> Option<Mat4> {
+    if matrix.determinant() == 0.0 {
+        return None;
+    }
+    // Nearly singular matrices overflow instead
+    Some(matrix.inverse()).filter(|inverse| inverse.is_finite())
+}
+
+/// Calculates the matrix that transforms normals of surfaces
+/// transformed by the provided matrix
+///
+/// This is the inverse-transpose of the linear (3x3) part of the matrix,
+/// so that normals stay perpendicular to the surface under shear and non-uniform scale.
+/// Returns `None` if the linear part is singular.
+///
+/// ## Parameters
+/// * `matrix` - the matrix that transforms the surface
+pub fn normal_matrix(matrix: Mat4) -> Option<Mat3A> {
+    let linear = Mat3A::from_mat4(matrix);
+    if linear.determinant() == 0.0 {
+        return None;
+    }
+    Some(linear.inverse().transpose()).filter(|normal_matrix| normal_matrix.is_finite())
 }
 
 /// Creates a random vector with components in range `[0.0, 1.0]`
+#[cfg(test)]
 pub fn uniform_random_vec3(rng: &mut Xoshiro256Plus) -> Vec3A {
     Vec3A::new(rng.gen(), rng.gen(), rng.gen())
 }
@@ -123,6 +260,176 @@ pub fn is_vec3_near_zero(vector: Vec3A) -> bool {
     vector.x < threshold && vector.y < threshold && vector.z < threshold
 }
 
+/// Returns the vector scaled to unit length
+///
+/// Zero vectors (and vectors with NaN or infinite components) have no direction,
+/// so in debug builds they fail right here, instead of spreading NaNs
+/// that only show up as black pixels much later.
+///
+/// ## Parameters
+/// * `vector` - the vector to normalize (must not be zero)
+pub fn normalize_vec3(vector: Vec3A) -> Vec3A {
+    let result = vector.normalize();
+    debug_assert!(
+        !is_invalid_vec3(result),
+        "Normalization of {} is invalid: {}",
+        vector,
+        result
+    );
+    result
+}
+
+/// Checks if any component of the vector is NaN or infinite
 pub fn is_invalid_vec3(vector: Vec3A) -> bool {
-    vector.x.is_nan() || vector.y.is_nan() || vector.z.is_nan()
+    !vector.is_finite()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn onb_is_orthonormal() {
+        let normals = [
+            Vec3A::Z,
+            -Vec3A::Z,
+            Vec3A::X,
+            Vec3A::Y,
+            Vec3A::new(1.0, 2.0, 3.0).normalize(),
+            Vec3A::new(1e-4, -1e-4, -1.0).normalize(),
+        ];
+        for normal in normals {
+            let (tangent, bitangent, returned_normal) = build_onb(normal);
+            assert_eq!(returned_normal, normal);
+            for vector in [tangent, bitangent] {
+                assert!((vector.length() - 1.0).abs() < 1e-5, "{}", normal);
+                assert!(vector.dot(normal).abs() < 1e-5, "{}", normal);
+            }
+            assert!(tangent.dot(bitangent).abs() < 1e-5, "{}", normal);
+        }
+    }
+
+    #[test]
+    fn random_sphere_vectors_are_bounded_and_reproducible() {
+        let mut rng = Xoshiro256Plus::seed_from_u64(42);
+        let mut same_seed_rng = Xoshiro256Plus::seed_from_u64(42);
+        for _ in 0..1000 {
+            let vector = random_unit_vec3(&mut rng);
+            // Dropping a coordinate of a point on the 4D sphere
+            // keeps the direction uniform, but not the length
+            assert!(vector.length() <= 1.0 + 1e-5, "{}", vector);
+            assert_eq!(vector, random_vec3_on_unit_sphere(&mut same_seed_rng));
+        }
+    }
+
+    #[test]
+    fn random_vectors_stay_in_range_and_are_reproducible() {
+        let mut rng = Xoshiro256Plus::seed_from_u64(5);
+        let mut same_seed_rng = Xoshiro256Plus::seed_from_u64(5);
+        for _ in 0..1000 {
+            let vector = random_vec3_in_range(-2.0, 0.5, &mut rng);
+            assert!(
+                vector.cmpge(Vec3A::splat(-2.0)).all() && vector.cmple(Vec3A::splat(0.5)).all(),
+                "{}",
+                vector
+            );
+            assert_eq!(vector, random_vec3_in_range(-2.0, 0.5, &mut same_seed_rng));
+
+            let vector = uniform_random_vec3(&mut rng);
+            assert!(
+                vector.cmpge(Vec3A::ZERO).all() && vector.cmple(Vec3A::ONE).all(),
+                "{}",
+                vector
+            );
+            assert_eq!(vector, uniform_random_vec3(&mut same_seed_rng));
+        }
+    }
+
+    #[test]
+    fn random_matrix_times_inverse_is_identity() {
+        let mut rng = Xoshiro256Plus::seed_from_u64(7);
+        let mut random_column = || {
+            glam::Vec4::new(
+                rng.gen_range(-2.0..2.0),
+                rng.gen_range(-2.0..2.0),
+                rng.gen_range(-2.0..2.0),
+                rng.gen_range(-2.0..2.0),
+            )
+        };
+        let matrix = Mat4::from_cols(
+            random_column(),
+            random_column(),
+            random_column(),
+            random_column(),
+        );
+        let inverse = inverse_mat4(matrix).unwrap();
+        assert!((matrix * inverse).abs_diff_eq(Mat4::IDENTITY, 1e-4));
+    }
+
+    #[test]
+    fn singular_matrices_have_no_inverse() {
+        // Everything is flattened onto the XY plane
+        let flattening = Mat4::from_scale(glam::Vec3::new(1.0, 1.0, 0.0));
+        assert!(inverse_mat4(flattening).is_none());
+        assert!(normal_matrix(flattening).is_none());
+    }
+
+    #[test]
+    fn normal_matrix_keeps_normals_perpendicular() {
+        let shear = Mat4::from_cols_array(&[
+            1.0, 0.0, 0.0, 0.0, //
+            0.7, 1.0, 0.0, 0.0, //
+            0.0, 0.3, 2.0, 0.0, //
+            1.0, 2.0, 3.0, 1.0,
+        ]);
+        let normal = Vec3A::new(1.0, -2.0, 0.5);
+        let tangent = Vec3A::new(2.0, 1.0, 0.0);
+        assert_eq!(normal.dot(tangent), 0.0);
+
+        let transformed_normal = normal_matrix(shear).unwrap() * normal;
+        let transformed_tangent = shear.transform_vector3a(tangent);
+        assert!(transformed_normal.dot(transformed_tangent).abs() < 1e-5);
+    }
+
+    #[test]
+    fn aperture_points_stay_inside_their_shape() {
+        let mut rng = Xoshiro256Plus::seed_from_u64(12);
+        for _ in 0..2000 {
+            let disk_point = random_vec3_on_unit_disk(&mut rng);
+            assert!(
+                disk_point.length() <= 1.0 && disk_point.z == 0.0,
+                "{}",
+                disk_point
+            );
+        }
+
+        // With a vertex pointing up, the square is the diamond |x| + |y| <= 1
+        let mut quadrants = [false; 4];
+        for _ in 0..2000 {
+            let square_point = random_vec3_in_regular_polygon(4, &mut rng);
+            assert!(
+                square_point.x.abs() + square_point.y.abs() <= 1.0 + 1e-5,
+                "{}",
+                square_point
+            );
+            let quadrant = (square_point.x > 0.0) as usize + 2 * (square_point.y > 0.0) as usize;
+            quadrants[quadrant] = true;
+        }
+        assert_eq!(quadrants, [true; 4]);
+    }
+
+    #[test]
+    fn normalized_vectors_have_unit_length() {
+        let normalized = normalize_vec3(Vec3A::new(3.0, 0.0, -4.0));
+        assert_eq!(normalized, Vec3A::new(0.6, 0.0, -0.8));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "Normalization of [0, 0, 0] is invalid")]
+    fn normalizing_a_zero_vector_panics_in_debug_builds() {
+        normalize_vec3(Vec3A::ZERO);
+    }
 }
@@ -0,0 +1,63 @@
+use glam::Vec3A;
+
+/// Interpolates the points with a uniform Catmull-Rom spline
+///
+/// The curve passes through every point: point `i` is reached at `t = i`,
+/// and the curve between two points also depends on their neighbours,
+/// so the direction of movement changes smoothly at each point.
+/// The first and the last point are repeated to define the ends of the curve.
+///
+/// Panics if there are no points.
+///
+/// ## Parameters
+/// * `points` - the control points
+/// * `t` - position along the curve, clamped to `[0, points.len() - 1]`
+pub fn catmull_rom(points: &[Vec3A], t: f32) -> Vec3A {
+    if points.is_empty() {
+        panic!("Catmull-Rom spline needs at least one point!");
+    }
+    let last = points.len() - 1;
+    let t = if t.is_nan() {
+        0.0
+    } else {
+        t.clamp(0.0, last as f32)
+    };
+
+    // The segment between points `index` and `index + 1`
+    let index = (t.floor() as usize).min(last.saturating_sub(1));
+    let local_t = t - index as f32;
+    let point = |i: isize| points[i.clamp(0, last as isize) as usize];
+    let p0 = point(index as isize - 1);
+    let p1 = point(index as isize);
+    let p2 = point(index as isize + 1);
+    let p3 = point(index as isize + 2);
+
+    let t2 = local_t * local_t;
+    let t3 = t2 * local_t;
+    0.5 * (2.0 * p1
+        + (p2 - p0) * local_t
+        + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2
+        + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn curve_passes_through_control_points() {
+        let points = [
+            Vec3A::new(0.0, 0.0, 0.0),
+            Vec3A::new(2.0, 1.0, -1.0),
+            Vec3A::new(4.0, -3.0, 2.0),
+            Vec3A::new(-1.0, 5.0, 3.0),
+            Vec3A::new(6.0, 2.0, -4.0),
+        ];
+        for (i, &point) in points.iter().enumerate() {
+            assert_eq!(catmull_rom(&points, i as f32), point);
+        }
+        // The parameter is clamped to the ends of the curve
+        assert_eq!(catmull_rom(&points, -1.0), points[0]);
+        assert_eq!(catmull_rom(&points, 10.0), points[4]);
+    }
+}
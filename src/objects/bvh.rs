@@ -0,0 +1,267 @@
+use std::sync::Arc;
+
+use crate::{aabb::Aabb, interval::Interval, ray::Ray};
+
+use super::{AnyHittable, HitRecord, Hittable};
+
+/// Nodes with at most this many hittables may become leaves
+const MAX_LEAF_SIZE: usize = 4;
+
+/// Subtrees with at least this many hittables are built on separate threads
+const PARALLEL_THRESHOLD: usize = 4096;
+
+/// Estimated cost of testing a ray against a child bounding box,
+/// relative to the cost of intersecting a single hittable
+const TRAVERSAL_COST: f32 = 0.125;
+
+/// Bounding volume hierarchy
+///
+/// A binary tree where each node bounds all hittables below it,
+/// so that rays only need to be tested against hittables
+/// whose bounding boxes they pass through.
+/// Splits are chosen with the surface area heuristic (SAH),
+/// which minimizes the expected cost of traversing the tree.
+pub enum BvhNode {
+    Leaf {
+        objects: Vec<Arc<AnyHittable>>,
+        bounding_box: Aabb,
+    },
+    Branch {
+        left: Box<BvhNode>,
+        right: Box<BvhNode>,
+        bounding_box: Aabb,
+    },
+}
+
+impl BvhNode {
+    /// Builds a new hierarchy over the hittables
+    ///
+    /// Large subtrees are built in parallel.
+    /// All hittables must have finite bounding boxes.
+    ///
+    /// ## Parameters
+    /// * `objects` - the hittables to put into the hierarchy, must not be empty
+    pub fn new(objects: Vec<Arc<AnyHittable>>) -> Self {
+        Self::build(objects, PARALLEL_THRESHOLD)
+    }
+
+    /// Builds a new hierarchy over the hittables (see `new`)
+    ///
+    /// ## Parameters
+    /// * `objects` - the hittables to put into the hierarchy, must not be empty
+    /// * `parallel_threshold` - subtrees with at least this many hittables are built on separate threads
+    fn build(mut objects: Vec<Arc<AnyHittable>>, parallel_threshold: usize) -> Self {
+        assert!(
+            !objects.is_empty(),
+            "Bounding volume hierarchy needs at least one hittable!"
+        );
+
+        let bounding_box = surrounding_box(&objects);
+        if objects.len() == 1 {
+            return BvhNode::Leaf {
+                objects,
+                bounding_box,
+            };
+        }
+
+        let (axis, split_index, split_cost) = best_split(&mut objects, &bounding_box);
+        let leaf_cost = objects.len() as f32;
+        if objects.len() <= MAX_LEAF_SIZE && split_cost >= leaf_cost {
+            return BvhNode::Leaf {
+                objects,
+                bounding_box,
+            };
+        }
+
+        objects.sort_unstable_by(|a, b| {
+            centroid_on_axis(a, axis).total_cmp(&centroid_on_axis(b, axis))
+        });
+        let right_objects = objects.split_off(split_index);
+        let left_objects = objects;
+
+        let (left, right) = if left_objects.len() + right_objects.len() >= parallel_threshold {
+            rayon::join(
+                || BvhNode::build(left_objects, parallel_threshold),
+                || BvhNode::build(right_objects, parallel_threshold),
+            )
+        } else {
+            (
+                BvhNode::build(left_objects, parallel_threshold),
+                BvhNode::build(right_objects, parallel_threshold),
+            )
+        };
+
+        BvhNode::Branch {
+            left: Box::new(left),
+            right: Box::new(right),
+            bounding_box,
+        }
+    }
+
+    /// Returns the bounding box of all hittables in the node
+    pub fn bounding_box(&self) -> Aabb {
+        match self {
+            BvhNode::Leaf { bounding_box, .. } => *bounding_box,
+            BvhNode::Branch { bounding_box, .. } => *bounding_box,
+        }
+    }
+
+    /// Returns the bounding boxes of this node and all nodes below it,
+    /// each parent before its children
+    pub fn node_boxes(&self) -> Vec<Aabb> {
+        let mut boxes = vec![self.bounding_box()];
+        if let BvhNode::Branch { left, right, .. } = self {
+            boxes.extend(left.node_boxes());
+            boxes.extend(right.node_boxes());
+        }
+        boxes
+    }
+}
+
+impl Hittable for BvhNode {
+    fn hit(&self, ray: &Ray, ray_interval: Interval) -> Option<HitRecord> {
+        if !self.bounding_box().hit(ray, ray_interval) {
+            return None;
+        }
+
+        match self {
+            BvhNode::Leaf { objects, .. } => closest_hit(objects, ray, ray_interval),
+            BvhNode::Branch { left, right, .. } => {
+                let left_hit = left.hit(ray, ray_interval);
+                // The right subtree only needs to find hits closer than the left one
+                let closest_so_far = left_hit.as_ref().map_or(ray_interval.max(), |hit| hit.t());
+                let right_hit = right.hit(ray, Interval::new(ray_interval.min(), closest_so_far));
+                right_hit.or(left_hit)
+            }
+        }
+    }
+}
+
+/// Finds the closest hit among the hittables by testing each of them
+///
+/// ## Parameters
+/// * `objects` - the hittables to test
+/// * `ray` - the ray to operate with
+/// * `ray_interval` - the allowed range of the parameter along the ray
+pub fn closest_hit(
+    objects: &[Arc<AnyHittable>],
+    ray: &Ray,
+    ray_interval: Interval,
+) -> Option<HitRecord> {
+    let mut hit_record: Option<HitRecord> = None;
+    let mut closest_so_far = ray_interval.max();
+
+    for hittable in objects.iter() {
+        let new_interval = Interval::new(ray_interval.min(), closest_so_far);
+        if let Some(current_hit_record) = hittable.hit(ray, new_interval) {
+            closest_so_far = current_hit_record.t();
+            hit_record = Some(current_hit_record);
+        }
+    }
+
+    hit_record
+}
+
+/// Returns the bounding box that contains all hittables
+pub fn surrounding_box(objects: &[Arc<AnyHittable>]) -> Aabb {
+    objects
+        .iter()
+        .map(|object| object.bounding_box())
+        .reduce(Aabb::surrounding)
+        .expect("At least one hittable is needed to compute the bounding box")
+}
+
+/// Returns the centroid of the hittable bounding box along the axis
+fn centroid_on_axis(object: &Arc<AnyHittable>, axis: usize) -> f32 {
+    object.bounding_box().centroid()[axis]
+}
+
+/// Finds the split with the lowest surface area heuristic cost
+///
+/// Hittables are sorted by their centroids along each axis
+/// and every position in the sorted order is considered as a split.
+/// Returns the axis, the index of the first hittable in the right child
+/// and the cost of the split (relative to intersecting a single hittable).
+///
+/// ## Parameters
+/// * `objects` - the hittables to split, reordered in the process
+/// * `bounding_box` - the box containing all hittables
+fn best_split(objects: &mut [Arc<AnyHittable>], bounding_box: &Aabb) -> (usize, usize, f32) {
+    let count = objects.len();
+    let parent_area = bounding_box.surface_area();
+    let mut best = (0, count / 2, f32::INFINITY);
+
+    for axis in 0..3 {
+        objects.sort_unstable_by(|a, b| {
+            centroid_on_axis(a, axis).total_cmp(&centroid_on_axis(b, axis))
+        });
+
+        // right_areas[i] is the area of the box around objects[i..]
+        let mut right_areas = vec![0.0; count];
+        let mut right_box = objects[count - 1].bounding_box();
+        for i in (1..count).rev() {
+            right_box.merge(&objects[i].bounding_box());
+            right_areas[i] = right_box.surface_area();
+        }
+
+        let mut left_box = objects[0].bounding_box();
+        for split_index in 1..count {
+            left_box.merge(&objects[split_index - 1].bounding_box());
+            let left_cost = left_box.surface_area() * split_index as f32;
+            let right_cost = right_areas[split_index] * (count - split_index) as f32;
+            let cost = TRAVERSAL_COST + (left_cost + right_cost) / parent_area;
+            if cost < best.2 {
+                best = (axis, split_index, cost);
+            }
+        }
+    }
+
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand_xoshiro::Xoshiro256Plus;
+
+    use crate::{
+        color::RGBColor,
+        materials::lambertarian::LambertarianDiffuse,
+        math::{random_vec3_in_range, random_vec3_on_unit_sphere},
+        objects::sphere::Sphere,
+    };
+
+    use super::*;
+
+    #[test]
+    fn parallel_build_finds_the_same_hits_as_serial_build() {
+        let mut rng = Xoshiro256Plus::seed_from_u64(31);
+        let material = LambertarianDiffuse::new_counter(RGBColor::white());
+        // Enough spheres for the top of the tree to be built in parallel
+        let objects: Vec<Arc<AnyHittable>> = (0..PARALLEL_THRESHOLD + 1000)
+            .map(|_| {
+                let center = random_vec3_in_range(-10.0, 10.0, &mut rng);
+                Sphere::new(center, rng.gen_range(0.01..0.2), material.clone()).into()
+            })
+            .collect();
+
+        let parallel = BvhNode::new(objects.clone());
+        let serial = BvhNode::build(objects.clone(), usize::MAX);
+        let interval = Interval::new(0.001, f32::INFINITY);
+        let mut hits = 0;
+        for _ in 0..1000 {
+            let origin = 15.0 * random_vec3_on_unit_sphere(&mut rng);
+            let target = 5.0 * random_vec3_on_unit_sphere(&mut rng);
+            let ray = Ray::new(origin, target - origin);
+
+            let parallel_hit = parallel.hit(&ray, interval);
+            let serial_hit = serial.hit(&ray, interval);
+            let linear_hit = closest_hit(&objects, &ray, interval);
+            let point = |hit: &Option<HitRecord>| hit.as_ref().map(HitRecord::point);
+            assert_eq!(point(&parallel_hit), point(&serial_hit));
+            assert_eq!(point(&parallel_hit), point(&linear_hit));
+            hits += parallel_hit.is_some() as usize;
+        }
+        assert!(hits > 100, "{}", hits);
+    }
+}
@@ -0,0 +1,208 @@
+use std::sync::Arc;
+
+use glam::Vec3A;
+
+use crate::{aabb::Aabb, interval::Interval, ray::Ray};
+
+use super::{bvh::surrounding_box, AnyHittable, HitRecord, Hittable};
+
+/// Average amount of cells per hittable
+const CELLS_PER_OBJECT: f32 = 3.0;
+
+/// The largest amount of cells along any axis
+const MAX_RESOLUTION: usize = 128;
+
+/// Uniform grid over the hittables
+///
+/// The bounding box of all hittables is split into equally sized cells,
+/// and every cell lists the hittables whose bounding boxes overlap it.
+/// Rays walk through the cells they pass (3D DDA), so only hittables
+/// close to the ray are tested. Works best for many small hittables
+/// that are spread evenly (e.g. particles), where it is cheaper to build
+/// and traverse than a bounding volume hierarchy.
+pub struct UniformGrid {
+    objects: Vec<Arc<AnyHittable>>,
+    cells: Vec<Vec<usize>>, // Indices of the objects overlapping each cell
+    resolution: [usize; 3],
+    cell_size: Vec3A,
+    bounding_box: Aabb,
+}
+
+impl UniformGrid {
+    /// Builds a new grid over the hittables
+    ///
+    /// All hittables must have finite bounding boxes.
+    ///
+    /// ## Parameters
+    /// * `objects` - the hittables to put into the grid, must not be empty
+    pub fn new(objects: Vec<Arc<AnyHittable>>) -> Self {
+        assert!(
+            !objects.is_empty(),
+            "Uniform grid needs at least one hittable!"
+        );
+
+        let bounding_box = surrounding_box(&objects);
+        let size = bounding_box.max() - bounding_box.min();
+
+        // Cells should be roughly cubic, with a few cells for each hittable
+        let cells_along_widest_axis = (CELLS_PER_OBJECT * objects.len() as f32).cbrt();
+        let cells_per_unit = cells_along_widest_axis / size.max_element();
+        let resolution = [0, 1, 2]
+            .map(|axis| ((size[axis] * cells_per_unit).round() as usize).clamp(1, MAX_RESOLUTION));
+        let cell_size = size
+            / Vec3A::new(
+                resolution[0] as f32,
+                resolution[1] as f32,
+                resolution[2] as f32,
+            );
+
+        let mut grid = Self {
+            objects: Vec::new(),
+            cells: vec![Vec::new(); resolution.iter().product()],
+            resolution,
+            cell_size,
+            bounding_box,
+        };
+        for (index, object) in objects.iter().enumerate() {
+            let object_box = object.bounding_box();
+            let min_cell = grid.cell_of(object_box.min());
+            let max_cell = grid.cell_of(object_box.max());
+            for k in min_cell[2]..=max_cell[2] {
+                for j in min_cell[1]..=max_cell[1] {
+                    for i in min_cell[0]..=max_cell[0] {
+                        let cell_index = grid.cell_index([i, j, k]);
+                        grid.cells[cell_index].push(index);
+                    }
+                }
+            }
+        }
+        grid.objects = objects;
+        grid
+    }
+
+    /// Returns the cell containing the point (points outside of the grid
+    /// are moved to the closest cell)
+    ///
+    /// ## Parameters
+    /// * `point` - the point to find the cell of
+    fn cell_of(&self, point: Vec3A) -> [usize; 3] {
+        let relative = (point - self.bounding_box.min()) / self.cell_size;
+        [0, 1, 2]
+            .map(|axis| (relative[axis].floor().max(0.0) as usize).min(self.resolution[axis] - 1))
+    }
+
+    /// Returns the index of the cell in the list of cells
+    ///
+    /// ## Parameters
+    /// * `cell` - column, row and layer of the cell
+    fn cell_index(&self, cell: [usize; 3]) -> usize {
+        (cell[2] * self.resolution[1] + cell[1]) * self.resolution[0] + cell[0]
+    }
+}
+
+impl Hittable for UniformGrid {
+    fn hit(&self, ray: &Ray, ray_interval: Interval) -> Option<HitRecord> {
+        let grid_interval = self.bounding_box.clip(ray, ray_interval)?;
+        let origin = ray.origin();
+        let direction = ray.direction();
+        let grid_min = self.bounding_box.min();
+
+        // The cell where the ray enters the grid
+        let mut cell = self.cell_of(ray.at(grid_interval.min()));
+
+        // Ray parameters at which the ray crosses the next cell border on each axis
+        let mut step = [0isize; 3];
+        let mut t_border = [f32::INFINITY; 3];
+        let mut t_delta = [f32::INFINITY; 3];
+        for axis in 0..3 {
+            // Rays parallel to the borders never cross them
+            if direction[axis] == 0.0 {
+                continue;
+            }
+            step[axis] = if direction[axis] > 0.0 { 1 } else { -1 };
+            let border_index = if step[axis] > 0 {
+                cell[axis] + 1
+            } else {
+                cell[axis]
+            };
+            let border = grid_min[axis] + border_index as f32 * self.cell_size[axis];
+            t_border[axis] = (border - origin[axis]) / direction[axis];
+            t_delta[axis] = (self.cell_size[axis] / direction[axis]).abs();
+        }
+
+        let mut hit_record: Option<HitRecord> = None;
+        let mut closest_so_far = ray_interval.max();
+        loop {
+            for &index in &self.cells[self.cell_index(cell)] {
+                let interval = Interval::new(ray_interval.min(), closest_so_far);
+                if let Some(current_hit_record) = self.objects[index].hit(ray, interval) {
+                    closest_so_far = current_hit_record.t();
+                    hit_record = Some(current_hit_record);
+                }
+            }
+
+            // Hittables can overlap several cells, so a hit is only final
+            // once the ray leaves the cell it lies in
+            let axis = if t_border[0] < t_border[1] {
+                if t_border[0] < t_border[2] {
+                    0
+                } else {
+                    2
+                }
+            } else if t_border[1] < t_border[2] {
+                1
+            } else {
+                2
+            };
+            if t_border[axis] > closest_so_far.min(grid_interval.max()) {
+                return hit_record;
+            }
+            cell[axis] = match cell[axis].checked_add_signed(step[axis]) {
+                Some(next) if next < self.resolution[axis] => next,
+                _ => return hit_record,
+            };
+            t_border[axis] += t_delta[axis];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_xoshiro::Xoshiro256Plus;
+
+    use crate::{
+        color::RGBColor,
+        materials::lambertarian::LambertarianDiffuse,
+        math::random_vec3_in_range,
+        objects::{bvh::closest_hit, sphere::Sphere},
+    };
+
+    use super::*;
+
+    #[test]
+    fn grid_finds_same_closest_hit_as_linear_scan() {
+        let mut objects: Vec<Arc<AnyHittable>> = Vec::new();
+        for k in 0..5 {
+            for j in 0..5 {
+                for i in 0..5 {
+                    let center = Vec3A::new(i as f32, j as f32, k as f32) * 1.5;
+                    let material = LambertarianDiffuse::new(RGBColor::white());
+                    objects.push(Sphere::new(center, 0.5, material).into());
+                }
+            }
+        }
+        let grid = UniformGrid::new(objects.clone());
+
+        let mut rng = Xoshiro256Plus::seed_from_u64(3);
+        let interval = Interval::new(0.001, f32::INFINITY);
+        for _ in 0..2000 {
+            let origin = random_vec3_in_range(-3.0, 9.0, &mut rng);
+            let direction = random_vec3_in_range(-1.0, 1.0, &mut rng);
+            let ray = Ray::new(origin, direction);
+            let expected = closest_hit(&objects, &ray, interval).map(|hit| hit.t());
+            let actual = grid.hit(&ray, interval).map(|hit| hit.t());
+            assert_eq!(actual, expected, "{} -> {}", origin, direction);
+        }
+    }
+}
@@ -0,0 +1,314 @@
+use std::{path::Path, sync::Arc};
+
+use glam::Vec3A;
+
+use crate::{
+    aabb::Aabb, input_formats::ImportError, interval::Interval, materials::AnyMaterial,
+    math::normalize_vec3, ray::Ray, textures::image::ImageTexture,
+};
+
+use super::{HitRecord, Hittable};
+
+/// Terrain defined by a grid of heights
+///
+/// Every pixel of the heightmap image is a point of the grid,
+/// and its luminance decides the height (black is the lowest, white the highest point).
+/// Each grid cell between four neighbouring points is split into two triangles.
+///
+/// The grid lies in the XZ plane, with the top row of the image at the lowest Z.
+/// Rays walk over the grid cells they pass above (as seen from the top),
+/// so only triangles close to the ray are tested.
+pub struct Heightmap {
+    corner: Vec3A,
+    columns: usize,
+    rows: usize,
+    cell_width: f32,
+    cell_depth: f32,
+    heights: Vec<f32>,
+    bounding_box: Aabb,
+
+    material: Arc<AnyMaterial>,
+}
+
+impl Heightmap {
+    /// Creates a new heightmap from the image
+    ///
+    /// The image needs to be at least 2x2 pixels.
+    ///
+    /// ## Parameters
+    /// * `corner` - the lowest point of the corner of the top left pixel
+    /// * `size` - extent along X and Z, and the height of white pixels (Y)
+    /// * `heights` - grayscale image of heights
+    /// * `material` - material of the terrain surface
+    pub fn new<M>(corner: Vec3A, size: Vec3A, heights: &ImageTexture, material: M) -> Self
+    where
+        M: Into<Arc<AnyMaterial>>,
+    {
+        let columns = heights.width();
+        let rows = heights.height();
+        if columns < 2 || rows < 2 {
+            panic!("Heightmap needs at least 2x2 pixels!");
+        }
+
+        let heights: Vec<f32> = (0..rows)
+            .flat_map(|j| (0..columns).map(move |i| (i, j)))
+            .map(|(i, j)| heights.pixel(i, j).luminance() * size.y)
+            .collect();
+
+        let min_height = heights.iter().copied().fold(f32::INFINITY, f32::min);
+        let max_height = heights.iter().copied().fold(-f32::INFINITY, f32::max);
+        let bounding_box = Aabb::new(
+            Interval::new(corner.x, corner.x + size.x),
+            Interval::new(corner.y + min_height, corner.y + max_height),
+            Interval::new(corner.z, corner.z + size.z),
+        );
+
+        Self {
+            corner,
+            columns,
+            rows,
+            cell_width: size.x / (columns - 1) as f32,
+            cell_depth: size.z / (rows - 1) as f32,
+            heights,
+            bounding_box,
+            material: material.into(),
+        }
+    }
+
+    /// Loads the heights from an image file and creates a new heightmap
+    ///
+    /// ## Parameters
+    /// * `path` - path to the image file
+    /// * `corner` - the lowest point of the corner of the top left pixel
+    /// * `size` - extent along X and Z, and the height of white pixels (Y)
+    /// * `material` - material of the terrain surface
+    pub fn load<M>(
+        path: &Path,
+        corner: Vec3A,
+        size: Vec3A,
+        material: M,
+    ) -> Result<Self, ImportError>
+    where
+        M: Into<Arc<AnyMaterial>>,
+    {
+        let heights = ImageTexture::load(path)?;
+        Ok(Self::new(corner, size, &heights, material))
+    }
+
+    /// Returns the bounding box of the heightmap
+    pub fn bounding_box(&self) -> Aabb {
+        self.bounding_box
+    }
+
+    /// Returns the point of the grid
+    ///
+    /// ## Parameters
+    /// * `i` - column of the point
+    /// * `j` - row of the point
+    fn grid_point(&self, i: usize, j: usize) -> Vec3A {
+        self.corner
+            + Vec3A::new(
+                i as f32 * self.cell_width,
+                self.heights[j * self.columns + i],
+                j as f32 * self.cell_depth,
+            )
+    }
+
+    /// Finds the closest intersection with both triangles of the cell
+    ///
+    /// Returns the ray parameter and the triangle normal.
+    ///
+    /// ## Parameters
+    /// * `i` - column of the cell
+    /// * `j` - row of the cell
+    /// * `ray` - the ray to operate with
+    /// * `ray_interval` - the allowed range of the ray parameter
+    fn hit_cell(
+        &self,
+        i: usize,
+        j: usize,
+        ray: &Ray,
+        ray_interval: Interval,
+    ) -> Option<(f32, Vec3A)> {
+        let top_left = self.grid_point(i, j);
+        let top_right = self.grid_point(i + 1, j);
+        let bottom_left = self.grid_point(i, j + 1);
+        let bottom_right = self.grid_point(i + 1, j + 1);
+
+        let first = hit_triangle(ray, ray_interval, top_left, bottom_left, bottom_right);
+        let closest_so_far = first.map_or(ray_interval.max(), |(t, _)| t);
+        let second = hit_triangle(
+            ray,
+            Interval::new(ray_interval.min(), closest_so_far),
+            top_left,
+            bottom_right,
+            top_right,
+        );
+        second.or(first)
+    }
+}
+
+impl Hittable for Heightmap {
+    fn hit(&self, ray: &Ray, ray_interval: Interval) -> Option<HitRecord> {
+        let box_interval = self.bounding_box.clip(ray, ray_interval)?;
+        let origin = ray.origin();
+        let direction = ray.direction();
+        let cell_columns = self.columns - 1;
+        let cell_rows = self.rows - 1;
+
+        // The cell where the ray enters the bounding box
+        let entry = ray.at(box_interval.min());
+        let cell_of = |coordinate: f32, start: f32, size: f32, count: usize| {
+            (((coordinate - start) / size).floor().max(0.0) as usize).min(count - 1)
+        };
+        let mut i = cell_of(entry.x, self.corner.x, self.cell_width, cell_columns);
+        let mut j = cell_of(entry.z, self.corner.z, self.cell_depth, cell_rows);
+
+        // Ray parameters at which the ray crosses the next cell border (2D DDA)
+        let step_i: isize = if direction.x > 0.0 { 1 } else { -1 };
+        let step_j: isize = if direction.z > 0.0 { 1 } else { -1 };
+        let next_border = |index: usize, step: isize, start: f32, size: f32, axis: usize| {
+            // Rays parallel to the borders never cross them
+            if direction[axis] == 0.0 {
+                return f32::INFINITY;
+            }
+            let border_index = if step > 0 { index + 1 } else { index };
+            let border = start + border_index as f32 * size;
+            (border - origin[axis]) / direction[axis]
+        };
+        let mut t_border_i = next_border(i, step_i, self.corner.x, self.cell_width, 0);
+        let mut t_border_j = next_border(j, step_j, self.corner.z, self.cell_depth, 2);
+        let t_delta_i = (self.cell_width / direction.x).abs();
+        let t_delta_j = (self.cell_depth / direction.z).abs();
+
+        // Cells are visited in the order the ray passes them,
+        // so the first hit is also the closest one
+        loop {
+            if let Some((t, normal)) = self.hit_cell(i, j, ray, ray_interval) {
+                let point = ray.at(t);
+                let mut hit_record = HitRecord::new(point, normal, t, true, self.material.clone());
+                hit_record.set_face_normal(ray, normal);
+                let u = (point.x - self.corner.x) / (self.cell_width * cell_columns as f32);
+                let v = (point.z - self.corner.z) / (self.cell_depth * cell_rows as f32);
+                // The top row of the image is at v = 1 (as in textures)
+                hit_record.set_uv(u.clamp(0.0, 1.0), 1.0 - v.clamp(0.0, 1.0));
+                return Some(hit_record);
+            }
+
+            if t_border_i < t_border_j {
+                if t_border_i > box_interval.max() {
+                    return None;
+                }
+                i = i.checked_add_signed(step_i).filter(|&i| i < cell_columns)?;
+                t_border_i += t_delta_i;
+            } else {
+                if t_border_j > box_interval.max() {
+                    return None;
+                }
+                j = j.checked_add_signed(step_j).filter(|&j| j < cell_rows)?;
+                t_border_j += t_delta_j;
+            }
+        }
+    }
+}
+
+/// Intersects the ray with a triangle (Möller-Trumbore algorithm)
+///
+/// Returns the ray parameter and the normal of the triangle (pointing up).
+///
+/// ## Parameters
+/// * `ray` - the ray to operate with
+/// * `ray_interval` - the allowed range of the ray parameter
+/// * `a`, `b`, `c` - corners of the triangle
+fn hit_triangle(
+    ray: &Ray,
+    ray_interval: Interval,
+    a: Vec3A,
+    b: Vec3A,
+    c: Vec3A,
+) -> Option<(f32, Vec3A)> {
+    let edge_1 = b - a;
+    let edge_2 = c - a;
+    let p = ray.direction().cross(edge_2);
+    let determinant = edge_1.dot(p);
+    if determinant.abs() < f32::EPSILON {
+        return None;
+    }
+
+    let inverse_determinant = 1.0 / determinant;
+    let s = ray.origin() - a;
+    let u = s.dot(p) * inverse_determinant;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = s.cross(edge_1);
+    let v = ray.direction().dot(q) * inverse_determinant;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    let t = edge_2.dot(q) * inverse_determinant;
+    if !ray_interval.contains(t) {
+        return None;
+    }
+
+    let normal = normalize_vec3(edge_1.cross(edge_2));
+    let normal = if normal.y < 0.0 { -normal } else { normal };
+    Some((t, normal))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        color::RGBColor, input_formats::ImportedImage, materials::lambertarian::LambertarianDiffuse,
+    };
+
+    use super::*;
+
+    /// A 3x3 heightmap over (0, 0, 0) - (2, 1, 2), with a raised center point
+    fn peak() -> Heightmap {
+        let mut image_data = vec![RGBColor::black(); 9];
+        image_data[4] = RGBColor::white();
+        let heights = ImageTexture::new(ImportedImage {
+            width: 3,
+            height: 3,
+            image_data,
+        });
+        Heightmap::new(
+            Vec3A::ZERO,
+            Vec3A::new(2.0, 1.0, 2.0),
+            &heights,
+            LambertarianDiffuse::new_counter(RGBColor::white()),
+        )
+    }
+
+    #[test]
+    fn ray_hits_the_raised_center() {
+        let heightmap = peak();
+        let interval = Interval::new(0.001, f32::INFINITY);
+
+        let down = Ray::new(Vec3A::new(1.0, 5.0, 1.0), -Vec3A::Y);
+        let hit_record = heightmap.hit(&down, interval).unwrap();
+        assert!(hit_record
+            .point()
+            .abs_diff_eq(Vec3A::new(1.0, 1.0, 1.0), 1e-4));
+        assert!(hit_record.front_face());
+
+        // A horizontal ray at half height hits the slope halfway up to the peak
+        let sideways = Ray::new(Vec3A::new(-1.0, 0.5, 1.0), Vec3A::X);
+        let hit_record = heightmap.hit(&sideways, interval).unwrap();
+        assert!(hit_record
+            .point()
+            .abs_diff_eq(Vec3A::new(0.5, 0.5, 1.0), 1e-4));
+        assert!(hit_record.normal().dot(Vec3A::X) < 0.0);
+
+        // Near the corner, the terrain is flat
+        let corner = Ray::new(Vec3A::new(0.1, 5.0, 0.1), -Vec3A::Y);
+        let hit_record = heightmap.hit(&corner, interval).unwrap();
+        assert!(hit_record.point().y < 0.25, "{}", hit_record.point());
+
+        // Rays above the peak miss
+        let above = Ray::new(Vec3A::new(-1.0, 1.5, 1.0), Vec3A::X);
+        assert!(heightmap.hit(&above, interval).is_none());
+    }
+}
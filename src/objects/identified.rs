@@ -0,0 +1,65 @@
+use std::sync::Arc;
+
+use glam::Vec3A;
+use rand_xoshiro::Xoshiro256Plus;
+
+use crate::{aabb::Aabb, interval::Interval, ray::Ray};
+
+use super::{AnyHittable, HitRecord, Hittable};
+
+/// Wrapper that gives an object of the scene its ID
+///
+/// The ID is written into the hit records of the wrapped object,
+/// so that every pixel can tell which object it belongs to.
+pub struct Identified {
+    id: u32,
+    object: Arc<AnyHittable>,
+}
+
+impl Identified {
+    /// Creates a new identified object
+    ///
+    /// ## Parameters
+    /// * `id` - ID of the object (0 is reserved for the background)
+    /// * `object` - the object to identify
+    pub fn new<H>(id: u32, object: H) -> Self
+    where
+        H: Into<Arc<AnyHittable>>,
+    {
+        Self {
+            id,
+            object: object.into(),
+        }
+    }
+
+    /// Returns the ID of the object
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Returns the wrapped object
+    pub fn object(&self) -> &AnyHittable {
+        &self.object
+    }
+
+    /// Returns the bounding box of the wrapped object
+    pub fn bounding_box(&self) -> Aabb {
+        self.object.bounding_box()
+    }
+}
+
+impl Hittable for Identified {
+    fn hit(&self, ray: &Ray, ray_interval: Interval) -> Option<HitRecord> {
+        let mut hit_record = self.object.hit(ray, ray_interval)?;
+        hit_record.set_object_id(self.id);
+        Some(hit_record)
+    }
+
+    fn pdf_value(&self, origin: Vec3A, direction: Vec3A) -> f32 {
+        self.object.pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: Vec3A, rng: &mut Xoshiro256Plus) -> Vec3A {
+        self.object.random(origin, rng)
+    }
+}
@@ -1,23 +1,45 @@
 use std::{fmt::Debug, sync::Arc};
 
 use glam::Vec3A;
+use rand_xoshiro::Xoshiro256Plus;
 
-use crate::{interval::Interval, materials::AnyMaterial, ray::Ray};
+use crate::{aabb::Aabb, interval::Interval, materials::AnyMaterial, ray::Ray};
 
-use self::{parallelogram::Parallelogram, sphere::Sphere};
+use self::{
+    heightmap::Heightmap,
+    identified::Identified,
+    parallelogram::Parallelogram,
+    plane::Plane,
+    sphere::Sphere,
+    stats::{record_intersection, HittableKind},
+    transformed::Transformed,
+    wireframe::WireframeBox,
+};
 
+pub mod bvh;
+pub mod grid;
+pub mod heightmap;
+pub mod identified;
 pub mod parallelogram;
+pub mod plane;
 pub mod sphere;
+pub mod stats;
+pub mod transformed;
+pub mod wireframe;
 
 /// A helper struct that stores information
 /// about the hit, such as the location of the
 /// hit, the normal and the parameter t along the ray
+#[derive(Clone)]
 pub struct HitRecord {
     point: Vec3A,
     normal: Vec3A,
     t: f32,
+    u: f32,
+    v: f32,
     front_face: bool,
     material: Arc<AnyMaterial>,
+    object_id: u32,
 }
 
 impl HitRecord {
@@ -32,8 +54,11 @@ impl HitRecord {
             point,
             normal,
             t,
+            u: 0.0,
+            v: 0.0,
             front_face,
             material,
+            object_id: 0,
         }
     }
 
@@ -55,6 +80,32 @@ impl HitRecord {
         };
     }
 
+    /// Sets the surface (texture) coordinates of the hit point
+    ///
+    /// ## Parameters
+    /// * `u` - horizontal surface coordinate on the interval `[0.0, 1.0]`
+    /// * `v` - vertical surface coordinate on the interval `[0.0, 1.0]`
+    pub fn set_uv(&mut self, u: f32, v: f32) {
+        self.u = u;
+        self.v = v;
+    }
+
+    /// Replaces the material of the hit surface
+    ///
+    /// ## Parameters
+    /// * `material` - the new material
+    pub fn set_material(&mut self, material: Arc<AnyMaterial>) {
+        self.material = material;
+    }
+
+    /// Sets the ID of the hit object
+    ///
+    /// ## Parameters
+    /// * `object_id` - ID of the object in the scene
+    pub fn set_object_id(&mut self, object_id: u32) {
+        self.object_id = object_id;
+    }
+
     /// Get current parameter along the ray
     pub fn t(&self) -> f32 {
         self.t
@@ -70,32 +121,38 @@ impl HitRecord {
         self.point
     }
 
+    /// Get horizontal surface coordinate of the hit point
+    pub fn u(&self) -> f32 {
+        self.u
+    }
+
+    /// Get vertical surface coordinate of the hit point
+    pub fn v(&self) -> f32 {
+        self.v
+    }
+
     /// Get information if front face was hit
     pub fn front_face(&self) -> bool {
         self.front_face
     }
 
+    /// Get the ID of the hit object (0 if it was not given one)
+    pub fn object_id(&self) -> u32 {
+        self.object_id
+    }
+
     /// Get current surface material
     pub fn material(&self) -> Arc<AnyMaterial> {
         self.material.clone()
     }
-
-    /// Copy data from one HitRecord to another
-    pub fn copy_from(&mut self, source: &HitRecord) {
-        self.point = source.point;
-        self.normal = source.normal;
-        self.t = source.t;
-        self.front_face = source.front_face;
-        self.material = source.material.clone();
-    }
 }
 
 impl Debug for HitRecord {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "[ {}, {}, {} ]",
-            self.point, self.normal, self.front_face
+            "[ {}, {}, ({}, {}), {} ]",
+            self.point, self.normal, self.u, self.v, self.front_face
         )
     }
 }
@@ -103,6 +160,39 @@ impl Debug for HitRecord {
 pub enum AnyHittable {
     Sphere(Sphere),
     Paralellogram(Parallelogram),
+    Transformed(Transformed),
+    Plane(Plane),
+    Heightmap(Heightmap),
+    WireframeBox(WireframeBox),
+    Identified(Identified),
+}
+
+impl AnyHittable {
+    /// Returns the type of the hittable
+    pub fn kind(&self) -> HittableKind {
+        match self {
+            AnyHittable::Sphere(_) => HittableKind::Sphere,
+            AnyHittable::Paralellogram(_) => HittableKind::Parallelogram,
+            AnyHittable::Transformed(_) => HittableKind::Transformed,
+            AnyHittable::Plane(_) => HittableKind::Plane,
+            AnyHittable::Heightmap(_) => HittableKind::Heightmap,
+            AnyHittable::WireframeBox(_) => HittableKind::WireframeBox,
+            AnyHittable::Identified(inner) => inner.object().kind(),
+        }
+    }
+
+    /// Returns the bounding box of the hittable
+    pub fn bounding_box(&self) -> Aabb {
+        match self {
+            AnyHittable::Sphere(inner) => inner.bounding_box(),
+            AnyHittable::Paralellogram(inner) => inner.bounding_box(),
+            AnyHittable::Transformed(inner) => inner.bounding_box(),
+            AnyHittable::Plane(inner) => inner.bounding_box(),
+            AnyHittable::Heightmap(inner) => inner.bounding_box(),
+            AnyHittable::WireframeBox(inner) => inner.bounding_box(),
+            AnyHittable::Identified(inner) => inner.bounding_box(),
+        }
+    }
 }
 
 impl From<Sphere> for AnyHittable {
@@ -123,11 +213,94 @@ impl From<Parallelogram> for Arc<AnyHittable> {
     }
 }
 
+impl From<Transformed> for Arc<AnyHittable> {
+    fn from(value: Transformed) -> Self {
+        Self::new(AnyHittable::Transformed(value))
+    }
+}
+
+impl From<Plane> for Arc<AnyHittable> {
+    fn from(value: Plane) -> Self {
+        Self::new(AnyHittable::Plane(value))
+    }
+}
+
+impl From<Heightmap> for Arc<AnyHittable> {
+    fn from(value: Heightmap) -> Self {
+        Self::new(AnyHittable::Heightmap(value))
+    }
+}
+
+impl From<WireframeBox> for Arc<AnyHittable> {
+    fn from(value: WireframeBox) -> Self {
+        Self::new(AnyHittable::WireframeBox(value))
+    }
+}
+
+impl From<Identified> for Arc<AnyHittable> {
+    fn from(value: Identified) -> Self {
+        Self::new(AnyHittable::Identified(value))
+    }
+}
+
 impl Hittable for AnyHittable {
     fn hit(&self, ray: &Ray, ray_interval: Interval) -> Option<HitRecord> {
-        match self {
+        // The wrapped object is counted on its own
+        if let AnyHittable::Identified(inner) = self {
+            return inner.hit(ray, ray_interval);
+        }
+
+        // Rays that miss the bounding box cannot hit the object,
+        // and the box check is cheaper than the precise intersection.
+        // Spheres are skipped, since their intersection is about as cheap as the box check,
+        // planes, since their box is infinite, and heightmaps and wireframes,
+        // which clip rays to their box themselves.
+        let skips_box = matches!(
+            self,
+            AnyHittable::Sphere(_)
+                | AnyHittable::Plane(_)
+                | AnyHittable::Heightmap(_)
+                | AnyHittable::WireframeBox(_)
+        );
+        if !skips_box && !self.bounding_box().hit(ray, ray_interval) {
+            record_intersection(self.kind(), true, false);
+            return None;
+        }
+
+        let hit_record = match self {
             AnyHittable::Sphere(inner) => inner.hit(ray, ray_interval),
             AnyHittable::Paralellogram(inner) => inner.hit(ray, ray_interval),
+            AnyHittable::Transformed(inner) => inner.hit(ray, ray_interval),
+            AnyHittable::Plane(inner) => inner.hit(ray, ray_interval),
+            AnyHittable::Heightmap(inner) => inner.hit(ray, ray_interval),
+            AnyHittable::WireframeBox(inner) => inner.hit(ray, ray_interval),
+            AnyHittable::Identified(inner) => inner.hit(ray, ray_interval),
+        };
+        record_intersection(self.kind(), false, hit_record.is_some());
+        hit_record
+    }
+
+    fn pdf_value(&self, origin: Vec3A, direction: Vec3A) -> f32 {
+        match self {
+            AnyHittable::Sphere(inner) => inner.pdf_value(origin, direction),
+            AnyHittable::Paralellogram(inner) => inner.pdf_value(origin, direction),
+            AnyHittable::Transformed(inner) => inner.pdf_value(origin, direction),
+            AnyHittable::Plane(inner) => inner.pdf_value(origin, direction),
+            AnyHittable::Heightmap(inner) => inner.pdf_value(origin, direction),
+            AnyHittable::WireframeBox(inner) => inner.pdf_value(origin, direction),
+            AnyHittable::Identified(inner) => inner.pdf_value(origin, direction),
+        }
+    }
+
+    fn random(&self, origin: Vec3A, rng: &mut Xoshiro256Plus) -> Vec3A {
+        match self {
+            AnyHittable::Sphere(inner) => inner.random(origin, rng),
+            AnyHittable::Paralellogram(inner) => inner.random(origin, rng),
+            AnyHittable::Transformed(inner) => inner.random(origin, rng),
+            AnyHittable::Plane(inner) => inner.random(origin, rng),
+            AnyHittable::Heightmap(inner) => inner.random(origin, rng),
+            AnyHittable::WireframeBox(inner) => inner.random(origin, rng),
+            AnyHittable::Identified(inner) => inner.random(origin, rng),
         }
     }
 }
@@ -144,4 +317,108 @@ pub trait Hittable {
     /// * `t_min` - the lower boundary of the path along the ray (how close to the camera we still allow the result to be)
     /// * `t_min` - the upper boundary of the path along the ray (how far from the camera we still allow the result to be)
     fn hit(&self, ray: &Ray, ray_interval: Interval) -> Option<HitRecord>;
+
+    /// Calculates the probability density (over solid angle) of sampling
+    /// the `direction` from `origin` with the `random` method.
+    ///
+    /// Only structures that can be sampled as lights need to implement this.
+    ///
+    /// ## Parameters
+    /// * `origin` - the point from which the structure is sampled
+    /// * `direction` - the sampled direction
+    fn pdf_value(&self, _origin: Vec3A, _direction: Vec3A) -> f32 {
+        0.0
+    }
+
+    /// Generates a random direction from `origin` towards the structure
+    ///
+    /// Only structures that can be sampled as lights need to implement this.
+    ///
+    /// ## Parameters
+    /// * `origin` - the point from which the structure is sampled
+    /// * `rng` - random number generator instance
+    fn random(&self, _origin: Vec3A, _rng: &mut Xoshiro256Plus) -> Vec3A {
+        Vec3A::X
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Mat4;
+    use rand::SeedableRng;
+    use rand_xoshiro::Xoshiro256Plus;
+
+    use crate::{
+        color::RGBColor,
+        materials::lambertarian::LambertarianDiffuse,
+        math::{random_vec3_in_range, random_vec3_on_unit_sphere},
+        objects::{parallelogram::Parallelogram, sphere::Sphere},
+    };
+
+    use super::*;
+
+    #[test]
+    fn cloned_hit_record_keeps_every_field() {
+        let material: Arc<AnyMaterial> = LambertarianDiffuse::new(RGBColor::white()).into();
+        let ray = Ray::new(Vec3A::ZERO, Vec3A::new(0.0, 0.0, 1.0));
+        let mut record = HitRecord::new(
+            Vec3A::new(1.0, 2.0, 3.0),
+            Vec3A::Z,
+            4.0,
+            true,
+            material.clone(),
+        );
+        record.set_face_normal(&ray, Vec3A::Z);
+        record.set_uv(0.25, 0.75);
+        record.set_object_id(7);
+
+        let copy = record.clone();
+        assert_eq!(copy.point(), Vec3A::new(1.0, 2.0, 3.0));
+        assert_eq!(copy.normal(), -Vec3A::Z);
+        assert_eq!(copy.t(), 4.0);
+        assert_eq!((copy.u(), copy.v()), (0.25, 0.75));
+        assert!(!copy.front_face());
+        assert_eq!(copy.object_id(), 7);
+        assert!(Arc::ptr_eq(&copy.material(), &material));
+    }
+
+    #[test]
+    fn bounding_box_check_keeps_the_hits() {
+        let material = || LambertarianDiffuse::new_counter(RGBColor::white());
+        let transform = Mat4::from_rotation_y(0.7) * Mat4::from_translation(glam::Vec3::X);
+        let objects: [Arc<AnyHittable>; 2] = [
+            Parallelogram::new(
+                Vec3A::new(-1.0, -0.5, 0.2),
+                Vec3A::new(0.3, 1.5, 0.0),
+                Vec3A::new(2.0, 0.0, -0.4),
+                material(),
+            )
+            .into(),
+            Transformed::new(Sphere::new(Vec3A::ZERO, 0.8, material()), transform).into(),
+        ];
+        let interval = Interval::new(0.001, f32::INFINITY);
+
+        let mut rng = Xoshiro256Plus::seed_from_u64(17);
+        for object in &objects {
+            let mut hits = 0;
+            for _ in 0..5000 {
+                let origin = 3.0 * random_vec3_on_unit_sphere(&mut rng);
+                let target = random_vec3_in_range(-1.5, 1.5, &mut rng);
+                let ray = Ray::new(origin, target - origin);
+                // The wrapped objects are hit directly, without the box check
+                let direct = match object.as_ref() {
+                    AnyHittable::Paralellogram(inner) => inner.hit(&ray, interval),
+                    AnyHittable::Transformed(inner) => inner.hit(&ray, interval),
+                    _ => unreachable!(),
+                };
+                let checked = object.hit(&ray, interval);
+                assert_eq!(
+                    checked.as_ref().map(HitRecord::t),
+                    direct.as_ref().map(HitRecord::t)
+                );
+                hits += checked.is_some() as usize;
+            }
+            assert!(hits > 500, "{}", hits);
+        }
+    }
 }
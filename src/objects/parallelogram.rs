@@ -1,8 +1,12 @@
 use std::sync::Arc;
 
 use glam::Vec3A;
+use rand::Rng;
+use rand_xoshiro::Xoshiro256Plus;
 
-use crate::{interval::Interval, materials::AnyMaterial, ray::Ray};
+use crate::{
+    aabb::Aabb, interval::Interval, materials::AnyMaterial, math::normalize_vec3, ray::Ray,
+};
 
 use super::{HitRecord, Hittable};
 
@@ -16,6 +20,8 @@ pub struct Parallelogram {
     normal: Vec3A,
     plane_parameter: f32,
     w: Vec3A,
+    area: f32,
+    bounding_box: Aabb,
 
     material: Arc<AnyMaterial>,
 }
@@ -26,9 +32,17 @@ impl Parallelogram {
         M: Into<Arc<AnyMaterial>>,
     {
         let n = right.cross(up);
-        let normal = n.normalize();
+        let normal = normalize_vec3(n);
         let plane_parameter = normal.dot(bottom_left_point);
         let w = n / n.dot(n);
+        let area = Self::area_of(up, right);
+        // Thin axes are padded by the bounding box itself
+        let bounding_box = Aabb::from_points(&[
+            bottom_left_point,
+            bottom_left_point + up,
+            bottom_left_point + right,
+            bottom_left_point + up + right,
+        ]);
 
         Self {
             bottom_left_point,
@@ -37,9 +51,31 @@ impl Parallelogram {
             normal,
             plane_parameter,
             w,
+            area,
+            bounding_box,
             material: material.into(),
         }
     }
+
+    /// Returns the bounding box of the parallelogram
+    pub fn bounding_box(&self) -> Aabb {
+        self.bounding_box
+    }
+
+    /// Returns the area of the parallelogram
+    pub fn area(&self) -> f32 {
+        self.area
+    }
+
+    /// Calculates the area of a parallelogram before it is created
+    /// (e.g. to give a light on it a fixed power, see `DiffuseLight::from_power`)
+    ///
+    /// ## Parameters
+    /// * `up` - vector along one pair of sides
+    /// * `right` - vector along the other pair of sides
+    pub fn area_of(up: Vec3A, right: Vec3A) -> f32 {
+        right.cross(up).length()
+    }
 }
 
 impl Hittable for Parallelogram {
@@ -73,6 +109,60 @@ impl Hittable for Parallelogram {
         let mut hit_record =
             HitRecord::new(intersection, self.normal, t, true, self.material.clone());
         hit_record.set_face_normal(ray, self.normal);
+        hit_record.set_uv(a, b);
         Some(hit_record)
     }
+
+    fn pdf_value(&self, origin: Vec3A, direction: Vec3A) -> f32 {
+        let ray = Ray::new(origin, direction);
+        let Some(hit_record) = self.hit(&ray, Interval::new(0.001, f32::INFINITY)) else {
+            return 0.0;
+        };
+
+        // We convert the uniform density over the area into density over solid angle
+        let distance_squared = hit_record.t() * hit_record.t() * direction.length_squared();
+        let cosine = (direction.dot(self.normal) / direction.length()).abs();
+        if cosine < f32::EPSILON {
+            return 0.0;
+        }
+
+        distance_squared / (cosine * self.area)
+    }
+
+    fn random(&self, origin: Vec3A, rng: &mut Xoshiro256Plus) -> Vec3A {
+        let point =
+            self.bottom_left_point + rng.gen::<f32>() * self.right + rng.gen::<f32>() * self.up;
+        point - origin
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{color::RGBColor, materials::lambertarian::LambertarianDiffuse};
+
+    use super::*;
+
+    #[test]
+    fn bounding_box_contains_corners_and_has_thickness() {
+        let corner = Vec3A::new(-1.0, 0.5, 2.0);
+        let up = Vec3A::new(0.0, 0.0, -3.0);
+        let right = Vec3A::new(2.0, 0.0, 1.0);
+        let parallelogram = Parallelogram::new(
+            corner,
+            up,
+            right,
+            LambertarianDiffuse::new_counter(RGBColor::white()),
+        );
+
+        let bounding_box = parallelogram.bounding_box();
+        for point in [corner, corner + up, corner + right, corner + up + right] {
+            assert!(
+                point.cmpge(bounding_box.min()).all() && point.cmple(bounding_box.max()).all(),
+                "{}",
+                point
+            );
+        }
+        // The parallelogram is flat along Y, but its box is not
+        assert!(bounding_box.max().y > bounding_box.min().y);
+    }
 }
@@ -0,0 +1,149 @@
+use std::sync::Arc;
+
+use glam::Vec3A;
+
+use crate::{
+    aabb::Aabb,
+    interval::Interval,
+    materials::AnyMaterial,
+    math::{build_onb, normalize_vec3},
+    ray::Ray,
+};
+
+use super::{HitRecord, Hittable};
+
+/// Infinite plane, defined by a point on the plane and its normal
+///
+/// Useful for ground planes that should never end at the edges of the image.
+pub struct Plane {
+    point: Vec3A,
+    normal: Vec3A,
+    plane_parameter: f32,
+    tangent: Vec3A,
+    bitangent: Vec3A,
+    material: Arc<AnyMaterial>,
+}
+
+impl Plane {
+    /// Creates a new plane
+    ///
+    /// ## Parameters
+    /// * `point` - any point on the plane (the origin of surface coordinates)
+    /// * `normal` - the normal of the plane (the front side)
+    /// * `material` - material of the plane
+    pub fn new<M>(point: Vec3A, normal: Vec3A, material: M) -> Self
+    where
+        M: Into<Arc<AnyMaterial>>,
+    {
+        let normal = normalize_vec3(normal);
+        let plane_parameter = normal.dot(point);
+        let (tangent, bitangent, _) = build_onb(normal);
+
+        Self {
+            point,
+            normal,
+            plane_parameter,
+            tangent,
+            bitangent,
+            material: material.into(),
+        }
+    }
+
+    /// Returns the bounding box of the plane, which is infinite
+    pub fn bounding_box(&self) -> Aabb {
+        Aabb::new(
+            Interval::default(),
+            Interval::default(),
+            Interval::default(),
+        )
+    }
+}
+
+impl Hittable for Plane {
+    fn hit(&self, ray: &Ray, ray_interval: Interval) -> Option<HitRecord> {
+        let denominator = self.normal.dot(ray.direction());
+
+        // Rays parallel to the plane never hit it
+        if denominator.abs() < f32::EPSILON {
+            return None;
+        }
+
+        let numerator = self.plane_parameter - self.normal.dot(ray.origin());
+        let t = numerator / denominator;
+        if !ray_interval.contains(t) {
+            return None;
+        }
+
+        let intersection = ray.at(t);
+
+        let mut hit_record =
+            HitRecord::new(intersection, self.normal, t, true, self.material.clone());
+        hit_record.set_face_normal(ray, self.normal);
+        // Surface coordinates are distances along the plane, so textures repeat every unit
+        let p = intersection - self.point;
+        hit_record.set_uv(p.dot(self.tangent), p.dot(self.bitangent));
+        Some(hit_record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        color::RGBColor,
+        materials::lambertarian::LambertarianDiffuse,
+        objects::sphere::Sphere,
+        rendering::renderables::{AccelerationStructure, Renderables},
+    };
+
+    use super::*;
+
+    fn ground() -> Plane {
+        Plane::new(
+            Vec3A::ZERO,
+            Vec3A::Y,
+            LambertarianDiffuse::new_counter(RGBColor::white()),
+        )
+    }
+
+    #[test]
+    fn downward_ray_hits_and_upward_ray_misses() {
+        let plane = ground();
+        let interval = Interval::new(0.001, f32::INFINITY);
+
+        // Far away from the point, where a parallelogram would have ended
+        let down = Ray::new(Vec3A::new(1000.0, 2.0, -500.0), Vec3A::new(0.0, -1.0, 0.5));
+        let hit_record = plane.hit(&down, interval).unwrap();
+        assert_eq!(hit_record.t(), 2.0);
+        assert!(hit_record.front_face());
+        assert_eq!(hit_record.normal(), Vec3A::Y);
+
+        let up = Ray::new(Vec3A::new(0.0, 2.0, 0.0), Vec3A::new(0.3, 1.0, 0.0));
+        assert!(plane.hit(&up, interval).is_none());
+        let parallel = Ray::new(Vec3A::new(0.0, 2.0, 0.0), Vec3A::X);
+        assert!(plane.hit(&parallel, interval).is_none());
+    }
+
+    #[test]
+    fn plane_is_hit_next_to_an_acceleration_structure() {
+        for structure in [AccelerationStructure::Bvh, AccelerationStructure::Grid] {
+            let mut renderables = Renderables::new();
+            renderables.add_hittable(ground());
+            renderables.add_hittable(Sphere::new(
+                Vec3A::new(0.0, 1.0, 0.0),
+                0.5,
+                LambertarianDiffuse::new_counter(RGBColor::white()),
+            ));
+            renderables.build_accelerator(structure);
+
+            let interval = Interval::new(0.001, f32::INFINITY);
+            let far_ray = Ray::new(Vec3A::new(50.0, 1.0, 50.0), -Vec3A::Y);
+            let far_hit = renderables.hit(&far_ray, interval).unwrap();
+            assert_eq!(far_hit.object_id(), 1);
+            assert_eq!(far_hit.t(), 1.0);
+
+            let sphere_ray = Ray::new(Vec3A::new(0.0, 3.0, 0.0), -Vec3A::Y);
+            let sphere_hit = renderables.hit(&sphere_ray, interval).unwrap();
+            assert_eq!(sphere_hit.object_id(), 2);
+        }
+    }
+}
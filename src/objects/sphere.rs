@@ -1,8 +1,16 @@
-use std::sync::Arc;
+use std::{f32::consts::PI, sync::Arc};
 
 use glam::Vec3A;
+use rand::Rng;
+use rand_xoshiro::Xoshiro256Plus;
 
-use crate::{interval::Interval, materials::AnyMaterial, ray::Ray};
+use crate::{
+    aabb::Aabb,
+    interval::Interval,
+    materials::AnyMaterial,
+    math::{build_onb, normalize_vec3},
+    ray::Ray,
+};
 
 use super::{HitRecord, Hittable};
 
@@ -10,6 +18,7 @@ pub struct Sphere {
     center: Vec3A,
     radius: f32,
     material: Arc<AnyMaterial>,
+    bounding_box: Aabb,
 }
 
 impl Sphere {
@@ -22,13 +31,36 @@ impl Sphere {
     where
         M: Into<Arc<AnyMaterial>>,
     {
+        let radius_vector = Vec3A::splat(radius.abs());
+        let bounding_box = Aabb::from_points(&[center - radius_vector, center + radius_vector]);
+
         Self {
             center,
             radius,
             material: material.into(),
+            bounding_box,
         }
     }
 
+    /// Returns the bounding box of the sphere
+    pub fn bounding_box(&self) -> Aabb {
+        self.bounding_box
+    }
+
+    /// Returns the surface area of the sphere
+    pub fn area(&self) -> f32 {
+        Self::area_of(self.radius)
+    }
+
+    /// Calculates the surface area of a sphere before it is created
+    /// (e.g. to give a light on it a fixed power, see `DiffuseLight::from_power`)
+    ///
+    /// ## Parameters
+    /// * `radius` - radius of the sphere
+    pub fn area_of(radius: f32) -> f32 {
+        4.0 * PI * radius * radius
+    }
+
     /// Calculates the outward normal based on provided point on the sphere
     ///
     /// ## Parameters
@@ -36,6 +68,34 @@ impl Sphere {
     pub fn get_outward_normal(&self, point_on_sphere: Vec3A) -> Vec3A {
         (point_on_sphere - self.center) / self.radius
     }
+
+    /// Calculates the surface coordinates of a point on the unit sphere
+    ///
+    /// `u` goes around the Y axis starting from -X, `v` goes from -Y to +Y.
+    ///
+    /// ## Parameters
+    /// * `outward_normal` - the normal at the point (equal to the point on the unit sphere)
+    pub fn get_uv(outward_normal: Vec3A) -> (f32, f32) {
+        let theta = (-outward_normal.y).clamp(-1.0, 1.0).acos();
+        let phi = (-outward_normal.z).atan2(outward_normal.x) + PI;
+        (phi / (2.0 * PI), theta / PI)
+    }
+
+    /// Returns a uniformly distributed random point on the surface of the sphere
+    ///
+    /// ## Parameters
+    /// * `rng` - random number generator instance
+    fn random_surface_point(&self, rng: &mut Xoshiro256Plus) -> Vec3A {
+        let z = 1.0 - 2.0 * rng.gen::<f32>();
+        let phi = 2.0 * PI * rng.gen::<f32>();
+        let r = (1.0 - z * z).max(0.0).sqrt();
+        self.center + self.radius.abs() * Vec3A::new(r * phi.cos(), r * phi.sin(), z)
+    }
+
+    /// Whether the point lies inside (or on) the sphere
+    fn contains(&self, point: Vec3A) -> bool {
+        (point - self.center).length_squared() <= self.radius * self.radius
+    }
 }
 
 impl Hittable for Sphere {
@@ -55,7 +115,12 @@ impl Hittable for Sphere {
         let half_b = distance.dot(ray.direction()); // The multiplication with 2 is unnecessary (it is undone by the denominator in the term above)
         let c = distance.dot(distance) - self.radius * self.radius;
 
-        let discriminant = half_b * half_b - a * c;
+        // Computed as half_b^2 - a * c, the discriminant would be a difference of two
+        // large numbers for small spheres far from the ray origin, and lose all precision.
+        // Instead, it is derived from the distance between the center and the ray line
+        // (`perpendicular`), which gives the same value: a * (radius^2 - perpendicular^2).
+        let perpendicular = distance - (half_b / a) * ray.direction();
+        let discriminant = a * (self.radius * self.radius - perpendicular.dot(perpendicular));
 
         if discriminant < 0.0 {
             return None; // There are no real solutions, so the ray misses the sphere
@@ -63,10 +128,27 @@ impl Hittable for Sphere {
 
         let sqrt_discriminant = discriminant.sqrt();
 
-        // Find the nearest root that lies in the acceptable range
-        let mut root = (-half_b - sqrt_discriminant) / a;
+        // Rays scattered on the surface start (almost) on the sphere, so one of the roots
+        // is close to 0.0. Computing it as a difference of two similar numbers loses
+        // precision, and the root may end up on the wrong side of the interval minimum.
+        // Instead we compute the larger root directly and derive the other one from
+        // the product of the roots (c / a).
+        let q = -(half_b + half_b.signum() * sqrt_discriminant);
+        if q == 0.0 {
+            return None; // The ray starts on the sphere and only touches it
+        }
+        let (near_root, far_root) = {
+            let root_a = q / a;
+            let root_b = c / q;
+            (root_a.min(root_b), root_a.max(root_b))
+        };
+
+        // Find the nearest root that lies in the acceptable range.
+        // When the ray starts inside the sphere, the near root is behind its origin,
+        // so the far root is used, and the hit is on the back face (normal points inward).
+        let mut root = near_root;
         if !ray_interval.surrounds(root) {
-            root = (-half_b + sqrt_discriminant) / a;
+            root = far_root;
             if !ray_interval.surrounds(root) {
                 return None;
             }
@@ -79,7 +161,235 @@ impl Hittable for Sphere {
         // To prevent z-fighting due to precision error, we offset hit point just a little bit
         //hit_record.point = hit_record.point + outward_normal * 0.00001;
         hit_record.set_face_normal(ray, outward_normal);
+        let (u, v) = Sphere::get_uv(outward_normal);
+        hit_record.set_uv(u, v);
 
         Some(hit_record)
     }
+
+    fn pdf_value(&self, origin: Vec3A, direction: Vec3A) -> f32 {
+        let ray = Ray::new(origin, direction);
+        let Some(hit_record) = self.hit(&ray, Interval::new(0.001, f32::INFINITY)) else {
+            return 0.0;
+        };
+
+        if self.contains(origin) {
+            // Points are sampled uniformly over the surface,
+            // so we convert the density over the area into density over solid angle
+            let distance_squared = hit_record.t() * hit_record.t() * direction.length_squared();
+            let cosine = (direction.dot(hit_record.normal()) / direction.length()).abs();
+            if cosine < f32::EPSILON {
+                return 0.0;
+            }
+            return distance_squared / (cosine * self.area());
+        }
+
+        // Directions are sampled uniformly inside the cone that contains the sphere
+        let distance_squared = (self.center - origin).length_squared();
+        let cos_theta_max = (1.0 - self.radius * self.radius / distance_squared).sqrt();
+        let solid_angle = 2.0 * PI * (1.0 - cos_theta_max);
+        if solid_angle <= 0.0 {
+            return 0.0;
+        }
+        1.0 / solid_angle
+    }
+
+    fn random(&self, origin: Vec3A, rng: &mut Xoshiro256Plus) -> Vec3A {
+        if self.contains(origin) {
+            return self.random_surface_point(rng) - origin;
+        }
+
+        // From the outside, the sphere covers a cone of directions,
+        // so we only sample directions inside of it
+        let direction = self.center - origin;
+        let distance_squared = direction.length_squared();
+        let cos_theta_max = (1.0 - self.radius * self.radius / distance_squared).sqrt();
+
+        let z = 1.0 + rng.gen::<f32>() * (cos_theta_max - 1.0);
+        let phi = 2.0 * PI * rng.gen::<f32>();
+        let r = (1.0 - z * z).max(0.0).sqrt();
+
+        let (tangent, bitangent, normal) = build_onb(normalize_vec3(direction));
+        r * phi.cos() * tangent + r * phi.sin() * bitangent + z * normal
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use crate::{
+        color::RGBColor,
+        materials::{dielectric::Dielectric, lambertarian::LambertarianDiffuse, Material},
+        math::random_vec3_on_unit_sphere,
+    };
+
+    use super::*;
+
+    fn sphere(center: Vec3A, radius: f32) -> Sphere {
+        Sphere::new(
+            center,
+            radius,
+            LambertarianDiffuse::new_counter(RGBColor::white()),
+        )
+    }
+
+    #[test]
+    fn bounding_box_is_two_radii_wide() {
+        let sphere = sphere(Vec3A::new(1.0, 2.0, 3.0), 0.5);
+        let bounding_box = sphere.bounding_box();
+        assert_eq!(bounding_box.min(), Vec3A::new(0.5, 1.5, 2.5));
+        assert_eq!(bounding_box.max() - bounding_box.min(), Vec3A::splat(1.0));
+    }
+
+    #[test]
+    fn ray_from_inside_hits_the_back_face() {
+        let sphere = sphere(Vec3A::ZERO, 1.0);
+        let ray = Ray::new(Vec3A::new(0.0, 0.5, 0.0), Vec3A::X);
+        let hit_record = sphere
+            .hit(&ray, Interval::new(0.001, f32::INFINITY))
+            .unwrap();
+
+        assert!((hit_record.t() - 0.75f32.sqrt()).abs() < 1e-6);
+        assert!(!hit_record.front_face());
+        // The normal points inward, against the ray
+        assert!(hit_record.normal().abs_diff_eq(-hit_record.point(), 1e-6));
+        assert!(hit_record.normal().dot(ray.direction()) < 0.0);
+    }
+
+    #[test]
+    fn ray_starting_on_the_surface_hits_the_far_side() {
+        // A refracted ray starts on the surface, far from the world origin
+        let center = Vec3A::new(100.0, -50.0, 20.0);
+        let sphere = sphere(center, 0.5);
+        let direction = Vec3A::new(1.0, 0.2, -0.1).normalize();
+        let ray = Ray::new(center - 0.5 * direction, direction);
+        let hit_record = sphere
+            .hit(&ray, Interval::new(0.001, f32::INFINITY))
+            .unwrap();
+
+        assert!((hit_record.t() - 1.0).abs() < 1e-4, "{}", hit_record.t());
+        assert!(!hit_record.front_face());
+    }
+
+    #[test]
+    fn glass_refracts_out_of_the_sphere() {
+        let glass = Sphere::new(Vec3A::ZERO, 1.0, Dielectric::new_counter(1.5));
+        let ray = Ray::new(Vec3A::new(0.0, 0.5, 0.0), Vec3A::X);
+        let hit_record = glass
+            .hit(&ray, Interval::new(0.001, f32::INFINITY))
+            .unwrap();
+        let output = Dielectric::new(1.5).transmit(&ray, &hit_record).unwrap();
+
+        // Snell's law: leaving glass, the ray bends away from the normal
+        let outward_normal = -hit_record.normal();
+        let direction = output.scattered_ray.direction().normalize();
+        let sin_outgoing = direction.cross(outward_normal).length();
+        assert!((sin_outgoing - 1.5 * 0.5).abs() < 1e-5, "{}", sin_outgoing);
+        assert!(direction.dot(outward_normal) > 0.0);
+        assert!(output.scattered_ray.origin() == hit_record.point());
+    }
+
+    /// Nearest root computed from the textbook discriminant, half_b^2 - a * c
+    fn naive_root(sphere: &Sphere, ray: &Ray) -> Option<f32> {
+        let distance = ray.origin() - sphere.center;
+        let a = ray.direction().dot(ray.direction());
+        let half_b = distance.dot(ray.direction());
+        let c = distance.dot(distance) - sphere.radius * sphere.radius;
+        let discriminant = half_b * half_b - a * c;
+        (discriminant >= 0.0).then(|| (-half_b - discriminant.sqrt()) / a)
+    }
+
+    #[test]
+    fn stable_discriminant_matches_the_naive_one_for_nearby_spheres() {
+        let sphere = sphere(Vec3A::new(0.0, 0.0, -5.0), 1.0);
+        let mut rng = Xoshiro256Plus::seed_from_u64(3);
+        let mut hits = 0;
+        for _ in 0..1000 {
+            let target = sphere.center + 1.5 * random_vec3_on_unit_sphere(&mut rng);
+            let ray = Ray::new(Vec3A::ZERO, target);
+            let hit = sphere.hit(&ray, Interval::new(0.001, f32::INFINITY));
+            match (hit, naive_root(&sphere, &ray)) {
+                (Some(hit), Some(naive)) => {
+                    assert!((hit.t() - naive).abs() < 1e-5, "{} != {}", hit.t(), naive);
+                    hits += 1;
+                }
+                (None, None) => {}
+                // Rounding may only decide differently for rays grazing the sphere
+                _ => {
+                    let line_distance = normalize_vec3(target).cross(sphere.center).length();
+                    assert!((line_distance - 1.0).abs() < 1e-3, "{}", line_distance);
+                }
+            }
+        }
+        assert!(hits > 100, "{}", hits);
+    }
+
+    #[test]
+    fn tiny_sphere_far_away_is_hit_only_inside_its_radius() {
+        let radius = 1e-3;
+        let center = Vec3A::new(0.0, 0.0, -10000.0);
+        let sphere = sphere(center, radius);
+        let ray_past = |offset: f32| Ray::new(Vec3A::ZERO, center + Vec3A::new(offset, 0.0, 0.0));
+        let interval = Interval::new(0.001, f32::INFINITY);
+
+        for offset in [0.0, 0.5 * radius, 0.9 * radius] {
+            let ray = ray_past(offset);
+            let hit = sphere.hit(&ray, interval).unwrap();
+            // The ray is as long as the distance, so the sphere is hit right before its end
+            assert!((hit.t() - 1.0).abs() < 1e-5, "{}", hit.t());
+        }
+        for offset in [1.1 * radius, 2.0 * radius] {
+            assert!(
+                sphere.hit(&ray_past(offset), interval).is_none(),
+                "{}",
+                offset
+            );
+        }
+
+        // The naive discriminant cancels out and cannot tell the same rays apart
+        let naive_hits = [0.0, 0.5, 0.9, 1.1, 2.0]
+            .map(|factor| naive_root(&sphere, &ray_past(factor * radius)).is_some());
+        assert!(
+            naive_hits != [true, true, true, false, false],
+            "{:?}",
+            naive_hits
+        );
+    }
+
+    /// Monte Carlo estimate of the integral of the pdf over all directions
+    fn integrated_pdf(sphere: &Sphere, origin: Vec3A) -> f32 {
+        let mut rng = Xoshiro256Plus::seed_from_u64(21);
+        let sample_count = 100000;
+        let sum: f32 = (0..sample_count)
+            .map(|_| sphere.pdf_value(origin, random_vec3_on_unit_sphere(&mut rng)))
+            .sum();
+        4.0 * PI * sum / sample_count as f32
+    }
+
+    #[test]
+    fn light_pdf_integrates_to_one() {
+        let sphere = sphere(Vec3A::new(0.0, 2.0, 0.0), 1.0);
+        for origin in [Vec3A::ZERO, Vec3A::new(0.0, 2.3, 0.4)] {
+            let integral = integrated_pdf(&sphere, origin);
+            assert!((integral - 1.0).abs() < 0.05, "{}: {}", origin, integral);
+        }
+    }
+
+    #[test]
+    fn sampled_directions_hit_the_light() {
+        let sphere = sphere(Vec3A::new(0.0, 2.0, 0.0), 1.0);
+        let mut rng = Xoshiro256Plus::seed_from_u64(8);
+        let cone_solid_angle = 2.0 * PI * (1.0 - 0.75f32.sqrt());
+        for origin in [Vec3A::ZERO, Vec3A::new(0.0, 2.3, 0.4)] {
+            for _ in 0..1000 {
+                let direction = sphere.random(origin, &mut rng);
+                let pdf = sphere.pdf_value(origin, direction);
+                assert!(pdf > 0.0, "{} towards {}", origin, direction);
+                if origin == Vec3A::ZERO {
+                    assert!((pdf * cone_solid_angle - 1.0).abs() < 1e-4);
+                }
+            }
+        }
+    }
 }
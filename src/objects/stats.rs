@@ -0,0 +1,142 @@
+use std::cell::Cell;
+
+/// Types of hittables, used to tell their statistics apart
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HittableKind {
+    Sphere,
+    Parallelogram,
+    Transformed,
+    Plane,
+    Heightmap,
+    WireframeBox,
+}
+
+impl HittableKind {
+    /// All hittable types, in the order of their counters
+    pub const ALL: [HittableKind; 6] = [
+        HittableKind::Sphere,
+        HittableKind::Parallelogram,
+        HittableKind::Transformed,
+        HittableKind::Plane,
+        HittableKind::Heightmap,
+        HittableKind::WireframeBox,
+    ];
+
+    /// Returns the name of the type
+    pub fn name(self) -> &'static str {
+        match self {
+            HittableKind::Sphere => "sphere",
+            HittableKind::Parallelogram => "parallelogram",
+            HittableKind::Transformed => "transformed",
+            HittableKind::Plane => "plane",
+            HittableKind::Heightmap => "heightmap",
+            HittableKind::WireframeBox => "wireframe box",
+        }
+    }
+}
+
+/// Counts of intersection tests of a single hittable type
+#[derive(Clone, Copy, Debug)]
+pub struct IntersectionStats {
+    pub kind: HittableKind,
+    /// All intersection tests, including those rejected by the bounding box
+    pub tests: u64,
+    /// Tests that ended early, because the ray missed the bounding box
+    pub box_rejections: u64,
+    /// Tests where the ray hit the object
+    pub hits: u64,
+}
+
+impl IntersectionStats {
+    /// Returns the counts added since the earlier counts (of the same type)
+    pub fn since(&self, earlier: &IntersectionStats) -> Self {
+        Self {
+            kind: self.kind,
+            tests: self.tests - earlier.tests,
+            box_rejections: self.box_rejections - earlier.box_rejections,
+            hits: self.hits - earlier.hits,
+        }
+    }
+}
+
+/// Counters of a single hittable type
+struct IntersectionCounter {
+    tests: Cell<u64>,
+    box_rejections: Cell<u64>,
+    hits: Cell<u64>,
+}
+
+impl IntersectionCounter {
+    const fn new() -> Self {
+        Self {
+            tests: Cell::new(0),
+            box_rejections: Cell::new(0),
+            hits: Cell::new(0),
+        }
+    }
+}
+
+thread_local! {
+    /// Counters of all hittable types, indexed the same as `HittableKind::ALL`
+    ///
+    /// Renders trace all rays on a single thread, so keeping the counters per thread
+    /// means that renders running at the same time (e.g. tests) do not mix their counts.
+    static COUNTERS: [IntersectionCounter; HittableKind::ALL.len()] =
+        const { [const { IntersectionCounter::new() }; HittableKind::ALL.len()] };
+
+    /// Counter of rays tested against the whole scene
+    static RAY_CASTS: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Adds one to the counter
+fn increment(counter: &Cell<u64>) {
+    counter.set(counter.get() + 1);
+}
+
+/// Counts a ray tested against the whole scene (e.g. a camera, bounce or shadow ray)
+pub fn record_ray_cast() {
+    RAY_CASTS.with(increment);
+}
+
+/// Returns the amount of rays tested against the whole scene on this thread
+pub fn ray_casts() -> u64 {
+    RAY_CASTS.with(Cell::get)
+}
+
+/// Counts an intersection test of the hittable type
+///
+/// ## Parameters
+/// * `kind` - type of the tested hittable
+/// * `box_rejected` - whether the test ended at the bounding box
+/// * `hit` - whether the ray hit the object
+pub fn record_intersection(kind: HittableKind, box_rejected: bool, hit: bool) {
+    COUNTERS.with(|counters| {
+        let counter = &counters[kind as usize];
+        increment(&counter.tests);
+        if box_rejected {
+            increment(&counter.box_rejections);
+        }
+        if hit {
+            increment(&counter.hits);
+        }
+    });
+}
+
+/// Returns the counts of intersection tests on this thread for every hittable type
+///
+/// The counters are never reset, so the work of a single render
+/// is the difference between the counts after and before it.
+pub fn intersection_stats() -> Vec<IntersectionStats> {
+    COUNTERS.with(|counters| {
+        HittableKind::ALL
+            .iter()
+            .zip(counters.iter())
+            .map(|(&kind, counter)| IntersectionStats {
+                kind,
+                tests: counter.tests.get(),
+                box_rejections: counter.box_rejections.get(),
+                hits: counter.hits.get(),
+            })
+            .collect()
+    })
+}
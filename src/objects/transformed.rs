@@ -0,0 +1,218 @@
+use std::sync::Arc;
+
+use glam::{Mat3A, Mat4, Vec3A};
+
+use crate::{
+    aabb::Aabb,
+    interval::Interval,
+    math::{inverse_mat4, normal_matrix, normalize_vec3},
+    ray::Ray,
+};
+
+use super::{AnyHittable, HitRecord, Hittable};
+
+/// Wrapper that places an object into the scene with an affine transform
+///
+/// The ray is transformed into the object space with the inverse matrix,
+/// the hit is calculated on the wrapped object, and the hit point is transformed back.
+/// Normals are transformed with the inverse-transpose of the matrix,
+/// so that they stay perpendicular to the surface under shear and non-uniform scale,
+/// and keep pointing outwards when the object is mirrored (negative scale).
+/// The side that was hit is decided again in the scene space.
+pub struct Transformed {
+    object: Arc<AnyHittable>,
+    transform: Mat4,
+    inverse: Mat4,
+    normal_matrix: Mat3A,
+    bounding_box: Aabb,
+}
+
+impl Transformed {
+    /// Creates a new transformed object
+    ///
+    /// ## Parameters
+    /// * `object` - the object to transform
+    /// * `transform` - affine transform from object space to the scene (must be invertible)
+    pub fn new<H>(object: H, transform: Mat4) -> Self
+    where
+        H: Into<Arc<AnyHittable>>,
+    {
+        let object = object.into();
+        let (Some(inverse), Some(normal_matrix)) =
+            (inverse_mat4(transform), normal_matrix(transform))
+        else {
+            panic!("Transform matrix is not invertible!");
+        };
+
+        // The transformed box is enclosed by the transformed corners of the original box
+        let object_box = object.bounding_box();
+        let (min, max) = (object_box.min(), object_box.max());
+        let corners: Vec<Vec3A> = (0..8)
+            .map(|i| {
+                let corner = Vec3A::new(
+                    if i & 1 == 0 { min.x } else { max.x },
+                    if i & 2 == 0 { min.y } else { max.y },
+                    if i & 4 == 0 { min.z } else { max.z },
+                );
+                transform.transform_point3a(corner)
+            })
+            .collect();
+        let bounding_box = Aabb::from_points(&corners);
+
+        Self {
+            object,
+            transform,
+            inverse,
+            normal_matrix,
+            bounding_box,
+        }
+    }
+
+    /// Returns the bounding box of the transformed object
+    pub fn bounding_box(&self) -> Aabb {
+        self.bounding_box
+    }
+}
+
+impl Hittable for Transformed {
+    fn hit(&self, ray: &Ray, ray_interval: Interval) -> Option<HitRecord> {
+        // The direction is not normalized, so the parameter t stays the same in both spaces
+        let object_ray = ray.transform(&self.inverse);
+        let object_hit = self.object.hit(&object_ray, ray_interval)?;
+
+        let object_outward_normal = if object_hit.front_face() {
+            object_hit.normal()
+        } else {
+            -object_hit.normal()
+        };
+        let point = self.transform.transform_point3a(object_hit.point());
+        let outward_normal = normalize_vec3(self.normal_matrix * object_outward_normal);
+
+        let mut hit_record = HitRecord::new(
+            point,
+            outward_normal,
+            object_hit.t(),
+            false,
+            object_hit.material(),
+        );
+        hit_record.set_face_normal(ray, outward_normal);
+        hit_record.set_uv(object_hit.u(), object_hit.v());
+        Some(hit_record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        color::RGBColor, materials::lambertarian::LambertarianDiffuse, math::build_onb,
+        objects::sphere::Sphere,
+    };
+
+    use super::*;
+
+    #[test]
+    fn scaled_sphere_is_an_ellipsoid() {
+        let sphere = Sphere::new(
+            Vec3A::ZERO,
+            1.0,
+            LambertarianDiffuse::new_counter(RGBColor::white()),
+        );
+        let ellipsoid = Transformed::new(sphere, Mat4::from_scale(glam::Vec3::new(2.0, 1.0, 1.0)));
+        let interval = Interval::new(0.001, f32::INFINITY);
+
+        let along_x = Ray::new(Vec3A::new(-5.0, 0.0, 0.0), Vec3A::X);
+        let x_hit = ellipsoid.hit(&along_x, interval).unwrap();
+        assert!((x_hit.t() - 3.0).abs() < 1e-5, "{}", x_hit.t());
+        assert!(x_hit.normal().abs_diff_eq(-Vec3A::X, 1e-5));
+
+        let along_y = Ray::new(Vec3A::new(0.0, -5.0, 0.0), Vec3A::Y);
+        let y_hit = ellipsoid.hit(&along_y, interval).unwrap();
+        assert!((y_hit.t() - 4.0).abs() < 1e-5, "{}", y_hit.t());
+
+        // The normal of the stretched surface is not the direction from the center
+        let diagonal = Ray::new(Vec3A::new(1.0, -5.0, 0.0), Vec3A::Y);
+        let diagonal_hit = ellipsoid.hit(&diagonal, interval).unwrap();
+        let point = diagonal_hit.point();
+        let expected_normal = Vec3A::new(point.x / 4.0, point.y, 0.0).normalize();
+        assert!(
+            diagonal_hit.normal().abs_diff_eq(expected_normal, 1e-5),
+            "{}",
+            diagonal_hit.normal()
+        );
+        assert_eq!(ellipsoid.bounding_box().max(), Vec3A::new(2.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn rotated_sphere_is_hit_like_a_sphere_placed_there() {
+        let material = LambertarianDiffuse::new_counter(RGBColor::white());
+        let center = Vec3A::new(0.0, 2.0, 0.0);
+        let sphere = Arc::new(AnyHittable::from(Sphere::new(
+            center,
+            0.5,
+            material.clone(),
+        )));
+        let interval = Interval::new(0.001, f32::INFINITY);
+
+        // Rotating Y onto itself or onto -Y has no unique rotation axis
+        for target in [glam::Vec3::Y, glam::Vec3::NEG_Y, glam::Vec3::X] {
+            let rotation = Mat4::from_quat(glam::Quat::from_rotation_arc(glam::Vec3::Y, target));
+            let rotated = Transformed::new(sphere.clone(), rotation);
+            let rotated_center = 2.0 * Vec3A::from(target);
+            let placed = Sphere::new(rotated_center, 0.5, material.clone());
+
+            // From outside (front face) and from the center (back face)
+            let (tangent, _, _) = build_onb(target.into());
+            for ray in [
+                Ray::new(Vec3A::ZERO, rotated_center + 0.3 * tangent),
+                Ray::new(rotated_center, Vec3A::new(1.0, 1.0, 1.0)),
+            ] {
+                let rotated_hit = rotated.hit(&ray, interval).unwrap();
+                let placed_hit = placed.hit(&ray, interval).unwrap();
+                assert!((rotated_hit.t() - placed_hit.t()).abs() < 1e-5);
+                assert!(
+                    rotated_hit.point().abs_diff_eq(placed_hit.point(), 1e-5),
+                    "{} != {}",
+                    rotated_hit.point(),
+                    placed_hit.point()
+                );
+                assert!(
+                    rotated_hit.normal().abs_diff_eq(placed_hit.normal(), 1e-5),
+                    "{} != {}",
+                    rotated_hit.normal(),
+                    placed_hit.normal()
+                );
+                assert_eq!(rotated_hit.front_face(), placed_hit.front_face());
+            }
+        }
+    }
+
+    #[test]
+    fn mirrored_sphere_normals_point_outward() {
+        let sphere = Sphere::new(
+            Vec3A::new(1.0, 0.0, 0.0),
+            0.5,
+            LambertarianDiffuse::new_counter(RGBColor::white()),
+        );
+        let mirrored = Transformed::new(sphere, Mat4::from_scale(glam::Vec3::new(-1.0, 1.0, 1.0)));
+        let center = Vec3A::new(-1.0, 0.0, 0.0);
+        let interval = Interval::new(0.001, f32::INFINITY);
+
+        let from_outside = Ray::new(Vec3A::new(-5.0, 0.2, 0.1), Vec3A::X);
+        let outside_hit = mirrored.hit(&from_outside, interval).unwrap();
+        let outward_normal = (outside_hit.point() - center).normalize();
+        assert!(outside_hit.front_face());
+        assert!(
+            outside_hit.normal().abs_diff_eq(outward_normal, 1e-5),
+            "{} != {}",
+            outside_hit.normal(),
+            outward_normal
+        );
+
+        // From the inside, the normal faces the ray (against the outward normal)
+        let from_inside = Ray::new(center, Vec3A::new(1.0, 0.5, 0.0));
+        let inside_hit = mirrored.hit(&from_inside, interval).unwrap();
+        let outward_normal = (inside_hit.point() - center).normalize();
+        assert!(!inside_hit.front_face());
+        assert!(inside_hit.normal().abs_diff_eq(-outward_normal, 1e-5));
+    }
+}
@@ -0,0 +1,95 @@
+use std::sync::Arc;
+
+use glam::Vec3A;
+
+use crate::{aabb::Aabb, interval::Interval, materials::AnyMaterial, ray::Ray};
+
+use super::{HitRecord, Hittable};
+
+/// Edges of a box, for showing bounding boxes while debugging
+///
+/// Only the parts of the box faces that are closer than the thickness
+/// to one of the twelve edges can be hit. The rest of the box is empty,
+/// so the far edges are seen through it.
+pub struct WireframeBox {
+    bounding_box: Aabb,
+    thickness: f32,
+
+    material: Arc<AnyMaterial>,
+}
+
+impl WireframeBox {
+    /// Creates the edges of the box
+    ///
+    /// ## Parameters
+    /// * `bounding_box` - the box to show
+    /// * `thickness` - width of the edges, as seen on the box faces
+    /// * `material` - material of the edges
+    pub fn new<M>(bounding_box: Aabb, thickness: f32, material: M) -> Self
+    where
+        M: Into<Arc<AnyMaterial>>,
+    {
+        Self {
+            bounding_box,
+            thickness,
+            material: material.into(),
+        }
+    }
+
+    /// Returns the bounding box of the edges
+    pub fn bounding_box(&self) -> Aabb {
+        self.bounding_box
+    }
+
+    /// Returns the outward normal of the face the point lies on,
+    /// if the point is close to an edge of that face
+    ///
+    /// ## Parameters
+    /// * `point` - point on the surface of the box
+    fn edge_normal(&self, point: Vec3A) -> Option<Vec3A> {
+        let min = self.bounding_box.min();
+        let max = self.bounding_box.max();
+        let mut near_faces = 0;
+        let mut closest = (f32::INFINITY, Vec3A::ZERO);
+        for axis in 0..3 {
+            let to_min = point[axis] - min[axis];
+            let to_max = max[axis] - point[axis];
+            let (distance, sign) = if to_min < to_max {
+                (to_min, -1.0)
+            } else {
+                (to_max, 1.0)
+            };
+            if distance <= self.thickness {
+                near_faces += 1;
+            }
+            if distance < closest.0 {
+                let mut normal = Vec3A::ZERO;
+                normal[axis] = sign;
+                closest = (distance, normal);
+            }
+        }
+        // Points on an edge are close to two faces
+        (near_faces >= 2).then_some(closest.1)
+    }
+}
+
+impl Hittable for WireframeBox {
+    fn hit(&self, ray: &Ray, ray_interval: Interval) -> Option<HitRecord> {
+        let inside = self
+            .bounding_box
+            .clip(ray, Interval::new(-f32::INFINITY, f32::INFINITY))?;
+
+        // The ray may pass the box where it enters and where it leaves it
+        [inside.min(), inside.max()]
+            .into_iter()
+            .filter(|&t| ray_interval.contains(t))
+            .find_map(|t| {
+                let point = ray.at(t);
+                let outward_normal = self.edge_normal(point)?;
+                let mut hit_record =
+                    HitRecord::new(point, outward_normal, t, true, self.material.clone());
+                hit_record.set_face_normal(ray, outward_normal);
+                Some(hit_record)
+            })
+    }
+}
@@ -0,0 +1,150 @@
+use crate::color::RGBColor;
+
+use super::ExportError;
+
+/// Character whose upper half is drawn with the foreground color
+/// and lower half with the background color
+const UPPER_HALF_BLOCK: char = '▀';
+
+/// Returns the image as text with 24-bit ANSI colors, to be printed to a terminal
+///
+/// The image is shrunk to fit the terminal (keeping its aspect ratio, never enlarged).
+/// Every character shows two pixels above each other, which keeps pixels roughly square.
+/// Each row of text ends with a line break.
+///
+/// ## Arguments
+/// * `rgb_data` - a 1D vector or slice of RGB colored pixels in the image
+/// * `width` - width of image
+/// * `height` - height of image
+/// * `columns` - width of the terminal in characters
+/// * `rows` - height of the terminal in characters
+pub fn rgb_to_ansi(
+    rgb_data: &[RGBColor],
+    width: usize,
+    height: usize,
+    columns: usize,
+    rows: usize,
+) -> Result<String, ExportError> {
+    // Check if we actually have enough data
+    if width * height > rgb_data.len() {
+        return Err(ExportError::SizeExceedsData(width, height, rgb_data.len()));
+    }
+    if width == 0 || height == 0 {
+        return Ok(String::new());
+    }
+
+    let (preview_width, preview_height) = preview_size(width, height, columns, rows * 2);
+    let preview = downscale(rgb_data, width, height, preview_width, preview_height);
+
+    let mut output = String::new();
+    for row in preview.chunks(preview_width * 2) {
+        let (upper, lower) = row.split_at(preview_width.min(row.len()));
+        for (i, color) in upper.iter().enumerate() {
+            let [r, g, b] = color.to_rgb8();
+            output.push_str(&format!("\x1b[38;2;{};{};{}m", r, g, b));
+            // An odd amount of pixel rows leaves the lower half of the last row empty
+            match lower.get(i) {
+                Some(color) => {
+                    let [r, g, b] = color.to_rgb8();
+                    output.push_str(&format!("\x1b[48;2;{};{};{}m", r, g, b));
+                }
+                None => output.push_str("\x1b[49m"),
+            }
+            output.push(UPPER_HALF_BLOCK);
+        }
+        output.push_str("\x1b[0m\n");
+    }
+
+    Ok(output)
+}
+
+/// Returns the size of the image shrunk to fit into the given amount of pixels
+///
+/// ## Parameters
+/// * `width` - width of image
+/// * `height` - height of image
+/// * `max_width` - the largest allowed width
+/// * `max_height` - the largest allowed height
+fn preview_size(
+    width: usize,
+    height: usize,
+    max_width: usize,
+    max_height: usize,
+) -> (usize, usize) {
+    let scale = (max_width as f32 / width as f32)
+        .min(max_height as f32 / height as f32)
+        .min(1.0);
+    let preview_width = ((width as f32 * scale).round() as usize).clamp(1, width);
+    let preview_height = ((height as f32 * scale).round() as usize).clamp(1, height);
+    (preview_width, preview_height)
+}
+
+/// Shrinks the image by averaging the pixels covered by each new pixel
+///
+/// ## Parameters
+/// * `rgb_data` - pixels of the image
+/// * `width` - width of image
+/// * `height` - height of image
+/// * `new_width` - width of the shrunk image (at most `width`)
+/// * `new_height` - height of the shrunk image (at most `height`)
+fn downscale(
+    rgb_data: &[RGBColor],
+    width: usize,
+    height: usize,
+    new_width: usize,
+    new_height: usize,
+) -> Vec<RGBColor> {
+    // Range of original pixels covered by the new pixel
+    let span = |index: usize, size: usize, new_size: usize| {
+        let start = index * size / new_size;
+        let end = ((index + 1) * size / new_size).max(start + 1);
+        start..end
+    };
+
+    let mut output = Vec::with_capacity(new_width * new_height);
+    for j in 0..new_height {
+        for i in 0..new_width {
+            let mut sum = RGBColor::black();
+            let mut count = 0;
+            for y in span(j, height, new_height) {
+                for x in span(i, width, new_width) {
+                    sum = sum + rgb_data[y * width + x];
+                    count += 1;
+                }
+            }
+            output.push(sum / count as f32);
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preview_fits_into_the_terminal() {
+        let image = vec![RGBColor::new(0.5, 0.25, 1.0); 100 * 60];
+        // 10 rows of text hold 20 rows of pixels, so the image is shrunk to 33x20
+        let preview = rgb_to_ansi(&image, 100, 60, 40, 10).unwrap();
+        let lines: Vec<&str> = preview.lines().collect();
+        assert_eq!(lines.len(), 10);
+        for line in lines {
+            assert_eq!(line.matches(UPPER_HALF_BLOCK).count(), 33);
+            assert!(line.ends_with("\x1b[0m"));
+        }
+    }
+
+    #[test]
+    fn single_pixel_has_an_empty_lower_half() {
+        let preview = rgb_to_ansi(&[RGBColor::white()], 1, 1, 80, 24).unwrap();
+        assert_eq!(
+            preview,
+            format!(
+                "\x1b[38;2;255;255;255m\x1b[49m{}\x1b[0m\n",
+                UPPER_HALF_BLOCK
+            )
+        );
+        assert!(rgb_to_ansi(&[], 1, 1, 80, 24).is_err());
+    }
+}
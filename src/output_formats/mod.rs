@@ -1,7 +1,63 @@
-use std::{error::Error, fmt::Display};
+use std::{error::Error, fmt::Display, str::FromStr};
 
+use crate::color::RGBColor;
+
+use self::{pfm::rgb_to_pfm, ppm::rgb_to_binary_ppm};
+
+pub mod ansi;
+pub mod pfm;
 pub mod ppm;
 
+/// File format of the written images
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum OutputFormat {
+    /// Binary PPM with 8 bits per channel (values are clamped)
+    #[default]
+    Ppm,
+    /// Portable Float Map with full float precision (values are kept as they are)
+    Pfm,
+}
+
+impl OutputFormat {
+    /// Returns the file extension of the format (without the dot)
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Ppm => "ppm",
+            OutputFormat::Pfm => "pfm",
+        }
+    }
+
+    /// Encodes the image in the format
+    ///
+    /// ## Arguments
+    /// * `rgb_data` - a 1D vector or slice of RGB colored pixels in the image
+    /// * `width` - width of image
+    /// * `height` - height of image
+    pub fn encode(
+        &self,
+        rgb_data: &[RGBColor],
+        width: usize,
+        height: usize,
+    ) -> Result<Vec<u8>, ExportError> {
+        match self {
+            OutputFormat::Ppm => rgb_to_binary_ppm(rgb_data, width, height),
+            OutputFormat::Pfm => rgb_to_pfm(rgb_data, width, height),
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ppm" => Ok(Self::Ppm),
+            "pfm" => Ok(Self::Pfm),
+            _ => Err(format!("Unknown output format '{}'", s)),
+        }
+    }
+}
+
 /// Errors in image generation
 #[derive(Debug)]
 pub enum ExportError {
@@ -0,0 +1,41 @@
+use crate::color::RGBColor;
+
+use super::ExportError;
+
+/// Returns a vector of bytes representing a color Portable Float Map (PFM) image
+///
+/// Values are written as they are (little-endian `f32`), without clamping,
+/// so colors brighter than `1.0` are preserved. As the format requires,
+/// scanlines go from the bottom to the top of the image.
+///
+/// ## Arguments
+/// * `rgb_data` - a 1D vector or slice of RGB colored pixels in the image
+/// * `width` - width of image
+/// * `height` - height of image
+pub fn rgb_to_pfm(
+    rgb_data: &[RGBColor],
+    width: usize,
+    height: usize,
+) -> Result<Vec<u8>, ExportError> {
+    // Check if we actually have enough data
+    if width * height > rgb_data.len() {
+        return Err(ExportError::SizeExceedsData(width, height, rgb_data.len()));
+    }
+
+    // Negative scale marks little-endian values
+    let mut output: Vec<u8> = format!("PF\n{} {}\n-1.0\n", width, height)
+        .bytes()
+        .collect();
+
+    if width > 0 {
+        for scanline in rgb_data[..width * height].chunks_exact(width).rev() {
+            for color in scanline.iter() {
+                for channel in [color.r(), color.g(), color.b()] {
+                    output.extend_from_slice(&channel.to_le_bytes());
+                }
+            }
+        }
+    }
+
+    Ok(output)
+}
@@ -26,12 +26,7 @@ pub fn rgb_to_binary_ppm(
     output.append(&mut header);
 
     for color in rgb_data.iter() {
-        let mut modified_color = *color;
-        modified_color.clamp();
-        let resized_color = modified_color * 255.0;
-        output.push(resized_color.r() as u8);
-        output.push(resized_color.g() as u8);
-        output.push(resized_color.b() as u8);
+        output.extend_from_slice(&color.to_rgb8());
     }
 
     Ok(output)
@@ -43,6 +38,7 @@ pub fn rgb_to_binary_ppm(
 /// * `rgb_data` - a 1D vector or slice of RGB colored pixels in the image
 /// * `width` - width of image
 /// * `height` - height of image
+#[allow(dead_code)] // Only used while debugging
 pub fn rgb_to_ascii_ppm(
     rgb_data: &[RGBColor],
     width: usize,
@@ -0,0 +1,84 @@
+use crate::color::RGBColor;
+
+/// Luminance around which the contrast is changed (middle gray)
+const CONTRAST_PIVOT: f32 = 0.18;
+
+/// Adjusts the saturation, hue and contrast of the image data
+///
+/// Saturation blends every color with the gray of the same luminance,
+/// so 0.0 gives a grayscale image and values above 1.0 make colors more vivid.
+/// Hue shift rotates colors around the color wheel (e.g. 120 turns red into green).
+/// Contrast is a power curve around middle gray, so black and middle gray stay in place,
+/// values above 1.0 darken the shadows and brighten the highlights.
+/// Saturation of 1.0, hue shift of 0.0 (or 360.0) and contrast of 1.0 leave the image unchanged.
+///
+/// ## Parameters
+/// * `image_data` - linear image data
+/// * `saturation` - factor of the color saturation (at least 0.0)
+/// * `hue_shift` - rotation of the hue in degrees
+/// * `contrast` - exponent of the contrast curve (larger than 0.0)
+pub fn color_grade(image_data: &mut [RGBColor], saturation: f32, hue_shift: f32, contrast: f32) {
+    let hue_shift = hue_shift.rem_euclid(360.0);
+    for color in image_data.iter_mut() {
+        if hue_shift != 0.0 {
+            let (hue, color_saturation, value) = color.to_hsv();
+            *color = RGBColor::from_hsv(hue + hue_shift, color_saturation, value);
+        }
+        if saturation != 1.0 {
+            let luminance = color.luminance();
+            let gray = RGBColor::new(luminance, luminance, luminance);
+            *color = RGBColor::lerp(gray, *color, saturation);
+        }
+        if contrast != 1.0 {
+            let curve =
+                |value: f32| CONTRAST_PIVOT * (value.max(0.0) / CONTRAST_PIVOT).powf(contrast);
+            *color = RGBColor::new(curve(color.r()), curve(color.g()), curve(color.b()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_color_near(actual: RGBColor, expected: RGBColor) {
+        let difference = actual - expected;
+        assert!(
+            [difference.r(), difference.g(), difference.b()]
+                .iter()
+                .all(|channel| channel.abs() < 1e-5),
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn zero_saturation_gives_the_gray_of_the_luminance() {
+        let color = RGBColor::new(0.9, 0.3, 0.1);
+        let mut image_data = [color];
+        color_grade(&mut image_data, 0.0, 0.0, 1.0);
+        let luminance = color.luminance();
+        assert!(image_data[0] == RGBColor::new(luminance, luminance, luminance));
+    }
+
+    #[test]
+    fn full_turn_of_the_hue_changes_nothing() {
+        let colors = [
+            RGBColor::new(0.9, 0.3, 0.1),
+            RGBColor::new(0.2, 0.5, 0.4),
+            RGBColor::new(1.5, 0.0, 2.0),
+        ];
+        let mut image_data = colors;
+        color_grade(&mut image_data, 1.0, 360.0, 1.0);
+        for (graded, color) in image_data.into_iter().zip(colors) {
+            assert!(graded == color, "{:?} != {:?}", graded, color);
+        }
+
+        // Two thirds of a turn move red to blue, and green back to red
+        let mut image_data = [RGBColor::new(1.0, 0.0, 0.0), RGBColor::new(0.0, 0.5, 0.0)];
+        color_grade(&mut image_data, 1.0, 240.0, 1.0);
+        assert_color_near(image_data[0], RGBColor::new(0.0, 0.0, 1.0));
+        assert_color_near(image_data[1], RGBColor::new(0.5, 0.0, 0.0));
+    }
+}
@@ -0,0 +1,73 @@
+use crate::{color::RGBColor, error::RaybowError};
+
+use super::PostProcessResult;
+
+/// Places the image over a background image, using the opacity of every pixel
+///
+/// Rendered colors are not multiplied by the opacity (transparent pixels show
+/// the background of the scene), so both images are weighted:
+/// `foreground * alpha + background * (1 - alpha)`.
+/// Opaque pixels keep the render, transparent ones show the background image.
+///
+/// ## Parameters
+/// * `fg` - the postprocessed render, with the alpha buffer
+/// * `bg` - colors of the background image, the same size as the render
+pub fn composite_over(
+    fg: &PostProcessResult,
+    bg: &[RGBColor],
+) -> Result<Vec<RGBColor>, RaybowError> {
+    let Some(alpha_data) = &fg.alpha_data else {
+        return Err(RaybowError::InvalidArguments(String::from(
+            "Compositing needs the opacity of every pixel",
+        )));
+    };
+    if bg.len() != fg.width * fg.height {
+        return Err(RaybowError::InvalidArguments(format!(
+            "Background image with {} pixels does not match the {}x{} image",
+            bg.len(),
+            fg.width,
+            fg.height
+        )));
+    }
+
+    Ok(fg
+        .image_data
+        .iter()
+        .zip(alpha_data)
+        .zip(bg)
+        .map(|((&foreground, &alpha), &background)| {
+            let alpha = alpha.clamp(0.0, 1.0);
+            foreground * alpha + background * (1.0 - alpha)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opacity_chooses_between_the_render_and_the_background() {
+        let red = RGBColor::new(1.0, 0.0, 0.0);
+        let blue = RGBColor::new(0.0, 0.0, 1.0);
+        let foreground = PostProcessResult {
+            width: 3,
+            height: 1,
+            image_data: vec![red; 3],
+            albedo_data: None,
+            variance_data: None,
+            alpha_data: Some(vec![1.0, 0.0, 0.25]),
+            object_id_data: None,
+        };
+        let composited = composite_over(&foreground, &[blue; 3]).unwrap();
+        assert!(composited[0] == red, "{:?}", composited[0]);
+        assert!(composited[1] == blue, "{:?}", composited[1]);
+        assert!(
+            composited[2] == RGBColor::new(0.25, 0.0, 0.75),
+            "{:?}",
+            composited[2]
+        );
+
+        assert!(composite_over(&foreground, &[blue; 2]).is_err());
+    }
+}
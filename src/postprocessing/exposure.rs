@@ -0,0 +1,115 @@
+use crate::color::RGBColor;
+
+/// Adjusts the exposure of the image data
+///
+/// Every stop (EV) doubles the brightness, so colors are multiplied by `2^ev`.
+/// Exposure of `0.0` leaves the image unchanged.
+///
+/// ## Parameters
+/// * `image_data` - linear image data
+/// * `ev` - exposure value (positive brightens, negative darkens)
+pub fn apply_exposure(image_data: &mut [RGBColor], ev: f32) {
+    apply_multiplier(image_data, 2.0f32.powf(ev));
+}
+
+/// Fraction of the pixels that may be brighter than white after auto-exposure
+const AUTO_EXPOSURE_HIGHLIGHTS: f32 = 0.05;
+
+/// Lowest and highest luminance (in stops) in the auto-exposure histogram
+const HISTOGRAM_RANGE: (f32, f32) = (-20.0, 20.0);
+
+/// Buckets of the auto-exposure histogram in every stop
+const BUCKETS_PER_STOP: usize = 8;
+
+/// Calculates the brightness multiplier that maps the 95th percentile
+/// of the image luminance to white
+///
+/// Luminances are collected into a histogram with buckets of equal width in stops.
+/// The brightest 5 % of pixels (e.g. lights and fireflies) do not affect the result,
+/// they are simply brighter than white. Black images keep their brightness (`1.0`).
+///
+/// ## Parameters
+/// * `image_data` - linear image data
+pub fn auto_exposure(image_data: &[RGBColor]) -> f32 {
+    let (min_stop, max_stop) = HISTOGRAM_RANGE;
+    let bucket_count = ((max_stop - min_stop) as usize) * BUCKETS_PER_STOP;
+    let bucket_size = 1.0 / BUCKETS_PER_STOP as f32;
+
+    let mut histogram = vec![0usize; bucket_count];
+    let mut pixel_count = 0;
+    for color in image_data {
+        let luminance = color.luminance();
+        // Black pixels would need an infinite exposure, and invalid ones can't be placed
+        if luminance <= 0.0 || !luminance.is_finite() {
+            continue;
+        }
+        let stop = luminance.log2().clamp(min_stop, max_stop);
+        let bucket = (((stop - min_stop) / bucket_size) as usize).min(bucket_count - 1);
+        histogram[bucket] += 1;
+        pixel_count += 1;
+    }
+    if pixel_count == 0 {
+        return 1.0;
+    }
+
+    // The bucket where the darker pixels (all but the highlights) end
+    let target_count = ((1.0 - AUTO_EXPOSURE_HIGHLIGHTS) * pixel_count as f32).ceil() as usize;
+    let mut count = 0;
+    let percentile_bucket = histogram
+        .iter()
+        .position(|bucket_pixels| {
+            count += bucket_pixels;
+            count >= target_count
+        })
+        .unwrap_or(bucket_count - 1);
+
+    // The upper edge of the bucket, so that the whole bucket stays below white
+    let percentile_stop = min_stop + (percentile_bucket + 1) as f32 * bucket_size;
+    2.0f32.powf(-percentile_stop)
+}
+
+/// Multiplies the image data by the factor
+///
+/// ## Parameters
+/// * `image_data` - linear image data
+/// * `factor` - brightness multiplier
+pub fn apply_multiplier(image_data: &mut [RGBColor], factor: f32) {
+    for color in image_data {
+        *color = *color * factor;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auto_exposure_maps_the_95th_percentile_to_white() {
+        // Luminances spread evenly from 0.002 to 2.0, so the 95th percentile is 1.9
+        let mut image_data: Vec<RGBColor> = (1..=1000)
+            .map(|i| {
+                let luminance = i as f32 / 500.0;
+                RGBColor::new(luminance, luminance, luminance)
+            })
+            .collect();
+        let multiplier = auto_exposure(&image_data);
+        // Within one histogram bucket
+        let mapped_percentile = 1.9 * multiplier;
+        assert!(
+            mapped_percentile <= 1.0 && mapped_percentile > 0.9,
+            "{}",
+            mapped_percentile
+        );
+
+        // A few fireflies do not darken the image
+        image_data.extend(std::iter::repeat_n(RGBColor::new(1e4, 1e4, 1e4), 10));
+        let with_fireflies = auto_exposure(&image_data);
+        assert!(
+            (with_fireflies / multiplier - 1.0).abs() < 0.1,
+            "{}",
+            with_fireflies
+        );
+
+        assert_eq!(auto_exposure(&[RGBColor::black(); 4]), 1.0);
+    }
+}
@@ -1,13 +1,68 @@
+use std::str::FromStr;
+
 use crate::color::RGBColor;
 
+/// Curve with which the image is transformed into gamma space
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum GammaCurve {
+    /// Power of 2 (square root), fast and close to sRGB in bright tones
+    #[default]
+    Power,
+    /// The exact sRGB transfer function, with a linear segment close to black
+    Srgb,
+}
+
+impl FromStr for GammaCurve {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "power" => Ok(Self::Power),
+            "srgb" => Ok(Self::Srgb),
+            _ => Err(format!("Unknown gamma curve '{}'", s)),
+        }
+    }
+}
+
 /// Transform image data from linear to gamma space.
 ///
 /// Render data is by default in linear space.
 /// This postprocessing step transforms it into gamma space,
 /// so the final image can be viewed by external programs
 /// correctly. By default, power of 2 is used for conversion.
-pub fn linear_to_gamma_space(image_data: &mut [RGBColor]) {
+///
+/// ## Parameters
+/// * `image_data` - linear colors of the image
+/// * `curve` - the curve used for the conversion
+pub fn linear_to_gamma_space(image_data: &mut [RGBColor], curve: GammaCurve) {
     for color in image_data {
-        color.linear_to_gamma();
+        match curve {
+            GammaCurve::Power => color.linear_to_gamma(),
+            GammaCurve::Srgb => color.linear_to_srgb(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoded(value: f32, curve: GammaCurve) -> f32 {
+        let mut image_data = [RGBColor::new(value, value, value)];
+        linear_to_gamma_space(&mut image_data, curve);
+        image_data[0].r()
+    }
+
+    #[test]
+    fn srgb_has_a_linear_segment_near_black() {
+        // 12.92 times the linear value, instead of the much brighter power curve
+        let dark = encoded(0.001, GammaCurve::Srgb);
+        assert!((dark - 0.01292).abs() < 1e-6, "{}", dark);
+        assert!(encoded(0.001, GammaCurve::Power) > 0.03);
+
+        // Linear mid-gray is encoded as 0.7354 (188 of 255)
+        let mid_gray = encoded(0.5, GammaCurve::Srgb);
+        assert!((mid_gray - 0.7354).abs() < 1e-4, "{}", mid_gray);
+        assert!((encoded(1.0, GammaCurve::Srgb) - 1.0).abs() < 1e-6);
     }
 }
@@ -1,11 +1,30 @@
-use crate::{color::RGBColor, rendering::RenderResult, Arguments};
+use std::path::Path;
 
+use crate::{
+    color::RGBColor, error::RaybowError, input_formats::read_image_file, rendering::RenderResult,
+    Arguments,
+};
+
+mod color_grade;
+mod composite;
+mod exposure;
 mod gamma_correction;
+mod resize;
+
+pub use gamma_correction::GammaCurve;
 
 pub struct PostProcessResult {
     pub width: usize,
     pub height: usize,
     pub image_data: Vec<RGBColor>,
+    /// Albedo buffer, passed through from rendering (only resized)
+    pub albedo_data: Option<Vec<RGBColor>>,
+    /// Variance buffer, passed through from rendering (only resized)
+    pub variance_data: Option<Vec<RGBColor>>,
+    /// Alpha buffer, passed through from rendering (only resized)
+    pub alpha_data: Option<Vec<f32>>,
+    /// Object ID buffer, passed through from rendering (only resized, without blending IDs)
+    pub object_id_data: Option<Vec<u32>>,
 }
 
 /// Run postprocessing steps, such as gamma correction, etc.
@@ -15,13 +34,142 @@ pub struct PostProcessResult {
 /// * `render_result` - render result
 pub fn postprocess(argumets: &Arguments, render_result: &RenderResult) -> PostProcessResult {
     let mut postprocessing_image_data = render_result.image_data.clone();
-    if argumets.gamma_correction {
-        gamma_correction::linear_to_gamma_space(&mut postprocessing_image_data);
-    }
+    postprocess_image(argumets, &mut postprocessing_image_data);
 
-    PostProcessResult {
+    let result = PostProcessResult {
         width: render_result.width,
         height: render_result.height,
         image_data: postprocessing_image_data,
+        albedo_data: render_result.albedo_data.clone(),
+        variance_data: render_result.variance_data.clone(),
+        alpha_data: render_result.alpha_data.clone(),
+        object_id_data: render_result.object_id_data.clone(),
+    };
+
+    // Resizing comes last, so that it sees the final colors
+    if argumets.output_scale != 1.0 {
+        scale_result(result, argumets.output_scale)
+    } else {
+        result
+    }
+}
+
+/// Scales the image and all buffers by the factor
+///
+/// Colors are interpolated bilinearly, object IDs take the closest pixel.
+///
+/// ## Parameters
+/// * `result` - the postprocessed image and buffers
+/// * `scale` - factor by which width and height change
+fn scale_result(result: PostProcessResult, scale: f32) -> PostProcessResult {
+    let (src_w, src_h) = (result.width, result.height);
+    let dst_w = ((src_w as f32 * scale).round() as usize).max(1);
+    let dst_h = ((src_h as f32 * scale).round() as usize).max(1);
+    log::debug!("Resizing {}x{} to {}x{}", src_w, src_h, dst_w, dst_h);
+
+    let resize_colors = |data: &[RGBColor]| resize::resize(data, src_w, src_h, dst_w, dst_h);
+    let alpha_data = result.alpha_data.map(|alpha_data| {
+        let gray: Vec<RGBColor> = alpha_data
+            .iter()
+            .map(|&alpha| RGBColor::new(alpha, alpha, alpha))
+            .collect();
+        resize_colors(&gray).iter().map(|color| color.r()).collect()
+    });
+
+    PostProcessResult {
+        width: dst_w,
+        height: dst_h,
+        image_data: resize_colors(&result.image_data),
+        albedo_data: result.albedo_data.as_deref().map(resize_colors),
+        variance_data: result.variance_data.as_deref().map(resize_colors),
+        alpha_data,
+        object_id_data: result
+            .object_id_data
+            .map(|ids| resize::resize_nearest(&ids, src_w, src_h, dst_w, dst_h)),
+    }
+}
+
+/// Places the postprocessed image over the background image from the file
+///
+/// Must be the last step, so the background image needs the final size of the image.
+/// The background image is loaded in linear space, so it is gamma corrected
+/// like the image when needed (exposure only changes the render).
+///
+/// ## Parameters
+/// * `arguments` - application configuration arguments
+/// * `result` - the postprocessed image, with the alpha buffer
+/// * `path` - path to the background image
+pub fn composite_over_file(
+    arguments: &Arguments,
+    result: &mut PostProcessResult,
+    path: &Path,
+) -> Result<(), RaybowError> {
+    let background = read_image_file(path)?;
+    if background.width != result.width || background.height != result.height {
+        return Err(RaybowError::InvalidArguments(format!(
+            "Cannot composite the {}x{} image over a {}x{} background",
+            result.width, result.height, background.width, background.height
+        )));
+    }
+    let mut background_data = background.image_data;
+    if arguments.gamma_correction {
+        gamma_correction::linear_to_gamma_space(&mut background_data, arguments.gamma_curve);
+    }
+    result.image_data = composite::composite_over(result, &background_data)?;
+    Ok(())
+}
+
+/// Runs the postprocessing steps on the image in place
+///
+/// ## Parameters
+/// * `arguments` - application configuration arguments
+/// * `image_data` - linear colors of the image
+pub fn postprocess_image(arguments: &Arguments, image_data: &mut [RGBColor]) {
+    // Exposure works on linear data, so it needs to be applied first.
+    // Manual exposure adjusts the automatic one.
+    if arguments.auto_exposure {
+        let factor = exposure::auto_exposure(image_data);
+        log::debug!("Auto-exposure: {:+.2} EV", factor.log2());
+        exposure::apply_multiplier(image_data, factor);
+    }
+    if arguments.exposure != 0.0 {
+        exposure::apply_exposure(image_data, arguments.exposure);
+    }
+    // Grading works on the exposed colors, before they are encoded for display
+    if arguments.saturation != 1.0 || arguments.hue_shift != 0.0 || arguments.contrast != 1.0 {
+        color_grade::color_grade(
+            image_data,
+            arguments.saturation,
+            arguments.hue_shift,
+            arguments.contrast,
+        );
+    }
+    if arguments.gamma_correction {
+        gamma_correction::linear_to_gamma_space(image_data, arguments.gamma_curve);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exposed(options: &[&str]) -> Vec<RGBColor> {
+        let arguments = Arguments::from_options(options);
+        let mut image_data = vec![RGBColor::new(0.2, 0.6, 0.8), RGBColor::new(4.0, 1.0, 0.0)];
+        postprocess_image(&arguments, &mut image_data);
+        image_data
+    }
+
+    #[test]
+    fn every_stop_doubles_the_brightness() {
+        let unchanged = exposed(&[]);
+        let brighter = exposed(&["--exposure", "1"]);
+        let darker = exposed(&["--exposure", "-1"]);
+        for i in 0..unchanged.len() {
+            // Values above 1 are not clamped before exposure
+            assert!(brighter[i] == unchanged[i] * 2.0, "{:?}", brighter[i]);
+            assert!(darker[i] == unchanged[i] * 0.5, "{:?}", darker[i]);
+        }
+        assert!(unchanged[1] == RGBColor::new(4.0, 1.0, 0.0));
     }
 }
@@ -0,0 +1,113 @@
+use crate::color::RGBColor;
+
+/// Resizes the image with bilinear interpolation
+///
+/// Every destination pixel samples the source image at its center,
+/// blending the four closest source pixels. Pixels at the borders
+/// repeat the outermost source pixels.
+///
+/// ## Parameters
+/// * `image_data` - colors of the source image, row by row
+/// * `src_w`, `src_h` - size of the source image
+/// * `dst_w`, `dst_h` - size of the resized image
+pub fn resize(
+    image_data: &[RGBColor],
+    src_w: usize,
+    src_h: usize,
+    dst_w: usize,
+    dst_h: usize,
+) -> Vec<RGBColor> {
+    let mut resized = Vec::with_capacity(dst_w * dst_h);
+    for y in 0..dst_h {
+        let (y0, y1, ty) = source_position(y, src_h, dst_h);
+        for x in 0..dst_w {
+            let (x0, x1, tx) = source_position(x, src_w, dst_w);
+            let top = image_data[y0 * src_w + x0] * (1.0 - tx) + image_data[y0 * src_w + x1] * tx;
+            let bottom =
+                image_data[y1 * src_w + x0] * (1.0 - tx) + image_data[y1 * src_w + x1] * tx;
+            resized.push(top * (1.0 - ty) + bottom * ty);
+        }
+    }
+    resized
+}
+
+/// Resizes the buffer by taking the closest source pixel
+///
+/// Used for values that must not be blended (e.g. object IDs).
+///
+/// ## Parameters
+/// * `data` - values of the source buffer, row by row
+/// * `src_w`, `src_h` - size of the source buffer
+/// * `dst_w`, `dst_h` - size of the resized buffer
+pub fn resize_nearest<T: Copy>(
+    data: &[T],
+    src_w: usize,
+    src_h: usize,
+    dst_w: usize,
+    dst_h: usize,
+) -> Vec<T> {
+    (0..dst_h)
+        .flat_map(|y| (0..dst_w).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let source_x = (x * src_w / dst_w).min(src_w - 1);
+            let source_y = (y * src_h / dst_h).min(src_h - 1);
+            data[source_y * src_w + source_x]
+        })
+        .collect()
+}
+
+/// Finds the two source pixels around the center of the destination pixel along one axis
+///
+/// Returns both pixel indices and the weight of the second one.
+///
+/// ## Parameters
+/// * `index` - index of the destination pixel
+/// * `src_size` - amount of source pixels along the axis
+/// * `dst_size` - amount of destination pixels along the axis
+fn source_position(index: usize, src_size: usize, dst_size: usize) -> (usize, usize, f32) {
+    let position = (index as f32 + 0.5) * src_size as f32 / dst_size as f32 - 0.5;
+    let position = position.clamp(0.0, (src_size - 1) as f32);
+    let first = position.floor() as usize;
+    let second = (first + 1).min(src_size - 1);
+    (first, second, position - first as f32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_color_near(actual: RGBColor, expected: RGBColor) {
+        let difference = actual - expected;
+        assert!(
+            [difference.r(), difference.g(), difference.b()]
+                .iter()
+                .all(|channel| channel.abs() < 1e-6),
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn doubling_interpolates_between_the_corners() {
+        let black = RGBColor::black();
+        let red = RGBColor::new(1.0, 0.0, 0.0);
+        let green = RGBColor::new(0.0, 1.0, 0.0);
+        let blue = RGBColor::new(0.0, 0.0, 1.0);
+        let resized = resize(&[black, red, green, blue], 2, 2, 4, 4);
+        assert_eq!(resized.len(), 16);
+
+        // The outer pixels keep the corner colors
+        assert_color_near(resized[0], black);
+        assert_color_near(resized[3], red);
+        assert_color_near(resized[12], green);
+        assert_color_near(resized[15], blue);
+
+        // Pixels in between are a quarter of the way from their closest corner
+        assert_color_near(resized[1], RGBColor::lerp(black, red, 0.25));
+        assert_color_near(resized[2], RGBColor::lerp(black, red, 0.75));
+        assert_color_near(resized[4], RGBColor::lerp(black, green, 0.25));
+        let center = (resized[5] + resized[6] + resized[9] + resized[10]) / 4.0;
+        assert_color_near(center, (black + red + green + blue) / 4.0);
+    }
+}
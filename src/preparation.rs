@@ -1,30 +1,252 @@
-use std::f32::consts::PI;
+use std::{f32::consts::PI, path::Path, sync::Arc};
 
 use glam::Vec3A;
 
 use crate::{
+    backgrounds::{
+        environment_map::EnvironmentMap, gradient_sky::GradientSky, sky::Sky,
+        solid_color::SolidColor, sun_sky::SunSky, Background, BackgroundKind,
+    },
     camera::Camera,
     color::RGBColor,
-    materials::lambertarian::LambertarianDiffuse,
-    objects::{parallelogram::Parallelogram, sphere::Sphere},
-    ray::Ray,
+    coordinates::UpAxis,
+    error::RaybowError,
+    materials::AnyMaterial,
+    objects::{parallelogram::Parallelogram, sphere::Sphere, AnyHittable},
     rendering::renderables::Renderables,
+    scene_file::{CameraDescription, MaterialDescription, ObjectDescription, SceneFile},
     Arguments,
 };
 
+/// Direction of the sun if none is given (up and to the side of the default camera)
+const DEFAULT_SUN_DIRECTION: Vec3A = Vec3A::new(1.0, 1.0, -1.0);
+
 pub struct SceneData {
     pub camera: Camera,
     pub renderables: Renderables,
-    pub background: Box<dyn Fn(&Ray) -> RGBColor>,
+    pub background: Box<dyn Background>,
+}
+
+/// Builds scene data step by step
+///
+/// Every method returns the builder, so the calls can be chained:
+/// `SceneBuilder::new().camera(camera).sphere(center, radius, material).build()`.
+/// Without other settings, the camera is the default one and the background is the sky.
+/// Hittables get their IDs in the order they are added (see `Renderables::add_hittable`).
+pub struct SceneBuilder {
+    camera: Camera,
+    renderables: Renderables,
+    background: Box<dyn Background>,
+}
+
+impl SceneBuilder {
+    /// Starts an empty scene
+    pub fn new() -> Self {
+        Self {
+            camera: Camera::default(),
+            renderables: Renderables::new(),
+            background: Box::new(Sky),
+        }
+    }
+
+    /// Sets the camera through which the scene is seen
+    ///
+    /// ## Parameters
+    /// * `camera` - the configured camera
+    pub fn camera(mut self, camera: Camera) -> Self {
+        self.camera = camera;
+        self
+    }
+
+    /// Adds a sphere
+    ///
+    /// ## Parameters
+    /// * `center` - the center point of the sphere
+    /// * `radius` - radius of the sphere
+    /// * `material` - material of the sphere
+    pub fn sphere<M>(self, center: Vec3A, radius: f32, material: M) -> Self
+    where
+        M: Into<Arc<AnyMaterial>>,
+    {
+        self.hittable(Sphere::new(center, radius, material))
+    }
+
+    /// Adds a parallelogram
+    ///
+    /// ## Parameters
+    /// * `bottom_left_point` - corner of the parallelogram
+    /// * `up` - vector from the corner along one pair of sides
+    /// * `right` - vector from the corner along the other pair of sides
+    /// * `material` - material of the parallelogram
+    pub fn parallelogram<M>(
+        self,
+        bottom_left_point: Vec3A,
+        up: Vec3A,
+        right: Vec3A,
+        material: M,
+    ) -> Self
+    where
+        M: Into<Arc<AnyMaterial>>,
+    {
+        self.hittable(Parallelogram::new(bottom_left_point, up, right, material))
+    }
+
+    /// Adds any hittable
+    ///
+    /// ## Parameters
+    /// * `hittable` - the hittable to add
+    pub fn hittable<H>(mut self, hittable: H) -> Self
+    where
+        H: Into<Arc<AnyHittable>>,
+    {
+        self.renderables.add_hittable(hittable);
+        self
+    }
+
+    /// Adds a hittable with an emissive material, which is also sampled directly
+    /// (see `Renderables::add_light`)
+    ///
+    /// ## Parameters
+    /// * `light` - the light to add
+    pub fn light<H>(mut self, light: H) -> Self
+    where
+        H: Into<Arc<AnyHittable>>,
+    {
+        self.renderables.add_light(light);
+        self
+    }
+
+    /// Sets the background seen by rays that miss all objects
+    ///
+    /// ## Parameters
+    /// * `background` - the background of the scene
+    pub fn background(mut self, background: Box<dyn Background>) -> Self {
+        self.background = background;
+        self
+    }
+
+    /// Finishes the scene
+    ///
+    /// The acceleration structure is not built yet
+    /// (see `Renderables::build_accelerator`).
+    pub fn build(self) -> SceneData {
+        SceneData {
+            camera: self.camera,
+            renderables: self.renderables,
+            background: self.background,
+        }
+    }
+}
+
+/// Describes the built-in scene, seen through the camera from the arguments
+///
+/// Positions and directions given in the arguments are converted to the internal
+/// (Y up) convention, like in scene files.
+///
+/// ## Parameters
+/// * `arguments` - application parameters
+pub fn default_scene(arguments: &Arguments) -> SceneFile {
+    let up_axis = arguments.up_axis;
+    let r = (PI / 4.0).cos();
+    let diffuse = |r, g, b| MaterialDescription::Diffuse {
+        color: RGBColor::new(r, g, b),
+    };
+
+    SceneFile {
+        background: background_to_y_up(&arguments.background, up_axis).to_string(),
+        camera: CameraDescription {
+            position: up_axis.to_y_up(arguments.camera_position),
+            look_at: up_axis.to_y_up(arguments.look_at),
+            fov: arguments.fov,
+        },
+        objects: vec![
+            ObjectDescription::Sphere {
+                center: Vec3A::new(-r, 0.0, -1.0),
+                radius: r,
+                material: diffuse(0.0, 0.0, 1.0),
+            },
+            ObjectDescription::Sphere {
+                center: Vec3A::new(r, 0.0, -1.0),
+                radius: r,
+                material: diffuse(1.0, 0.0, 0.0),
+            },
+            ObjectDescription::Parallelogram {
+                corner: Vec3A::new(-1.0, 0.0, -1.0),
+                up: Vec3A::new(1.0, 0.0, 0.0),
+                right: Vec3A::new(0.0, 0.0, 1.0),
+                material: diffuse(0.0, 1.0, 0.0),
+            },
+        ],
+    }
 }
 
-/// Calculates sky background color
-pub fn sky_background(ray: &Ray) -> RGBColor {
-    let unit_direction = ray.direction().normalize();
-    let parameter = 0.5 * (unit_direction.y + 1.0);
-    let start_color = RGBColor::new(1.0, 1.0, 1.0); // White
-    let end_color = RGBColor::new(0.5, 0.7, 1.0); // Blue
-    RGBColor::lerp(start_color, end_color, parameter) // We interpolate between white and blue based on vertical direction of the ray
+/// Converts the directions of the background to the internal (Y up) convention
+///
+/// Missing directions are replaced with the default ones, so the background
+/// looks the same in every convention.
+///
+/// ## Parameters
+/// * `background_kind` - the background with directions in the given convention
+/// * `up_axis` - convention of the directions
+fn background_to_y_up(background_kind: &BackgroundKind, up_axis: UpAxis) -> BackgroundKind {
+    match *background_kind {
+        BackgroundKind::Gradient {
+            zenith_color,
+            horizon_color,
+            zenith_direction,
+        } => BackgroundKind::Gradient {
+            zenith_color,
+            horizon_color,
+            zenith_direction: Some(
+                zenith_direction
+                    .map(|direction| up_axis.to_y_up(direction))
+                    .unwrap_or(Vec3A::Y),
+            ),
+        },
+        BackgroundKind::SunSky {
+            sun_direction,
+            angular_size,
+            intensity,
+        } => BackgroundKind::SunSky {
+            sun_direction: Some(
+                sun_direction
+                    .map(|direction| up_axis.to_y_up(direction))
+                    .unwrap_or(DEFAULT_SUN_DIRECTION),
+            ),
+            angular_size,
+            intensity,
+        },
+        ref other => other.clone(),
+    }
+}
+
+/// Creates the background (with directions in the internal convention)
+///
+/// ## Parameters
+/// * `background_kind` - the kind of background to create
+fn create_background(background_kind: &BackgroundKind) -> Box<dyn Background> {
+    match *background_kind {
+        BackgroundKind::Sky => Box::new(Sky),
+        BackgroundKind::SolidColor(color) => Box::new(SolidColor::new(color)),
+        BackgroundKind::Gradient {
+            zenith_color,
+            horizon_color,
+            zenith_direction,
+        } => Box::new(GradientSky::new(
+            zenith_color,
+            horizon_color,
+            zenith_direction.unwrap_or(Vec3A::Y),
+        )),
+        BackgroundKind::SunSky {
+            sun_direction,
+            angular_size,
+            intensity,
+        } => Box::new(SunSky::new(
+            sun_direction.unwrap_or(DEFAULT_SUN_DIRECTION),
+            angular_size,
+            intensity,
+        )),
+    }
 }
 
 /// Preparation stage before rendering
@@ -32,42 +254,171 @@ pub fn sky_background(ray: &Ray) -> RGBColor {
 /// Prepares all renderables, constructs the scene,
 /// and configures the camera
 ///
+/// The scene is read from the scene file if one is given,
+/// otherwise the built-in scene is used (see `default_scene`).
+///
 /// ## Parameters
 /// * `parameters` - application parameters
-pub fn prepare_render_data(arguments: &Arguments) -> SceneData {
+pub fn prepare_render_data(arguments: &Arguments) -> Result<SceneData, RaybowError> {
+    let scene = match &arguments.scene {
+        Some(path) => SceneFile::load(Path::new(path))?,
+        None => default_scene(arguments),
+    };
+
     let mut camera = Camera::default();
     camera.set_width(arguments.output_width);
     camera.set_height(arguments.output_height);
-    camera.set_vertical_fov(arguments.fov);
+    camera.set_vertical_fov(scene.camera.fov);
     camera.set_defocus(arguments.dof_distance, arguments.dof_size);
-    camera.look_at(Vec3A::new(0.0, 0.0, -1.0));
-    camera.set_position(Vec3A::new(-3.0, 3.0, 1.0));
+    camera.set_aperture_blades(arguments.aperture_blades);
+    camera.set_chromatic_aberration(arguments.chromatic_aberration);
+    camera.look_at(scene.camera.look_at);
+    camera.set_position(scene.camera.position);
 
-    let mut renderables = Renderables::new();
+    // The environment map and the preset replace the background of the scene
+    let background: Box<dyn Background> = match (&arguments.environment_map, arguments.preset) {
+        (Some(path), _) => Box::new(EnvironmentMap::load(Path::new(path))?),
+        (None, Some(preset)) => {
+            create_background(&background_to_y_up(&preset.background(), arguments.up_axis))
+        }
+        (None, None) => create_background(&scene.background_kind()?),
+    };
 
-    let r = (PI / 4.0).cos();
+    let mut builder = SceneBuilder::new().camera(camera).background(background);
+    for object in scene.objects.iter() {
+        builder = match object {
+            _ if object.is_light() => builder.light(object.to_hittable(arguments.accurate_fresnel)),
+            ObjectDescription::Sphere {
+                center,
+                radius,
+                material,
+            } => builder.sphere(
+                *center,
+                *radius,
+                material.to_material(object.area(), arguments.accurate_fresnel),
+            ),
+            ObjectDescription::Parallelogram {
+                corner,
+                up,
+                right,
+                material,
+            } => builder.parallelogram(
+                *corner,
+                *up,
+                *right,
+                material.to_material(object.area(), arguments.accurate_fresnel),
+            ),
+        };
+    }
+    let mut scene_data = builder.build();
 
-    let material_left = LambertarianDiffuse::new(RGBColor::new(0.0, 0.0, 1.0));
-    let material_right = LambertarianDiffuse::new(RGBColor::new(1.0, 0.0, 0.0));
+    // Lights of the preset are placed around the objects, so they come last
+    if let Some(preset) = arguments.preset {
+        preset.apply(&mut scene_data.renderables);
+    }
+    if let Some(id) = arguments.isolate {
+        if !scene_data.renderables.isolate(id) {
+            return Err(RaybowError::InvalidArguments(format!(
+                "There is no object with ID {} to isolate",
+                id
+            )));
+        }
+    }
+    for object in arguments.visibility.iter() {
+        if !scene_data
+            .renderables
+            .set_visibility(object.id, object.flags)
+        {
+            return Err(RaybowError::InvalidArguments(format!(
+                "There is no object with ID {} to change the visibility of",
+                object.id
+            )));
+        }
+    }
 
-    let sphere_left = Sphere::new((-r, 0.0, -1.0).into(), r, material_left);
-    let sphere_right = Sphere::new((r, 0.0, -1.0).into(), r, material_right);
+    Ok(scene_data)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        interval::Interval,
+        materials::{lambertarian::LambertarianDiffuse, metal::Metal},
+        objects::Hittable,
+        ray::Ray,
+        rendering::{render::render, visibility::VisibilityFlags},
+    };
+
+    use super::*;
+
+    #[test]
+    fn builder_adds_objects_and_camera() {
+        let create_camera = || {
+            let mut camera = Camera::default();
+            camera.set_position(Vec3A::new(0.0, 1.0, 4.0));
+            camera.look_at(Vec3A::new(0.0, 0.0, -1.0));
+            camera
+        };
+        let red = RGBColor::new(0.8, 0.1, 0.1);
+        let mut scene_data = SceneBuilder::new()
+            .camera(create_camera())
+            .sphere(
+                Vec3A::new(0.0, 0.0, -1.0),
+                0.5,
+                LambertarianDiffuse::new(red),
+            )
+            .parallelogram(
+                Vec3A::new(-2.0, -0.5, 1.0),
+                Vec3A::new(0.0, 0.0, -4.0),
+                Vec3A::new(4.0, 0.0, 0.0),
+                Metal::new(RGBColor::white(), 0.0),
+            )
+            .build();
+
+        // Exactly the IDs 1 and 2 were given out
+        let flags = VisibilityFlags::default();
+        assert!(scene_data.renderables.set_visibility(1, flags));
+        assert!(scene_data.renderables.set_visibility(2, flags));
+        assert!(!scene_data.renderables.set_visibility(3, flags));
+
+        let ray = scene_data.camera.get_ray_through_pixel_center(128, 128);
+        let expected_ray = create_camera().get_ray_through_pixel_center(128, 128);
+        assert_eq!(ray.origin(), expected_ray.origin());
+        assert_eq!(ray.direction(), expected_ray.direction());
+        let hit_record = scene_data
+            .renderables
+            .hit(&ray, Interval::new(0.001, f32::INFINITY))
+            .unwrap();
+        assert_eq!(hit_record.object_id(), 1);
+        let floor_ray = Ray::new(Vec3A::new(1.5, 1.0, 0.0), Vec3A::new(0.0, -1.0, 0.0));
+        let hit_record = scene_data
+            .renderables
+            .hit(&floor_ray, Interval::new(0.001, f32::INFINITY))
+            .unwrap();
+        assert_eq!(hit_record.object_id(), 2);
+    }
 
-    let material_plane = LambertarianDiffuse::new(RGBColor::new(0.0, 1.0, 0.0));
-    let plane = Parallelogram::new(
-        (-1.0, 0.0, -1.0).into(),
-        (1.0, 0.0, 0.0).into(),
-        (0.0, 0.0, 1.0).into(),
-        material_plane,
-    );
+    #[test]
+    fn dumped_default_scene_renders_the_same() {
+        let path = std::env::temp_dir().join(format!("raybow-dump-{}.toml", std::process::id()));
+        let options = ["--output-width", "8", "--output-height", "6", "--seed", "3"];
+        let arguments = Arguments::from_options(&options);
+        let scene = default_scene(&arguments);
+        scene.save(&path).unwrap();
+        let loaded = SceneFile::load(&path);
 
-    renderables.add_hittable(sphere_left);
-    renderables.add_hittable(sphere_right);
-    renderables.add_hittable(plane);
+        let path_string = path.to_str().unwrap().to_string();
+        let mut loading_options = options.to_vec();
+        loading_options.extend_from_slice(&["--scene", &path_string]);
+        let loading_arguments = Arguments::from_options(&loading_options);
+        let loaded_scene_data = prepare_render_data(&loading_arguments);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap(), scene);
 
-    SceneData {
-        camera,
-        renderables,
-        background: Box::new(sky_background),
+        let scene_data = prepare_render_data(&arguments).unwrap();
+        let loaded_scene_data = loaded_scene_data.unwrap();
+        let image_data = render(&arguments, &scene_data, 0).image_data;
+        let loaded_image_data = render(&loading_arguments, &loaded_scene_data, 0).image_data;
+        assert!(image_data == loaded_image_data);
     }
 }
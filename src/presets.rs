@@ -0,0 +1,166 @@
+use std::{str::FromStr, sync::Arc};
+
+use glam::Vec3A;
+
+use crate::{
+    aabb::Aabb,
+    backgrounds::BackgroundKind,
+    color::RGBColor,
+    interval::Interval,
+    materials::{diffuse_light::DiffuseLight, lambertarian::LambertarianDiffuse, AnyMaterial},
+    math::normalize_vec3,
+    objects::parallelogram::Parallelogram,
+    rendering::renderables::Renderables,
+};
+
+/// Color of the clay material (neutral gray)
+const CLAY_COLOR: RGBColor = RGBColor::new(0.5, 0.5, 0.5);
+
+/// Ready-made lighting setups for quickly looking at a scene
+///
+/// A preset replaces the background chosen with `--background`,
+/// and can add lights or override the materials of all objects.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScenePreset {
+    /// All objects except lights are neutral gray and lit by a soft sky,
+    /// to judge shapes and composition without materials
+    Clay,
+    /// Three area lights (key, fill and rim) in front of a dark background
+    Studio,
+    /// Sun and sky
+    Outdoor,
+}
+
+impl FromStr for ScenePreset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "clay" => Ok(Self::Clay),
+            "studio" => Ok(Self::Studio),
+            "outdoor" => Ok(Self::Outdoor),
+            _ => Err(format!("Unknown preset '{}'", s)),
+        }
+    }
+}
+
+impl ScenePreset {
+    /// Returns the background of the preset
+    pub fn background(self) -> BackgroundKind {
+        match self {
+            ScenePreset::Clay => BackgroundKind::Gradient {
+                zenith_color: RGBColor::new(0.9, 0.9, 0.9),
+                horizon_color: RGBColor::new(0.6, 0.6, 0.6),
+                zenith_direction: None,
+            },
+            ScenePreset::Studio => BackgroundKind::SolidColor(RGBColor::new(0.02, 0.02, 0.02)),
+            ScenePreset::Outdoor => BackgroundKind::SunSky {
+                sun_direction: None,
+                angular_size: 0.53,
+                intensity: 50.0,
+            },
+        }
+    }
+
+    /// Returns the material that replaces the materials of all objects, if any
+    pub fn material_override(self) -> Option<Arc<AnyMaterial>> {
+        match self {
+            ScenePreset::Clay => Some(Arc::new(LambertarianDiffuse::new(CLAY_COLOR).into())),
+            ScenePreset::Studio | ScenePreset::Outdoor => None,
+        }
+    }
+
+    /// Adds the lights and the material override of the preset to the scene
+    ///
+    /// Should be called after all objects were added,
+    /// since the lights are placed around them.
+    ///
+    /// ## Parameters
+    /// * `renderables` - objects of the scene
+    pub fn apply(self, renderables: &mut Renderables) {
+        if let Some(material) = self.material_override() {
+            renderables.set_material_override(material);
+        }
+        if self == ScenePreset::Studio {
+            add_studio_lights(renderables);
+        }
+    }
+}
+
+/// Adds key, fill and rim lights around the objects of the scene
+///
+/// Lights are placed relative to the bounding box of the scene
+/// (or a unit box around the origin, if the scene is empty or infinite).
+///
+/// ## Parameters
+/// * `renderables` - objects of the scene
+fn add_studio_lights(renderables: &mut Renderables) {
+    let bounding_box = renderables
+        .bounding_box()
+        .filter(|bounding_box| bounding_box.is_finite())
+        .unwrap_or_else(|| {
+            let unit = Interval::new(-1.0, 1.0);
+            Aabb::new(unit, unit, unit)
+        });
+    let center = bounding_box.centroid();
+    let radius = ((bounding_box.max() - bounding_box.min()).length() / 2.0).max(0.5);
+
+    // Direction from the center (the camera looks along -Z by default), size and intensity
+    let lights = [
+        (Vec3A::new(-1.0, 1.0, 1.0), 1.0, 12.0), // Key
+        (Vec3A::new(1.0, 0.3, 1.0), 1.5, 2.0),   // Fill
+        (Vec3A::new(0.0, 1.0, -1.5), 0.7, 12.0), // Rim
+    ];
+    for (direction, size, intensity) in lights {
+        let direction = normalize_vec3(direction);
+        let position = center + direction * radius * 3.0;
+        // Square facing the center
+        let side = normalize_vec3(direction.cross(Vec3A::Y)) * radius * size;
+        let up = normalize_vec3(side.cross(direction)) * radius * size;
+        let corner = position - (side + up) / 2.0;
+        let material = DiffuseLight::new(RGBColor::white() * intensity);
+        renderables.add_light(Parallelogram::new(corner, up, side, material));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        materials::{dielectric::Dielectric, metal::Metal},
+        objects::{sphere::Sphere, Hittable},
+        ray::Ray,
+        rendering::renderables::AccelerationStructure,
+    };
+
+    use super::*;
+
+    #[test]
+    fn clay_preset_overrides_all_materials_with_the_same_gray() {
+        let mut renderables = Renderables::new();
+        renderables.add_hittable(Sphere::new(
+            Vec3A::new(-1.0, 0.0, 0.0),
+            0.5,
+            Metal::new(RGBColor::new(1.0, 0.0, 0.0), 0.0),
+        ));
+        renderables.add_hittable(Sphere::new(
+            Vec3A::new(1.0, 0.0, 0.0),
+            0.5,
+            Dielectric::new(1.5),
+        ));
+        ScenePreset::Clay.apply(&mut renderables);
+        renderables.build_accelerator(AccelerationStructure::Bvh);
+
+        let materials: Vec<Arc<AnyMaterial>> = [-1.0, 1.0]
+            .into_iter()
+            .map(|x| {
+                let ray = Ray::new(Vec3A::new(x, 0.0, 5.0), -Vec3A::Z);
+                let interval = Interval::new(0.001, f32::INFINITY);
+                renderables.hit(&ray, interval).unwrap().material()
+            })
+            .collect();
+        assert!(matches!(*materials[0], AnyMaterial::Lambertarian(_)));
+        assert!(Arc::ptr_eq(&materials[0], &materials[1]));
+        // Clay only relies on the sky for lighting
+        assert!(renderables.lights().is_empty());
+    }
+}
@@ -1,18 +1,103 @@
-use glam::Vec3A;
+use glam::{Mat4, Vec3A};
+
+use crate::color::spectrum::Wavelength;
+
+/// Purpose of the ray, which decides the objects it can see (see `VisibilityFlags`)
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum RayKind {
+    /// Ray from the camera towards the first surface
+    #[default]
+    Camera,
+    /// Ray from a hit point towards a light (or the background), looking for occluders
+    Shadow,
+    /// Ray scattered off a surface (reflections, refractions and indirect light)
+    Secondary,
+}
 
 pub struct Ray {
     origin: Vec3A,
     direction: Vec3A,
+    time: f32,
+    wavelength: Option<Wavelength>,   // Only set in spectral rendering
+    outside_index_of_refraction: f32, // Medium around the next refracting surface
+    kind: RayKind,
 }
 
 impl Ray {
     /// Creates a new ray
     ///
+    /// The direction does not need to be normalized, but it should not be zero
+    /// (see `is_degenerate`).
+    ///
     /// ## Parameters
     /// * `origin` - where the ray starts
     /// * `direction` - direction of the ray
     pub fn new(origin: Vec3A, direction: Vec3A) -> Self {
-        Self { origin, direction }
+        Self::new_at_time(origin, direction, 0.0)
+    }
+
+    /// Creates a new ray at the given moment
+    ///
+    /// ## Parameters
+    /// * `origin` - where the ray starts
+    /// * `direction` - direction of the ray
+    /// * `time` - moment of the ray during the shutter interval `[0.0, 1.0]`
+    pub fn new_at_time(origin: Vec3A, direction: Vec3A, time: f32) -> Self {
+        Self {
+            origin,
+            direction,
+            time,
+            wavelength: None,
+            outside_index_of_refraction: 1.0,
+            kind: RayKind::default(),
+        }
+    }
+
+    /// Returns the ray with the index of refraction of the medium
+    /// on the outer side of the next refracting surface it hits
+    ///
+    /// Used for objects inside of other refracting objects (e.g. glass in water).
+    ///
+    /// ## Parameters
+    /// * `index_of_refraction` - index of refraction of the outside medium (1.0 is vacuum)
+    pub fn with_outside_index_of_refraction(mut self, index_of_refraction: f32) -> Self {
+        self.outside_index_of_refraction = index_of_refraction;
+        self
+    }
+
+    /// Returns the ray carrying a single wavelength of light (for spectral rendering)
+    ///
+    /// ## Parameters
+    /// * `wavelength` - the wavelength, or `None` for a ray carrying all colors
+    pub fn with_wavelength(mut self, wavelength: Option<Wavelength>) -> Self {
+        self.wavelength = wavelength;
+        self
+    }
+
+    /// Returns the ray with the given purpose (new rays are camera rays)
+    ///
+    /// ## Parameters
+    /// * `kind` - purpose of the ray
+    pub fn with_kind(mut self, kind: RayKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Returns the ray transformed by the affine matrix
+    ///
+    /// The origin is transformed as a point and the direction as a vector
+    /// (so translation does not affect it). The direction is not normalized,
+    /// so the ray parameter `t` of a point stays the same in both spaces.
+    /// Time, wavelength, the outside medium and the purpose are kept.
+    ///
+    /// ## Parameters
+    /// * `matrix` - the affine transform
+    pub fn transform(&self, matrix: &Mat4) -> Ray {
+        Ray {
+            origin: matrix.transform_point3a(self.origin),
+            direction: matrix.transform_vector3a(self.direction),
+            ..*self
+        }
     }
 
     /// Retrieves direction of the ray
@@ -25,6 +110,34 @@ impl Ray {
         self.origin
     }
 
+    /// Retrieves the moment of the ray during the shutter interval
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    /// Retrieves the wavelength carried by the ray (only in spectral rendering)
+    pub fn wavelength(&self) -> Option<Wavelength> {
+        self.wavelength
+    }
+
+    /// Retrieves the purpose of the ray
+    pub fn kind(&self) -> RayKind {
+        self.kind
+    }
+
+    /// Retrieves the index of refraction of the medium
+    /// on the outer side of the next refracting surface (1.0 unless set)
+    pub fn outside_index_of_refraction(&self) -> f32 {
+        self.outside_index_of_refraction
+    }
+
+    /// Returns true if the direction of the ray is zero (or invalid),
+    /// so the ray does not point anywhere
+    pub fn is_degenerate(&self) -> bool {
+        let length_squared = self.direction.length_squared();
+        length_squared == 0.0 || !length_squared.is_finite()
+    }
+
     /// Calculates 3D position based on how far along the ray we are
     ///
     /// ## Parameters
@@ -33,3 +146,36 @@ impl Ray {
         self.origin + t * self.direction
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use glam::{Quat, Vec3};
+
+    use super::*;
+
+    #[test]
+    fn translation_moves_only_the_origin() {
+        let ray = Ray::new_at_time(Vec3A::new(1.0, 2.0, 3.0), Vec3A::new(0.0, 0.0, -2.0), 0.5);
+        let translated = ray.transform(&Mat4::from_translation(Vec3::new(1.0, -1.0, 0.5)));
+        assert_eq!(translated.origin(), Vec3A::new(2.0, 1.0, 3.5));
+        assert_eq!(translated.direction(), ray.direction());
+        assert_eq!(translated.time(), 0.5);
+    }
+
+    #[test]
+    fn rotation_turns_the_origin_and_the_direction() {
+        let ray = Ray::new(Vec3A::new(1.0, 0.0, 0.0), Vec3A::new(0.0, 0.0, -2.0));
+        let rotation = Mat4::from_quat(Quat::from_rotation_y(std::f32::consts::FRAC_PI_2));
+        let rotated = ray.transform(&rotation);
+        assert!(rotated
+            .origin()
+            .abs_diff_eq(Vec3A::new(0.0, 0.0, -1.0), 1e-6));
+        assert!(rotated
+            .direction()
+            .abs_diff_eq(Vec3A::new(-2.0, 0.0, 0.0), 1e-6));
+        // Points keep their ray parameter
+        assert!(rotated
+            .at(1.5)
+            .abs_diff_eq(rotation.transform_point3a(ray.at(1.5)), 1e-6));
+    }
+}
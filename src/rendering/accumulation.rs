@@ -0,0 +1,136 @@
+use crate::color::RGBColor;
+
+/// How many times brighter than the median sample a sample
+/// can be before it is considered a firefly
+const FIREFLY_MEDIAN_FACTOR: f32 = 4.0;
+
+/// Samples with luminance below this value are never rejected,
+/// since they cannot produce bright speckles on their own
+const FIREFLY_MIN_THRESHOLD: f32 = 1.0;
+
+/// Averages the samples of a single pixel, dropping outliers (fireflies)
+///
+/// Rare paths (such as caustics through dielectrics) can produce samples
+/// that are orders of magnitude brighter than the rest. A simple mean turns
+/// them into permanent bright speckles. Here every sample whose luminance
+/// exceeds a multiple of the median luminance is dropped before averaging.
+/// This introduces a slight bias (energy loss), but removes most fireflies.
+///
+/// ## Parameters
+/// * `samples` - all color samples of the pixel
+pub fn firefly_rejected_mean(samples: &[RGBColor]) -> RGBColor {
+    if samples.is_empty() {
+        return RGBColor::black();
+    }
+
+    let mut luminances: Vec<f32> = samples.iter().map(|sample| sample.luminance()).collect();
+    luminances.sort_by(|a, b| a.total_cmp(b));
+    let median = luminances[luminances.len() / 2];
+    let threshold = (median * FIREFLY_MEDIAN_FACTOR).max(FIREFLY_MIN_THRESHOLD);
+
+    let mut sum = RGBColor::black();
+    let mut count = 0;
+    for sample in samples {
+        if sample.luminance() <= threshold {
+            sum = sum + *sample;
+            count += 1;
+        }
+    }
+
+    // Only possible if the samples are invalid (NaN)
+    if count == 0 {
+        return RGBColor::black();
+    }
+
+    sum / count as f32
+}
+
+/// Accumulates color samples of a single pixel
+///
+/// Besides the mean, it tracks the variance of the samples
+/// with Welford's online algorithm, so the samples don't need to be stored.
+#[derive(Clone, Copy)]
+pub struct SampleAccumulator {
+    count: usize,
+    sum: RGBColor,
+    running_mean: RGBColor,
+    squared_differences: RGBColor,
+}
+
+impl SampleAccumulator {
+    /// Creates an empty accumulator
+    pub fn new() -> Self {
+        Self {
+            count: 0,
+            sum: RGBColor::black(),
+            running_mean: RGBColor::black(),
+            squared_differences: RGBColor::black(),
+        }
+    }
+
+    /// Adds a new sample
+    pub fn add(&mut self, sample: RGBColor) {
+        self.count += 1;
+        self.sum = self.sum + sample;
+
+        let difference = sample - self.running_mean;
+        self.running_mean = self.running_mean + difference / self.count as f32;
+        self.squared_differences =
+            self.squared_differences + difference * (sample - self.running_mean);
+    }
+
+    /// Returns the amount of added samples
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Returns the average of all samples
+    pub fn mean(&self) -> RGBColor {
+        if self.count == 0 {
+            return RGBColor::black();
+        }
+        self.sum / self.count as f32
+    }
+
+    /// Returns the (unbiased) variance of the samples in each channel
+    pub fn variance(&self) -> RGBColor {
+        if self.count < 2 {
+            return RGBColor::black();
+        }
+        self.squared_differences / (self.count - 1) as f32
+    }
+}
+
+impl Default for SampleAccumulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejected_mean_ignores_extreme_outlier() {
+        let mut samples: Vec<RGBColor> = (0..31)
+            .map(|i| RGBColor::new(0.4, 0.5, 0.6) * (0.9 + 0.2 * i as f32 / 30.0))
+            .collect();
+        let mut inliers = SampleAccumulator::new();
+        for sample in &samples {
+            inliers.add(*sample);
+        }
+        samples.insert(17, RGBColor::new(5000.0, 4000.0, 3000.0));
+
+        let rejected_mean = firefly_rejected_mean(&samples);
+        let difference = rejected_mean - inliers.mean();
+        assert!(
+            difference.r().abs() < 1e-5
+                && difference.g().abs() < 1e-5
+                && difference.b().abs() < 1e-5,
+            "{:?} != {:?}",
+            rejected_mean,
+            inliers.mean()
+        );
+    }
+}
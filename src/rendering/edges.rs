@@ -0,0 +1,79 @@
+use glam::Vec3A;
+
+use crate::color::RGBColor;
+
+/// Smallest cosine of the angle between normals of neighbouring pixels on the same surface
+const NORMAL_THRESHOLD: f32 = 0.9;
+/// Largest relative difference of depth between neighbouring pixels on the same surface
+const DEPTH_THRESHOLD: f32 = 0.05;
+/// Largest difference of albedo (in any channel) between neighbouring pixels on the same surface
+const ALBEDO_THRESHOLD: f32 = 0.1;
+
+/// What the camera sees through the center of a pixel, used to find edges
+#[derive(Clone, Copy, Debug)]
+pub struct SurfaceSample {
+    /// ID of the hit object (0 is the background)
+    pub object_id: u32,
+    /// Normal of the hit surface (zero for the background)
+    pub normal: Vec3A,
+    /// Distance to the hit surface (infinite for the background)
+    pub depth: f32,
+    /// Color of the hit surface, without lighting
+    pub albedo: RGBColor,
+}
+
+impl SurfaceSample {
+    /// Whether the samples of neighbouring pixels belong to different surfaces
+    /// (or to a surface that bends or changes its color sharply)
+    ///
+    /// ## Parameters
+    /// * `other` - the sample of the neighbouring pixel
+    fn differs_from(&self, other: &SurfaceSample) -> bool {
+        if self.object_id != other.object_id {
+            return true;
+        }
+        if self.object_id == 0 {
+            return false;
+        }
+        let difference = self.albedo - other.albedo;
+        let albedo_difference = difference
+            .r()
+            .abs()
+            .max(difference.g().abs())
+            .max(difference.b().abs());
+        self.normal.dot(other.normal) < NORMAL_THRESHOLD
+            || (self.depth - other.depth).abs() > DEPTH_THRESHOLD * self.depth.min(other.depth)
+            || albedo_difference > ALBEDO_THRESHOLD
+    }
+}
+
+/// Marks the pixels on edges, where the surface seen by the pixel
+/// differs from the surface seen by any of its four direct neighbours
+///
+/// ## Parameters
+/// * `samples` - surface samples of all pixels, row by row
+/// * `width` - width of image
+/// * `height` - height of image
+pub fn find_edges(samples: &[SurfaceSample], width: usize, height: usize) -> Vec<bool> {
+    let mut edges = vec![false; width * height];
+    for y in 0..height {
+        for x in 0..width {
+            let sample = &samples[y * width + x];
+            // Both pixels of a differing pair are marked
+            let mut mark_if_differs = |other_x: usize, other_y: usize| {
+                let other_index = other_y * width + other_x;
+                if sample.differs_from(&samples[other_index]) {
+                    edges[y * width + x] = true;
+                    edges[other_index] = true;
+                }
+            };
+            if x + 1 < width {
+                mark_if_differs(x + 1, y);
+            }
+            if y + 1 < height {
+                mark_if_differs(x, y + 1);
+            }
+        }
+    }
+    edges
+}
@@ -0,0 +1,147 @@
+use std::{fs, io, path::Path};
+
+use crate::{color::RGBColor, error::RaybowError, input_formats::ImportError};
+
+use super::RenderResult;
+
+/// The first line of the samples file
+const SAMPLES_FILE_MAGIC: &str = "RAYBOW-SAMPLES";
+
+/// Linear (not postprocessed) image from earlier runs, with the amount of samples behind it
+///
+/// Stored next to the output image, so that new samples of the same scene
+/// can be merged into it on the next run.
+///
+/// ## File format
+/// A text header `RAYBOW-SAMPLES\n<width> <height> <sample count>\n`,
+/// followed by the red, green and blue value of every pixel as little-endian `f32`, row by row.
+pub struct RenderHistory {
+    width: usize,
+    height: usize,
+    sample_count: usize,
+    image_data: Vec<RGBColor>,
+}
+
+impl RenderHistory {
+    /// Creates the history from a finished render
+    ///
+    /// ## Parameters
+    /// * `render_result` - the result of the render stage
+    pub fn from_result(render_result: &RenderResult) -> Self {
+        Self {
+            width: render_result.width,
+            height: render_result.height,
+            sample_count: render_result.sample_count,
+            image_data: render_result.image_data.clone(),
+        }
+    }
+
+    /// Reads the history from the samples file
+    ///
+    /// Returns `None` if the file does not exist yet.
+    ///
+    /// ## Parameters
+    /// * `path` - path to the samples file
+    pub fn load(path: &Path) -> Result<Option<Self>, ImportError> {
+        let data = match fs::read(path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+
+        let invalid = |reason: &str| ImportError::InvalidData(reason.to_string());
+
+        let mut lines = data.splitn(3, |&byte| byte == b'\n');
+        if lines.next() != Some(SAMPLES_FILE_MAGIC.as_bytes()) {
+            return Err(invalid("Not a samples file"));
+        }
+        let header = lines
+            .next()
+            .and_then(|line| std::str::from_utf8(line).ok())
+            .ok_or_else(|| invalid("Missing samples file header"))?;
+        let values: Vec<usize> = header
+            .split_whitespace()
+            .map(|value| value.parse::<usize>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| invalid("Invalid samples file header"))?;
+        let [width, height, sample_count] = values[..] else {
+            return Err(invalid(
+                "Samples file header needs width, height and sample count",
+            ));
+        };
+
+        let pixel_data = lines.next().unwrap_or_default();
+        if pixel_data.len() != width * height * 3 * 4 {
+            return Err(invalid("Samples file size does not match its resolution"));
+        }
+        let image_data = pixel_data
+            .chunks_exact(12)
+            .map(|pixel| {
+                let channel = |i: usize| {
+                    f32::from_le_bytes([pixel[i], pixel[i + 1], pixel[i + 2], pixel[i + 3]])
+                };
+                RGBColor::new(channel(0), channel(4), channel(8))
+            })
+            .collect();
+
+        Ok(Some(Self {
+            width,
+            height,
+            sample_count,
+            image_data,
+        }))
+    }
+
+    /// Writes the history to the samples file
+    ///
+    /// ## Parameters
+    /// * `path` - path to the samples file
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut data = format!(
+            "{}\n{} {} {}\n",
+            SAMPLES_FILE_MAGIC, self.width, self.height, self.sample_count
+        )
+        .into_bytes();
+        for color in self.image_data.iter() {
+            for channel in [color.r(), color.g(), color.b()] {
+                data.extend_from_slice(&channel.to_le_bytes());
+            }
+        }
+        fs::write(path, data)
+    }
+
+    /// Returns the amount of samples per pixel behind the image
+    pub fn sample_count(&self) -> usize {
+        self.sample_count
+    }
+
+    /// Merges the earlier samples into the new render
+    ///
+    /// Every pixel becomes the average of both images, weighted by their sample counts,
+    /// as if all samples were taken in a single render.
+    /// Only the image is merged, other buffers keep the values of the new render.
+    ///
+    /// ## Parameters
+    /// * `render_result` - the new render, which receives the merged image
+    pub fn merge_into(&self, render_result: &mut RenderResult) -> Result<(), RaybowError> {
+        if self.width != render_result.width || self.height != render_result.height {
+            return Err(RaybowError::InvalidArguments(format!(
+                "Cannot accumulate a {}x{} render into {}x{} samples",
+                render_result.width, render_result.height, self.width, self.height
+            )));
+        }
+
+        let total_count = self.sample_count + render_result.sample_count;
+        if total_count == 0 {
+            return Ok(());
+        }
+        let old_weight = self.sample_count as f32 / total_count as f32;
+        let new_weight = render_result.sample_count as f32 / total_count as f32;
+        for (new_color, old_color) in render_result.image_data.iter_mut().zip(&self.image_data) {
+            *new_color = *old_color * old_weight + *new_color * new_weight;
+        }
+        render_result.sample_count = total_count;
+
+        Ok(())
+    }
+}
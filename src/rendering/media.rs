@@ -0,0 +1,146 @@
+use std::sync::Arc;
+
+use crate::materials::AnyMaterial;
+
+/// Refracting media (e.g. glass or water) that a path is currently inside of
+///
+/// Objects can be nested, like a glass in water. Light is refracted by the ratio
+/// of the media on both sides of a surface, so the path needs to remember
+/// which media it entered and did not leave yet. The last one entered
+/// is the medium the path is in. Outside of all objects, there is vacuum (air).
+#[derive(Default)]
+pub struct MediumStack {
+    media: Vec<(usize, f32)>, // Identity of the material and its index of refraction
+}
+
+impl MediumStack {
+    /// Creates an empty stack (the path starts in vacuum)
+    pub fn new() -> Self {
+        Self { media: Vec::new() }
+    }
+
+    /// Returns the index of refraction on the other side of the surface
+    /// than the inside of the material
+    ///
+    /// When entering the material, it is the medium the path is in.
+    /// When leaving it, it is the medium the path is in after leaving.
+    ///
+    /// ## Parameters
+    /// * `material` - material of the surface
+    /// * `front_face` - whether the path enters the material at the surface
+    pub fn outside_index_of_refraction(
+        &self,
+        material: &Arc<AnyMaterial>,
+        front_face: bool,
+    ) -> f32 {
+        let id = material_id(material);
+        let mut media = self.media.iter().rev();
+        let outside = if front_face {
+            media.next()
+        } else {
+            // The material itself is not around its own surface
+            let mut skipped = false;
+            media.find(|(medium_id, _)| {
+                if !skipped && *medium_id == id {
+                    skipped = true;
+                    return false;
+                }
+                true
+            })
+        };
+        outside.map_or(1.0, |(_, index_of_refraction)| *index_of_refraction)
+    }
+
+    /// Remembers that the path entered the material
+    ///
+    /// ## Parameters
+    /// * `material` - the entered material
+    /// * `index_of_refraction` - index of refraction of the material
+    pub fn enter(&mut self, material: &Arc<AnyMaterial>, index_of_refraction: f32) {
+        self.media
+            .push((material_id(material), index_of_refraction));
+    }
+
+    /// Forgets the material, after the path left it
+    ///
+    /// ## Parameters
+    /// * `material` - the left material
+    pub fn exit(&mut self, material: &Arc<AnyMaterial>) {
+        let id = material_id(material);
+        if let Some(index) = self
+            .media
+            .iter()
+            .rposition(|(medium_id, _)| *medium_id == id)
+        {
+            self.media.remove(index);
+        }
+    }
+}
+
+/// Returns a value identifying the material instance
+fn material_id(material: &Arc<AnyMaterial>) -> usize {
+    Arc::as_ptr(material) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3A;
+    use rand::SeedableRng;
+    use rand_xoshiro::Xoshiro256Plus;
+
+    use crate::{
+        materials::{dielectric::Dielectric, Material},
+        objects::HitRecord,
+        ray::Ray,
+    };
+
+    use super::*;
+
+    #[test]
+    fn nested_media_are_remembered_until_left() {
+        let water: Arc<AnyMaterial> = Dielectric::new(1.33).into();
+        let glass: Arc<AnyMaterial> = Dielectric::new(1.5).into();
+        let mut media = MediumStack::new();
+        assert_eq!(media.outside_index_of_refraction(&water, true), 1.0);
+
+        media.enter(&water, 1.33);
+        assert_eq!(media.outside_index_of_refraction(&glass, true), 1.33);
+        media.enter(&glass, 1.5);
+        // Leaving the glass goes back into the water
+        assert_eq!(media.outside_index_of_refraction(&glass, false), 1.33);
+        media.exit(&glass);
+        assert_eq!(media.outside_index_of_refraction(&water, false), 1.0);
+        media.exit(&water);
+        assert_eq!(media.outside_index_of_refraction(&glass, true), 1.0);
+    }
+
+    /// Sine of the angle of the ray refracted into glass from the medium,
+    /// for light hitting the glass at 45 degrees
+    fn refracted_sine(outside_index_of_refraction: f32) -> f32 {
+        let glass: Arc<AnyMaterial> = Dielectric::new(1.5).into();
+        let incoming_direction = Vec3A::new(1.0, -1.0, 0.0).normalize();
+        let incoming_ray = Ray::new(-incoming_direction, incoming_direction)
+            .with_outside_index_of_refraction(outside_index_of_refraction);
+        let hit_record = HitRecord::new(Vec3A::ZERO, Vec3A::Y, 1.0, true, glass.clone());
+        let mut rng = Xoshiro256Plus::seed_from_u64(5);
+        let refracted = (0..100)
+            .filter_map(|_| glass.scatter(&incoming_ray, &hit_record, &mut rng))
+            .map(|output| output.scattered_ray.direction().normalize())
+            .find(|direction| direction.y < 0.0)
+            .unwrap();
+        refracted.x
+    }
+
+    #[test]
+    fn glass_in_water_bends_light_less_than_in_air() {
+        let incoming_sine = 0.5f32.sqrt();
+        let in_air = refracted_sine(1.0);
+        let in_water = refracted_sine(1.33);
+        assert!((in_air - incoming_sine / 1.5).abs() < 1e-5, "{}", in_air);
+        assert!(
+            (in_water - incoming_sine * 1.33 / 1.5).abs() < 1e-5,
+            "{}",
+            in_water
+        );
+    }
+}
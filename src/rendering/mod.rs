@@ -1,10 +1,154 @@
+use std::fmt::Debug;
+
 use crate::color::RGBColor;
 
+use self::{stats::RenderStats, summary::RenderSummary};
+
+pub mod accumulation;
+pub mod edges;
+pub mod history;
+pub mod media;
+pub mod order;
+pub mod path;
+pub mod preview;
 pub mod render;
 pub mod renderables;
+pub mod stats;
+pub mod summary;
+pub mod tiles;
+pub mod visibility;
 
 pub struct RenderResult {
     pub width: usize,
     pub height: usize,
     pub image_data: Vec<RGBColor>,
+    /// Amount of samples taken in every pixel
+    pub sample_count: usize,
+    /// Color of the first surface hit in every pixel (for denoisers)
+    pub albedo_data: Option<Vec<RGBColor>>,
+    /// Variance of samples in every pixel (for denoisers)
+    pub variance_data: Option<Vec<RGBColor>>,
+    /// Opacity of every pixel (0.0 is the background, 1.0 an object)
+    pub alpha_data: Option<Vec<f32>>,
+    /// ID of the object seen in every pixel (0 is the background)
+    pub object_id_data: Option<Vec<u32>>,
+    /// Statistics about the work done while rendering
+    pub stats: RenderStats,
+}
+
+// The pipeline works on whole buffers, single pixels are only looked at by tests
+#[cfg(test)]
+impl RenderResult {
+    /// Returns the color of the pixel
+    ///
+    /// Panics if the pixel is outside of the image.
+    ///
+    /// ## Parameters
+    /// * `x` - horizontal pixel coordinate
+    /// * `y` - vertical pixel coordinate
+    pub fn pixel(&self, x: usize, y: usize) -> RGBColor {
+        if x >= self.width || y >= self.height {
+            panic!(
+                "Pixel ({}, {}) is outside of the {}x{} image!",
+                x, y, self.width, self.height
+            );
+        }
+        self.image_data[y * self.width + x]
+    }
+
+    /// Returns an iterator over all pixels as `(x, y, color)`, row by row
+    pub fn pixels(&self) -> impl Iterator<Item = (usize, usize, RGBColor)> + '_ {
+        self.image_data
+            .iter()
+            .take(self.width * self.height)
+            .enumerate()
+            .map(|(index, color)| (index % self.width, index / self.width, *color))
+    }
+}
+
+impl RenderResult {
+    /// Returns a short description of the image (size, luminance range and broken pixels)
+    pub fn summary(&self) -> RenderSummary {
+        RenderSummary::new(self.width, self.height, &self.image_data)
+    }
+}
+
+impl Debug for RenderResult {
+    /// Prints the summary instead of the whole image
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "RenderResult({}, {} samples per pixel)",
+            self.summary(),
+            self.sample_count
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient_result(width: usize, height: usize) -> RenderResult {
+        let image_data = (0..width * height)
+            .map(|index| RGBColor::new(index as f32, (index % width) as f32, 0.5))
+            .collect();
+        RenderResult {
+            width,
+            height,
+            image_data,
+            sample_count: 1,
+            albedo_data: None,
+            variance_data: None,
+            alpha_data: None,
+            object_id_data: None,
+            stats: RenderStats::default(),
+        }
+    }
+
+    #[test]
+    fn pixel_matches_flat_image_data() {
+        let result = gradient_result(5, 3);
+        for y in 0..result.height {
+            for x in 0..result.width {
+                assert!(result.pixel(x, y) == result.image_data[y * result.width + x]);
+            }
+        }
+
+        let pixels: Vec<_> = result.pixels().collect();
+        assert_eq!(pixels.len(), 15);
+        for (x, y, color) in pixels {
+            assert!(color == result.pixel(x, y));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "outside of the 5x3 image")]
+    fn pixel_outside_of_image_panics() {
+        gradient_result(5, 3).pixel(5, 0);
+    }
+
+    #[test]
+    fn summary_counts_broken_pixels_and_averages_the_rest() {
+        let mut result = gradient_result(2, 2);
+        result.image_data = vec![
+            RGBColor::new(1.0, 1.0, 1.0),
+            RGBColor::new(0.0, 0.0, 0.0),
+            RGBColor::new(0.5, 0.5, 0.5),
+            RGBColor::new(f32::NAN, 0.0, 0.0),
+        ];
+        let summary = result.summary();
+        assert_eq!(
+            summary,
+            RenderSummary {
+                width: 2,
+                height: 2,
+                min_luminance: 0.0,
+                max_luminance: 1.0,
+                mean_luminance: 0.5,
+                non_finite_pixels: 1,
+            }
+        );
+        assert!(format!("{:?}", result).contains("1 non-finite pixels"));
+    }
 }
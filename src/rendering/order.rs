@@ -0,0 +1,102 @@
+use std::str::FromStr;
+
+/// The order in which pixels are rendered
+///
+/// The order does not change the final image, only how it is produced
+/// (cache locality and which parts of the image are finished first).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RenderOrder {
+    /// Row by row, from the top of the image
+    Scanline,
+    /// From the center of the image outwards
+    Spiral,
+    /// Along the Z-order curve, which keeps nearby pixels together
+    Morton,
+}
+
+impl FromStr for RenderOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "scanline" => Ok(Self::Scanline),
+            "spiral" => Ok(Self::Spiral),
+            "morton" => Ok(Self::Morton),
+            _ => Err(format!("Unknown render order '{}'", s)),
+        }
+    }
+}
+
+impl RenderOrder {
+    /// Returns image coordinates of all pixels in the order they should be rendered
+    ///
+    /// ## Parameters
+    /// * `width` - image width
+    /// * `height` - image height
+    pub fn pixels(&self, width: usize, height: usize) -> Vec<(usize, usize)> {
+        let mut pixels: Vec<(usize, usize)> = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .collect();
+
+        match self {
+            RenderOrder::Scanline => {}
+            RenderOrder::Spiral => {
+                let center_x = width as f32 / 2.0;
+                let center_y = height as f32 / 2.0;
+                // Pixels are ordered by square rings around the center, and by angle inside a ring
+                let key = |(x, y): &(usize, usize)| {
+                    let dx = *x as f32 + 0.5 - center_x;
+                    let dy = *y as f32 + 0.5 - center_y;
+                    (dx.abs().max(dy.abs()), dy.atan2(dx))
+                };
+                pixels.sort_by(|a, b| {
+                    let (ring_a, angle_a) = key(a);
+                    let (ring_b, angle_b) = key(b);
+                    ring_a.total_cmp(&ring_b).then(angle_a.total_cmp(&angle_b))
+                });
+            }
+            RenderOrder::Morton => {
+                pixels.sort_by_key(|(x, y)| morton_code(*x as u32, *y as u32));
+            }
+        }
+
+        pixels
+    }
+}
+
+/// Interleaves bits of both coordinates (`x` on even bits, `y` on odd bits)
+fn morton_code(x: u32, y: u32) -> u64 {
+    let spread = |value: u32| {
+        let mut value = value as u64;
+        value = (value | (value << 16)) & 0x0000_ffff_0000_ffff;
+        value = (value | (value << 8)) & 0x00ff_00ff_00ff_00ff;
+        value = (value | (value << 4)) & 0x0f0f_0f0f_0f0f_0f0f;
+        value = (value | (value << 2)) & 0x3333_3333_3333_3333;
+        value = (value | (value << 1)) & 0x5555_5555_5555_5555;
+        value
+    };
+    spread(x) | (spread(y) << 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_order_visits_each_pixel_once() {
+        let (width, height) = (7, 5);
+        let scanline = RenderOrder::Scanline.pixels(width, height);
+        assert_eq!(scanline[..3], [(0, 0), (1, 0), (2, 0)]);
+        for order in [RenderOrder::Spiral, RenderOrder::Morton] {
+            let mut pixels = order.pixels(width, height);
+            assert_ne!(pixels, scanline, "{:?}", order);
+            pixels.sort_by_key(|(x, y)| (*y, *x));
+            assert_eq!(pixels, scanline, "{:?}", order);
+        }
+        // Z-order starts with the upper left 2x2 block
+        assert_eq!(
+            RenderOrder::Morton.pixels(width, height)[..4],
+            [(0, 0), (1, 0), (0, 1), (1, 1)]
+        );
+    }
+}
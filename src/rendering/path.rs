@@ -0,0 +1,105 @@
+use std::fmt::Display;
+
+use glam::Vec3A;
+
+use crate::{color::RGBColor, math::normalize_vec3};
+
+/// Length of the last segment of an OBJ polyline that escapes to the background
+const ESCAPE_SEGMENT_LENGTH: f32 = 1.0;
+
+/// A single bounce of a traced path
+#[derive(Clone, Debug)]
+pub struct PathVertex {
+    /// Where the ray hit the surface
+    pub point: Vec3A,
+    /// Direction of the ray arriving at the point
+    pub direction: Vec3A,
+    /// ID of the hit object
+    pub object_id: u32,
+    /// Name of the material type at the point
+    pub material: &'static str,
+}
+
+/// The path of a single camera ray through the scene, bounce by bounce
+///
+/// Recorded by `trace_pixel_path`, for teaching and debugging.
+#[derive(Clone, Debug)]
+pub struct LightPath {
+    /// Where the camera ray starts
+    pub origin: Vec3A,
+    /// Surfaces hit along the path, in order
+    pub vertices: Vec<PathVertex>,
+    /// Direction of the last ray, if it missed all objects and reached the background
+    pub escape_direction: Option<Vec3A>,
+    /// Color that the path brings to the pixel
+    pub color: RGBColor,
+}
+
+impl LightPath {
+    /// Starts an empty path
+    ///
+    /// ## Parameters
+    /// * `origin` - where the camera ray starts
+    pub fn new(origin: Vec3A) -> Self {
+        Self {
+            origin,
+            vertices: Vec::new(),
+            escape_direction: None,
+            color: RGBColor::black(),
+        }
+    }
+
+    /// Returns the amount of straight segments of the path
+    /// (including the one towards the background)
+    pub fn segment_count(&self) -> usize {
+        self.vertices.len() + usize::from(self.escape_direction.is_some())
+    }
+
+    /// Returns the path as a Wavefront OBJ polyline
+    ///
+    /// A path that reaches the background ends with a short segment
+    /// in the direction of the background.
+    pub fn to_obj(&self) -> String {
+        let mut points = vec![self.origin];
+        points.extend(self.vertices.iter().map(|vertex| vertex.point));
+        if let Some(direction) = self.escape_direction {
+            let last_point = points[points.len() - 1];
+            points.push(last_point + normalize_vec3(direction) * ESCAPE_SEGMENT_LENGTH);
+        }
+
+        let mut obj = String::from("# Path of a single camera ray\n");
+        for point in points.iter() {
+            obj.push_str(&format!("v {} {} {}\n", point.x, point.y, point.z));
+        }
+        let indices: Vec<String> = (1..=points.len()).map(|i| i.to_string()).collect();
+        obj.push_str(&format!("l {}\n", indices.join(" ")));
+        obj
+    }
+}
+
+impl Display for LightPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Camera at {}", self.origin)?;
+        for (bounce, vertex) in self.vertices.iter().enumerate() {
+            writeln!(
+                f,
+                "{}. hit object {} ({}) at {} coming from direction {}",
+                bounce + 1,
+                vertex.object_id,
+                vertex.material,
+                vertex.point,
+                vertex.direction
+            )?;
+        }
+        match self.escape_direction {
+            Some(direction) => writeln!(f, "Reached the background in direction {}", direction)?,
+            None => writeln!(f, "Ended without reaching the background")?,
+        }
+        write!(
+            f,
+            "Color {:?} over {} segments",
+            self.color,
+            self.segment_count()
+        )
+    }
+}
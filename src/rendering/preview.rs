@@ -0,0 +1,70 @@
+use std::{
+    fs,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    color::RGBColor, output_formats::ppm::rgb_to_binary_ppm, postprocessing::postprocess_image,
+    Arguments,
+};
+
+use super::accumulation::SampleAccumulator;
+
+/// Periodically writes the partially rendered image (`<output>_preview.ppm`)
+///
+/// The preview goes through the same postprocessing as the final image.
+/// Pixels without samples yet are black.
+pub struct PreviewWriter {
+    interval: Duration,
+    last_write: Instant,
+    path: String,
+}
+
+impl PreviewWriter {
+    /// Creates the writer, if previews were requested
+    ///
+    /// ## Parameters
+    /// * `arguments` - global application parameters
+    pub fn new(arguments: &Arguments) -> Option<Self> {
+        let interval = arguments.preview_every?;
+        // Invalid (negative) intervals write a preview at every chance
+        let interval = Duration::try_from_secs_f32(interval).unwrap_or(Duration::ZERO);
+        Some(Self {
+            interval,
+            last_write: Instant::now(),
+            path: format!("{}_preview.ppm", arguments.output_path),
+        })
+    }
+
+    /// Writes the preview, if enough time has passed since the last one
+    ///
+    /// Failing to write the preview does not stop the render, it is only logged.
+    ///
+    /// ## Parameters
+    /// * `arguments` - global application parameters
+    /// * `accumulators` - samples accumulated so far in every pixel
+    pub fn write_if_due(&mut self, arguments: &Arguments, accumulators: &[SampleAccumulator]) {
+        if self.last_write.elapsed() < self.interval {
+            return;
+        }
+
+        // The snapshot is independent of the accumulators, which keep changing
+        let mut image_data: Vec<RGBColor> = accumulators
+            .iter()
+            .map(|accumulator| accumulator.mean())
+            .collect();
+        postprocess_image(arguments, &mut image_data);
+
+        let written =
+            rgb_to_binary_ppm(&image_data, arguments.output_width, arguments.output_height)
+                .map_err(|err| err.to_string())
+                .and_then(|ppm_data| {
+                    fs::write(&self.path, ppm_data).map_err(|err| err.to_string())
+                });
+        match written {
+            Ok(()) => log::debug!(" Preview written to {}", self.path),
+            Err(err) => log::warn!("Could not write the preview: {}", err),
+        }
+        self.last_write = Instant::now();
+    }
+}
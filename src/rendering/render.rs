@@ -1,98 +1,2472 @@
-use rand::{thread_rng, SeedableRng};
+use std::{
+    cell::Cell,
+    time::{Duration, Instant},
+};
+
+use glam::Vec3A;
+use rand::{Rng, SeedableRng};
 use rand_xoshiro::Xoshiro256Plus;
 
 use crate::{
-    color::RGBColor, interval::Interval, materials::Material, objects::Hittable,
-    preparation::SceneData, progress::ProgressTracker, ray::Ray, Arguments,
+    color::{spectrum::Wavelength, RGBColor},
+    interval::Interval,
+    materials::{Material, MaterialScatterOutput},
+    math::{normalize_vec3, power_heuristic},
+    objects::{HitRecord, Hittable},
+    preparation::SceneData,
+    progress::ProgressTracker,
+    ray::{Ray, RayKind},
+    Arguments,
+};
+
+use super::{
+    accumulation::{firefly_rejected_mean, SampleAccumulator},
+    edges::{find_edges, SurfaceSample},
+    media::MediumStack,
+    path::{LightPath, PathVertex},
+    preview::PreviewWriter,
+    stats::RenderStats,
+    tiles::EmptyTiles,
+    RenderResult,
 };
 
-use super::RenderResult;
+/// Follows the shadow ray towards its target (a light or the background)
+///
+/// Normally any object in the way blocks the light. In caustics mode, the shadow ray
+/// is refracted through transparent surfaces instead. The refracted ray may then
+/// miss the target, but when it doesn't, light focused by glass (caustics) is found.
+/// Since the same light can also be found by scattered rays, this adds some energy (bias).
+///
+/// Returns the ray that reaches the target unoccluded and the fraction of light
+/// that is transmitted along the way, or `None` if the target can't be reached.
+///
+/// ## Parameters
+/// * `shadow_ray` - the ray from the hit point towards the target
+/// * `target_distance` - returns the ray parameter at which the ray reaches the target,
+///   or `None` if the ray misses it
+/// * `arguments` - global application parameters
+/// * `scene_data` - scene data to render
+fn trace_shadow_ray<F>(
+    shadow_ray: Ray,
+    target_distance: F,
+    arguments: &Arguments,
+    scene_data: &SceneData,
+) -> Option<(Ray, RGBColor)>
+where
+    F: Fn(&Ray) -> Option<f32>,
+{
+    let mut shadow_ray = shadow_ray;
+    let mut transmittance = RGBColor::white();
+
+    // Passing through a surface counts as a bounce
+    for _ in 0..arguments.steps {
+        // Hittables hidden from shadows don't block the light, even after refraction
+        shadow_ray = shadow_ray.with_kind(RayKind::Shadow);
+        let distance = target_distance(&shadow_ray)?;
+
+        // The shadow ray is occluded if anything is hit before the target
+        let occlusion_interval = Interval::new(0.001, distance - 0.001);
+        let Some(occluder_hit) = scene_data.renderables.hit(&shadow_ray, occlusion_interval) else {
+            return Some((shadow_ray, transmittance));
+        };
+
+        // Shadow catchers are invisible, so they never block the light
+        if occluder_hit.material().is_shadow_catcher() {
+            shadow_ray = Ray::new_at_time(
+                occluder_hit.point(),
+                shadow_ray.direction(),
+                shadow_ray.time(),
+            );
+            continue;
+        }
+
+        if !arguments.caustics {
+            return None;
+        }
+        let transmitted = occluder_hit
+            .material()
+            .transmit(&shadow_ray, &occluder_hit)?;
+        transmittance = transmittance * transmitted.attenuation;
+        shadow_ray = transmitted.scattered_ray;
+    }
+
+    None
+}
+
+/// Estimates the light arriving to the hit point directly from the lights
+///
+/// Every light is sampled with `arguments.shadow_samples` shadow rays.
+/// Rays that hit other objects before reaching the light do not contribute,
+/// so more samples produce smoother penumbras.
+///
+/// The same light can also be found by the ray scattered from the material,
+/// so both estimates are weighted with multiple importance sampling
+/// (see `light_weight`).
+///
+/// ## Parameters
+/// * `ray` - the ray that hit the surface
+/// * `hit_record` - the record of the current hit
+/// * `attenuation` - attenuation of the surface material
+/// * `arguments` - global application parameters
+/// * `scene_data` - scene data to render
+/// * `rng` - random number generator instance
+fn direct_light(
+    ray: &Ray,
+    hit_record: &HitRecord,
+    attenuation: RGBColor,
+    arguments: &Arguments,
+    scene_data: &SceneData,
+    rng: &mut Xoshiro256Plus,
+) -> RGBColor {
+    let material = hit_record.material();
+    let origin = hit_record.point();
+
+    let mut result = RGBColor::black();
+    for light in scene_data.renderables.lights() {
+        let mut light_result = RGBColor::black();
+        for _ in 0..arguments.shadow_samples {
+            let direction = light.random(origin, rng);
+            let shadow_ray = Ray::new_at_time(origin, direction, ray.time());
+
+            let light_pdf = light.pdf_value(origin, direction);
+            let scattering_pdf = material
+                .scattering_pdf(ray, hit_record, &shadow_ray)
+                .unwrap_or(0.0);
+            if light_pdf <= 0.0 || scattering_pdf <= 0.0 {
+                continue;
+            }
+
+            let light_interval = Interval::new(0.001, f32::INFINITY);
+            let light_distance = |ray: &Ray| {
+                light
+                    .hit(ray, light_interval)
+                    .map(|light_hit| light_hit.t())
+            };
+            let Some((shadow_ray, transmittance)) =
+                trace_shadow_ray(shadow_ray, light_distance, arguments, scene_data)
+            else {
+                continue;
+            };
+            let Some(light_hit) = light.hit(&shadow_ray, light_interval) else {
+                continue;
+            };
+
+            let emitted = transmittance * light_hit.material().emitted(&shadow_ray, &light_hit);
+            let weight =
+                power_heuristic(arguments.shadow_samples as f32 * light_pdf, scattering_pdf);
+            light_result =
+                light_result + attenuation * emitted * (weight * scattering_pdf / light_pdf);
+        }
+        result = result + light_result / arguments.shadow_samples as f32;
+    }
+
+    // Some backgrounds (environment maps) are sampled like the lights, towards their brightest regions
+    let background = &scene_data.background;
+    if background.is_importance_sampled() {
+        let mut environment_result = RGBColor::black();
+        for _ in 0..arguments.shadow_samples {
+            let Some((direction, environment_pdf)) = background.sample(rng) else {
+                continue;
+            };
+            let shadow_ray = Ray::new_at_time(origin, direction, ray.time());
+
+            let scattering_pdf = material
+                .scattering_pdf(ray, hit_record, &shadow_ray)
+                .unwrap_or(0.0);
+            if environment_pdf <= 0.0 || scattering_pdf <= 0.0 {
+                continue;
+            }
+
+            // The background is only visible if nothing is in the way
+            let background_distance = |_: &Ray| Some(f32::INFINITY);
+            let Some((shadow_ray, transmittance)) =
+                trace_shadow_ray(shadow_ray, background_distance, arguments, scene_data)
+            else {
+                continue;
+            };
+
+            let weight = power_heuristic(
+                arguments.shadow_samples as f32 * environment_pdf,
+                scattering_pdf,
+            );
+            environment_result = environment_result
+                + attenuation
+                    * transmittance
+                    * background.radiance(&shadow_ray)
+                    * (weight * scattering_pdf / environment_pdf);
+        }
+        result = result + environment_result / arguments.shadow_samples as f32;
+    }
+
+    result
+}
+
+/// Calculates the fraction of direct light that reaches the point on a shadow catcher
+///
+/// The light arriving from the lights (and importance sampled backgrounds)
+/// is estimated twice from the same directions: once as if nothing was in the way,
+/// and once with occlusion. Their ratio tells how much of the light is blocked.
+/// Returns 1.0 if the point is fully lit (or there is no light to cast shadows)
+/// and 0.0 if it is fully in shadow.
+///
+/// ## Parameters
+/// * `hit_record` - the record of the shadow catcher hit
+/// * `time` - the moment at which the shadows are calculated
+/// * `arguments` - global application parameters
+/// * `scene_data` - scene data to render
+/// * `rng` - random number generator instance
+fn shadow_catcher_visibility(
+    hit_record: &HitRecord,
+    time: f32,
+    arguments: &Arguments,
+    scene_data: &SceneData,
+    rng: &mut Xoshiro256Plus,
+) -> f32 {
+    let origin = hit_record.point();
+    let normal = hit_record.normal();
+    // Shadows are needed even if direct lighting is disabled
+    let samples = arguments.shadow_samples.max(1);
+
+    let mut unoccluded_light = 0.0;
+    let mut visible_light = 0.0;
+
+    let light_interval = Interval::new(0.001, f32::INFINITY);
+    for light in scene_data.renderables.lights() {
+        for _ in 0..samples {
+            let direction = light.random(origin, rng);
+            let light_pdf = light.pdf_value(origin, direction);
+            let cosine = normal.dot(normalize_vec3(direction));
+            if light_pdf <= 0.0 || cosine <= 0.0 {
+                continue;
+            }
+
+            let shadow_ray = Ray::new_at_time(origin, direction, time);
+            let Some(light_hit) = light.hit(&shadow_ray, light_interval) else {
+                continue;
+            };
+            let emitted = light_hit.material().emitted(&shadow_ray, &light_hit);
+            let contribution = emitted.luminance() * cosine / light_pdf;
+            unoccluded_light += contribution;
+
+            let light_distance = |ray: &Ray| {
+                light
+                    .hit(ray, light_interval)
+                    .map(|light_hit| light_hit.t())
+            };
+            if let Some((_, transmittance)) =
+                trace_shadow_ray(shadow_ray, light_distance, arguments, scene_data)
+            {
+                visible_light += contribution * transmittance.luminance();
+            }
+        }
+    }
+
+    let background = &scene_data.background;
+    if background.is_importance_sampled() {
+        for _ in 0..samples {
+            let Some((direction, environment_pdf)) = background.sample(rng) else {
+                continue;
+            };
+            let cosine = normal.dot(normalize_vec3(direction));
+            if environment_pdf <= 0.0 || cosine <= 0.0 {
+                continue;
+            }
+
+            let shadow_ray = Ray::new_at_time(origin, direction, time);
+            let contribution =
+                background.radiance(&shadow_ray).luminance() * cosine / environment_pdf;
+            unoccluded_light += contribution;
+
+            let background_distance = |_: &Ray| Some(f32::INFINITY);
+            if let Some((_, transmittance)) =
+                trace_shadow_ray(shadow_ray, background_distance, arguments, scene_data)
+            {
+                visible_light += contribution * transmittance.luminance();
+            }
+        }
+    }
+
+    if unoccluded_light > 0.0 {
+        (visible_light / unoccluded_light).clamp(0.0, 1.0)
+    } else {
+        1.0
+    }
+}
+
+/// Calculates the multiple importance sampling weight of the light
+/// emitted by the surface that the scattered `ray` hit.
+///
+/// If the lights were sampled directly on the previous bounce, the same light
+/// is counted twice, once by each strategy. The weights of both
+/// strategies are calculated with the power heuristic, so they sum to 1.
+///
+/// ## Parameters
+/// * `ray` - the ray scattered on the previous bounce
+/// * `hit_record` - the record of the current hit
+/// * `scattering_pdf` - probability density of the previous bounce scattering the `ray`
+/// * `arguments` - global application parameters
+/// * `scene_data` - scene data to render
+fn light_weight(
+    ray: &Ray,
+    hit_record: &HitRecord,
+    scattering_pdf: f32,
+    arguments: &Arguments,
+    scene_data: &SceneData,
+) -> f32 {
+    // Only the light that was actually hit could have been sampled in this direction
+    let ray_interval = Interval::new(0.001, f32::INFINITY);
+    let hit_light = scene_data.renderables.lights().iter().find(|light| {
+        light
+            .hit(ray, ray_interval)
+            .is_some_and(|light_hit| (light_hit.t() - hit_record.t()).abs() < 0.0001)
+    });
+
+    match hit_light {
+        Some(light) => {
+            let light_pdf = light.pdf_value(ray.origin(), ray.direction());
+            power_heuristic(scattering_pdf, arguments.shadow_samples as f32 * light_pdf)
+        }
+        // Emissive surfaces that are not lights are never sampled directly
+        None => 1.0,
+    }
+}
+
+/// Picks one of the ways the light scatters off the surface (see `Material::scatter_multi`)
+///
+/// Each ray is chosen with its probability, so its attenuation stays unchanged.
+///
+/// ## Parameters
+/// * `scatters` - the scattered rays with their attenuation and probability
+/// * `rng` - random number generator instance
+fn choose_scatter(
+    scatters: Vec<(Ray, RGBColor, f32)>,
+    rng: &mut Xoshiro256Plus,
+) -> Option<MaterialScatterOutput> {
+    let into_output =
+        |(scattered_ray, attenuation, _): (Ray, RGBColor, f32)| MaterialScatterOutput {
+            scattered_ray,
+            attenuation,
+        };
+
+    // A single ray is always followed, without using up a random number
+    if scatters.len() <= 1 {
+        return scatters.into_iter().next().map(into_output);
+    }
+
+    let total_probability: f32 = scatters.iter().map(|(_, _, probability)| probability).sum();
+    let mut threshold = rng.gen::<f32>() * total_probability;
+    let last_index = scatters.len() - 1;
+    for (index, scatter) in scatters.into_iter().enumerate() {
+        if threshold < scatter.2 || index == last_index {
+            return Some(into_output(scatter));
+        }
+        threshold -= scatter.2;
+    }
+    None
+}
 
 /// Calculates the color of the pixel
 /// based on the ray hits
 ///
+/// The ray is followed bounce by bounce (without recursion, so that long paths
+/// cannot overflow the stack). The throughput, i.e. the fraction of light
+/// that reaches the camera from the current bounce, shrinks with every attenuation.
+///
+/// In spectral mode, the path carries a single random wavelength. All colors are reduced
+/// to their value at that wavelength (kept in every channel), and the result is turned
+/// back into RGB at the end.
+///
 /// ## Parameters
-/// * `ray`
-/// * `scene_data`
+/// * `ray` - the camera ray
+/// * `arguments` - global application parameters
+/// * `scene_data` - scene data to render
+/// * `rng` - random number generator instance
 fn ray_color(
     ray: &Ray,
+    arguments: &Arguments,
+    scene_data: &SceneData,
+    rng: &mut Xoshiro256Plus,
+) -> RGBColor {
+    traced_ray_color(ray, arguments, scene_data, rng, None)
+}
+
+/// Calculates the color of the pixel like `ray_color`,
+/// optionally recording every bounce of the ray into the path
+///
+/// ## Parameters
+/// * `ray` - the camera ray
+/// * `arguments` - global application parameters
+/// * `scene_data` - scene data to render
+/// * `rng` - random number generator instance
+/// * `path` - receives the bounces and the final color, if given
+fn traced_ray_color(
+    ray: &Ray,
+    arguments: &Arguments,
     scene_data: &SceneData,
-    depth: usize,
     rng: &mut Xoshiro256Plus,
+    mut path: Option<&mut LightPath>,
 ) -> RGBColor {
-    // After some steps we conclude that the recursion
-    // will not hit a light source, so we return black
-    if depth == 0 {
-        return RGBColor::new(0.0, 0.0, 0.0);
+    if arguments.background_only {
+        return background_ray_color(ray, arguments, scene_data, rng);
     }
 
-    // The interval starts at 0.001,
-    // so that we don't get shadow acne or z-fighting
-    let ray_interval = Interval::new(0.001, f32::INFINITY);
-    if let Some(hit_record) = scene_data.renderables.hit(ray, ray_interval) {
-        if let Some(material_result) = hit_record.material().scatter(ray, &hit_record, rng) {
-            let deeper_result =
-                ray_color(&material_result.scattered_ray, scene_data, depth - 1, rng);
-            let result = material_result.attenuation * deeper_result;
-            return result;
+    let mut color = RGBColor::black();
+    let mut throughput = RGBColor::white();
+    let wavelength = arguments.spectral.then(|| Wavelength::sample(rng));
+    let at_wavelength = |color: RGBColor| match wavelength {
+        Some(wavelength) => {
+            let value = wavelength.value_of(color);
+            RGBColor::new(value, value, value)
+        }
+        None => color,
+    };
+    let mut ray =
+        Ray::new_at_time(ray.origin(), ray.direction(), ray.time()).with_wavelength(wavelength);
+    // Probability density with which the previous bounce scattered the ray,
+    // if the lights were also sampled directly there (used to weight the emitted light)
+    let mut scattering_pdf: Option<f32> = None;
+    // Shadow catcher hit by the camera ray, its shadow darkens everything seen through it
+    let mut shadow_catcher_hit = None;
+    let mut media = MediumStack::new();
+    // Bounces off diffuse and specular surfaces have their own limits
+    let mut diffuse_bounces = 0;
+    let mut specular_bounces = 0;
+
+    // After some steps we conclude that the ray
+    // will not hit a light source, so nothing more is added
+    for bounce in 0..arguments.steps {
+        // Rays without direction would only produce NaNs
+        if ray.is_degenerate() {
+            break;
+        }
+
+        // The interval starts at 0.001,
+        // so that we don't get shadow acne or z-fighting.
+        // The camera ray only sees objects between the clipping planes.
+        let ray_interval = if bounce == 0 {
+            camera_ray_interval(&ray, arguments, scene_data)
         } else {
-            return RGBColor::new(0.0, 0.0, 0.0);
+            Interval::new(0.001, f32::INFINITY)
+        };
+        let Some(hit_record) = scene_data.renderables.hit(&ray, ray_interval) else {
+            if let Some(path) = &mut path {
+                path.escape_direction = Some(ray.direction());
+            }
+            // If there is no hit, we calculate background
+            let background = scene_data.background.radiance(&ray);
+            let background = match scattering_pdf {
+                // The background was also sampled directly on the previous bounce
+                Some(scattering_pdf) if scene_data.background.is_importance_sampled() => {
+                    let environment_pdf = scene_data.background.pdf(ray.direction());
+                    let weight = power_heuristic(
+                        scattering_pdf,
+                        arguments.shadow_samples as f32 * environment_pdf,
+                    );
+                    weight * background
+                }
+                _ => background,
+            };
+            color = color + throughput * at_wavelength(background);
+            break;
+        };
+        let material = hit_record.material();
+        if let Some(path) = &mut path {
+            path.vertices.push(PathVertex {
+                point: hit_record.point(),
+                direction: ray.direction(),
+                object_id: hit_record.object_id(),
+                material: material.name(),
+            });
+        }
+
+        // Shadow catchers are invisible, the ray continues behind them.
+        // Only the camera sees the shadows on them.
+        if material.is_shadow_catcher() {
+            ray = Ray::new_at_time(hit_record.point(), ray.direction(), ray.time())
+                .with_wavelength(wavelength)
+                .with_kind(ray.kind());
+            if bounce == 0 {
+                shadow_catcher_hit = Some(hit_record);
+            }
+            continue;
+        }
+
+        let mut emitted = material.emitted(&ray, &hit_record);
+        if let Some(scattering_pdf) = scattering_pdf {
+            if emitted != RGBColor::black() {
+                emitted = light_weight(&ray, &hit_record, scattering_pdf, arguments, scene_data)
+                    * emitted;
+            }
+        }
+        color = color + throughput * at_wavelength(emitted);
+
+        // Refraction depends on the media on both sides of the surface
+        let index_of_refraction = material.index_of_refraction(&ray);
+        if index_of_refraction.is_some() {
+            let outside = media.outside_index_of_refraction(&material, hit_record.front_face());
+            ray = ray.with_outside_index_of_refraction(outside);
+        }
+
+        let scatters = material.scatter_multi(&ray, &hit_record, rng);
+        let Some(mut material_result) = choose_scatter(scatters, rng) else {
+            break;
+        };
+
+        // Rays passing through the surface enter or leave the medium
+        if let Some(index_of_refraction) = index_of_refraction {
+            let scattered_direction = material_result.scattered_ray.direction();
+            if scattered_direction.dot(hit_record.normal()) < 0.0 {
+                if hit_record.front_face() {
+                    media.enter(&material, index_of_refraction);
+                } else {
+                    media.exit(&material);
+                }
+            }
+        }
+        material_result.attenuation = at_wavelength(material_result.attenuation);
+
+        // Only materials that scatter in many directions can sample lights directly
+        let has_lights = !scene_data.renderables.lights().is_empty()
+            || scene_data.background.is_importance_sampled();
+        scattering_pdf = if arguments.shadow_samples > 0 && has_lights {
+            material.scattering_pdf(&ray, &hit_record, &material_result.scattered_ray)
+        } else {
+            None
+        };
+        if scattering_pdf.is_some() {
+            let direct_result = direct_light(
+                &ray,
+                &hit_record,
+                material_result.attenuation,
+                arguments,
+                scene_data,
+                rng,
+            );
+            color = color + throughput * at_wavelength(direct_result);
+        }
+
+        throughput = throughput * material_result.attenuation;
+        ray = material_result
+            .scattered_ray
+            .with_wavelength(wavelength)
+            .with_kind(RayKind::Secondary);
+
+        // Diffuse light is spread out, so later bounces add little to it,
+        // while light through glass still needs to find its way out
+        if material.is_specular() {
+            specular_bounces += 1;
+        } else {
+            diffuse_bounces += 1;
+        }
+        if arguments
+            .diffuse_depth
+            .is_some_and(|depth| diffuse_bounces >= depth)
+            || arguments
+                .specular_depth
+                .is_some_and(|depth| specular_bounces >= depth)
+        {
+            break;
         }
     }
 
-    // If there is no hit, we calculate background
-    scene_data.background.as_ref()(ray)
+    if let Some(hit_record) = shadow_catcher_hit {
+        color =
+            color * shadow_catcher_visibility(&hit_record, ray.time(), arguments, scene_data, rng);
+    }
+    let color = match wavelength {
+        Some(wavelength) => wavelength.to_rgb(color.r()),
+        None => color,
+    };
+    if let Some(path) = path {
+        path.color = color;
+    }
+    color
 }
 
-/// The main rendering process
+/// Returns the interval of the camera ray in which objects are visible
+///
+/// Objects in front of the near or behind the far clipping plane are cut away.
+/// The planes are perpendicular to the viewing direction, so distances are
+/// measured along it, not along the ray.
+/// With `--background-only` no object is visible at all.
 ///
 /// ## Parameters
-/// * `parameters` - global application parameters
+/// * `ray` - the camera ray
+/// * `arguments` - global application parameters
+/// * `scene_data` - scene data to render
+fn camera_ray_interval(ray: &Ray, arguments: &Arguments, scene_data: &SceneData) -> Interval {
+    if arguments.background_only {
+        return Interval::empty();
+    }
+    let default_interval = Interval::new(0.001, f32::INFINITY);
+    // Depth gained per unit of the ray parameter
+    let depth_per_t = ray.direction().dot(scene_data.camera.forward());
+    if depth_per_t <= 0.0 {
+        return default_interval;
+    }
+    let near = arguments.near.map_or(default_interval.min(), |near| {
+        (near / depth_per_t).max(default_interval.min())
+    });
+    let far = arguments
+        .far
+        .map_or(default_interval.max(), |far| far / depth_per_t);
+    Interval::new(near, far)
+}
+
+/// Calculates the color of a pixel sample with a separate ray for each color channel
+///
+/// Used for chromatic aberration, where each channel sees a slightly different image.
+///
+/// ## Parameters
+/// * `rays` - the rays of the red, green and blue channel
+/// * `arguments` - global application parameters
+/// * `scene_data` - scene data to render
+/// * `rng` - random number generator instance
+fn channel_ray_color(
+    rays: [Ray; 3],
+    arguments: &Arguments,
+    scene_data: &SceneData,
+    rng: &mut Xoshiro256Plus,
+) -> RGBColor {
+    let [red_ray, green_ray, blue_ray] = rays;
+    let red = ray_color(&red_ray, arguments, scene_data, rng);
+    let green = ray_color(&green_ray, arguments, scene_data, rng);
+    let blue = ray_color(&blue_ray, arguments, scene_data, rng);
+    RGBColor::new(red.r(), green.g(), blue.b())
+}
+
+/// Calculates the color of a ray that is known to miss every object
+///
+/// Gives the same result as `ray_color`, without searching the scene for hits.
+///
+/// ## Parameters
+/// * `ray` - the ray to operate with
+/// * `arguments` - global application parameters
+/// * `scene_data` - scene data to render
+/// * `rng` - random number generator instance
+fn background_ray_color(
+    ray: &Ray,
+    arguments: &Arguments,
+    scene_data: &SceneData,
+    rng: &mut Xoshiro256Plus,
+) -> RGBColor {
+    let background = scene_data.background.radiance(ray);
+    match arguments.spectral.then(|| Wavelength::sample(rng)) {
+        Some(wavelength) => wavelength.to_rgb(wavelength.value_of(background)),
+        None => background,
+    }
+}
+
+/// Calculates the color of a random sample on the pixel
+///
+/// ## Parameters
+/// * `x` - horizontal pixel coordinate
+/// * `y` - vertical pixel coordinate
+/// * `arguments` - global application parameters
+/// * `scene_data` - scene data to render
+/// * `rng` - random number generator instance
+/// * `background_only` - whether only the background can be seen through the pixel
+fn random_pixel_sample(
+    x: usize,
+    y: usize,
+    arguments: &Arguments,
+    scene_data: &SceneData,
+    rng: &mut Xoshiro256Plus,
+    background_only: bool,
+) -> RGBColor {
+    let camera = &scene_data.camera;
+    if background_only {
+        let ray = camera.get_random_ray_through_pixel(x, y, rng);
+        background_ray_color(&ray, arguments, scene_data, rng)
+    } else if camera.has_chromatic_aberration() {
+        let rays = camera.get_random_channel_rays_through_pixel(x, y, rng);
+        channel_ray_color(rays, arguments, scene_data, rng)
+    } else {
+        let ray = camera.get_random_ray_through_pixel(x, y, rng);
+        ray_color(&ray, arguments, scene_data, rng)
+    }
+}
+
+/// Traces a single camera ray through the pixel and records its path
+///
+/// The ray goes through the pixel center (or a random point of the lens
+/// with depth of field), with the same random numbers as the first sample
+/// of the pixel in a render with the same seed.
+///
+/// ## Parameters
+/// * `x` - horizontal pixel coordinate
+/// * `y` - vertical pixel coordinate
+/// * `arguments` - global application parameters
+/// * `scene_data` - scene data to render
+pub fn trace_pixel_path(
+    x: usize,
+    y: usize,
+    arguments: &Arguments,
+    scene_data: &SceneData,
+) -> LightPath {
+    let seed = arguments.seed.unwrap_or_else(rand::random);
+    let mut rng = pixel_rng(seed, x, y);
+    let camera = &scene_data.camera;
+    let ray = if camera.has_depth_of_field() {
+        camera.get_random_ray_through_pixel(x, y, &mut rng)
+    } else {
+        camera.get_ray_through_pixel_center(x, y)
+    };
+
+    let mut path = LightPath::new(ray.origin());
+    traced_ray_color(&ray, arguments, scene_data, &mut rng, Some(&mut path));
+    path
+}
+
+/// Creates the random number generator of a single pixel
+///
+/// Every pixel gets its own generator derived from the render seed,
+/// so the result does not depend on the order in which pixels are rendered.
+///
+/// ## Parameters
+/// * `seed` - seed of the whole render
+/// * `x` - horizontal pixel coordinate
+/// * `y` - vertical pixel coordinate
+fn pixel_rng(seed: u64, x: usize, y: usize) -> Xoshiro256Plus {
+    let pixel_index = ((y as u64) << 32) | x as u64;
+    // Seeding from u64 runs SplitMix64, which steps its state by the golden ratio constant.
+    // Seeds of neighbouring pixels must not differ by multiples of that step,
+    // otherwise their generators would share most of their state,
+    // so the pixel index is hashed before it is combined with the seed.
+    Xoshiro256Plus::seed_from_u64(seed ^ scramble_bits(pixel_index))
+}
+
+/// Creates the random number generator of a single sample of a pixel
+///
+/// Every sample gets its own generator, so the samples only depend on their index.
+/// A render that continues an earlier one starts at a later index
+/// and takes new samples instead of repeating the earlier ones.
+/// The first sample uses the same generator as `pixel_rng`.
+///
+/// ## Parameters
+/// * `seed` - seed of the whole render
+/// * `x` - horizontal pixel coordinate
+/// * `y` - vertical pixel coordinate
+/// * `sample` - index of the sample in the pixel
+fn sample_rng(seed: u64, x: usize, y: usize, sample: usize) -> Xoshiro256Plus {
+    pixel_rng(seed.wrapping_add(sample as u64), x, y)
+}
+
+/// Scrambles the bits of the value, so that close values map to unrelated ones
+///
+/// Uses the finalizer of SplitMix64 (each input bit affects every output bit).
+///
+/// ## Parameters
+/// * `value` - the value to scramble
+fn scramble_bits(value: u64) -> u64 {
+    let mut value = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    value = (value ^ (value >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    value ^ (value >> 31)
+}
+
+/// Calculates the albedo (color of the surface) seen by the ray
+///
+/// Denoisers use it to tell texture detail apart from noise,
+/// so it only depends on the material of the first surface, not on the lighting.
+/// For lights and for the background, their (clamped) emitted color is used instead.
+/// Shadow catchers are invisible, so the surface behind them is used.
+///
+/// ## Parameters
+/// * `ray` - the camera ray
+/// * `arguments` - global application parameters
+/// * `scene_data` - scene data to render
+fn first_hit_albedo(ray: &Ray, arguments: &Arguments, scene_data: &SceneData) -> RGBColor {
+    let mut ray_interval = camera_ray_interval(ray, arguments, scene_data);
+    let mut ray = Ray::new_at_time(ray.origin(), ray.direction(), ray.time());
+    let mut albedo = loop {
+        let Some(hit_record) = scene_data.renderables.hit_scene(&ray, ray_interval) else {
+            break scene_data.background.radiance(&ray);
+        };
+        let material = hit_record.material();
+        if material.is_shadow_catcher() {
+            ray = Ray::new_at_time(hit_record.point(), ray.direction(), ray.time());
+            ray_interval = Interval::new(0.001, f32::INFINITY);
+            continue;
+        }
+        let emitted = material.emitted(&ray, &hit_record);
+        if emitted != RGBColor::black() {
+            break emitted;
+        }
+        break material.albedo(&hit_record);
+    };
+    albedo.clamp();
+    albedo
+}
+
+/// Calculates the opacity of the scene seen by the ray
+///
+/// Objects are opaque and the background is transparent.
+/// Shadow catchers are as opaque as the shadow on them is dark.
+///
+/// ## Parameters
+/// * `ray` - the camera ray
+/// * `arguments` - global application parameters
+/// * `scene_data` - scene data to render
+/// * `rng` - random number generator instance
+fn first_hit_alpha(
+    ray: &Ray,
+    arguments: &Arguments,
+    scene_data: &SceneData,
+    rng: &mut Xoshiro256Plus,
+) -> f32 {
+    let ray_interval = camera_ray_interval(ray, arguments, scene_data);
+    match scene_data.renderables.hit(ray, ray_interval) {
+        Some(hit_record) if hit_record.material().is_shadow_catcher() => {
+            1.0 - shadow_catcher_visibility(&hit_record, ray.time(), arguments, scene_data, rng)
+        }
+        Some(_) => 1.0,
+        None => 0.0,
+    }
+}
+
+/// Renders the alpha buffer (see `first_hit_alpha`)
+///
+/// Every pixel is sampled as many times as the image (but at least once),
+/// so that edges of objects get partial opacity.
+///
+/// ## Parameters
+/// * `arguments` - global application parameters
+/// * `scene_data` - scene data to render
+/// * `seed` - seed of the whole render
+fn render_alpha(arguments: &Arguments, scene_data: &SceneData, seed: u64) -> Vec<f32> {
+    let width = arguments.output_width;
+    let height = arguments.output_height;
+    let camera = &scene_data.camera;
+
+    let mut alpha_data = vec![0.0; width * height];
+    for (x, y) in arguments.render_order.pixels(width, height) {
+        let mut rng = pixel_rng(seed, x, y);
+        // Like the image, a single sample goes through the pixel center without depth of field
+        alpha_data[y * width + x] =
+            if arguments.samples_per_pixel <= 1 && !camera.has_depth_of_field() {
+                let ray = camera.get_ray_through_pixel_center(x, y);
+                first_hit_alpha(&ray, arguments, scene_data, &mut rng)
+            } else {
+                let alpha_sum: f32 = (0..arguments.samples_per_pixel)
+                    .map(|_| {
+                        let ray = camera.get_random_ray_through_pixel(x, y, &mut rng);
+                        first_hit_alpha(&ray, arguments, scene_data, &mut rng)
+                    })
+                    .sum();
+                alpha_sum / arguments.samples_per_pixel as f32
+            };
+    }
+    alpha_data
+}
+
+/// Renders the object ID buffer
+///
+/// Every pixel gets the ID of the first object hit through its center,
+/// or 0 if only the background is seen. Shadow catchers are objects as well.
+///
+/// ## Parameters
+/// * `arguments` - global application parameters
+/// * `scene_data` - scene data to render
+fn render_object_ids(arguments: &Arguments, scene_data: &SceneData) -> Vec<u32> {
+    let width = arguments.output_width;
+    let height = arguments.output_height;
+
+    let mut object_id_data = vec![0; width * height];
+    for (x, y) in arguments.render_order.pixels(width, height) {
+        let ray = scene_data.camera.get_ray_through_pixel_center(x, y);
+        let ray_interval = camera_ray_interval(&ray, arguments, scene_data);
+        object_id_data[y * width + x] = scene_data
+            .renderables
+            .hit_scene(&ray, ray_interval)
+            .map_or(0, |hit_record| hit_record.object_id());
+    }
+    object_id_data
+}
+
+/// Renders the albedo buffer (see `first_hit_albedo`)
+///
+/// ## Parameters
+/// * `arguments` - global application parameters
 /// * `scene_data` - scene data to render
-pub fn render(arguments: &Arguments, scene_data: SceneData) -> RenderResult {
+fn render_albedo(arguments: &Arguments, scene_data: &SceneData) -> Vec<RGBColor> {
+    let width = arguments.output_width;
+    let height = arguments.output_height;
+
+    let mut albedo_data = vec![RGBColor::black(); width * height];
+    for (x, y) in arguments.render_order.pixels(width, height) {
+        let ray = scene_data.camera.get_ray_through_pixel_center(x, y);
+        albedo_data[y * width + x] = first_hit_albedo(&ray, arguments, scene_data);
+    }
+    albedo_data
+}
+
+/// Renders the image in sample passes until the time budget runs out
+///
+/// Every pass adds one random sample to each pixel. The budget is only
+/// checked between passes, so all pixels end up with the same amount of samples,
+/// and at least one pass is always done.
+///
+/// Returns the samples accumulated in every pixel.
+///
+/// ## Parameters
+/// * `arguments` - global application parameters
+/// * `scene_data` - scene data to render
+/// * `seed` - seed of the whole render
+/// * `first_sample` - index of the first sample in every pixel
+/// * `time_budget` - how long to keep adding passes
+/// * `report_progress` - receives the used fraction of the budget after every pass
+fn render_with_time_budget(
+    arguments: &Arguments,
+    scene_data: &SceneData,
+    seed: u64,
+    first_sample: usize,
+    time_budget: Duration,
+    report_progress: &dyn Fn(f32),
+) -> Vec<SampleAccumulator> {
+    let width = arguments.output_width;
+    let height = arguments.output_height;
+
+    let pixels = arguments.render_order.pixels(width, height);
+
+    if arguments.firefly_reject {
+        log::warn!("Firefly rejection is not supported with a time budget, ignoring it");
+    }
+    if arguments.edge_anti_aliasing {
+        log::warn!("Anti-aliasing only on edges is not supported with a time budget, ignoring it");
+    }
+
+    let empty_tiles = arguments
+        .skip_empty_tiles
+        .then(|| EmptyTiles::new(arguments, scene_data))
+        .flatten();
+
+    let start_time = Instant::now();
+    let mut accumulators = vec![SampleAccumulator::new(); width * height];
+    let mut preview_writer = PreviewWriter::new(arguments);
+    let mut pass_count = 0;
+    while pass_count == 0 || start_time.elapsed() < time_budget {
+        for &(x, y) in &pixels {
+            let mut rng = sample_rng(seed, x, y, first_sample + pass_count);
+            let background_only = empty_tiles
+                .as_ref()
+                .is_some_and(|empty_tiles| empty_tiles.is_empty_at(x, y));
+            let sample =
+                random_pixel_sample(x, y, arguments, scene_data, &mut rng, background_only);
+            accumulators[y * width + x].add(sample);
+            if let Some(preview_writer) = &mut preview_writer {
+                preview_writer.write_if_due(arguments, &accumulators);
+            }
+        }
+        pass_count += 1;
+        log::debug!(" Render pass {} done", pass_count);
+        report_progress((start_time.elapsed().as_secs_f32() / time_budget.as_secs_f32()).min(1.0));
+    }
+
+    log::debug!(
+        "Rendered {} samples per pixel in {:.2?}",
+        pass_count,
+        start_time.elapsed()
+    );
+
+    accumulators
+}
+
+/// Renders the image with a fixed amount of samples per pixel
+///
+/// Returns the final pixel colors and the samples accumulated in every pixel.
+///
+/// ## Parameters
+/// * `arguments` - global application parameters
+/// * `scene_data` - scene data to render
+/// * `seed` - seed of the whole render
+/// * `first_sample` - index of the first sample in every pixel
+/// * `report_progress` - receives the rendered fraction of the image on every milestone
+fn render_with_samples(
+    arguments: &Arguments,
+    scene_data: &SceneData,
+    seed: u64,
+    first_sample: usize,
+    report_progress: &dyn Fn(f32),
+) -> (Vec<RGBColor>, Vec<SampleAccumulator>) {
     let width = arguments.output_width;
     let height = arguments.output_height;
 
     let camera = &scene_data.camera;
+    let empty_tiles = arguments
+        .skip_empty_tiles
+        .then(|| EmptyTiles::new(arguments, scene_data))
+        .flatten();
 
     // For progress tracking
     let mut progress_tracker = ProgressTracker::new(0.0, (width * height) as f32, 1.0, 0.1);
 
-    // Random number generator - fast (less accurate) implementation
-    let mut rng = Xoshiro256Plus::from_rng(thread_rng()).expect("Could not get RNG");
-
-    let mut color_data = Vec::with_capacity(width * height);
-    for y in 0..height {
-        for x in 0..width {
-            let mut pixel_color = RGBColor::new(0.0, 0.0, 0.0);
+    let mut color_data = vec![RGBColor::black(); width * height];
+    let mut accumulators = vec![SampleAccumulator::new(); width * height];
+    let mut preview_writer = PreviewWriter::new(arguments);
+    for (x, y) in arguments.render_order.pixels(width, height) {
+        let samples = first_sample..first_sample + arguments.samples_per_pixel;
+        let accumulator = &mut accumulators[y * width + x];
+        let background_only = empty_tiles
+            .as_ref()
+            .is_some_and(|empty_tiles| empty_tiles.is_empty_at(x, y));
 
-            if arguments.samples_per_pixel == 1 {
-                // We only shoot one ray through the center
+        let pixel_color = if arguments.samples_per_pixel == 1 {
+            // We only shoot one ray through the center,
+            // unless the lens needs to be sampled to show depth of field
+            let mut rng = sample_rng(seed, x, y, first_sample);
+            let pixel_color = if camera.has_depth_of_field() {
+                random_pixel_sample(x, y, arguments, scene_data, &mut rng, background_only)
+            } else if background_only {
                 let ray = camera.get_ray_through_pixel_center(x, y);
-                let result = ray_color(&ray, &scene_data, arguments.steps, &mut rng);
-                pixel_color = result;
+                background_ray_color(&ray, arguments, scene_data, &mut rng)
+            } else if camera.has_chromatic_aberration() {
+                let rays = camera.get_channel_rays_through_pixel_center(x, y);
+                channel_ray_color(rays, arguments, scene_data, &mut rng)
             } else {
-                // For more rays, we do random sampling inside pixel
-                for _ in 0..arguments.samples_per_pixel {
-                    let ray = camera.get_random_ray_through_pixel(x, y, &mut rng);
-                    let new_result = ray_color(&ray, &scene_data, arguments.steps, &mut rng);
-                    pixel_color = pixel_color + new_result;
-                }
+                let ray = camera.get_ray_through_pixel_center(x, y);
+                ray_color(&ray, arguments, scene_data, &mut rng)
+            };
+            accumulator.add(pixel_color);
+            pixel_color
+        } else if arguments.firefly_reject {
+            // We keep all samples, so that outliers can be dropped before averaging
+            let mut kept_samples = Vec::with_capacity(arguments.samples_per_pixel);
+            for sample_index in samples {
+                let mut rng = sample_rng(seed, x, y, sample_index);
+                let sample =
+                    random_pixel_sample(x, y, arguments, scene_data, &mut rng, background_only);
+                accumulator.add(sample);
+                kept_samples.push(sample);
+            }
+            firefly_rejected_mean(&kept_samples)
+        } else {
+            // For more rays, we do random sampling inside pixel
+            for sample_index in samples {
+                let mut rng = sample_rng(seed, x, y, sample_index);
+                let new_result =
+                    random_pixel_sample(x, y, arguments, scene_data, &mut rng, background_only);
+                accumulator.add(new_result);
             }
 
             // We take average of all color samples
-            pixel_color = pixel_color / arguments.samples_per_pixel as f32;
-            color_data.push(pixel_color);
+            accumulator.mean()
+        };
 
-            if let Some(progress) = progress_tracker.increment() {
-                log::debug!(" Render on {:.0}%", progress * 100.0)
-            };
+        color_data[y * width + x] = pixel_color;
+        if let Some(preview_writer) = &mut preview_writer {
+            preview_writer.write_if_due(arguments, &accumulators);
         }
+
+        if let Some(progress) = progress_tracker.increment() {
+            log::debug!(" Render on {:.0}%", progress * 100.0);
+            report_progress(progress);
+        };
     }
 
+    (color_data, accumulators)
+}
+
+/// Finds what the camera ray sees first, to detect edges
+///
+/// ## Parameters
+/// * `ray` - the camera ray
+/// * `arguments` - global application parameters
+/// * `scene_data` - scene data to render
+fn surface_sample(ray: &Ray, arguments: &Arguments, scene_data: &SceneData) -> SurfaceSample {
+    let ray_interval = camera_ray_interval(ray, arguments, scene_data);
+    let albedo = first_hit_albedo(ray, arguments, scene_data);
+    match scene_data.renderables.hit(ray, ray_interval) {
+        Some(hit_record) => SurfaceSample {
+            object_id: hit_record.object_id(),
+            normal: hit_record.normal(),
+            depth: hit_record.t() * ray.direction().length(),
+            albedo,
+        },
+        None => SurfaceSample {
+            object_id: 0,
+            normal: Vec3A::ZERO,
+            depth: f32::INFINITY,
+            albedo,
+        },
+    }
+}
+
+/// Renders the image with anti-aliasing only on edges
+///
+/// The first pass traces a single ray through the center of every pixel.
+/// Pixels where the seen surface changes (a different object, a sharp bend,
+/// a jump in depth or color) are then rendered again with the full amount of samples.
+/// Flat regions keep their single sample, so they stay noisier than with uniform sampling.
+///
+/// Returns the final pixel colors and the samples accumulated in every pixel.
+///
+/// ## Parameters
+/// * `arguments` - global application parameters
+/// * `scene_data` - scene data to render
+/// * `seed` - seed of the whole render
+/// * `report_progress` - receives the rendered fraction of the image on every milestone
+fn render_with_edge_anti_aliasing(
+    arguments: &Arguments,
+    scene_data: &SceneData,
+    seed: u64,
+    report_progress: &dyn Fn(f32),
+) -> (Vec<RGBColor>, Vec<SampleAccumulator>) {
+    let width = arguments.output_width;
+    let height = arguments.output_height;
+    let camera = &scene_data.camera;
+    let pixels = arguments.render_order.pixels(width, height);
+
+    // Both passes visit every pixel
+    let mut progress_tracker = ProgressTracker::new(0.0, (2 * width * height) as f32, 1.0, 0.1);
+    let mut track_pixel = || {
+        if let Some(progress) = progress_tracker.increment() {
+            log::debug!(" Render on {:.0}%", progress * 100.0);
+            report_progress(progress);
+        }
+    };
+
+    let mut color_data = vec![RGBColor::black(); width * height];
+    let mut accumulators = vec![SampleAccumulator::new(); width * height];
+    let mut surface_samples = vec![
+        SurfaceSample {
+            object_id: 0,
+            normal: Vec3A::ZERO,
+            depth: f32::INFINITY,
+            albedo: RGBColor::black(),
+        };
+        width * height
+    ];
+    let mut preview_writer = PreviewWriter::new(arguments);
+    for &(x, y) in &pixels {
+        let mut rng = pixel_rng(seed, x, y);
+        let index = y * width + x;
+        let pixel_color = if camera.has_chromatic_aberration() {
+            let rays = camera.get_channel_rays_through_pixel_center(x, y);
+            channel_ray_color(rays, arguments, scene_data, &mut rng)
+        } else {
+            let ray = camera.get_ray_through_pixel_center(x, y);
+            ray_color(&ray, arguments, scene_data, &mut rng)
+        };
+        accumulators[index].add(pixel_color);
+        color_data[index] = pixel_color;
+        let ray = camera.get_ray_through_pixel_center(x, y);
+        surface_samples[index] = surface_sample(&ray, arguments, scene_data);
+
+        if let Some(preview_writer) = &mut preview_writer {
+            preview_writer.write_if_due(arguments, &accumulators);
+        }
+        track_pixel();
+    }
+
+    let edges = find_edges(&surface_samples, width, height);
+    log::debug!(
+        "Anti-aliasing {} edge pixels of {}",
+        edges.iter().filter(|&&edge| edge).count(),
+        edges.len()
+    );
+
+    // The edge pixels get a new generator, so they do not repeat the first sample
+    let edge_seed = seed.wrapping_add(1);
+    for &(x, y) in &pixels {
+        let index = y * width + x;
+        if edges[index] {
+            let mut rng = pixel_rng(edge_seed, x, y);
+            let mut accumulator = SampleAccumulator::new();
+            for _ in 0..arguments.samples_per_pixel {
+                let sample = random_pixel_sample(x, y, arguments, scene_data, &mut rng, false);
+                accumulator.add(sample);
+            }
+            color_data[index] = accumulator.mean();
+            accumulators[index] = accumulator;
+
+            if let Some(preview_writer) = &mut preview_writer {
+                preview_writer.write_if_due(arguments, &accumulators);
+            }
+        }
+        track_pixel();
+    }
+
+    (color_data, accumulators)
+}
+
+/// The main rendering process
+///
+/// ## Parameters
+/// * `parameters` - global application parameters
+/// * `scene_data` - scene data to render
+/// * `first_sample` - index of the first sample in every pixel
+///   (the amount of samples taken by an earlier render that is continued, otherwise 0)
+pub fn render(arguments: &Arguments, scene_data: &SceneData, first_sample: usize) -> RenderResult {
+    render_with_callback(arguments, scene_data, first_sample, |_| {})
+}
+
+/// The main rendering process, which reports its progress to the callback
+///
+/// The callback receives the finished fraction of the render (from `0.0` to `1.0`)
+/// at the same milestones as the progress log (after every pass with a time budget).
+/// The values only increase, and the last one is always `1.0`.
+///
+/// ## Parameters
+/// * `parameters` - global application parameters
+/// * `scene_data` - scene data to render
+/// * `first_sample` - index of the first sample in every pixel (see `render`)
+/// * `callback` - receives the progress, e.g. to show it in a user interface
+pub fn render_with_callback<F>(
+    arguments: &Arguments,
+    scene_data: &SceneData,
+    first_sample: usize,
+    callback: F,
+) -> RenderResult
+where
+    F: Fn(f32) + Sync,
+{
+    let last_progress = Cell::new(0.0);
+    let report_progress = |progress: f32| {
+        if progress > last_progress.get() {
+            last_progress.set(progress);
+            callback(progress);
+        }
+    };
+
+    let seed = arguments.seed.unwrap_or_else(rand::random);
+    log::debug!("Render seed: {}", seed);
+    let stats_before = RenderStats::collect();
+
+    let (color_data, accumulators) = match arguments.time_budget {
+        Some(time_budget) => {
+            // Invalid (negative) budgets still render a single pass
+            let time_budget = Duration::try_from_secs_f32(time_budget).unwrap_or(Duration::ZERO);
+            let accumulators = render_with_time_budget(
+                arguments,
+                scene_data,
+                seed,
+                first_sample,
+                time_budget,
+                &report_progress,
+            );
+            let color_data = accumulators
+                .iter()
+                .map(|accumulator| accumulator.mean())
+                .collect();
+            (color_data, accumulators)
+        }
+        None if arguments.edge_anti_aliasing && arguments.samples_per_pixel > 1 => {
+            render_with_edge_anti_aliasing(arguments, scene_data, seed, &report_progress)
+        }
+        None => render_with_samples(arguments, scene_data, seed, first_sample, &report_progress),
+    };
+    // All pixels get the same amount of samples (except with anti-aliasing only on edges)
+    let sample_count = accumulators
+        .first()
+        .map_or(0, |accumulator| accumulator.count());
+    // Auxiliary buffers are not counted, the stats describe the image itself
+    let stats = RenderStats::collect().since(&stats_before);
+    log::debug!("Render stats: {}", stats);
+
+    // Auxiliary buffers for external denoisers
+    let (albedo_data, variance_data) = if arguments.denoiser_buffers {
+        let albedo_data = render_albedo(arguments, scene_data);
+        let variance_data = accumulators
+            .iter()
+            .map(|accumulator| accumulator.variance())
+            .collect();
+        (Some(albedo_data), Some(variance_data))
+    } else {
+        (None, None)
+    };
+
+    let alpha_data = arguments
+        .alpha
+        .then(|| render_alpha(arguments, scene_data, seed));
+    let object_id_data = arguments
+        .object_ids
+        .then(|| render_object_ids(arguments, scene_data));
+    report_progress(1.0);
+
     RenderResult {
-        width,
-        height,
+        width: arguments.output_width,
+        height: arguments.output_height,
         image_data: color_data,
+        sample_count,
+        albedo_data,
+        variance_data,
+        alpha_data,
+        object_id_data,
+        stats,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{
+        backgrounds::{gradient_sky::GradientSky, solid_color::SolidColor},
+        camera::Camera,
+        materials::{
+            dielectric::Dielectric,
+            diffuse_light::DiffuseLight,
+            lambertarian::LambertarianDiffuse,
+            metal::Metal,
+            shadow_catcher::ShadowCatcher,
+            stats::{ScatterStats, ScatteringMaterialKind},
+            AnyMaterial,
+        },
+        objects::{parallelogram::Parallelogram, stats::HittableKind},
+        preparation::{prepare_render_data, SceneBuilder},
+        presets::ScenePreset,
+        rendering::history::RenderHistory,
+    };
+
+    use super::*;
+
+    /// Renders the default scene at a tiny resolution
+    fn render_default_scene(samples: usize, first_sample: usize) -> RenderResult {
+        render_default_scene_with(samples, first_sample, &[])
+    }
+
+    /// Renders the default scene at a tiny resolution, with additional options
+    fn render_default_scene_with(
+        samples: usize,
+        first_sample: usize,
+        options: &[&str],
+    ) -> RenderResult {
+        let samples = samples.to_string();
+        let mut all_options = vec![
+            "--output-width",
+            "8",
+            "--output-height",
+            "6",
+            "--samples-per-pixel",
+            &samples,
+            "--seed",
+            "11",
+        ];
+        all_options.extend_from_slice(options);
+        let arguments = Arguments::from_options(&all_options);
+        let mut scene_data = prepare_render_data(&arguments).unwrap();
+        scene_data
+            .renderables
+            .build_accelerator(arguments.accelerator);
+        render(&arguments, &scene_data, first_sample)
+    }
+
+    /// Estimates the direct light at a floor point under a square light
+    /// that is partially hidden by a sphere, once for each seed
+    fn penumbra_estimates(shadow_samples: usize) -> Vec<f32> {
+        let shadow_samples = shadow_samples.to_string();
+        let arguments = Arguments::from_options(&["--shadow-samples", &shadow_samples]);
+        let white = RGBColor::white();
+        let scene_data = SceneBuilder::new()
+            .light(Parallelogram::new(
+                Vec3A::new(-1.0, 2.0, -1.0),
+                Vec3A::new(0.0, 0.0, 2.0),
+                Vec3A::new(2.0, 0.0, 0.0),
+                DiffuseLight::new(white),
+            ))
+            .sphere(
+                Vec3A::new(0.0, 1.0, 0.0),
+                0.5,
+                LambertarianDiffuse::new(white),
+            )
+            .build();
+
+        let point = Vec3A::new(0.3, 0.0, 0.0);
+        let ray = Ray::new(Vec3A::new(0.3, 1.0, 1.0), point - Vec3A::new(0.3, 1.0, 1.0));
+        let hit_record = HitRecord::new(
+            point,
+            Vec3A::Y,
+            1.0,
+            true,
+            LambertarianDiffuse::new_counter(white),
+        );
+        (0..200)
+            .map(|seed| {
+                let mut rng = Xoshiro256Plus::seed_from_u64(seed);
+                direct_light(&ray, &hit_record, white, &arguments, &scene_data, &mut rng)
+                    .luminance()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn more_shadow_samples_smooth_the_penumbra() {
+        let mean = |values: &[f32]| values.iter().sum::<f32>() / values.len() as f32;
+        let variance = |values: &[f32]| {
+            let mean = mean(values);
+            values
+                .iter()
+                .map(|value| (value - mean).powi(2))
+                .sum::<f32>()
+                / values.len() as f32
+        };
+        let fully_shadowed = |values: &[f32]| values.iter().filter(|&&value| value == 0.0).count();
+
+        let single = penumbra_estimates(1);
+        let many = penumbra_estimates(16);
+        // With one shadow ray, the point is either lit or in shadow
+        assert!(fully_shadowed(&single) > 20, "{}", fully_shadowed(&single));
+        assert!(fully_shadowed(&many) * 10 < fully_shadowed(&single));
+        assert!(variance(&many) * 8.0 < variance(&single));
+        assert!((mean(&many) - mean(&single)).abs() < 0.2 * mean(&many));
+    }
+
+    /// Mean and variance of the values
+    fn mean_and_variance(values: &[f32]) -> (f32, f32) {
+        let mean = values.iter().sum::<f32>() / values.len() as f32;
+        let variance = values
+            .iter()
+            .map(|value| (value - mean).powi(2))
+            .sum::<f32>()
+            / values.len() as f32;
+        (mean, variance)
+    }
+
+    const SMALL_LIGHT_ESTIMATES: usize = 20000;
+
+    /// Estimates the light reflected by a diffuse floor under a small light,
+    /// once for each seed
+    ///
+    /// Unless the light is added as a light, it is only found by scattered rays.
+    /// The preset (if any) is applied to the scene before rendering.
+    fn small_light_estimates(sample_light: bool, preset: Option<ScenePreset>) -> Vec<f32> {
+        let arguments = Arguments::from_options(&["--steps", "2"]);
+        let light = Parallelogram::new(
+            Vec3A::new(-0.1, 1.0, -0.1),
+            Vec3A::new(0.0, 0.0, 0.2),
+            Vec3A::new(0.2, 0.0, 0.0),
+            DiffuseLight::new(RGBColor::white() * 50.0),
+        );
+        let builder = SceneBuilder::new()
+            .background(Box::new(SolidColor::new(RGBColor::black())))
+            .parallelogram(
+                Vec3A::new(-5.0, 0.0, 5.0),
+                Vec3A::new(0.0, 0.0, -10.0),
+                Vec3A::new(10.0, 0.0, 0.0),
+                LambertarianDiffuse::new(RGBColor::white() * 0.5),
+            );
+        let mut scene_data = if sample_light {
+            builder.light(light).build()
+        } else {
+            builder.hittable(light).build()
+        };
+        if let Some(preset) = preset {
+            preset.apply(&mut scene_data.renderables);
+        }
+
+        let ray = Ray::new(Vec3A::new(0.0, 1.0, 2.0), Vec3A::new(0.0, -1.0, -1.5));
+        (0..SMALL_LIGHT_ESTIMATES)
+            .map(|seed| {
+                let mut rng = Xoshiro256Plus::seed_from_u64(seed as u64);
+                ray_color(&ray, &arguments, &scene_data, &mut rng).luminance()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn light_sampling_reduces_variance_without_bias() {
+        let (mis_mean, mis_variance) = mean_and_variance(&small_light_estimates(true, None));
+        let (bsdf_mean, bsdf_variance) = mean_and_variance(&small_light_estimates(false, None));
+        assert!(
+            mis_variance * 20.0 < bsdf_variance,
+            "{} {}",
+            mis_variance,
+            bsdf_variance
+        );
+        // Both estimates converge to the same value, within three standard errors
+        let standard_error = (bsdf_variance / SMALL_LIGHT_ESTIMATES as f32).sqrt();
+        assert!(
+            (mis_mean - bsdf_mean).abs() < 3.0 * standard_error,
+            "{} {}",
+            mis_mean,
+            bsdf_mean
+        );
+    }
+
+    #[test]
+    fn clay_preset_keeps_the_lights_shining() {
+        // The floor is as gray as clay, so the lighting stays the same
+        let (clay_mean, _) =
+            mean_and_variance(&small_light_estimates(true, Some(ScenePreset::Clay)));
+        let (bsdf_mean, bsdf_variance) =
+            mean_and_variance(&small_light_estimates(false, Some(ScenePreset::Clay)));
+        let (mean, _) = mean_and_variance(&small_light_estimates(true, None));
+        let standard_error = (bsdf_variance / SMALL_LIGHT_ESTIMATES as f32).sqrt();
+        assert!(
+            (clay_mean - bsdf_mean).abs() < 3.0 * standard_error,
+            "{} {}",
+            clay_mean,
+            bsdf_mean
+        );
+        assert!(
+            (clay_mean - mean).abs() < 1e-3 * mean,
+            "{} {}",
+            clay_mean,
+            mean
+        );
+    }
+
+    #[test]
+    fn continued_render_matches_single_render() {
+        let single = render_default_scene(100, 0);
+
+        let mut accumulated = render_default_scene(50, 50);
+        RenderHistory::from_result(&render_default_scene(50, 0))
+            .merge_into(&mut accumulated)
+            .unwrap();
+
+        assert_eq!(accumulated.sample_count, 100);
+        for (x, y, color) in single.pixels() {
+            let difference = color - accumulated.pixel(x, y);
+            let tolerance = 1e-4 * (1.0 + color.luminance());
+            assert!(
+                difference.r().abs() < tolerance
+                    && difference.g().abs() < tolerance
+                    && difference.b().abs() < tolerance,
+                "pixel ({}, {}): {:?} != {:?}",
+                x,
+                y,
+                color,
+                accumulated.pixel(x, y)
+            );
+        }
+    }
+
+    #[test]
+    fn ray_without_direction_is_black() {
+        let arguments = Arguments::from_options(&[]);
+        let mut scene_data = prepare_render_data(&arguments).unwrap();
+        scene_data
+            .renderables
+            .build_accelerator(arguments.accelerator);
+        let ray = Ray::new(Vec3A::new(0.0, 1.0, 0.0), Vec3A::ZERO);
+        let mut rng = Xoshiro256Plus::seed_from_u64(1);
+        let color = ray_color(&ray, &arguments, &scene_data, &mut rng);
+        assert!(color == RGBColor::black(), "{:?}", color);
+    }
+
+    #[test]
+    fn render_order_does_not_change_the_image() {
+        let scanline = render_default_scene_with(4, 0, &["--render-order", "scanline"]);
+        for order in ["morton", "spiral"] {
+            let reordered = render_default_scene_with(4, 0, &["--render-order", order]);
+            let same_bytes = scanline
+                .image_data
+                .iter()
+                .zip(&reordered.image_data)
+                .all(|(a, b)| a.to_rgb8() == b.to_rgb8() && a == b);
+            assert!(same_bytes, "{} order changed the image", order);
+        }
+    }
+
+    #[test]
+    fn larger_time_budget_renders_more_passes() {
+        let tiny = render_default_scene_with(1, 0, &["--time-budget", "0"]);
+        assert_eq!(tiny.sample_count, 1);
+        assert!(tiny.image_data.iter().all(|color| color.luminance() > 0.0));
+
+        let larger = render_default_scene_with(1, 0, &["--time-budget", "0.2"]);
+        assert!(larger.sample_count > 1, "{}", larger.sample_count);
+    }
+
+    #[test]
+    fn noisy_pixels_have_higher_variance() {
+        let result = render_default_scene_with(32, 0, &["--denoiser-buffers"]);
+        let variance = result.variance_data.unwrap();
+        // The corner only sees the smooth sky, the center is a diffusely lit object
+        let flat_pixel = variance[0].luminance();
+        let noisy_pixel = variance[8 + 4].luminance();
+        assert!(flat_pixel < 1e-3, "{}", flat_pixel);
+        assert!(noisy_pixel > 100.0 * flat_pixel, "{}", noisy_pixel);
+        assert_eq!(result.albedo_data.unwrap().len(), 8 * 6);
+    }
+
+    /// Total direct light at a floor point in the shadow of a glass ball,
+    /// which focuses the light of a small square light onto the point
+    fn light_under_glass_ball(caustics: bool) -> f32 {
+        let mut options = vec!["--shadow-samples", "4"];
+        if caustics {
+            options.push("--caustics");
+        }
+        let arguments = Arguments::from_options(&options);
+        let white = RGBColor::white();
+        let scene_data = SceneBuilder::new()
+            .light(Parallelogram::new(
+                Vec3A::new(-0.5, 4.0, -0.5),
+                Vec3A::new(0.0, 0.0, 1.0),
+                Vec3A::new(1.0, 0.0, 0.0),
+                DiffuseLight::new(white),
+            ))
+            // The focal point of the ball lens is 0.75 below its center
+            .sphere(Vec3A::new(0.0, 0.75, 0.0), 0.5, Dielectric::new(1.5))
+            .background(Box::new(SolidColor::new(RGBColor::black())))
+            .build();
+
+        let point = Vec3A::ZERO;
+        let ray = Ray::new(Vec3A::new(0.0, 0.1, 1.0), point - Vec3A::new(0.0, 0.1, 1.0));
+        let hit_record = HitRecord::new(
+            point,
+            Vec3A::Y,
+            1.0,
+            true,
+            LambertarianDiffuse::new_counter(white),
+        );
+        (0..200)
+            .map(|seed| {
+                let mut rng = Xoshiro256Plus::seed_from_u64(seed);
+                direct_light(&ray, &hit_record, white, &arguments, &scene_data, &mut rng)
+                    .luminance()
+            })
+            .sum()
+    }
+
+    #[test]
+    fn caustics_light_points_behind_glass() {
+        assert_eq!(light_under_glass_ball(false), 0.0);
+        let caustic_light = light_under_glass_ball(true);
+        assert!(caustic_light > 0.0, "{}", caustic_light);
+    }
+
+    /// Renders a row through a vertical edge of a white light, right of the image center,
+    /// and returns the first column where each color channel is lit
+    fn lit_columns(chromatic_aberration: f32) -> [usize; 3] {
+        let (width, height) = (64, 4);
+        let arguments = Arguments::from_options(&[
+            "--output-width",
+            &width.to_string(),
+            "--output-height",
+            &height.to_string(),
+            "--seed",
+            "2",
+        ]);
+        let mut camera = Camera::default();
+        camera.set_width(width);
+        camera.set_height(height);
+        camera.set_chromatic_aberration(chromatic_aberration);
+        let scene_data = SceneBuilder::new()
+            .camera(camera)
+            .hittable(Parallelogram::new(
+                Vec3A::new(12.0, -10.0, -2.0),
+                Vec3A::new(0.0, 20.0, 0.0),
+                Vec3A::new(20.0, 0.0, 0.0),
+                DiffuseLight::new(RGBColor::white()),
+            ))
+            .background(Box::new(SolidColor::new(RGBColor::black())))
+            .build();
+
+        let result = render(&arguments, &scene_data, 0);
+        let row = &result.image_data[width..2 * width];
+        let first_lit = |channel: fn(&RGBColor) -> f32| {
+            row.iter().position(|color| channel(color) > 0.5).unwrap()
+        };
+        [
+            first_lit(RGBColor::r),
+            first_lit(RGBColor::g),
+            first_lit(RGBColor::b),
+        ]
+    }
+
+    #[test]
+    fn chromatic_aberration_splits_the_channels_at_edges() {
+        let [red, green, blue] = lit_columns(0.0);
+        assert!(red == green && green == blue && green > 40, "{}", green);
+
+        // Red is magnified, so it reaches the edge closer to the center than blue
+        let [red, green, blue] = lit_columns(0.1);
+        assert!(red < green && green < blue, "{} {} {}", red, green, blue);
+    }
+
+    #[test]
+    fn shadow_catcher_only_shows_the_shadow() {
+        let arguments = Arguments::from_options(&["--shadow-samples", "4", "--alpha"]);
+        let background = RGBColor::new(0.2, 0.4, 0.6);
+        let scene_data = SceneBuilder::new()
+            .light(Parallelogram::new(
+                Vec3A::new(-0.2, 4.0, -0.2),
+                Vec3A::new(0.0, 0.0, 0.4),
+                Vec3A::new(0.4, 0.0, 0.0),
+                DiffuseLight::new(RGBColor::white()),
+            ))
+            // Casts a shadow onto the origin
+            .sphere(
+                Vec3A::new(0.0, 2.0, 0.0),
+                0.5,
+                LambertarianDiffuse::new(RGBColor::white()),
+            )
+            .hittable(Parallelogram::new(
+                Vec3A::new(-10.0, 0.0, -10.0),
+                Vec3A::new(0.0, 0.0, 20.0),
+                Vec3A::new(20.0, 0.0, 0.0),
+                ShadowCatcher::new(),
+            ))
+            .background(Box::new(SolidColor::new(background)))
+            .build();
+        let mut rng = Xoshiro256Plus::seed_from_u64(4);
+
+        let lit_ray = Ray::new(Vec3A::new(3.0, 0.5, 0.0), Vec3A::NEG_Y);
+        let color = ray_color(&lit_ray, &arguments, &scene_data, &mut rng);
+        assert!(color == background, "{:?}", color);
+        assert_eq!(
+            first_hit_alpha(&lit_ray, &arguments, &scene_data, &mut rng),
+            0.0
+        );
+
+        let shadowed_ray = Ray::new(Vec3A::new(0.0, 0.5, 0.0), Vec3A::NEG_Y);
+        let color = ray_color(&shadowed_ray, &arguments, &scene_data, &mut rng);
+        assert!(color == RGBColor::black(), "{:?}", color);
+        assert_eq!(
+            first_hit_alpha(&shadowed_ray, &arguments, &scene_data, &mut rng),
+            1.0
+        );
+    }
+
+    /// Renders a sphere on a parallelogram floor and returns the statistics
+    fn sphere_on_floor_stats() -> RenderStats {
+        let arguments = Arguments::from_options(&[
+            "--output-width",
+            "8",
+            "--output-height",
+            "6",
+            "--samples-per-pixel",
+            "2",
+            "--seed",
+            "5",
+        ]);
+        let mut camera = Camera::default();
+        camera.set_width(8);
+        camera.set_height(6);
+        camera.set_position(Vec3A::new(0.0, 1.0, 3.0));
+        camera.look_at(Vec3A::ZERO);
+        let white = RGBColor::white();
+        let scene_data = SceneBuilder::new()
+            .camera(camera)
+            .sphere(
+                Vec3A::new(0.0, 0.5, 0.0),
+                0.5,
+                LambertarianDiffuse::new(white),
+            )
+            .hittable(Parallelogram::new(
+                Vec3A::new(-5.0, 0.0, -5.0),
+                Vec3A::new(0.0, 0.0, 10.0),
+                Vec3A::new(10.0, 0.0, 0.0),
+                LambertarianDiffuse::new(white),
+            ))
+            .background(Box::new(SolidColor::new(white)))
+            .build();
+        render(&arguments, &scene_data, 0).stats
+    }
+
+    #[test]
+    fn stats_count_the_tests_of_each_hittable_type() {
+        let stats = sphere_on_floor_stats();
+        let tests_of = |kind: HittableKind| {
+            stats
+                .intersections
+                .iter()
+                .find(|stats| stats.kind == kind)
+                .map_or(0, |stats| stats.tests)
+        };
+        let sphere_tests = tests_of(HittableKind::Sphere);
+        let parallelogram_tests = tests_of(HittableKind::Parallelogram);
+        assert!(sphere_tests > 0 && parallelogram_tests > 0);
+        assert_eq!(sphere_tests + parallelogram_tests, stats.total_tests());
+        // Every ray is tested against both objects
+        assert!(stats.rays >= 8 * 6 * 2);
+        assert_eq!(sphere_tests, stats.rays);
+
+        // Each render only counts its own work
+        let repeated_stats = sphere_on_floor_stats();
+        assert_eq!(repeated_stats.rays, stats.rays);
+        assert_eq!(repeated_stats.total_tests(), stats.total_tests());
+    }
+
+    /// Renders the albedo buffer of a red sphere filling the center of the image
+    fn red_sphere_albedo(background: RGBColor) -> Vec<RGBColor> {
+        let arguments = Arguments::from_options(&[
+            "--output-width",
+            "4",
+            "--output-height",
+            "4",
+            "--denoiser-buffers",
+            "--seed",
+            "3",
+        ]);
+        let mut camera = Camera::default();
+        camera.set_width(4);
+        camera.set_height(4);
+        camera.set_position(Vec3A::new(0.0, 0.0, 3.0));
+        camera.look_at(Vec3A::ZERO);
+        let scene_data = SceneBuilder::new()
+            .camera(camera)
+            .sphere(
+                Vec3A::ZERO,
+                1.0,
+                LambertarianDiffuse::new(RGBColor::new(0.8, 0.1, 0.1)),
+            )
+            .background(Box::new(SolidColor::new(background)))
+            .build();
+        render(&arguments, &scene_data, 0).albedo_data.unwrap()
+    }
+
+    #[test]
+    fn albedo_does_not_depend_on_the_lighting() {
+        let red = RGBColor::new(0.8, 0.1, 0.1);
+        let dark = red_sphere_albedo(RGBColor::new(0.01, 0.01, 0.01));
+        let bright = red_sphere_albedo(RGBColor::new(0.9, 0.9, 0.9));
+        // The four center pixels show the sphere
+        for index in [5, 6, 9, 10] {
+            assert!(dark[index] == red, "{:?}", dark[index]);
+            assert!(bright[index] == red, "{:?}", bright[index]);
+        }
+        // Corners show the background
+        assert!(dark[0] != bright[0]);
+    }
+
+    /// Color of a ray bouncing about a thousand times between two mirrors
+    /// before it escapes into a white background
+    fn mirror_corridor_color(steps: usize) -> RGBColor {
+        let arguments = Arguments::from_options(&["--steps", &steps.to_string()]);
+        let mirror = || Metal::new(RGBColor::new(0.999, 0.999, 0.999), 0.0);
+        let scene_data = SceneBuilder::new()
+            .hittable(Parallelogram::new(
+                Vec3A::new(-1.0, 0.0, -10.0),
+                Vec3A::new(0.0, 0.0, 20.0),
+                Vec3A::new(2.0, 0.0, 0.0),
+                mirror(),
+            ))
+            .hittable(Parallelogram::new(
+                Vec3A::new(-1.0, 1.0, -10.0),
+                Vec3A::new(2.0, 0.0, 0.0),
+                Vec3A::new(0.0, 0.0, 20.0),
+                mirror(),
+            ))
+            .background(Box::new(SolidColor::new(RGBColor::white())))
+            .build();
+        let ray = Ray::new(Vec3A::new(0.0, 0.5, 0.0), Vec3A::new(0.0, -1.0, 0.01));
+        let mut rng = Xoshiro256Plus::seed_from_u64(1);
+        ray_color(&ray, &arguments, &scene_data, &mut rng)
+    }
+
+    #[test]
+    fn long_paths_do_not_overflow_the_stack() {
+        // Not enough steps to get out
+        assert!(mirror_corridor_color(500) == RGBColor::black());
+
+        let color = mirror_corridor_color(2000);
+        let expected = 0.999f32.powi(1000);
+        assert!((color.g() - expected).abs() < 0.01, "{:?}", color);
+    }
+
+    #[test]
+    fn single_reflection_multiplies_the_background() {
+        let arguments = Arguments::from_options(&["--steps", "3"]);
+        let albedo = RGBColor::new(0.9, 0.6, 0.3);
+        let background = RGBColor::new(0.5, 1.0, 2.0);
+        let scene_data = SceneBuilder::new()
+            .sphere(Vec3A::ZERO, 1.0, Metal::new(albedo, 0.0))
+            .background(Box::new(SolidColor::new(background)))
+            .build();
+        let ray = Ray::new(Vec3A::new(0.0, 0.0, 5.0), Vec3A::NEG_Z);
+        let mut rng = Xoshiro256Plus::seed_from_u64(1);
+        let color = ray_color(&ray, &arguments, &scene_data, &mut rng);
+        assert!(color == albedo * background, "{:?}", color);
+    }
+
+    #[test]
+    fn previews_have_the_size_of_the_image() {
+        let output_path =
+            std::env::temp_dir().join(format!("raybow-preview-{}", std::process::id()));
+        let output_path = output_path.to_str().unwrap();
+        let result = render_default_scene_with(2, 0, &["-o", output_path, "--preview-every", "0"]);
+
+        let preview_path = format!("{}_preview.ppm", output_path);
+        let preview = std::fs::read(&preview_path).unwrap();
+        std::fs::remove_file(&preview_path).unwrap();
+        let header = format!("P6\n{} {}\n255\n", result.width, result.height);
+        assert!(preview.starts_with(header.as_bytes()));
+        assert_eq!(
+            preview.len(),
+            header.len() + result.width * result.height * 3
+        );
+    }
+
+    #[test]
+    fn progress_increases_up_to_one() {
+        for options in [
+            &["--samples-per-pixel", "3"][..],
+            &["--time-budget", "0.05"],
+        ] {
+            let mut all_options = vec!["--output-width", "8", "--output-height", "6"];
+            all_options.extend_from_slice(options);
+            let arguments = Arguments::from_options(&all_options);
+            let scene_data = prepare_render_data(&arguments).unwrap();
+
+            let progress = std::sync::Mutex::new(Vec::new());
+            render_with_callback(&arguments, &scene_data, 0, |fraction| {
+                progress.lock().unwrap().push(fraction);
+            });
+            let progress = progress.into_inner().unwrap();
+            assert!(
+                progress.windows(2).all(|pair| pair[0] < pair[1]),
+                "{:?}",
+                progress
+            );
+            assert!(progress.iter().all(|fraction| *fraction > 0.0));
+            assert_eq!(progress.last(), Some(&1.0));
+        }
+    }
+
+    /// Renders the object IDs of two spheres next to each other
+    fn two_spheres_object_ids() -> Vec<u32> {
+        let arguments = Arguments::from_options(&[
+            "--output-width",
+            "8",
+            "--output-height",
+            "4",
+            "--object-ids",
+        ]);
+        let mut camera = Camera::default();
+        camera.set_width(8);
+        camera.set_height(4);
+        camera.set_position(Vec3A::new(0.0, 0.0, 4.0));
+        camera.look_at(Vec3A::ZERO);
+        let white = RGBColor::white();
+        let scene_data = SceneBuilder::new()
+            .camera(camera)
+            .sphere(
+                Vec3A::new(-1.0, 0.0, 0.0),
+                0.9,
+                LambertarianDiffuse::new(white),
+            )
+            .sphere(
+                Vec3A::new(1.0, 0.0, 0.0),
+                0.9,
+                LambertarianDiffuse::new(white),
+            )
+            .background(Box::new(SolidColor::new(white)))
+            .build();
+        render(&arguments, &scene_data, 0).object_id_data.unwrap()
+    }
+
+    #[test]
+    fn each_object_has_its_own_id() {
+        let object_ids = two_spheres_object_ids();
+        // The middle row, with the left sphere left of the center
+        let row = &object_ids[16..24];
+        assert_eq!(row[2], 1, "{:?}", row);
+        assert_eq!(row[5], 2, "{:?}", row);
+        // The background is 0
+        assert_eq!(object_ids[0], 0);
+        assert_eq!(object_ids[31], 0);
+        // IDs only depend on the order the scene is built in
+        assert_eq!(two_spheres_object_ids(), object_ids);
+    }
+
+    /// Renders a small sphere in the top left corner of the image, in front of a sky gradient
+    fn corner_sphere(options: &[&str]) -> (RenderResult, Option<EmptyTiles>) {
+        let mut all_options = vec![
+            "--output-width",
+            "64",
+            "--output-height",
+            "48",
+            "--samples-per-pixel",
+            "2",
+            "--seed",
+            "6",
+        ];
+        all_options.extend_from_slice(options);
+        let arguments = Arguments::from_options(&all_options);
+        let mut camera = Camera::default();
+        camera.set_width(64);
+        camera.set_height(48);
+        camera.set_position(Vec3A::new(0.0, 0.0, 4.0));
+        camera.look_at(Vec3A::ZERO);
+        let corner = camera.get_ray_through_pixel_center(4, 4);
+        let scene_data = SceneBuilder::new()
+            .camera(camera)
+            .sphere(
+                corner.origin() + corner.direction().normalize() * 4.0,
+                0.1,
+                LambertarianDiffuse::new(RGBColor::new(0.9, 0.2, 0.2)),
+            )
+            .background(Box::new(GradientSky::default()))
+            .build();
+        let empty_tiles = EmptyTiles::new(&arguments, &scene_data);
+        (render(&arguments, &scene_data, 0), empty_tiles)
+    }
+
+    #[test]
+    fn empty_tiles_match_the_full_trace() {
+        let (skipped, empty_tiles) = corner_sphere(&["--skip-empty-tiles"]);
+        let empty_tiles = empty_tiles.unwrap();
+        assert!(!empty_tiles.is_empty_at(4, 4));
+        assert!(empty_tiles.is_empty_at(60, 40));
+
+        let (traced, _) = corner_sphere(&[]);
+        for y in 0..48 {
+            for x in 0..64 {
+                let index = y * 64 + x;
+                assert!(
+                    skipped.image_data[index] == traced.image_data[index],
+                    "{} {}: {:?} != {:?}",
+                    x,
+                    y,
+                    skipped.image_data[index],
+                    traced.image_data[index]
+                );
+            }
+        }
+        // Pixels of empty tiles are not traced through the scene
+        assert!(skipped.stats.rays * 2 < traced.stats.rays);
+        // The sphere is still traced
+        assert!(traced.image_data[4 * 64 + 4].r() > traced.image_data[4 * 64 + 4].b());
+    }
+
+    /// Renders a single sample per pixel of a light's edge, far in front of the focus,
+    /// and returns the middle row
+    fn defocused_edge_row(dof_angle: f32, seed: u64) -> Vec<RGBColor> {
+        let seed = seed.to_string();
+        let arguments = Arguments::from_options(&[
+            "--output-width",
+            "32",
+            "--output-height",
+            "4",
+            "--samples-per-pixel",
+            "1",
+            "--seed",
+            &seed,
+        ]);
+        let mut camera = Camera::default();
+        camera.set_width(32);
+        camera.set_height(4);
+        camera.set_position(Vec3A::new(0.0, 0.0, 2.0));
+        camera.look_at(Vec3A::ZERO);
+        camera.set_defocus(50.0, dof_angle);
+        let scene_data = SceneBuilder::new()
+            .camera(camera)
+            .hittable(Parallelogram::new(
+                Vec3A::new(0.0, -10.0, 0.0),
+                Vec3A::new(0.0, 20.0, 0.0),
+                Vec3A::new(20.0, 0.0, 0.0),
+                DiffuseLight::new(RGBColor::white()),
+            ))
+            .background(Box::new(SolidColor::new(RGBColor::black())))
+            .build();
+        let result = render(&arguments, &scene_data, 0);
+        result.image_data[32..64].to_vec()
+    }
+
+    #[test]
+    fn single_sample_shows_depth_of_field() {
+        // Without depth of field, the single ray goes through the pixel center
+        assert!(defocused_edge_row(0.0, 1) == defocused_edge_row(0.0, 2));
+
+        // With it, the ray starts at a random point of the lens,
+        // so the blurred edge moves between renders
+        let first = defocused_edge_row(5.0, 1);
+        let second = defocused_edge_row(5.0, 2);
+        assert!(first != second);
+        let sharp = defocused_edge_row(0.0, 1);
+        assert!(first != sharp);
+    }
+
+    /// Counts the pixels of two spheres, the left one straddling the near clipping plane
+    fn sphere_pixel_counts(near: Option<&str>) -> (usize, usize) {
+        let mut options = vec![
+            "--output-width",
+            "32",
+            "--output-height",
+            "16",
+            "--object-ids",
+        ];
+        if let Some(near) = near {
+            options.extend_from_slice(&["--near", near]);
+        }
+        let arguments = Arguments::from_options(&options);
+        let mut camera = Camera::default();
+        camera.set_width(32);
+        camera.set_height(16);
+        camera.set_position(Vec3A::new(0.0, 0.0, 4.0));
+        camera.look_at(Vec3A::ZERO);
+        let white = RGBColor::white();
+        let scene_data = SceneBuilder::new()
+            .camera(camera)
+            // Between 2.7 and 4.5 from the camera
+            .sphere(
+                Vec3A::new(-1.0, 0.0, 0.4),
+                0.9,
+                LambertarianDiffuse::new(white),
+            )
+            .sphere(
+                Vec3A::new(1.0, 0.0, -1.0),
+                0.9,
+                LambertarianDiffuse::new(white),
+            )
+            .background(Box::new(SolidColor::new(white)))
+            .build();
+        let object_ids = render(&arguments, &scene_data, 0).object_id_data.unwrap();
+        let count = |id: u32| object_ids.iter().filter(|&&pixel| pixel == id).count();
+        (count(1), count(2))
+    }
+
+    #[test]
+    fn near_plane_clips_objects_in_front_of_it() {
+        let (straddling, behind) = sphere_pixel_counts(None);
+        let (clipped, clipped_behind) = sphere_pixel_counts(Some("4"));
+        // Rays near the rim leave the sphere before the near plane,
+        // while the center still shows its back side
+        assert!(
+            clipped > 0 && clipped < straddling,
+            "{} {}",
+            clipped,
+            straddling
+        );
+        assert_eq!(clipped_behind, behind);
+
+        // In front of everything, the near plane changes nothing
+        assert_eq!(sphere_pixel_counts(Some("1")), (straddling, behind));
+    }
+
+    /// Renders a glass sphere filling the view and returns the counted dielectric scattering
+    fn glass_sphere_scatters() -> ScatterStats {
+        let arguments = Arguments::from_options(&[
+            "--output-width",
+            "8",
+            "--output-height",
+            "8",
+            "--samples-per-pixel",
+            "4",
+            "--seed",
+            "3",
+        ]);
+        let mut camera = Camera::default();
+        camera.set_width(8);
+        camera.set_height(8);
+        camera.set_position(Vec3A::new(0.0, 0.0, 3.0));
+        camera.look_at(Vec3A::ZERO);
+        let scene_data = SceneBuilder::new()
+            .camera(camera)
+            .sphere(Vec3A::ZERO, 1.5, Dielectric::new(1.5))
+            .background(Box::new(SolidColor::new(RGBColor::white())))
+            .build();
+        render(&arguments, &scene_data, 0)
+            .stats
+            .scatters
+            .into_iter()
+            .find(|stats| stats.kind == ScatteringMaterialKind::Dielectric)
+            .unwrap()
+    }
+
+    #[test]
+    fn glass_sphere_both_reflects_and_refracts() {
+        let scatters = glass_sphere_scatters();
+        assert!(
+            scatters.reflected > 0 && scatters.refracted > 0,
+            "{:?}",
+            scatters
+        );
+        assert_eq!(scatters.absorbed, 0);
+
+        // Each render only counts its own scattering
+        let repeated_scatters = glass_sphere_scatters();
+        assert_eq!(repeated_scatters.reflected, scatters.reflected);
+        assert_eq!(repeated_scatters.refracted, scatters.refracted);
+    }
+
+    #[test]
+    fn edge_anti_aliasing_only_supersamples_the_outline() {
+        let arguments = Arguments::from_options(&[
+            "--output-width",
+            "16",
+            "--output-height",
+            "16",
+            "--samples-per-pixel",
+            "8",
+            "--edge-anti-aliasing",
+            "--seed",
+            "2",
+        ]);
+        let mut camera = Camera::default();
+        camera.set_width(16);
+        camera.set_height(16);
+        camera.set_position(Vec3A::new(0.0, 0.0, 4.0));
+        camera.look_at(Vec3A::ZERO);
+        let white = RGBColor::white();
+        let scene_data = SceneBuilder::new()
+            .camera(camera)
+            // A flat square, so that only its outline is an edge
+            .hittable(Parallelogram::new(
+                Vec3A::new(-1.0, -1.0, 0.0),
+                Vec3A::new(2.0, 0.0, 0.0),
+                Vec3A::new(0.0, 2.0, 0.0),
+                LambertarianDiffuse::new(white),
+            ))
+            .background(Box::new(SolidColor::new(white)))
+            .build();
+        let (_, accumulators) = render_with_edge_anti_aliasing(&arguments, &scene_data, 2, &|_| {});
+
+        let object_id = |x: usize, y: usize| {
+            let ray = scene_data.camera.get_ray_through_pixel_center(x, y);
+            surface_sample(&ray, &arguments, &scene_data).object_id
+        };
+        let mut supersampled = 0;
+        for y in 0..16 {
+            for x in 0..16 {
+                let count = accumulators[y * 16 + x].count();
+                let neighbours = [
+                    (x.wrapping_sub(1), y),
+                    (x + 1, y),
+                    (x, y.wrapping_sub(1)),
+                    (x, y + 1),
+                ];
+                let on_outline = neighbours
+                    .into_iter()
+                    .filter(|&(nx, ny)| nx < 16 && ny < 16)
+                    .any(|(nx, ny)| object_id(nx, ny) != object_id(x, y));
+                assert_eq!(count, if on_outline { 8 } else { 1 }, "{} {}", x, y);
+                supersampled += on_outline as usize;
+            }
+        }
+        assert!(
+            supersampled > 0 && supersampled < 16 * 16 / 2,
+            "{}",
+            supersampled
+        );
+    }
+
+    #[test]
+    fn up_in_the_world_is_the_top_of_the_image() {
+        let arguments = Arguments::from_options(&["--output-width", "16", "--output-height", "16"]);
+        let mut camera = Camera::default();
+        camera.set_width(16);
+        camera.set_height(16);
+        camera.set_position(Vec3A::new(0.0, 0.0, 4.0));
+        camera.look_at(Vec3A::ZERO);
+        // A light above the origin, and right of it
+        let scene_data = SceneBuilder::new()
+            .camera(camera)
+            .sphere(
+                Vec3A::new(0.7, 0.7, 0.0),
+                0.3,
+                DiffuseLight::new(RGBColor::white()),
+            )
+            .background(Box::new(SolidColor::new(RGBColor::black())))
+            .build();
+        let image_data = render(&arguments, &scene_data, 0).image_data;
+
+        let brightness = |rows: std::ops::Range<usize>, columns: std::ops::Range<usize>| {
+            rows.flat_map(|y| columns.clone().map(move |x| (x, y)))
+                .map(|(x, y)| image_data[y * 16 + x].luminance())
+                .sum::<f32>()
+        };
+        assert!(brightness(0..8, 0..16) > 0.0);
+        assert_eq!(brightness(8..16, 0..16), 0.0);
+        assert_eq!(brightness(0..16, 0..8), 0.0);
+    }
+
+    #[test]
+    fn first_samples_of_neighbouring_pixels_are_uncorrelated() {
+        let offsets: Vec<f32> = (0..4096)
+            .map(|x| pixel_rng(7, x, 3).gen::<f32>() - 0.5)
+            .collect();
+        let mean = offsets.iter().sum::<f32>() / offsets.len() as f32;
+        let variance = offsets.iter().map(|a| (a - mean).powi(2)).sum::<f32>();
+        let covariance = offsets
+            .windows(2)
+            .map(|pair| (pair[0] - mean) * (pair[1] - mean))
+            .sum::<f32>();
+        let correlation = covariance / variance;
+        assert!(correlation.abs() < 0.05, "{}", correlation);
+    }
+
+    /// Renders a gradient sky, with or without a sphere in front of the camera
+    fn sky_render(with_sphere: bool, options: &[&str]) -> Vec<RGBColor> {
+        let mut all_options = vec![
+            "--output-width",
+            "8",
+            "--output-height",
+            "6",
+            "--samples-per-pixel",
+            "4",
+            "--seed",
+            "6",
+        ];
+        all_options.extend_from_slice(options);
+        let arguments = Arguments::from_options(&all_options);
+        let mut camera = Camera::default();
+        camera.set_width(8);
+        camera.set_height(6);
+        camera.set_position(Vec3A::new(0.0, 0.0, 4.0));
+        camera.look_at(Vec3A::ZERO);
+        let mut scene_builder = SceneBuilder::new()
+            .camera(camera)
+            .background(Box::new(GradientSky::default()));
+        if with_sphere {
+            scene_builder = scene_builder.sphere(
+                Vec3A::ZERO,
+                1.5,
+                LambertarianDiffuse::new(RGBColor::new(1.0, 0.0, 0.0)),
+            );
+        }
+        render(&arguments, &scene_builder.build(), 0).image_data
+    }
+
+    #[test]
+    fn background_only_ignores_the_objects() {
+        assert!(sky_render(true, &[]) != sky_render(false, &[]));
+        assert!(
+            sky_render(true, &["--background-only"]) == sky_render(false, &["--background-only"])
+        );
+    }
+
+    /// Traces the path of the center pixel from inside a closed sphere of the material
+    fn enclosed_path_length(material: Arc<AnyMaterial>) -> usize {
+        let arguments = Arguments::from_options(&[
+            "--output-width",
+            "4",
+            "--output-height",
+            "4",
+            "--steps",
+            "10",
+            "--diffuse-depth",
+            "3",
+            "--seed",
+            "8",
+        ]);
+        let mut camera = Camera::default();
+        camera.set_width(4);
+        camera.set_height(4);
+        let scene_data = SceneBuilder::new()
+            .camera(camera)
+            .sphere(Vec3A::ZERO, 5.0, material)
+            .background(Box::new(SolidColor::new(RGBColor::white())))
+            .build();
+        trace_pixel_path(2, 2, &arguments, &scene_data).segment_count()
+    }
+
+    #[test]
+    fn diffuse_paths_end_before_specular_paths() {
+        let white = RGBColor::white();
+        let diffuse = enclosed_path_length(LambertarianDiffuse::new_counter(white));
+        let mirror = enclosed_path_length(Metal::new_counter(white, 0.0));
+        assert_eq!(diffuse, 3);
+        assert_eq!(mirror, 10);
+    }
+
+    /// Renders a floor under a sphere and a light, looking down through the sphere,
+    /// and returns the object IDs and colors of the center pixel and of a corner pixel
+    fn floor_under_sphere(sphere_visibility: &str) -> ((u32, RGBColor), (u32, RGBColor)) {
+        let arguments = Arguments::from_options(&[
+            "--output-width",
+            "9",
+            "--output-height",
+            "9",
+            "--samples-per-pixel",
+            "16",
+            "--seed",
+            "4",
+            "--object-ids",
+        ]);
+        let mut camera = Camera::default();
+        camera.set_width(9);
+        camera.set_height(9);
+        camera.set_position(Vec3A::new(0.0, 2.0, 0.1));
+        camera.look_at(Vec3A::ZERO);
+        let white = RGBColor::white();
+        let mut scene_data = SceneBuilder::new()
+            .camera(camera)
+            .sphere(
+                Vec3A::new(0.0, 1.0, 0.0),
+                0.5,
+                LambertarianDiffuse::new(RGBColor::new(1.0, 0.0, 0.0)),
+            )
+            .hittable(Parallelogram::new(
+                Vec3A::new(-5.0, 0.0, -5.0),
+                Vec3A::new(0.0, 0.0, 10.0),
+                Vec3A::new(10.0, 0.0, 0.0),
+                LambertarianDiffuse::new(white),
+            ))
+            .light(Parallelogram::new(
+                Vec3A::new(-0.25, 4.0, -0.25),
+                Vec3A::new(0.0, 0.0, 0.5),
+                Vec3A::new(0.5, 0.0, 0.0),
+                DiffuseLight::new(white * 50.0),
+            ))
+            .background(Box::new(SolidColor::new(RGBColor::black())))
+            .build();
+        assert!(scene_data
+            .renderables
+            .set_visibility(1, sphere_visibility.parse().unwrap()));
+        let result = render(&arguments, &scene_data, 0);
+        let object_ids = result.object_id_data.unwrap();
+        let pixel = |index: usize| (object_ids[index], result.image_data[index]);
+        (pixel(4 * 9 + 4), pixel(0))
+    }
+
+    #[test]
+    fn object_hidden_from_the_camera_still_casts_a_shadow() {
+        let ((center_id, _), _) = floor_under_sphere("camera,shadow,reflection");
+        assert_eq!(center_id, 1);
+
+        let ((center_id, shadowed), (corner_id, lit)) = floor_under_sphere("shadow");
+        assert_eq!((center_id, corner_id), (2, 2));
+        // The floor is white, so the red sphere would show through its color
+        assert!(shadowed.r() == shadowed.g(), "{:?}", shadowed);
+        assert!(
+            shadowed.luminance() < 0.5 * lit.luminance(),
+            "{:?} {:?}",
+            shadowed,
+            lit
+        );
+
+        // Without the shadow, the floor under the sphere is the brightest
+        let ((_, unshadowed), _) = floor_under_sphere("none");
+        assert!(
+            unshadowed.luminance() > lit.luminance(),
+            "{:?} {:?}",
+            unshadowed,
+            lit
+        );
+    }
+
+    #[test]
+    fn path_off_a_mirror_ends_in_the_sky() {
+        let arguments = Arguments::from_options(&["--output-width", "5", "--output-height", "5"]);
+        let mut camera = Camera::default();
+        camera.set_width(5);
+        camera.set_height(5);
+        camera.set_position(Vec3A::new(0.0, 0.0, 4.0));
+        camera.look_at(Vec3A::ZERO);
+        let sky = RGBColor::new(0.2, 0.4, 0.8);
+        // A mirror tilted by 45 degrees, which turns the camera ray upwards
+        let scene_data = SceneBuilder::new()
+            .camera(camera)
+            .hittable(Parallelogram::new(
+                Vec3A::new(-1.0, -1.0, 1.0),
+                Vec3A::new(0.0, 2.0, -2.0),
+                Vec3A::new(2.0, 0.0, 0.0),
+                Metal::new(RGBColor::white(), 0.0),
+            ))
+            .background(Box::new(SolidColor::new(sky)))
+            .build();
+
+        let path = trace_pixel_path(2, 2, &arguments, &scene_data);
+        assert_eq!(path.segment_count(), 2);
+        assert_eq!(path.vertices.len(), 1);
+        let vertex = &path.vertices[0];
+        assert!(
+            vertex.point.abs_diff_eq(Vec3A::ZERO, 1e-5),
+            "{}",
+            vertex.point
+        );
+        assert_eq!(vertex.material, "metal");
+        let escape_direction = path.escape_direction.unwrap().normalize();
+        assert!(
+            escape_direction.abs_diff_eq(Vec3A::Y, 1e-5),
+            "{}",
+            escape_direction
+        );
+        assert!(path.color == sky, "{:?}", path.color);
     }
 }
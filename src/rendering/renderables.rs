@@ -1,47 +1,512 @@
-use std::sync::Arc;
+use std::{collections::HashMap, str::FromStr, sync::Arc};
 
 use crate::{
+    aabb::Aabb,
+    color::RGBColor,
     interval::Interval,
-    objects::{AnyHittable, HitRecord, Hittable},
+    materials::{diffuse_light::DiffuseLight, AnyMaterial, Material},
+    objects::{
+        bvh::{closest_hit, surrounding_box, BvhNode},
+        grid::UniformGrid,
+        identified::Identified,
+        stats::record_ray_cast,
+        wireframe::WireframeBox,
+        AnyHittable, HitRecord, Hittable,
+    },
+    ray::Ray,
 };
 
+use super::visibility::VisibilityFlags;
+
+/// Width of the edges added by `Renderables::show_bvh`, relative to the size of the scene
+const BVH_EDGE_THICKNESS: f32 = 0.002;
+
+/// First ID of the edges added by `Renderables::show_bvh`
+///
+/// The edges are not part of the scene, so they get IDs of their own,
+/// which never collide with the IDs of the scene's hittables.
+const DEBUG_ID_START: u32 = 1 << 31;
+
+/// Structure that speeds up finding the hittables hit by a ray
+///
+/// The structure does not change the final image, only how fast it is rendered.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum AccelerationStructure {
+    /// Bounding volume hierarchy, good for most scenes
+    #[default]
+    Bvh,
+    /// Uniform grid, good for many small hittables spread evenly over the scene
+    Grid,
+}
+
+impl FromStr for AccelerationStructure {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bvh" => Ok(Self::Bvh),
+            "grid" => Ok(Self::Grid),
+            _ => Err(format!("Unknown acceleration structure '{}'", s)),
+        }
+    }
+}
+
+/// Built acceleration structure over the bounded hittables
+enum Accelerator {
+    Bvh(BvhNode),
+    Grid(UniformGrid),
+}
+
+impl Hittable for Accelerator {
+    fn hit(&self, ray: &Ray, ray_interval: Interval) -> Option<HitRecord> {
+        match self {
+            Accelerator::Bvh(inner) => inner.hit(ray, ray_interval),
+            Accelerator::Grid(inner) => inner.hit(ray, ray_interval),
+        }
+    }
+}
+
 pub struct Renderables {
     hittable_renderables: Vec<Arc<AnyHittable>>,
+    lights: Vec<Arc<AnyHittable>>,
+    accelerator: Option<Accelerator>, // Acceleration structure over the bounded hittables
+    unbounded_renderables: Vec<Arc<AnyHittable>>, // Hittables that cannot be put into the structure
+    material_override: Option<Arc<AnyMaterial>>, // Material that replaces the materials of all hittables
+    visibility: HashMap<u32, VisibilityFlags>,   // Hittables hidden from some rays, by ID
+    next_id: u32,                                // ID of the next added hittable, never reused
+    next_debug_id: u32,                          // ID of the next edge added by `show_bvh`
 }
 
 impl Renderables {
     pub fn new() -> Self {
         Self {
             hittable_renderables: Vec::new(),
+            lights: Vec::new(),
+            accelerator: None,
+            unbounded_renderables: Vec::new(),
+            material_override: None,
+            visibility: HashMap::new(),
+            next_id: 1,
+            next_debug_id: DEBUG_ID_START,
         }
     }
 
-    pub fn add_hittable<H>(&mut self, hittable: H)
+    /// Replaces the materials of all hittables with a single material
+    ///
+    /// Emissive materials are kept, since lights are also sampled directly
+    /// (with their own materials), so they have to look the same to every ray.
+    ///
+    /// ## Parameters
+    /// * `material` - the material of every hit surface
+    pub fn set_material_override<M>(&mut self, material: M)
+    where
+        M: Into<Arc<AnyMaterial>>,
+    {
+        self.material_override = Some(material.into());
+    }
+
+    /// Adds a hittable to the scene
+    ///
+    /// Every added hittable gets the next free ID (starting with 1, since 0 is the background),
+    /// so the IDs only depend on the order in which the scene is built.
+    /// IDs of removed hittables (see `isolate`) are not given out again.
+    ///
+    /// Returns the ID of the hittable.
+    pub fn add_hittable<H>(&mut self, hittable: H) -> u32
     where
         H: Into<Arc<AnyHittable>>,
     {
-        self.hittable_renderables.push(hittable.into());
+        let hittable = self.identify(hittable);
+        let id = hittable_id(&hittable);
+        self.hittable_renderables.push(hittable);
+        id
+    }
+
+    /// Adds a hittable with an emissive material,
+    /// which is also sampled directly when calculating lighting.
+    ///
+    /// Emissive hittables should always be added this way,
+    /// otherwise their light is only found by chance.
+    ///
+    /// Returns the ID of the light (see `add_hittable`).
+    pub fn add_light<H>(&mut self, light: H) -> u32
+    where
+        H: Into<Arc<AnyHittable>>,
+    {
+        let light = self.identify(light);
+        let id = hittable_id(&light);
+        self.lights.push(light.clone());
+        self.hittable_renderables.push(light);
+        id
+    }
+
+    /// Removes all hittables (including lights) except the one with the ID,
+    /// so that it can be looked at alone
+    ///
+    /// Should be called before the acceleration structure is built.
+    /// Returns `false` (and keeps all hittables) if there is no hittable with the ID.
+    ///
+    /// ## Parameters
+    /// * `id` - ID of the hittable to keep (see `add_hittable`)
+    pub fn isolate(&mut self, id: u32) -> bool {
+        if !self
+            .hittable_renderables
+            .iter()
+            .any(|hittable| hittable_id(hittable) == id)
+        {
+            return false;
+        }
+        self.hittable_renderables
+            .retain(|hittable| hittable_id(hittable) == id);
+        self.lights.retain(|light| hittable_id(light) == id);
+        true
+    }
+
+    /// Decides which kinds of rays can see the hittable with the ID
+    ///
+    /// Returns `false` if there is no hittable with the ID.
+    ///
+    /// ## Parameters
+    /// * `id` - ID of the hittable (see `add_hittable`)
+    /// * `flags` - kinds of rays that see the hittable
+    pub fn set_visibility(&mut self, id: u32, flags: VisibilityFlags) -> bool {
+        if !self
+            .hittable_renderables
+            .iter()
+            .any(|hittable| hittable_id(hittable) == id)
+        {
+            return false;
+        }
+        self.visibility.insert(id, flags);
+        true
+    }
+
+    /// Returns true if the ray can see the hittable hit by it (see `set_visibility`)
+    ///
+    /// ## Parameters
+    /// * `ray` - the ray that hit the hittable
+    /// * `hit_record` - the record of the hit
+    fn is_visible(&self, ray: &Ray, hit_record: &HitRecord) -> bool {
+        self.visibility
+            .get(&hit_record.object_id())
+            .is_none_or(|flags| flags.is_visible_to(ray.kind()))
+    }
+
+    /// Wraps the hittable with the next free ID
+    fn identify<H>(&mut self, hittable: H) -> Arc<AnyHittable>
+    where
+        H: Into<Arc<AnyHittable>>,
+    {
+        let id = self.next_id;
+        self.next_id += 1;
+        Identified::new(id, hittable).into()
+    }
+
+    /// Builds the acceleration structure over the added hittables
+    ///
+    /// Until it is built, rays are tested against every hittable.
+    /// Hittables with infinite bounding boxes (such as planes)
+    /// are kept outside of the structure and always tested.
+    /// Should be called again after adding more hittables.
+    ///
+    /// ## Parameters
+    /// * `structure` - the kind of structure to build
+    pub fn build_accelerator(&mut self, structure: AccelerationStructure) {
+        let (bounded, unbounded): (Vec<_>, Vec<_>) = self
+            .hittable_renderables
+            .iter()
+            .cloned()
+            .partition(|hittable| hittable.bounding_box().is_finite());
+
+        self.accelerator = if bounded.is_empty() {
+            None
+        } else {
+            match structure {
+                AccelerationStructure::Bvh => Some(Accelerator::Bvh(BvhNode::new(bounded))),
+                AccelerationStructure::Grid => Some(Accelerator::Grid(UniformGrid::new(bounded))),
+            }
+        };
+        self.unbounded_renderables = unbounded;
+    }
+
+    /// Adds the edges of every box in the bounding volume hierarchy to the scene,
+    /// so that the hierarchy can be seen in the image
+    ///
+    /// The edges glow, so they are visible in dark parts of the scene too.
+    /// They are left out of the object ID and albedo buffers (see `hit_scene`).
+    /// The hierarchy needs to be built first, and is built again with the edges
+    /// (which are not part of the shown boxes).
+    /// Returns `false` (and adds nothing) if there is no hierarchy.
+    pub fn show_bvh(&mut self) -> bool {
+        let Some(Accelerator::Bvh(bvh)) = &self.accelerator else {
+            return false;
+        };
+        let boxes = bvh.node_boxes();
+        let root_box = bvh.bounding_box();
+        let thickness = (root_box.max() - root_box.min()).length() * BVH_EDGE_THICKNESS;
+        let material: Arc<AnyMaterial> = DiffuseLight::new(RGBColor::new(1.0, 0.5, 0.0)).into();
+        for node_box in boxes {
+            let edges = WireframeBox::new(node_box, thickness, material.clone());
+            let edges = Identified::new(self.next_debug_id, edges);
+            self.next_debug_id += 1;
+            self.hittable_renderables.push(edges.into());
+        }
+        self.build_accelerator(AccelerationStructure::Bvh);
+        true
+    }
+
+    /// Returns the bounding box of all hittables, or `None` if there are none
+    pub fn bounding_box(&self) -> Option<Aabb> {
+        if self.hittable_renderables.is_empty() {
+            None
+        } else {
+            Some(surrounding_box(&self.hittable_renderables))
+        }
+    }
+
+    /// Finds the closest hit on the hittables of the scene,
+    /// passing through the edges added by `show_bvh`
+    ///
+    /// Used for buffers that describe the scene itself (such as object IDs and albedo).
+    ///
+    /// ## Parameters
+    /// * `ray` - the ray to operate with
+    /// * `ray_interval` - the allowed range of the ray parameter
+    pub fn hit_scene(&self, ray: &Ray, ray_interval: Interval) -> Option<HitRecord> {
+        let mut ray_interval = ray_interval;
+        loop {
+            let hit_record = self.hit(ray, ray_interval)?;
+            if hit_record.object_id() < DEBUG_ID_START {
+                return Some(hit_record);
+            }
+            ray_interval = Interval::new(hit_record.t() + 0.001, ray_interval.max());
+        }
+    }
+
+    /// Returns all hittables that act as lights
+    pub fn lights(&self) -> &[Arc<AnyHittable>] {
+        &self.lights
+    }
+}
+
+/// Returns the ID of a hittable added to the scene
+fn hittable_id(hittable: &AnyHittable) -> u32 {
+    match hittable {
+        AnyHittable::Identified(inner) => inner.id(),
+        _ => 0,
+    }
+}
+
+impl Renderables {
+    /// Finds the closest hit, without overriding its material
+    ///
+    /// ## Parameters
+    /// * `ray` - the ray to operate with
+    /// * `ray_interval` - the allowed range of the ray parameter
+    fn closest_hit(&self, ray: &Ray, ray_interval: Interval) -> Option<HitRecord> {
+        if self.accelerator.is_none() && self.unbounded_renderables.is_empty() {
+            return closest_hit(&self.hittable_renderables, ray, ray_interval);
+        }
+
+        let bounded_hit = self
+            .accelerator
+            .as_ref()
+            .and_then(|accelerator| accelerator.hit(ray, ray_interval));
+        let closest_so_far = bounded_hit
+            .as_ref()
+            .map_or(ray_interval.max(), |hit| hit.t());
+        let unbounded_hit = closest_hit(
+            &self.unbounded_renderables,
+            ray,
+            Interval::new(ray_interval.min(), closest_so_far),
+        );
+        unbounded_hit.or(bounded_hit)
     }
 }
 
 impl Hittable for Renderables {
-    fn hit(&self, ray: &crate::ray::Ray, ray_interval: Interval) -> Option<HitRecord> {
-        let mut hit_record: Option<HitRecord> = None;
-        let mut closest_so_far = ray_interval.max();
-
-        for hittable in self.hittable_renderables.iter() {
-            let new_interval = Interval::new(ray_interval.min(), closest_so_far);
-            if let Some(current_hit_record) = hittable.hit(ray, new_interval) {
-                if hit_record.is_none()
-                    || (hit_record.is_some()
-                        && current_hit_record.t() < hit_record.as_ref().unwrap().t())
-                {
-                    closest_so_far = current_hit_record.t();
-                    hit_record = Some(current_hit_record);
+    fn hit(&self, ray: &Ray, ray_interval: Interval) -> Option<HitRecord> {
+        record_ray_cast();
+        let mut ray_interval = ray_interval;
+        let mut hit_record = loop {
+            let hit_record = self.closest_hit(ray, ray_interval)?;
+            if !hit_record.material().is_cut_out(&hit_record) && self.is_visible(ray, &hit_record) {
+                break hit_record;
+            }
+            // The ray passes through holes in the surface and hidden hittables,
+            // so the search continues behind them
+            ray_interval = Interval::new(hit_record.t() + 0.001, ray_interval.max());
+        };
+        if let Some(material) = &self.material_override {
+            if !hit_record.material().is_emissive() {
+                hit_record.set_material(material.clone());
+            }
+        }
+        Some(hit_record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3A;
+    use rand::{Rng, SeedableRng};
+    use rand_xoshiro::Xoshiro256Plus;
+
+    use crate::{
+        input_formats::ImportedImage,
+        materials::lambertarian::LambertarianDiffuse,
+        math::random_vec3_in_range,
+        objects::{parallelogram::Parallelogram, plane::Plane, sphere::Sphere},
+        textures::image::ImageTexture,
+    };
+
+    use super::*;
+
+    /// A lattice of spheres above an (unbounded) floor
+    fn lattice_on_floor() -> Renderables {
+        let material = LambertarianDiffuse::new_counter(RGBColor::white());
+        let mut renderables = Renderables::new();
+        for k in 0..4 {
+            for j in 0..4 {
+                for i in 0..4 {
+                    let center = Vec3A::new(i as f32, j as f32 + 1.0, k as f32) * 1.5;
+                    renderables.add_hittable(Sphere::new(center, 0.5, material.clone()));
                 }
             }
         }
+        renderables.add_hittable(Plane::new(Vec3A::ZERO, Vec3A::Y, material));
+        renderables
+    }
+
+    #[test]
+    fn every_accelerator_finds_the_same_hits() {
+        let linear = lattice_on_floor();
+        let mut bvh = lattice_on_floor();
+        bvh.build_accelerator(AccelerationStructure::Bvh);
+        let mut grid = lattice_on_floor();
+        grid.build_accelerator("grid".parse().unwrap());
+
+        let mut rng = Xoshiro256Plus::seed_from_u64(9);
+        let interval = Interval::new(0.001, f32::INFINITY);
+        for _ in 0..1000 {
+            let origin = Vec3A::new(
+                rng.gen_range(-3.0..9.0),
+                rng.gen_range(0.5..9.0),
+                rng.gen_range(-3.0..9.0),
+            );
+            let direction = random_vec3_in_range(-1.0, 1.0, &mut rng);
+            let ray = Ray::new(origin, direction);
+            let hit_of = |renderables: &Renderables| {
+                renderables
+                    .hit(&ray, interval)
+                    .map(|hit| (hit.t(), hit.object_id()))
+            };
+            let expected = hit_of(&linear);
+            assert_eq!(hit_of(&bvh), expected);
+            assert_eq!(hit_of(&grid), expected);
+        }
+    }
+
+    #[test]
+    fn isolated_object_is_the_only_one_hit() {
+        let mut renderables = lattice_on_floor();
+        assert!(!renderables.isolate(100));
+        assert!(renderables.isolate(2));
+        // Hittables added later do not take over the ID of the isolated one
+        let material = LambertarianDiffuse::new_counter(RGBColor::white());
+        let added_id = renderables.add_hittable(Sphere::new(Vec3A::splat(20.0), 0.5, material));
+        assert_eq!(added_id, 66);
+        renderables.build_accelerator(AccelerationStructure::Bvh);
+
+        // The isolated sphere is at (1.5, 1.5, 0), the rays pass close to it
+        let mut rng = Xoshiro256Plus::seed_from_u64(4);
+        let interval = Interval::new(0.001, f32::INFINITY);
+        let mut hits = 0;
+        for _ in 0..1000 {
+            let origin = Vec3A::new(1.5, 1.5, 8.0);
+            let target = Vec3A::new(
+                rng.gen_range(0.0..3.0),
+                rng.gen_range(0.0..3.0),
+                rng.gen_range(-1.0..1.0),
+            );
+            if let Some(hit) = renderables.hit(&Ray::new(origin, target - origin), interval) {
+                assert_eq!(hit.object_id(), 2);
+                hits += 1;
+            }
+        }
+        assert!(hits > 100 && hits < 1000, "{}", hits);
+    }
+
+    #[test]
+    fn shown_bvh_adds_edges_around_the_spheres() {
+        let material = LambertarianDiffuse::new_counter(RGBColor::white());
+        let mut renderables = Renderables::new();
+        renderables.add_hittable(Sphere::new(
+            Vec3A::new(-2.0, 0.0, 0.0),
+            1.0,
+            material.clone(),
+        ));
+        renderables.add_hittable(Sphere::new(Vec3A::new(2.0, 0.0, 0.0), 1.0, material));
+        assert!(!renderables.show_bvh());
+        renderables.build_accelerator(AccelerationStructure::Bvh);
+        assert!(renderables.show_bvh());
+
+        // A box around both spheres and one around each of them
+        assert_eq!(renderables.hittable_renderables.len(), 2 + 3);
+        assert_eq!(
+            renderables.bounding_box().unwrap().max(),
+            Vec3A::new(3.0, 1.0, 1.0)
+        );
+
+        // Between the spheres, the ray only meets the top edge of the box around both
+        let ray = Ray::new(Vec3A::new(0.0, 0.999, 5.0), -Vec3A::Z);
+        let interval = Interval::new(0.001, f32::INFINITY);
+        let edge_hit = renderables.hit(&ray, interval).unwrap();
+        assert!((edge_hit.t() - 4.0).abs() < 1e-4, "{}", edge_hit.t());
+        assert!(edge_hit.object_id() >= DEBUG_ID_START);
+        assert!(renderables.hit_scene(&ray, interval).is_none());
+
+        // The spheres keep their IDs and are still hit through the edges
+        let ray = Ray::new(Vec3A::new(2.0, 0.0, 5.0), -Vec3A::Z);
+        assert_eq!(
+            renderables.hit_scene(&ray, interval).unwrap().object_id(),
+            2
+        );
+    }
+
+    #[test]
+    fn rays_pass_through_the_cut_out_cells() {
+        let (white, black) = (RGBColor::white(), RGBColor::black());
+        let checker = ImageTexture::new(ImportedImage {
+            width: 2,
+            height: 2,
+            image_data: vec![white, black, black, white],
+        });
+        let mut fence = LambertarianDiffuse::new(white);
+        fence.set_alpha_texture(checker);
+
+        let mut renderables = Renderables::new();
+        let fence_id = renderables.add_hittable(Parallelogram::new(
+            Vec3A::ZERO,
+            Vec3A::new(0.0, 2.0, 0.0),
+            Vec3A::new(2.0, 0.0, 0.0),
+            fence,
+        ));
+        let wall_id = renderables.add_hittable(Plane::new(
+            Vec3A::new(0.0, 0.0, -1.0),
+            Vec3A::Z,
+            LambertarianDiffuse::new(white),
+        ));
 
-        hit_record
+        let interval = Interval::new(0.001, f32::INFINITY);
+        let id_behind = |x: f32, y: f32| {
+            let ray = Ray::new(Vec3A::new(x, y, 5.0), -Vec3A::Z);
+            renderables.hit(&ray, interval).unwrap().object_id()
+        };
+        // The black cells are holes
+        assert_eq!(id_behind(0.5, 0.5), wall_id);
+        assert_eq!(id_behind(1.5, 1.5), wall_id);
+        assert_eq!(id_behind(0.5, 1.5), fence_id);
+        assert_eq!(id_behind(1.5, 0.5), fence_id);
     }
 }
@@ -0,0 +1,93 @@
+use std::fmt::Display;
+
+use crate::{
+    materials::stats::{scatter_stats, ScatterStats},
+    objects::stats::{intersection_stats, ray_casts, IntersectionStats},
+};
+
+/// Statistics about the work done while rendering
+///
+/// Transformed objects test their wrapped object too,
+/// so such tests are counted under both types.
+#[derive(Clone, Debug, Default)]
+pub struct RenderStats {
+    /// Rays tested against the whole scene (camera, bounce and shadow rays)
+    pub rays: u64,
+    /// Intersection tests for every hittable type
+    pub intersections: Vec<IntersectionStats>,
+    /// Scatter events for every counted material type
+    pub scatters: Vec<ScatterStats>,
+}
+
+impl RenderStats {
+    /// Collects the statistics counted on this thread so far
+    ///
+    /// The statistics of a render are the difference (see `since`)
+    /// between the statistics collected after and before it.
+    pub fn collect() -> Self {
+        Self {
+            rays: ray_casts(),
+            intersections: intersection_stats(),
+            scatters: scatter_stats(),
+        }
+    }
+
+    /// Returns the statistics counted since the earlier ones were collected
+    ///
+    /// ## Parameters
+    /// * `earlier` - statistics collected before these, on the same thread
+    pub fn since(&self, earlier: &RenderStats) -> Self {
+        Self {
+            rays: self.rays - earlier.rays,
+            intersections: self
+                .intersections
+                .iter()
+                .zip(earlier.intersections.iter())
+                .map(|(stats, earlier_stats)| stats.since(earlier_stats))
+                .collect(),
+            scatters: self
+                .scatters
+                .iter()
+                .zip(earlier.scatters.iter())
+                .map(|(stats, earlier_stats)| stats.since(earlier_stats))
+                .collect(),
+        }
+    }
+
+    /// Returns the amount of intersection tests of all hittable types
+    pub fn total_tests(&self) -> u64 {
+        self.intersections.iter().map(|stats| stats.tests).sum()
+    }
+}
+
+impl Display for RenderStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} rays, {} intersection tests",
+            self.rays,
+            self.total_tests()
+        )?;
+        for stats in self.intersections.iter().filter(|stats| stats.tests > 0) {
+            write!(
+                f,
+                "\n  {}: {} tests ({} rejected by bounding box, {} hits)",
+                stats.kind.name(),
+                stats.tests,
+                stats.box_rejections,
+                stats.hits
+            )?;
+        }
+        for stats in self.scatters.iter().filter(|stats| stats.total() > 0) {
+            write!(
+                f,
+                "\n  {}: {} reflected, {} refracted, {} absorbed",
+                stats.kind.name(),
+                stats.reflected,
+                stats.refracted,
+                stats.absorbed
+            )?;
+        }
+        Ok(())
+    }
+}
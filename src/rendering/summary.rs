@@ -0,0 +1,74 @@
+use std::fmt::Display;
+
+use crate::color::RGBColor;
+
+/// Short description of a rendered image, for quick checks while debugging
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RenderSummary {
+    pub width: usize,
+    pub height: usize,
+    /// Lowest luminance of the finite pixels (0.0 if there are none)
+    pub min_luminance: f32,
+    /// Highest luminance of the finite pixels (0.0 if there are none)
+    pub max_luminance: f32,
+    /// Average luminance of the finite pixels (0.0 if there are none)
+    pub mean_luminance: f32,
+    /// Pixels with a NaN or infinite channel, which are left out of the luminances
+    pub non_finite_pixels: usize,
+}
+
+impl RenderSummary {
+    /// Summarizes the image
+    ///
+    /// ## Parameters
+    /// * `width` - width of the image
+    /// * `height` - height of the image
+    /// * `image_data` - colors of the image
+    pub fn new(width: usize, height: usize, image_data: &[RGBColor]) -> Self {
+        let mut summary = Self {
+            width,
+            height,
+            min_luminance: f32::INFINITY,
+            max_luminance: -f32::INFINITY,
+            mean_luminance: 0.0,
+            non_finite_pixels: 0,
+        };
+
+        // Summed in f64, so that large images do not lose precision
+        let mut luminance_sum = 0.0f64;
+        for color in image_data {
+            if !(color.r().is_finite() && color.g().is_finite() && color.b().is_finite()) {
+                summary.non_finite_pixels += 1;
+                continue;
+            }
+            let luminance = color.luminance();
+            summary.min_luminance = summary.min_luminance.min(luminance);
+            summary.max_luminance = summary.max_luminance.max(luminance);
+            luminance_sum += luminance as f64;
+        }
+
+        let finite_pixels = image_data.len() - summary.non_finite_pixels;
+        if finite_pixels == 0 {
+            summary.min_luminance = 0.0;
+            summary.max_luminance = 0.0;
+        } else {
+            summary.mean_luminance = (luminance_sum / finite_pixels as f64) as f32;
+        }
+        summary
+    }
+}
+
+impl Display for RenderSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}x{}, luminance {:.4} to {:.4} (mean {:.4}), {} non-finite pixels",
+            self.width,
+            self.height,
+            self.min_luminance,
+            self.max_luminance,
+            self.mean_luminance,
+            self.non_finite_pixels
+        )
+    }
+}
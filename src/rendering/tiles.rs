@@ -0,0 +1,126 @@
+use glam::Vec3A;
+
+use crate::{aabb::Aabb, camera::Camera, preparation::SceneData, Arguments};
+
+/// Width and height of a tile in pixels
+const TILE_SIZE: usize = 16;
+
+/// Pixel bounds of the whole image (and more)
+const UNBOUNDED: (f32, f32, f32, f32) = (
+    f32::NEG_INFINITY,
+    f32::NEG_INFINITY,
+    f32::INFINITY,
+    f32::INFINITY,
+);
+
+/// Tiles of the image in which no object can be seen
+///
+/// The bounding box of the whole scene is projected onto the image,
+/// and tiles outside of its projection only show the background,
+/// so their pixels do not need to be traced through the scene.
+/// The projection is only exact if all camera rays start in the same point,
+/// so it is not used with depth of field or chromatic aberration.
+pub struct EmptyTiles {
+    columns: usize,
+    empty: Vec<bool>,
+}
+
+impl EmptyTiles {
+    /// Finds the empty tiles of the image
+    ///
+    /// Returns `None` if the camera does not allow it.
+    ///
+    /// ## Parameters
+    /// * `arguments` - global application parameters
+    /// * `scene_data` - scene data to render
+    pub fn new(arguments: &Arguments, scene_data: &SceneData) -> Option<Self> {
+        let camera = &scene_data.camera;
+        if !camera.is_pinhole() {
+            log::warn!(
+                "Empty tiles cannot be skipped with depth of field or chromatic aberration, ignoring it"
+            );
+            return None;
+        }
+
+        let columns = arguments.output_width.div_ceil(TILE_SIZE);
+        let rows = arguments.output_height.div_ceil(TILE_SIZE);
+
+        // Without hittables, every tile is empty
+        let covered = scene_data
+            .renderables
+            .bounding_box()
+            .map(|bounding_box| projected_bounds(camera, bounding_box));
+
+        let empty = (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (column, row)))
+            .map(|(column, row)| {
+                let Some((min_x, min_y, max_x, max_y)) = covered else {
+                    return true;
+                };
+                // Pixels reach half a pixel around their centers,
+                // the extra pixel of margin covers rounding errors
+                let tile_min_x = (column * TILE_SIZE) as f32 - 1.5;
+                let tile_min_y = (row * TILE_SIZE) as f32 - 1.5;
+                let tile_max_x = ((column + 1) * TILE_SIZE) as f32 + 0.5;
+                let tile_max_y = ((row + 1) * TILE_SIZE) as f32 + 0.5;
+                max_x < tile_min_x || min_x > tile_max_x || max_y < tile_min_y || min_y > tile_max_y
+            })
+            .collect::<Vec<bool>>();
+
+        log::debug!(
+            "{} of {} tiles only show the background",
+            empty.iter().filter(|&&empty| empty).count(),
+            empty.len()
+        );
+        Some(Self { columns, empty })
+    }
+
+    /// Whether only the background can be seen through the pixel
+    ///
+    /// ## Parameters
+    /// * `x` - horizontal pixel coordinate
+    /// * `y` - vertical pixel coordinate
+    pub fn is_empty_at(&self, x: usize, y: usize) -> bool {
+        self.empty[(y / TILE_SIZE) * self.columns + x / TILE_SIZE]
+    }
+}
+
+/// Returns the smallest and largest pixel coordinates covered by the projection of the box
+/// (as `(min_x, min_y, max_x, max_y)`)
+///
+/// ## Parameters
+/// * `camera` - the camera that sees the box
+/// * `bounding_box` - the box to project
+fn projected_bounds(camera: &Camera, bounding_box: Aabb) -> (f32, f32, f32, f32) {
+    if !bounding_box.is_finite() {
+        return UNBOUNDED;
+    }
+
+    let (min, max) = (bounding_box.min(), bounding_box.max());
+    let corners = (0..8).map(|i| {
+        Vec3A::new(
+            if i & 1 == 0 { min.x } else { max.x },
+            if i & 2 == 0 { min.y } else { max.y },
+            if i & 4 == 0 { min.z } else { max.z },
+        )
+    });
+    // The box is convex, so its projection lies between the projected corners.
+    // If any corner is behind the camera, the box can cover any pixel.
+    let projected: Option<Vec<(f32, f32)>> = corners
+        .map(|corner| camera.project_to_image(corner))
+        .collect();
+    let Some(projected) = projected else {
+        return UNBOUNDED;
+    };
+    projected.iter().fold(
+        (
+            f32::INFINITY,
+            f32::INFINITY,
+            f32::NEG_INFINITY,
+            f32::NEG_INFINITY,
+        ),
+        |(min_x, min_y, max_x, max_y), &(x, y)| {
+            (min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y))
+        },
+    )
+}
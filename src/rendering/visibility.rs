@@ -0,0 +1,94 @@
+use std::str::FromStr;
+
+use crate::ray::RayKind;
+
+/// Kinds of rays that can see an object
+///
+/// Hidden objects are skipped by those rays, as if they were not in the scene.
+/// For example, an object hidden only from the camera does not appear in the image,
+/// but still casts shadows and shows up in reflections.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VisibilityFlags {
+    /// Seen by camera rays
+    pub camera: bool,
+    /// Blocks shadow rays (casts shadows)
+    pub shadow: bool,
+    /// Seen by rays scattered off other surfaces (reflections, refractions and indirect light)
+    pub reflection: bool,
+}
+
+impl Default for VisibilityFlags {
+    fn default() -> Self {
+        Self {
+            camera: true,
+            shadow: true,
+            reflection: true,
+        }
+    }
+}
+
+impl VisibilityFlags {
+    /// Returns true if rays of the kind can see the object
+    ///
+    /// ## Parameters
+    /// * `kind` - purpose of the ray
+    pub fn is_visible_to(&self, kind: RayKind) -> bool {
+        match kind {
+            RayKind::Camera => self.camera,
+            RayKind::Shadow => self.shadow,
+            RayKind::Secondary => self.reflection,
+        }
+    }
+}
+
+impl FromStr for VisibilityFlags {
+    type Err = String;
+
+    /// Parses the comma separated kinds of rays that see the object
+    /// (`camera`, `shadow`, `reflection`), or `none`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut flags = Self {
+            camera: false,
+            shadow: false,
+            reflection: false,
+        };
+        if s == "none" {
+            return Ok(flags);
+        }
+        for kind in s.split(',') {
+            match kind.trim() {
+                "camera" => flags.camera = true,
+                "shadow" => flags.shadow = true,
+                "reflection" => flags.reflection = true,
+                _ => return Err(format!("Unknown kind of rays '{}' in '{}'", kind, s)),
+            }
+        }
+        Ok(flags)
+    }
+}
+
+/// Visibility of a single object of the scene, given as `<id>:<flags>`
+/// (e.g. `3:shadow,reflection` hides object 3 from the camera)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ObjectVisibility {
+    pub id: u32,
+    pub flags: VisibilityFlags,
+}
+
+impl FromStr for ObjectVisibility {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((id, flags)) = s.split_once(':') else {
+            return Err(format!("Visibility '{}' needs the form `<id>:<flags>`", s));
+        };
+        let id = id
+            .trim()
+            .parse::<u32>()
+            .map_err(|_| format!("Invalid object ID '{}' in visibility '{}'", id, s))?;
+        Ok(Self {
+            id,
+            flags: flags.parse()?,
+        })
+    }
+}
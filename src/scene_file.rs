@@ -0,0 +1,286 @@
+use std::{fs, io, path::Path, sync::Arc};
+
+use glam::Vec3A;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    backgrounds::BackgroundKind,
+    color::RGBColor,
+    error::RaybowError,
+    materials::{
+        dielectric::Dielectric, diffuse_light::DiffuseLight, lambertarian::LambertarianDiffuse,
+        metal::Metal, AnyMaterial,
+    },
+    objects::{parallelogram::Parallelogram, sphere::Sphere, AnyHittable},
+};
+
+/// Scene (camera, objects and background) stored in a TOML file
+///
+/// All positions and directions use the internal convention (right-handed, Y up),
+/// regardless of `--up-axis`. Objects get their IDs in the order they are listed.
+///
+/// ## Example
+/// ```toml
+/// background = "sky"
+///
+/// [camera]
+/// position = [-3.0, 3.0, 1.0]
+/// look_at = [0.0, 0.0, -1.0]
+/// fov = 45.0
+///
+/// [[objects]]
+/// type = "sphere"
+/// center = [0.0, 0.0, -1.0]
+/// radius = 0.5
+/// material = { type = "diffuse", color = [1.0, 0.0, 0.0] }
+/// ```
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct SceneFile {
+    /// Background in the same format as `--background`
+    pub background: String,
+    pub camera: CameraDescription,
+    #[serde(default)]
+    pub objects: Vec<ObjectDescription>,
+}
+
+/// Placement of the camera (other camera settings come from the arguments)
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct CameraDescription {
+    pub position: Vec3A,
+    pub look_at: Vec3A,
+    /// Vertical field of view in degrees
+    pub fov: f32,
+}
+
+/// Object of the scene with its material
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum ObjectDescription {
+    Sphere {
+        center: Vec3A,
+        radius: f32,
+        material: MaterialDescription,
+    },
+    Parallelogram {
+        corner: Vec3A,
+        up: Vec3A,
+        right: Vec3A,
+        material: MaterialDescription,
+    },
+}
+
+/// Material of an object
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum MaterialDescription {
+    Diffuse {
+        color: RGBColor,
+    },
+    Metal {
+        color: RGBColor,
+        roughness: f32,
+    },
+    Dielectric {
+        index_of_refraction: f32,
+    },
+    /// Objects with this material are added as lights
+    Light {
+        /// Emitted radiance (ignored if the power is given)
+        #[serde(default = "RGBColor::white")]
+        color: RGBColor,
+        /// Total emitted power, spread over the surface of the object (see `DiffuseLight::from_power`)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        power: Option<RGBColor>,
+        /// Whether light is emitted from both sides of the surface
+        #[serde(default = "two_sided_by_default")]
+        two_sided: bool,
+    },
+}
+
+/// Lights are two-sided unless the scene says otherwise
+fn two_sided_by_default() -> bool {
+    true
+}
+
+impl SceneFile {
+    /// Reads the scene from a TOML file
+    ///
+    /// ## Parameters
+    /// * `path` - path to the scene file
+    pub fn load(path: &Path) -> Result<Self, RaybowError> {
+        let content = fs::read_to_string(path)?;
+        let scene: Self =
+            toml::from_str(&content).map_err(|err| RaybowError::SceneParse(err.to_string()))?;
+        scene.background_kind()?;
+        Ok(scene)
+    }
+
+    /// Writes the scene to a TOML file
+    ///
+    /// ## Parameters
+    /// * `path` - path to the scene file
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let content = toml::to_string(self).map_err(io::Error::other)?;
+        fs::write(path, content)
+    }
+
+    /// Returns the parsed background of the scene
+    pub fn background_kind(&self) -> Result<BackgroundKind, RaybowError> {
+        self.background.parse().map_err(RaybowError::SceneParse)
+    }
+}
+
+impl ObjectDescription {
+    /// Creates the hittable described by the object
+    ///
+    /// ## Parameters
+    /// * `accurate_fresnel` - whether dielectrics use the full Fresnel equations (see `Dielectric::set_accurate_fresnel`)
+    pub fn to_hittable(&self, accurate_fresnel: bool) -> Arc<AnyHittable> {
+        match self {
+            ObjectDescription::Sphere {
+                center,
+                radius,
+                material,
+            } => Sphere::new(
+                *center,
+                *radius,
+                material.to_material(self.area(), accurate_fresnel),
+            )
+            .into(),
+            ObjectDescription::Parallelogram {
+                corner,
+                up,
+                right,
+                material,
+            } => Parallelogram::new(
+                *corner,
+                *up,
+                *right,
+                material.to_material(self.area(), accurate_fresnel),
+            )
+            .into(),
+        }
+    }
+
+    /// Returns the area of the object's surface
+    pub fn area(&self) -> f32 {
+        match self {
+            ObjectDescription::Sphere { radius, .. } => Sphere::area_of(*radius),
+            ObjectDescription::Parallelogram { up, right, .. } => {
+                Parallelogram::area_of(*up, *right)
+            }
+        }
+    }
+
+    /// Returns true if the object emits light (see `Renderables::add_light`)
+    pub fn is_light(&self) -> bool {
+        let material = match self {
+            ObjectDescription::Sphere { material, .. }
+            | ObjectDescription::Parallelogram { material, .. } => material,
+        };
+        matches!(material, MaterialDescription::Light { .. })
+    }
+}
+
+impl MaterialDescription {
+    /// Creates the material described
+    ///
+    /// ## Parameters
+    /// * `area` - area of the surface the material is used on (for lights given by their power)
+    /// * `accurate_fresnel` - whether dielectrics use the full Fresnel equations (see `Dielectric::set_accurate_fresnel`)
+    pub fn to_material(&self, area: f32, accurate_fresnel: bool) -> Arc<AnyMaterial> {
+        match *self {
+            MaterialDescription::Diffuse { color } => LambertarianDiffuse::new(color).into(),
+            MaterialDescription::Metal { color, roughness } => Metal::new(color, roughness).into(),
+            MaterialDescription::Dielectric {
+                index_of_refraction,
+            } => {
+                let mut dielectric = Dielectric::new(index_of_refraction);
+                dielectric.set_accurate_fresnel(accurate_fresnel);
+                dielectric.into()
+            }
+            MaterialDescription::Light {
+                color,
+                power,
+                two_sided,
+            } => {
+                let light = match power {
+                    Some(power) => DiffuseLight::from_power(power, area, two_sided),
+                    None => {
+                        let mut light = DiffuseLight::new(color);
+                        light.set_two_sided(two_sided);
+                        light
+                    }
+                };
+                light.into()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn light_power_and_sides_are_optional() {
+        let light: ObjectDescription = toml::from_str(
+            "type = \"sphere\"\ncenter = [0.0, 0.0, 0.0]\nradius = 1.0\n\
+             material = { type = \"light\", color = [2.0, 2.0, 2.0] }",
+        )
+        .unwrap();
+        let ObjectDescription::Sphere { material, .. } = &light else {
+            panic!("{:?}", light);
+        };
+        assert_eq!(
+            *material,
+            MaterialDescription::Light {
+                color: RGBColor::new(2.0, 2.0, 2.0),
+                power: None,
+                two_sided: true,
+            }
+        );
+
+        let powered: MaterialDescription =
+            toml::from_str("type = \"light\"\npower = [10.0, 10.0, 10.0]\ntwo_sided = false")
+                .unwrap();
+        assert!(matches!(
+            powered,
+            MaterialDescription::Light {
+                power: Some(_),
+                two_sided: false,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn unknown_background_is_a_scene_parse_error() {
+        let path =
+            std::env::temp_dir().join(format!("raybow-background-{}.toml", std::process::id()));
+        let scene = SceneFile {
+            background: String::from("nebula"),
+            camera: CameraDescription {
+                position: Vec3A::ZERO,
+                look_at: Vec3A::NEG_Z,
+                fov: 45.0,
+            },
+            objects: Vec::new(),
+        };
+        scene.save(&path).unwrap();
+        let loaded = SceneFile::load(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert!(
+            matches!(loaded, Err(RaybowError::SceneParse(_))),
+            "{:?}",
+            loaded
+        );
+        assert!(matches!(
+            scene.background_kind(),
+            Err(RaybowError::SceneParse(_))
+        ));
+    }
+}
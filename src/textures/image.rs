@@ -0,0 +1,79 @@
+use std::path::Path;
+
+use glam::Vec3A;
+
+use crate::{
+    color::RGBColor,
+    input_formats::{read_image_file, ImportError, ImportedImage},
+};
+
+use super::Texture;
+
+/// Texture backed by image data
+///
+/// Colors are stored in linear space and are not clamped,
+/// so HDR images keep values above `1.0`.
+pub struct ImageTexture {
+    width: usize,
+    height: usize,
+    image_data: Vec<RGBColor>,
+}
+
+impl ImageTexture {
+    /// Creates a new image texture from decoded image data
+    ///
+    /// ## Parameters
+    /// * `image` - decoded image
+    pub fn new(image: ImportedImage) -> Self {
+        Self {
+            width: image.width,
+            height: image.height,
+            image_data: image.image_data,
+        }
+    }
+
+    /// Loads an image texture from file
+    ///
+    /// The format is detected from the file extension.
+    ///
+    /// ## Parameters
+    /// * `path` - path to the image file
+    pub fn load(path: &Path) -> Result<Self, ImportError> {
+        Ok(Self::new(read_image_file(path)?))
+    }
+
+    /// Returns the width of the image
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the height of the image
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the color of the pixel at image coordinates
+    ///
+    /// ## Parameters
+    /// * `i` - horizontal image location of the pixel
+    /// * `j` - vertical image location of the pixel (`0` is the top row)
+    pub fn pixel(&self, i: usize, j: usize) -> RGBColor {
+        self.image_data[j * self.width + i]
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f32, v: f32, _point: Vec3A) -> RGBColor {
+        if self.image_data.is_empty() {
+            return RGBColor::black();
+        }
+
+        // Image rows go from top to bottom, so v needs to be flipped
+        let u = u.clamp(0.0, 1.0);
+        let v = 1.0 - v.clamp(0.0, 1.0);
+
+        let i = ((u * self.width as f32) as usize).min(self.width - 1);
+        let j = ((v * self.height as f32) as usize).min(self.height - 1);
+        self.pixel(i, j)
+    }
+}
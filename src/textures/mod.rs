@@ -0,0 +1,73 @@
+use std::sync::Arc;
+
+use glam::Vec3A;
+
+use crate::color::RGBColor;
+
+use self::{image::ImageTexture, ramp::RampTexture, solid_color::SolidColor};
+
+pub mod image;
+pub mod ramp;
+pub mod solid_color;
+
+pub enum AnyTexture {
+    SolidColor(SolidColor),
+    Image(ImageTexture),
+    Ramp(RampTexture),
+}
+
+impl From<SolidColor> for AnyTexture {
+    fn from(value: SolidColor) -> Self {
+        Self::SolidColor(value)
+    }
+}
+
+impl From<SolidColor> for Arc<AnyTexture> {
+    fn from(value: SolidColor) -> Self {
+        Arc::new(AnyTexture::SolidColor(value))
+    }
+}
+
+impl From<ImageTexture> for AnyTexture {
+    fn from(value: ImageTexture) -> Self {
+        Self::Image(value)
+    }
+}
+
+impl From<ImageTexture> for Arc<AnyTexture> {
+    fn from(value: ImageTexture) -> Self {
+        Arc::new(AnyTexture::Image(value))
+    }
+}
+
+impl From<RampTexture> for AnyTexture {
+    fn from(value: RampTexture) -> Self {
+        Self::Ramp(value)
+    }
+}
+
+impl From<RampTexture> for Arc<AnyTexture> {
+    fn from(value: RampTexture) -> Self {
+        Arc::new(AnyTexture::Ramp(value))
+    }
+}
+
+impl Texture for AnyTexture {
+    fn value(&self, u: f32, v: f32, point: Vec3A) -> RGBColor {
+        match self {
+            AnyTexture::SolidColor(inner) => inner.value(u, v, point),
+            AnyTexture::Image(inner) => inner.value(u, v, point),
+            AnyTexture::Ramp(inner) => inner.value(u, v, point),
+        }
+    }
+}
+
+pub trait Texture {
+    /// Returns the color of the texture at the given location
+    ///
+    /// ## Parameters
+    /// * `u` - horizontal texture coordinate on the interval `[0.0, 1.0]`
+    /// * `v` - vertical texture coordinate on the interval `[0.0, 1.0]` (`1.0` is the top)
+    /// * `point` - in-scene location of the sampled point
+    fn value(&self, u: f32, v: f32, point: Vec3A) -> RGBColor;
+}
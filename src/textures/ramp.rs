@@ -0,0 +1,95 @@
+use glam::Vec3A;
+
+use crate::color::RGBColor;
+
+use super::Texture;
+
+/// Value at the surface point that drives a ramp texture
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RampInput {
+    /// Horizontal texture coordinate
+    U,
+    /// Vertical texture coordinate
+    V,
+    /// Height of the point in the scene (its Y coordinate)
+    Height,
+}
+
+/// Texture that maps a value at the surface to a color ramp
+///
+/// The ramp is defined by color stops, and colors between neighbouring stops
+/// are linearly interpolated. Values outside of the stops get the color
+/// of the closest stop.
+pub struct RampTexture {
+    stops: Vec<(f32, RGBColor)>,
+    input: RampInput,
+}
+
+impl RampTexture {
+    /// Creates a new ramp texture
+    ///
+    /// ## Parameters
+    /// * `stops` - positions (in units of the input) and colors of the ramp, in any order; at least one
+    /// * `input` - value that selects the color of the ramp
+    pub fn new(mut stops: Vec<(f32, RGBColor)>, input: RampInput) -> Self {
+        if stops.is_empty() {
+            panic!("Ramp texture needs at least one color stop!");
+        }
+        stops.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+        Self { stops, input }
+    }
+
+    /// Returns the color of the ramp at the position
+    ///
+    /// ## Parameters
+    /// * `position` - position on the ramp (in units of the input)
+    pub fn color_at(&self, position: f32) -> RGBColor {
+        // The first stop after the position
+        let next_index = self.stops.partition_point(|(stop, _)| *stop <= position);
+        if next_index == 0 {
+            return self.stops[0].1;
+        }
+        if next_index == self.stops.len() {
+            return self.stops[next_index - 1].1;
+        }
+
+        let (start, start_color) = self.stops[next_index - 1];
+        let (end, end_color) = self.stops[next_index];
+        RGBColor::lerp(start_color, end_color, (position - start) / (end - start))
+    }
+}
+
+impl Texture for RampTexture {
+    fn value(&self, u: f32, v: f32, point: Vec3A) -> RGBColor {
+        let position = match self.input {
+            RampInput::U => u,
+            RampInput::V => v,
+            RampInput::Height => point.y,
+        };
+        self.color_at(position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn two_stop_ramp_interpolates_between_the_endpoints() {
+        let (black, orange) = (RGBColor::black(), RGBColor::new(1.0, 0.5, 0.0));
+        // Stops are sorted, so their order does not matter
+        let ramp = RampTexture::new(vec![(1.0, orange), (0.0, black)], RampInput::V);
+
+        let at = |v: f32| ramp.value(0.3, v, Vec3A::new(5.0, -2.0, 1.0));
+        assert!(at(0.0) == black);
+        assert!(at(1.0) == orange);
+        assert!(at(0.5) == RGBColor::new(0.5, 0.25, 0.0), "{:?}", at(0.5));
+        // Outside of the stops, the closest stop is used
+        assert!(at(-3.0) == black);
+        assert!(at(2.0) == orange);
+
+        let height_ramp = RampTexture::new(vec![(0.0, black), (4.0, orange)], RampInput::Height);
+        let color = height_ramp.value(0.0, 0.0, Vec3A::new(0.0, 2.0, 0.0));
+        assert!(color == RGBColor::new(0.5, 0.25, 0.0), "{:?}", color);
+    }
+}
@@ -0,0 +1,26 @@
+use glam::Vec3A;
+
+use crate::color::RGBColor;
+
+use super::Texture;
+
+/// Texture with the same color everywhere
+pub struct SolidColor {
+    color: RGBColor,
+}
+
+impl SolidColor {
+    /// Creates a new solid color texture
+    ///
+    /// ## Parameters
+    /// * `color` - color of the texture
+    pub fn new(color: RGBColor) -> Self {
+        Self { color }
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: f32, _v: f32, _point: Vec3A) -> RGBColor {
+        self.color
+    }
+}
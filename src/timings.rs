@@ -0,0 +1,55 @@
+use std::{fmt::Display, time::Duration};
+
+/// Durations of the pipeline stages
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StageTimings {
+    pub preparation: Duration,
+    pub accelerator_build: Duration,
+    pub rendering: Duration,
+    pub postprocessing: Duration,
+    pub export: Duration,
+}
+
+impl StageTimings {
+    /// Returns the combined duration of all stages
+    pub fn total(&self) -> Duration {
+        self.preparation
+            + self.accelerator_build
+            + self.rendering
+            + self.postprocessing
+            + self.export
+    }
+}
+
+impl Display for StageTimings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "preparation {:.2?}, accelerator build {:.2?}, rendering {:.2?}, postprocessing {:.2?}, export {:.2?} (total {:.2?})",
+            self.preparation,
+            self.accelerator_build,
+            self.rendering,
+            self.postprocessing,
+            self.export,
+            self.total()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn total_adds_up_all_stages() {
+        let timings = StageTimings {
+            preparation: Duration::from_millis(1),
+            accelerator_build: Duration::from_millis(2),
+            rendering: Duration::from_millis(30),
+            postprocessing: Duration::from_millis(4),
+            export: Duration::from_millis(5),
+        };
+        assert_eq!(timings.total(), Duration::from_millis(42));
+        assert!(timings.to_string().ends_with("(total 42.00ms)"));
+    }
+}